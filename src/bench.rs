@@ -0,0 +1,221 @@
+use crate::grid::Grid;
+use crate::solver::{
+    label, solve_grid_with_solve_controller, SolveAction, SolveController, SolveStatistics,
+};
+use std::cell::RefCell;
+use std::fmt::Formatter;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    // Running total of the time spent in each solving strategy on this thread
+    static STRATEGY_TIMINGS: RefCell<StrategyTimings> = RefCell::new(StrategyTimings::new());
+}
+
+pub(crate) fn record_strategy_time(action: &SolveAction, elapsed: Duration) {
+    STRATEGY_TIMINGS.with(|timings| {
+        let timings = &mut *timings.borrow_mut();
+        match action {
+            SolveAction::Single => timings.singles += elapsed,
+            SolveAction::HiddenSingle => timings.hidden_singles += elapsed,
+            SolveAction::NakedSubset => timings.naked_subsets += elapsed,
+            SolveAction::HiddenSubset => timings.hidden_subsets += elapsed,
+            SolveAction::PossibilityGroup => timings.possibility_groups += elapsed,
+            SolveAction::UsefulConstraints => timings.useful_constraints += elapsed,
+            SolveAction::Guess => {} // Guesses recurse into the other strategies
+        }
+    });
+}
+
+/// Total time spent running each solving strategy, whether or not it made progress.
+#[derive(Copy, Clone, Debug)]
+pub struct StrategyTimings {
+    pub singles: Duration,
+    pub hidden_singles: Duration,
+    pub naked_subsets: Duration,
+    pub hidden_subsets: Duration,
+    pub possibility_groups: Duration,
+    pub useful_constraints: Duration,
+}
+
+impl StrategyTimings {
+    /// Create a new StrategyTimings with all durations set to zero.
+    pub fn new() -> StrategyTimings {
+        StrategyTimings {
+            singles: Duration::default(),
+            hidden_singles: Duration::default(),
+            naked_subsets: Duration::default(),
+            hidden_subsets: Duration::default(),
+            possibility_groups: Duration::default(),
+            useful_constraints: Duration::default(),
+        }
+    }
+}
+
+impl Default for StrategyTimings {
+    fn default() -> Self {
+        StrategyTimings::new()
+    }
+}
+
+/// The results of `run_benchmark`.
+#[derive(Clone, Debug)]
+pub struct BenchmarkReport {
+    /// How long each solve took, sorted from fastest to slowest.
+    pub solve_times: Vec<Duration>,
+
+    /// Time spent in each solving strategy, summed over all solves.
+    pub strategy_timings: StrategyTimings,
+
+    /// Solve statistics summed over all solves, except for `max_guess_depth` which is the deepest
+    /// of any one solve, and `solved_with_guesses` which is set if any solve needed guesses.
+    pub solve_statistics: SolveStatistics,
+}
+
+impl BenchmarkReport {
+    /// The time of the solve at the `percentile`th percentile (between 0 and 100), using the
+    /// nearest-rank method. Returns a zero duration if nothing was solved.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.solve_times.is_empty() {
+            return Duration::default();
+        }
+
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * self.solve_times.len() as f64).ceil();
+        let index = (rank as usize).clamp(1, self.solve_times.len()) - 1;
+        self.solve_times[index]
+    }
+
+    /// The fastest solve, or a zero duration if nothing was solved.
+    pub fn min(&self) -> Duration {
+        self.percentile(0.0)
+    }
+
+    /// The median solve time, or a zero duration if nothing was solved.
+    pub fn median(&self) -> Duration {
+        self.percentile(50.0)
+    }
+
+    /// The slowest solve, or a zero duration if nothing was solved.
+    pub fn max(&self) -> Duration {
+        self.percentile(100.0)
+    }
+
+    /// The time spent on every solve added together, or a zero duration if nothing was solved.
+    pub fn total(&self) -> Duration {
+        self.solve_times.iter().sum()
+    }
+
+    /// Returns a zero duration if nothing was solved.
+    pub fn mean(&self) -> Duration {
+        if self.solve_times.is_empty() {
+            return Duration::default();
+        }
+        self.total() / self.solve_times.len() as u32
+    }
+}
+
+impl std::fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Solved {} puzzles in {:?}",
+            self.solve_times.len(),
+            self.total()
+        )?;
+        writeln!(
+            f,
+            "\tmin {:?}, median {:?}, mean {:?}, p90 {:?}, p99 {:?}, max {:?}",
+            self.min(),
+            self.median(),
+            self.mean(),
+            self.percentile(90.0),
+            self.percentile(99.0),
+            self.max()
+        )?;
+        writeln!(f, "Time spent per strategy:")?;
+        writeln!(
+            f,
+            "\t{:?} {}",
+            self.strategy_timings.singles,
+            label(SolveAction::Single)
+        )?;
+        writeln!(
+            f,
+            "\t{:?} {}",
+            self.strategy_timings.hidden_singles,
+            label(SolveAction::HiddenSingle)
+        )?;
+        writeln!(
+            f,
+            "\t{:?} {}",
+            self.strategy_timings.naked_subsets,
+            label(SolveAction::NakedSubset)
+        )?;
+        writeln!(
+            f,
+            "\t{:?} {}",
+            self.strategy_timings.hidden_subsets,
+            label(SolveAction::HiddenSubset)
+        )?;
+        writeln!(
+            f,
+            "\t{:?} {}",
+            self.strategy_timings.possibility_groups,
+            label(SolveAction::PossibilityGroup)
+        )?;
+        write!(
+            f,
+            "\t{:?} {}",
+            self.strategy_timings.useful_constraints,
+            label(SolveAction::UsefulConstraints)
+        )
+    }
+}
+
+/// Solve each of `puzzles` with `solve_controller`, timing each solve and each solving strategy.
+/// The input `Grid`s aren't modified; copying them is not included in the timings.
+pub fn run_benchmark(puzzles: &[Grid], solve_controller: &SolveController) -> BenchmarkReport {
+    STRATEGY_TIMINGS.with(|timings| timings.replace(StrategyTimings::new()));
+
+    let mut solve_times = Vec::with_capacity(puzzles.len());
+    let mut total_statistics = SolveStatistics::new();
+
+    for puzzle in puzzles.iter() {
+        let mut grid = puzzle.clone();
+        let mut solve_statistics = SolveStatistics::new();
+
+        let start = Instant::now();
+        solve_grid_with_solve_controller(&mut grid, solve_controller, &mut solve_statistics);
+        solve_times.push(start.elapsed());
+
+        total_statistics.add(&solve_statistics);
+    }
+
+    solve_times.sort();
+
+    BenchmarkReport {
+        solve_times,
+        strategy_timings: STRATEGY_TIMINGS.with(|timings| *timings.borrow()),
+        solve_statistics: total_statistics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_percentile() {
+        let report = BenchmarkReport {
+            solve_times: (1..=10).map(Duration::from_millis).collect(),
+            strategy_timings: StrategyTimings::new(),
+            solve_statistics: SolveStatistics::new(),
+        };
+
+        assert_eq!(report.min(), Duration::from_millis(1));
+        assert_eq!(report.median(), Duration::from_millis(5));
+        assert_eq!(report.percentile(90.0), Duration::from_millis(9));
+        assert_eq!(report.max(), Duration::from_millis(10));
+        assert_eq!(report.mean(), Duration::from_micros(5500));
+    }
+}
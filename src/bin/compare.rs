@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use sudoku_solver::grid::Grid;
+use sudoku_solver::io::{load_puzzles, PuzzleFormat};
+use sudoku_solver::transform::canonical_form;
+
+// Exit codes, so that a script can check a new volume against earlier ones. argparse already uses
+// 2 for bad arguments.
+const EXIT_NO_REPEATS: i32 = 0;
+const EXIT_READ_ERROR: i32 = 1;
+const EXIT_REPEATS: i32 = 3;
+
+fn main() {
+    let mut first = String::new();
+    let mut second = String::new();
+    let mut from: Option<String> = None;
+    {
+        // this block limits scope of borrows by ap.refer() method
+        let mut ap = argparse::ArgumentParser::new();
+        ap.set_description(
+            "Find the puzzles of one file that repeat a puzzle of another, either exactly or disguised by relabelling digits and rearranging or reflecting the grid",
+        );
+
+        ap.refer(&mut first).required().add_argument(
+            "first",
+            argparse::Store,
+            "Path to the earlier puzzles, or - for standard input",
+        );
+
+        ap.refer(&mut second).required().add_argument(
+            "second",
+            argparse::Store,
+            "Path to the puzzles to check against them",
+        );
+
+        ap.refer(&mut from).add_option(
+            &["--from"],
+            argparse::StoreOption,
+            "Format of both files; guessed from their extensions if not given",
+        );
+
+        ap.parse_args_or_exit();
+    }
+
+    let first_grids = read_puzzles(&first, &from);
+    let second_grids = read_puzzles(&second, &from);
+
+    // Each canonical form of the first file, and which of its puzzles have it
+    let mut earlier: HashMap<[[u8; 9]; 9], Vec<usize>> = HashMap::new();
+    for (index, grid) in first_grids.iter().enumerate() {
+        earlier
+            .entry(canonical_form(grid).to_digits())
+            .or_default()
+            .push(index);
+    }
+
+    let mut repeats = 0;
+    for (index, grid) in second_grids.iter().enumerate() {
+        let matches = match earlier.get(&canonical_form(grid).to_digits()) {
+            Some(matches) => matches,
+            None => continue,
+        };
+        for &earlier_index in matches {
+            let kind = if first_grids[earlier_index].to_digits() == grid.to_digits() {
+                "the same as"
+            } else {
+                "a disguised copy of"
+            };
+            println!(
+                "Puzzle {} of {} is {} puzzle {} of {}",
+                index + 1,
+                second,
+                kind,
+                earlier_index + 1,
+                first
+            );
+            repeats += 1;
+        }
+    }
+
+    println!(
+        "Found {} repeat(s) between {} puzzle(s) and {} puzzle(s)",
+        repeats,
+        first_grids.len(),
+        second_grids.len()
+    );
+    std::process::exit(if repeats > 0 {
+        EXIT_REPEATS
+    } else {
+        EXIT_NO_REPEATS
+    });
+}
+
+fn read_puzzles(path: &str, from: &Option<String>) -> Vec<Grid> {
+    let format = match from {
+        Some(format) => PuzzleFormat::from_str(format),
+        None => PuzzleFormat::from_filename(path)
+            .ok_or(format!("Unable to tell the format of {}; use --from", path)),
+    };
+    let format = match format {
+        Ok(format) => format,
+        Err(e) => exit_with_error(&e),
+    };
+
+    match load_puzzles(path, format) {
+        Ok((grids, warnings)) => {
+            for warning in warnings {
+                eprintln!("Warning: {}: {}", path, warning);
+            }
+            grids
+        }
+        Err(e) => exit_with_error(&e),
+    }
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(EXIT_READ_ERROR);
+}
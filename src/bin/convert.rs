@@ -0,0 +1,126 @@
+use std::str::FromStr;
+
+use sudoku_solver::grid::DisplayOptions;
+use sudoku_solver::io::{format_puzzles, load_puzzles, write_puzzles, PuzzleFormat};
+use sudoku_solver::pdf::{PdfLayout, PdfOptions, WorkingGrid};
+
+fn main() {
+    let mut input = String::new();
+    let mut output: Option<String> = None;
+    let mut from: Option<String> = None;
+    let mut to: Option<String> = None;
+    let mut print_possibilities = false;
+    let mut coordinates = false;
+    let mut working_grid: Option<WorkingGrid> = None;
+    {
+        // this block limits scope of borrows by ap.refer() method
+        let mut ap = argparse::ArgumentParser::new();
+        ap.set_description(
+            "Convert Sudoku puzzles between the CSV, LINE (81 characters), SDM, SDK, JSON, and PDF formats",
+        );
+
+        ap.refer(&mut input).required().add_argument(
+            "input",
+            argparse::Store,
+            "Path to the puzzle file to read, or - for standard input",
+        );
+
+        ap.refer(&mut output).add_argument(
+            "output",
+            argparse::StoreOption,
+            "Path to write the converted puzzles to; printed if not given",
+        );
+
+        ap.refer(&mut from).add_option(
+            &["--from"],
+            argparse::StoreOption,
+            "Format of the input; guessed from its extension if not given",
+        );
+
+        ap.refer(&mut to).add_option(
+            &["--to"],
+            argparse::StoreOption,
+            "Format of the output; guessed from its extension if not given",
+        );
+
+        ap.refer(&mut print_possibilities).add_option(
+            &["-p", "--possibilities"],
+            argparse::StoreTrue,
+            "Fill in each empty cell's possibilities; applies only to PDF output",
+        );
+
+        ap.refer(&mut coordinates).add_option(
+            &["--coordinates"],
+            argparse::StoreTrue,
+            "Number the rows and columns along the edges of the grid; applies only to PDF output",
+        );
+
+        ap.refer(&mut working_grid).add_option(
+            &["--working-grid"],
+            argparse::StoreOption,
+            "Print a BLANK grid, or one with the puzzle's CANDIDATES, beside the puzzle for working in; applies only to PDF output",
+        );
+
+        ap.parse_args_or_exit();
+    }
+
+    let from = match choose_format(from, Some(&input)) {
+        Ok(format) => format,
+        Err(e) => exit_with_error(&e),
+    };
+    let to = match choose_format(to, output.as_deref()) {
+        Ok(format) => format,
+        Err(e) => exit_with_error(&e),
+    };
+
+    let grids = match load_puzzles(&input, from) {
+        Ok((grids, warnings)) => {
+            for warning in warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            grids
+        }
+        Err(e) => exit_with_error(&e),
+    };
+
+    let pdf_options = PdfOptions {
+        display: DisplayOptions {
+            show_candidates: print_possibilities,
+            coordinates,
+            ..DisplayOptions::default()
+        },
+        layout: working_grid.map_or(PdfLayout::Single, PdfLayout::WithWorkingGrid),
+    };
+    let result = match &output {
+        Some(output) => write_puzzles(&grids, output, to, &pdf_options),
+        None => format_puzzles(&grids, to)
+            .map(|text| print!("{}", text))
+            .map_err(|e| e.into()),
+    };
+
+    match result {
+        Ok(()) => {
+            if let Some(output) = output {
+                println!("Converted {} puzzle(s) to {}", grids.len(), output);
+            }
+        }
+        Err(e) => exit_with_error(&format!("Error while writing puzzles: \"{}\"", e)),
+    }
+}
+
+// Use the format given on the command line, or else guess it from the filename
+fn choose_format(format: Option<String>, filename: Option<&str>) -> Result<PuzzleFormat, String> {
+    match (format, filename) {
+        (Some(format), _) => PuzzleFormat::from_str(&format),
+        (None, Some(filename)) => PuzzleFormat::from_filename(filename).ok_or(format!(
+            "Unable to tell the format of {}; use --from or --to",
+            filename
+        )),
+        (None, None) => Err("--to is required when printing the output".to_string()),
+    }
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
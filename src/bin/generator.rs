@@ -2,110 +2,210 @@ use rand::prelude::*;
 use std::error::Error;
 use std::io::Write;
 use std::process::exit;
-use std::str::FromStr;
+use std::sync::atomic::AtomicI32;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{mpsc, Arc};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use sudoku_solver::grid::{CellValue, Grid};
+use std::time::{Duration, Instant};
+use sudoku_solver::difficulty::{parse_book, DifficultyTier, Rejection, RejectionCounts};
+use sudoku_solver::generator::{
+    generate_grid_for_tier_from_cache, CompletedGridCache, DigOptions, DigOrder, DigStrategy,
+};
+use sudoku_solver::grading::{DistributionReport, Grader};
+use sudoku_solver::grid::{DisplayOptions, Grid, GridSnapshot, SharedGrid};
+use sudoku_solver::io::{
+    format_puzzles, write_puzzles_with_metadata, PuzzleFormat, PuzzleMetadata,
+};
+use sudoku_solver::pdf::{BookSection, PdfLayout, PdfOptions, WorkingGrid};
 use sudoku_solver::solver::{SolveController, SolveStatistics};
 
-/*
-We have to be very careful here because Grid contains lots of Rcs and RefCells which could enable mutability
-across multiple threads (with Rcs specifically even just counting the number of active references to the object
-involves mutability of the Rc itself). In my specific case with the generator here I know that all those Rcs
-and RefCells are fully encapsulated in the one Grid object I'm Sending and will never be accessed again from the thread
-that sent them after it's been Sent, so it's safe in this narrowly specific context.
-*/
-struct SafeGridWrapper(Grid);
-unsafe impl Send for SafeGridWrapper {}
-
-#[derive(Clone, Copy)] // Needed for argparse
-enum Difficulty {
-    Challenge,
-    Hard,
-    Medium,
-    Easy,
+// State shared by the threads searching for a puzzle. Live attempt counts are shown on stderr
+// while searching, so long runs aren't silent.
+struct Progress {
+    should_stop: AtomicBool,
+    enabled: bool,
+    start: Instant,
+    attempts_per_thread: Vec<AtomicI32>,
+
+    // The candidate with the fewest hints seen so far, and how it rated
+    best: Mutex<Option<(i32, f64)>>,
 }
 
-impl Difficulty {
-    fn map_to_solve_controller(&self) -> SolveController {
-        let mut controller = SolveController {
-            determine_uniqueness: true,
-            search_singles: true,
-            search_hidden_singles: true,
-            find_possibility_groups: true,
-            search_useful_constraint: true,
-            make_guesses: true,
-        };
+impl Progress {
+    fn new(enabled: bool, threads: i32) -> Progress {
+        Progress {
+            should_stop: AtomicBool::new(false),
+            enabled,
+            start: Instant::now(),
+            attempts_per_thread: (0..threads).map(|_| AtomicI32::new(0)).collect(),
+            best: Mutex::new(None),
+        }
+    }
 
-        match self {
-            Difficulty::Challenge => {} // Do nothing, already hard
-            Difficulty::Hard => {
-                controller.make_guesses = false;
-            }
-            Difficulty::Medium => {
-                controller.make_guesses = false;
-            }
-            Difficulty::Easy => {
-                controller.make_guesses = false;
-                controller.search_useful_constraint = false;
-                controller.find_possibility_groups = false;
+    fn record_attempt(&self, thread: usize, num_hints: i32, solve_statistics: &SolveStatistics) {
+        if !self.enabled {
+            return;
+        }
+
+        self.attempts_per_thread[thread].fetch_add(1, Ordering::Relaxed);
+
+        let best = {
+            let mut best = self.best.lock().unwrap();
+            if best.is_none_or(|(best_hints, _score)| num_hints < best_hints) {
+                let grade = Grader::default().grade(solve_statistics);
+                *best = Some((num_hints, grade.score));
             }
+            *best
+        };
+
+        let attempts: Vec<i32> = self
+            .attempts_per_thread
+            .iter()
+            .map(|attempts| attempts.load(Ordering::Relaxed))
+            .collect();
+        let total: i32 = attempts.iter().sum();
+        let elapsed = self.start.elapsed().as_secs();
+
+        let mut line = format!(
+            "\r{} attempts, {}:{:02} elapsed",
+            total,
+            elapsed / 60,
+            elapsed % 60
+        );
+        if attempts.len() > 1 {
+            let attempts: Vec<String> = attempts
+                .iter()
+                .map(|attempts| attempts.to_string())
+                .collect();
+            line.push_str(&format!(" (per thread: {})", attempts.join(", ")));
+        }
+        if let Some((best_hints, best_score)) = best {
+            line.push_str(&format!(
+                "; best so far has {} hints, rated {:.1}",
+                best_hints, best_score
+            ));
         }
 
-        controller
+        eprint!("{}   ", line);
     }
 
-    // Note that we only need to check for minimum requirements because easier difficulities
-    // have turned off parts of the solver. For example, Difficulty::EASY cannot have any
-    // guesses simply by virtue of not allowing guesses during the generation process.
-    fn meets_minimum_requirements(&self, solve_statistics: &SolveStatistics) -> bool {
-        match self {
-            Difficulty::Challenge => {
-                (solve_statistics.guesses > 0)
-                    && (solve_statistics.possibility_groups > 20)
-                    && (solve_statistics.useful_constraints > 20)
-            }
-            Difficulty::Hard => {
-                (solve_statistics.possibility_groups > 5)
-                    && (solve_statistics.useful_constraints > 10)
-            }
-            Difficulty::Medium => {
-                (solve_statistics.possibility_groups > 5)
-                    && (solve_statistics.useful_constraints > 5)
-            }
-            Difficulty::Easy => true, // easy has no minimum
+    // Move off of the progress line before anything else is printed
+    fn finish(&self) {
+        if self.enabled {
+            eprintln!();
         }
     }
 }
 
-impl FromStr for Difficulty {
-    // Needed for argparse
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.eq_ignore_ascii_case("EASY") {
-            return Ok(Difficulty::Easy);
-        } else if s.eq_ignore_ascii_case("MEDIUM") {
-            return Ok(Difficulty::Medium);
-        } else if s.eq_ignore_ascii_case("HARD") {
-            return Ok(Difficulty::Hard);
-        } else if s.eq_ignore_ascii_case("CHALLENGE") {
-            return Ok(Difficulty::Challenge);
+// What one thread did while searching for a puzzle
+struct ThreadReport {
+    thread: usize,
+    attempts: i32,
+
+    // How often each criterion was missed
+    rejections: RejectionCounts,
+
+    // How long after the search started the thread found its puzzle
+    found_after: Option<Duration>,
+
+    // The rejected puzzle that missed the fewest criteria
+    near_miss: Option<NearMiss>,
+}
+
+// How many puzzles a search may generate, how many of them are dug from each completed grid, and
+// how clues are dug out
+#[derive(Clone, Copy)]
+struct SearchSettings {
+    max_attempts: i32,
+    digs_per_grid: usize,
+    dig_options: DigOptions,
+}
+
+// How every section is searched for, whatever its difficulty
+struct GenerationOptions {
+    threads: i32,
+    settings: SearchSettings,
+    debug: bool,
+    quiet: bool,
+    keep_near_miss: bool,
+    seed: Option<u64>,
+    printed_options: DisplayOptions,
+}
+
+// The puzzles generated for one difficulty, with their solutions and metadata in the same order
+#[derive(Default)]
+struct GeneratedSection {
+    title: String,
+    puzzles: Vec<Grid>,
+    solutions: Vec<Grid>,
+    metadata: Vec<PuzzleMetadata>,
+}
+
+// A puzzle that met the criteria, how solving it went, its hint count and the seed that found it
+type FoundPuzzle = (Grid, SolveStatistics, i32, u64);
+
+struct NearMiss {
+    grid: GridSnapshot,
+    statistics: SolveStatistics,
+    num_hints: i32,
+    rejections: Vec<Rejection>,
+    seed: u64,
+}
+
+impl ThreadReport {
+    fn new(thread: usize) -> ThreadReport {
+        ThreadReport {
+            thread,
+            attempts: 0,
+            rejections: RejectionCounts::new(),
+            found_after: None,
+            near_miss: None,
         }
+    }
+}
 
-        return Err(format!("{} is not a valid difficulty", s));
+impl std::fmt::Display for ThreadReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Thread {}: {} attempts", self.thread, self.attempts)?;
+        if let Some(found_after) = self.found_after {
+            write!(
+                f,
+                ", found a puzzle after {:.1}s",
+                found_after.as_secs_f64()
+            )?;
+        }
+        if !self.rejections.is_empty() {
+            write!(f, "; rejected for {}", self.rejections)?;
+        }
+        Ok(())
     }
 }
 
 fn main() {
     let mut debug = false;
     let mut max_hints = 81;
+    let mut min_hints = 0;
     let mut max_attempts = 100;
+    let mut digs_per_grid: usize = 1;
+    let mut dig_order = DigOrder::Random;
+    let mut min_clues_per_house: usize = 0;
+    let mut exhaustive_dig: Option<usize> = None;
     let mut filename: Option<String> = None;
-    let mut difficulty = Difficulty::Challenge;
+    let mut difficulty = String::from("CHALLENGE");
+    #[cfg_attr(not(feature = "config"), allow(unused_mut))]
+    let mut difficulty_file: Option<String> = None;
     let mut threads = 1;
     let mut print_possibilities = false;
+    let mut coordinates = false;
+    let mut working_grid: Option<WorkingGrid> = None;
+    let mut count: usize = 1;
+    let mut solutions_filename: Option<String> = None;
+    let mut pairs_filename: Option<String> = None;
+    let mut quiet = false;
+    let mut keep_near_miss = false;
+    let mut seed: Option<u64> = None;
+    let mut book: Option<String> = None;
+    let mut report: Option<String> = None;
 
     {
         // this block limits scope of borrows by ap.refer() method
@@ -114,25 +214,110 @@ fn main() {
         ap.refer(&mut debug)
             .add_option(&["--debug"], argparse::StoreTrue, "Run in debug mode");
 
+        ap.refer(&mut quiet).add_option(
+            &["-q", "--quiet", "--line"],
+            argparse::StoreTrue,
+            "Only print each puzzle as a line of 81 characters, with . for empty cells",
+        );
+
+        ap.refer(&mut keep_near_miss).add_option(
+            &["--near-miss"],
+            argparse::StoreTrue,
+            "If no puzzle meets the difficulty criteria, return the candidate that missed the fewest of them instead",
+        );
+
+        ap.refer(&mut seed).add_option(
+            &["--seed"],
+            argparse::StoreOption,
+            "Seed for the random number generator; with --threads 1, the same seed always generates the same puzzle. Each saved puzzle records the seed that made it",
+        );
+
         ap.refer(&mut max_hints).add_option(
             &["--hints"],
             argparse::Store,
             "Only return a puzzle with less than or equal to this number of hints",
         );
 
+        ap.refer(&mut min_hints).add_option(
+            &["--min-hints"],
+            argparse::Store,
+            "Only return a puzzle with at least this number of hints; hints stop being removed once it's reached",
+        );
+
         ap.refer(&mut max_attempts)
             .add_option(&["--attempts"], argparse::Store, "Number of puzzles each thread will generate to find an appropriate puzzle; default is 100");
 
+        ap.refer(&mut digs_per_grid).add_option(
+            &["--digs-per-grid"],
+            argparse::Store,
+            "Number of attempts that dig clues out of the same completed grid, each in a different order, before a new one is made; default is 1",
+        );
+
+        ap.refer(&mut dig_order).add_option(
+            &["--dig-first"],
+            argparse::Store,
+            "Which clues to try removing first; values are RANDOM (the default), CENTER, or EDGES, leaving more clues in the other part of the grid",
+        );
+
+        ap.refer(&mut min_clues_per_house).add_option(
+            &["--min-clues-per-house"],
+            argparse::Store,
+            "Never remove a clue from a row, column, or box that has this many clues or fewer, so that no region is left empty; default is 0",
+        );
+
+        ap.refer(&mut exhaustive_dig).add_option(
+            &["--exhaustive-dig"],
+            argparse::StoreOption,
+            "Instead of a single pass over the clues, search other orders of removing them for up to this many removal attempts per puzzle, which can reach far fewer clues; try 10000",
+        );
+
         ap.refer(&mut filename).add_argument(
             "filename",
             argparse::StoreOption,
-            "Optional filename to store puzzle in as a CSV",
+            "Optional filename to store the puzzles in; the format is determined by the extension, defaulting to CSV",
+        );
+
+        ap.refer(&mut count).add_option(
+            &["--count"],
+            argparse::Store,
+            "Number of puzzles to generate; default is 1. Saving more than one requires a format that holds many, like SDM",
+        );
+
+        ap.refer(&mut solutions_filename).add_option(
+            &["--solutions"],
+            argparse::StoreOption,
+            "Also save the solutions to this file, in the format determined by its extension",
+        );
+
+        ap.refer(&mut pairs_filename).add_option(
+            &["--pairs"],
+            argparse::StoreOption,
+            "Also save every puzzle and its solution to this file, one pair of 81 digit strings per line",
         );
 
         ap.refer(&mut difficulty).add_option(
             &["-d", "--difficulty"],
             argparse::Store,
-            "Max difficulty setting; values are EASY, MEDIUM, HARD, or CHALLENGE",
+            "Max difficulty setting; values are EASY, MEDIUM, HARD, or CHALLENGE, or the name of a tier in --difficulty-file",
+        );
+
+        ap.refer(&mut book).add_option(
+            &["--book"],
+            argparse::StoreOption,
+            "Generate a book in sections of different difficulties, like EASY:20,MEDIUM:20,HARD:20, instead of --count puzzles of --difficulty. A PDF gets a title page for each section and numbers the puzzles within it",
+        );
+
+        ap.refer(&mut report).add_option(
+            &["--report"],
+            argparse::StoreOption,
+            "Also save a summary of how the puzzles spread across difficulties, hint counts and techniques to this file; as JSON if it ends in .json, otherwise as text",
+        );
+
+        #[cfg(feature = "config")]
+        ap.refer(&mut difficulty_file).add_option(
+            &["--difficulty-file"],
+            argparse::StoreOption,
+            "TOML file of difficulty tiers to choose --difficulty from instead of the built-in ones",
         );
 
         ap.refer(&mut threads).add_option(
@@ -141,138 +326,534 @@ fn main() {
             "Number of threads to use when generating possible puzzles",
         );
 
-        ap.refer(&mut print_possibilities)
-            .add_option(
+        ap.refer(&mut print_possibilities).add_option(
             &["-p", "--possibilities"],
             argparse::StoreTrue,
-            "Include each cell's possibilities in the output; applies only to PDF output"
+            "Include each cell's possibilities in the output; applies only to PDF output",
+        );
+
+        ap.refer(&mut coordinates).add_option(
+            &["--coordinates"],
+            argparse::StoreTrue,
+            "Number the rows and columns along the edges of the printed and PDF grids",
+        );
+
+        ap.refer(&mut working_grid)
+            .add_option(
+            &["--working-grid"],
+            argparse::StoreOption,
+            "Print a BLANK grid, or one with the puzzle's CANDIDATES, beside each puzzle for working in; applies only to PDF output"
             );
 
         ap.parse_args_or_exit();
     }
 
-    let solve_controller = difficulty.map_to_solve_controller();
+    let printed_options = DisplayOptions {
+        coordinates,
+        ..DisplayOptions::default()
+    };
+
+    let sections = match &book {
+        Some(book) => match parse_book(book) {
+            Ok(sections) => sections,
+            Err(message) => {
+                eprintln!("{}", message);
+                exit(1);
+            }
+        },
+        None => vec![(difficulty, count)],
+    };
 
-    let (result, num_attempts) = if threads < 1 {
+    if threads < 1 {
         eprintln!("--threads must be at least 1");
         exit(1);
-    } else if threads == 1 {
-        let mut rng = SmallRng::from_entropy();
-        get_puzzle_matching_conditions(
-            &mut rng,
-            &difficulty,
-            &solve_controller,
-            max_attempts,
-            max_hints,
-            &AtomicBool::new(false),
-            debug
-        )
-    } else {
-        run_multi_threaded(
+    }
+    if digs_per_grid < 1 {
+        eprintln!("--digs-per-grid must be at least 1");
+        exit(1);
+    }
+
+    let options = GenerationOptions {
+        threads,
+        settings: SearchSettings {
             max_attempts,
-            max_hints,
-            threads,
-            debug,
-            solve_controller,
-            difficulty,
-        )
+            digs_per_grid,
+            dig_options: DigOptions {
+                order: dig_order,
+                min_clues_per_house,
+                strategy: match exhaustive_dig {
+                    Some(node_budget) => DigStrategy::Exhaustive { node_budget },
+                    None => DigStrategy::Greedy,
+                },
+            },
+        },
+        debug,
+        quiet,
+        keep_near_miss,
+        seed,
+        printed_options: printed_options.clone(),
     };
 
-    let (grid, solve_statistics, num_hints) = match result {
-        Some(x) => x,
-        None => {
-            println!("Unable to find a desired puzzle in {} tries.", num_attempts);
-            return;
+    let mut generated = Vec::new();
+    let mut first_index = 0;
+    for (name, count) in sections {
+        let mut difficulty = match find_difficulty_tier(&name, difficulty_file.clone()) {
+            Ok(tier) => tier,
+            Err(message) => {
+                eprintln!("{}", message);
+                exit(1);
+            }
+        };
+
+        // --hints and --min-hints narrow whatever bounds the tier already has
+        let tier_max_hints = difficulty.max_hints.unwrap_or(81);
+        let tier_min_hints = difficulty.min_hints.unwrap_or(0);
+        difficulty.max_hints = Some(tier_max_hints.min(max_hints));
+        difficulty.min_hints = Some(tier_min_hints.max(min_hints));
+
+        if book.is_some() && !quiet {
+            println!("Generating {} {} puzzles", count, difficulty.name);
         }
+
+        let section = generate_puzzles(&difficulty, count, first_index, &options);
+        if section.puzzles.len() < count && !quiet {
+            println!(
+                "Only {} of {} puzzles were found.",
+                section.puzzles.len(),
+                count
+            );
+        }
+        first_index += count;
+        generated.push(section);
+    }
+
+    if generated.iter().all(|section| section.puzzles.is_empty()) {
+        return;
+    }
+
+    let puzzles: Vec<Grid> = generated
+        .iter()
+        .flat_map(|section| section.puzzles.clone())
+        .collect();
+    let solutions: Vec<Grid> = generated
+        .iter()
+        .flat_map(|section| section.solutions.clone())
+        .collect();
+    let metadata: Vec<PuzzleMetadata> = generated
+        .iter()
+        .flat_map(|section| section.metadata.clone())
+        .collect();
+
+    if let Some(filename) = filename {
+        let pdf_options = PdfOptions {
+            display: DisplayOptions {
+                show_candidates: print_possibilities,
+                ..printed_options.clone()
+            },
+            layout: working_grid.map_or(PdfLayout::Single, PdfLayout::WithWorkingGrid),
+        };
+        if book.is_some() {
+            let sections = book_sections(&generated, |section| &section.puzzles);
+            save_book(
+                &sections,
+                &puzzles,
+                &metadata,
+                &filename,
+                &pdf_options,
+                "Puzzles",
+                quiet,
+            );
+        } else {
+            save_puzzles(
+                &puzzles,
+                &metadata,
+                &filename,
+                &pdf_options,
+                "Puzzles",
+                quiet,
+            );
+        }
+    }
+    if let Some(solutions_filename) = solutions_filename {
+        let pdf_options = PdfOptions {
+            display: printed_options.clone(),
+            ..PdfOptions::default()
+        };
+        if book.is_some() {
+            let sections = book_sections(&generated, |section| &section.solutions);
+            save_book(
+                &sections,
+                &solutions,
+                &metadata,
+                &solutions_filename,
+                &pdf_options,
+                "Solutions",
+                quiet,
+            );
+        } else {
+            save_puzzles(
+                &solutions,
+                &metadata,
+                &solutions_filename,
+                &pdf_options,
+                "Solutions",
+                quiet,
+            );
+        }
+    }
+    if let Some(pairs_filename) = pairs_filename {
+        let pairs: Vec<(Grid, Grid)> = puzzles.into_iter().zip(solutions).collect();
+        match save_pairs(&pairs, &pairs_filename) {
+            Ok(()) => {
+                if !quiet {
+                    println!("Puzzles and solutions saved to {}", pairs_filename);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error while saving to {}: \"{}\"", pairs_filename, e);
+                exit(1);
+            }
+        }
+    }
+    if let Some(report) = report {
+        let tier_names: Vec<String> = generated
+            .iter()
+            .map(|section| section.title.clone())
+            .collect();
+        let hints = generated.iter().flat_map(|section| {
+            section
+                .puzzles
+                .iter()
+                .map(|puzzle| puzzle.clue_count() as i32)
+        });
+        let entries = metadata
+            .iter()
+            .zip(hints)
+            .filter_map(|(metadata, num_hints)| {
+                let statistics = metadata.statistics.as_ref()?;
+                Some((metadata.difficulty.as_deref(), num_hints, statistics))
+            });
+        match DistributionReport::new(&tier_names, entries).save(&report) {
+            Ok(()) => {
+                if !quiet {
+                    println!("Report saved to {}", report);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error while saving to {}: \"{}\"", report, e);
+                exit(1);
+            }
+        }
+    }
+}
+
+// Generate up to `count` puzzles for `difficulty`, stopping early if one can't be found. With
+// --seed, the puzzles use the seeds after the first `first_index`, so every puzzle in a book gets
+// its own.
+fn generate_puzzles(
+    difficulty: &DifficultyTier,
+    count: usize,
+    first_index: usize,
+    options: &GenerationOptions,
+) -> GeneratedSection {
+    let mut solve_controller = difficulty.solve_controller();
+    // With a single generator thread there are spare cores for exploring guesses in parallel
+    solve_controller.parallel_guesses = options.threads == 1;
+
+    let mut section = GeneratedSection {
+        title: difficulty.name.clone(),
+        ..GeneratedSection::default()
     };
 
-    println!("{}", grid);
-    println!(
-        "Puzzle has {} hints and was found in {} attempts.",
-        num_hints, num_attempts
-    );
-
-    if debug {
-        println!("Solving this puzzle involves roughly:");
-        println!("\t{} SINGLE actions", solve_statistics.singles);
-        println!(
-            "\t{} HIDDEN_SINGLE actions",
-            solve_statistics.hidden_singles
-        );
-        println!(
-            "\t{} USEFUL_CONSTRAINT actions",
-            solve_statistics.useful_constraints
-        );
-        println!(
-            "\t{} POSSIBILITY_GROUP actions",
-            solve_statistics.possibility_groups
-        );
-        println!("\t{} GUESS actions", solve_statistics.guesses);
+    while section.puzzles.len() < count {
+        let progress = Arc::new(Progress::new(!options.quiet, options.threads));
+
+        // Consecutive seeds for each puzzle, so any one of them can be made again on its own
+        let puzzle_seed = match options.seed {
+            Some(seed) => seed.wrapping_add((first_index + section.puzzles.len()) as u64),
+            None => thread_rng().gen(),
+        };
+
+        let (result, reports) = if options.threads == 1 {
+            let (result, report) = get_puzzle_matching_conditions(
+                puzzle_seed,
+                difficulty,
+                &solve_controller,
+                options.settings,
+                &progress,
+                0,
+                options.debug,
+            );
+            (result, vec![report])
+        } else {
+            run_multi_threaded(
+                puzzle_seed,
+                options.settings,
+                options.threads,
+                options.debug,
+                solve_controller,
+                difficulty.clone(),
+                Arc::clone(&progress),
+            )
+        };
+        progress.finish();
+
+        let num_attempts: i32 = reports.iter().map(|report| report.attempts).sum();
+        if !options.quiet {
+            for report in reports.iter() {
+                println!("{}", report);
+            }
+            if reports.len() > 1 {
+                let mut rejections = RejectionCounts::new();
+                for report in reports.iter() {
+                    rejections.add(&report.rejections);
+                }
+                if !rejections.is_empty() {
+                    println!("All threads: rejected for {}", rejections);
+                }
+            }
+        }
+
+        let (grid, solve_statistics, num_hints, puzzle_seed) = match result {
+            Some(x) => x,
+            None => {
+                if options.quiet {
+                    eprintln!("Unable to find a desired puzzle in {} tries.", num_attempts);
+                } else {
+                    println!("Unable to find a desired puzzle in {} tries.", num_attempts);
+                }
+
+                let near_miss = reports
+                    .into_iter()
+                    .filter_map(|report| report.near_miss)
+                    .min_by_key(|near_miss| near_miss.rejections.len());
+                match near_miss {
+                    Some(near_miss) if options.keep_near_miss => {
+                        let missed: Vec<String> = near_miss
+                            .rejections
+                            .iter()
+                            .map(|rejection| rejection.to_string())
+                            .collect();
+                        if !options.quiet {
+                            println!(
+                                "Using the closest candidate instead, which had {}.",
+                                missed.join(", ")
+                            );
+                        }
+                        (
+                            near_miss.grid.to_grid(),
+                            near_miss.statistics,
+                            near_miss.num_hints,
+                            near_miss.seed,
+                        )
+                    }
+                    _ => break,
+                }
+            }
+        };
+
+        if options.quiet {
+            print!(
+                "{}",
+                format_puzzles(std::slice::from_ref(&grid), PuzzleFormat::Line).unwrap()
+            );
+        } else {
+            println!("{}", grid.render(&options.printed_options));
+            println!(
+                "Puzzle has {} hints and was found in {} attempts.",
+                num_hints, num_attempts
+            );
+        }
+
+        if options.debug {
+            print_statistics(&solve_statistics);
+        }
+
+        // Solve a copy that's rebuilt from the digits, since the generated grid's pending updates
+        // have already been used up
+        let mut solution = grid.snapshot().to_grid();
+        sudoku_solver::solver::dlx::solve_grid(&mut solution);
+
+        section.puzzles.push(grid);
+        section.solutions.push(solution);
+        section.metadata.push(PuzzleMetadata::generated(
+            &difficulty.name,
+            puzzle_seed,
+            solve_statistics,
+        ));
     }
 
-    match filename {
-        Some(filename) => {
-            // check if we save to a csv or a pdf
-            if filename.ends_with(".pdf") {
-                sudoku_solver::pdf::draw_grid(&grid, &filename, print_possibilities).unwrap();
-                println!("Grid saved as pdf to {}", filename);
-            } else {
-                save_grid_csv(&grid, &filename).unwrap();
-                println!("Grid saved as CSV to {}", filename);
+    section
+}
+
+fn print_statistics(solve_statistics: &SolveStatistics) {
+    println!("Solving this puzzle involves roughly:");
+    for line in solve_statistics.to_string().lines() {
+        println!("\t{}", line);
+    }
+
+    let grade = Grader::default().grade(solve_statistics);
+    println!("Rated {:.1} ({} stars)", grade.score, grade.stars);
+}
+
+// Files without a recognized extension are saved as CSVs
+fn save_puzzles(
+    grids: &[Grid],
+    metadata: &[PuzzleMetadata],
+    filename: &str,
+    pdf_options: &PdfOptions,
+    description: &str,
+    quiet: bool,
+) {
+    let format = PuzzleFormat::from_filename(filename).unwrap_or(PuzzleFormat::Csv);
+
+    match write_puzzles_with_metadata(grids, metadata, filename, format, pdf_options) {
+        Ok(()) => {
+            if !quiet {
+                println!("{} saved as {:?} to {}", description, format, filename);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error while saving to {}: \"{}\"", filename, e);
+            exit(1);
+        }
+    }
+}
+
+// Books are only laid out in sections as PDFs; any other format holds the puzzles one after
+// another, with each one's difficulty in its metadata
+fn save_book(
+    sections: &[BookSection],
+    grids: &[Grid],
+    metadata: &[PuzzleMetadata],
+    filename: &str,
+    pdf_options: &PdfOptions,
+    description: &str,
+    quiet: bool,
+) {
+    let format = PuzzleFormat::from_filename(filename).unwrap_or(PuzzleFormat::Csv);
+    if format != PuzzleFormat::Pdf {
+        save_puzzles(grids, metadata, filename, pdf_options, description, quiet);
+        return;
+    }
+
+    match sudoku_solver::pdf::draw_book(sections, filename, pdf_options) {
+        Ok(()) => {
+            if !quiet {
+                println!(
+                    "{} saved as a book of {} sections to {}",
+                    description,
+                    sections.len(),
+                    filename
+                );
             }
         }
-        None => {}
+        Err(e) => {
+            eprintln!("Error while saving to {}: \"{}\"", filename, e);
+            exit(1);
+        }
+    }
+}
+
+// The book sections holding either the puzzles or the solutions of each generated section
+fn book_sections(
+    generated: &[GeneratedSection],
+    grids: impl Fn(&GeneratedSection) -> &Vec<Grid>,
+) -> Vec<BookSection> {
+    generated
+        .iter()
+        .map(|section| BookSection {
+            title: section.title.clone(),
+            puzzles: grids(section).clone(),
+            metadata: section.metadata.clone(),
+        })
+        .collect()
+}
+
+// One line per puzzle, holding the puzzle and then its solution as 81 digits with 0 for empty cells
+fn save_pairs(pairs: &[(Grid, Grid)], filename: &str) -> Result<(), Box<dyn Error>> {
+    let mut file = std::fs::File::create(filename)?;
+    for (puzzle, solution) in pairs {
+        let puzzle = format_puzzles(std::slice::from_ref(puzzle), PuzzleFormat::Sdm)?;
+        let solution = format_puzzles(std::slice::from_ref(solution), PuzzleFormat::Sdm)?;
+        writeln!(file, "{},{}", puzzle.trim_end(), solution.trim_end())?;
+    }
+    Ok(())
+}
+
+// Look up the tier named by --difficulty, either from --difficulty-file or the built-in ones
+fn find_difficulty_tier(
+    name: &str,
+    difficulty_file: Option<String>,
+) -> Result<DifficultyTier, String> {
+    match difficulty_file {
+        #[cfg(feature = "config")]
+        Some(filename) => {
+            let tiers = sudoku_solver::difficulty::load_tiers_from_file(&filename)
+                .map_err(|error| format!("Unable to load {}: {}", filename, error))?;
+            tiers
+                .into_iter()
+                .find(|tier| tier.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("{} has no difficulty tier named {}", filename, name))
+        }
+        #[cfg(not(feature = "config"))]
+        Some(_) => unreachable!("--difficulty-file is only available with the config feature"),
+        None => DifficultyTier::built_in(name)
+            .ok_or_else(|| format!("{} is not a valid difficulty", name)),
     }
 }
 
 fn run_multi_threaded(
-    max_attempts: i32,
-    max_hints: i32,
+    seed: u64,
+    settings: SearchSettings,
     threads: i32,
     debug: bool,
     solve_controller: SolveController,
-    difficulty: Difficulty,
-) -> (Option<(Grid, SolveStatistics, i32)>, i32) {
-    let mut thread_rng = thread_rng();
+    difficulty: DifficultyTier,
+    progress: Arc<Progress>,
+) -> (Option<FoundPuzzle>, Vec<ThreadReport>) {
+    // Each thread gets its own seed, drawn from the one for the puzzle
+    let mut seed_rng = SmallRng::seed_from_u64(seed);
     let (transmitter, receiver) = mpsc::channel();
-    let mut remaining_attempts = max_attempts;
-
-    let should_stop = AtomicBool::new(false);
-    let should_stop = Arc::new(should_stop);
+    let mut remaining_attempts = settings.max_attempts;
 
     for i in 0..threads {
         let cloned_transmitter = mpsc::Sender::clone(&transmitter);
-        let mut rng = SmallRng::from_rng(&mut thread_rng).unwrap();
+        let thread_seed: u64 = seed_rng.gen();
         let thread_attempts = remaining_attempts / (threads - i);
         remaining_attempts -= thread_attempts;
-        let should_stop = Arc::clone(&should_stop);
+        let difficulty = difficulty.clone();
+        let progress = Arc::clone(&progress);
 
         thread::spawn(move || {
             if debug {
                 println!("Thread {} spawned with {} max attempts", i, thread_attempts);
             }
 
-            let should_stop = &*should_stop;
-            let (result, num_attempts) = get_puzzle_matching_conditions(
-                &mut rng,
+            let (result, report) = get_puzzle_matching_conditions(
+                thread_seed,
                 &difficulty,
                 &solve_controller,
-                thread_attempts,
-                max_hints,
-                should_stop,
-                debug
+                SearchSettings {
+                    max_attempts: thread_attempts,
+                    ..settings
+                },
+                &progress,
+                i as usize,
+                debug,
             );
 
             let mut result_was_some = false;
             let result = match result {
                 None => None,
-                Some((grid, solve_statistics, num_hints)) => {
+                Some((grid, solve_statistics, num_hints, seed)) => {
                     result_was_some = true;
-                    Some((SafeGridWrapper(grid), solve_statistics, num_hints))
+                    Some((SharedGrid::new(&grid), solve_statistics, num_hints, seed))
                 }
             };
 
-            cloned_transmitter.send((result, num_attempts)).unwrap();
+            let num_attempts = report.attempts;
+            cloned_transmitter.send((result, report)).unwrap();
 
             if debug {
                 println!(
@@ -284,77 +865,81 @@ fn run_multi_threaded(
     }
 
     let mut threads_running = threads;
-    let mut attempt_count = 0;
+    let mut reports = Vec::new();
     let mut result_to_return = None;
 
     while threads_running > 0 {
         let signal = receiver.recv().unwrap(); // Not sure what errors can result here but they are unexpected and deserve a panic
         threads_running -= 1;
 
-        let (result, attempts) = signal;
-        attempt_count += attempts;
+        let (result, report) = signal;
+        reports.push(report);
 
         match result {
-            Some((safe_grid, solve_statistics, num_hints)) => {
-                result_to_return = Some((safe_grid.0, solve_statistics, num_hints));
-                should_stop.store(true, Ordering::Relaxed);
+            Some((shared_grid, solve_statistics, num_hints, seed)) => {
+                result_to_return = Some((shared_grid.to_grid(), solve_statistics, num_hints, seed));
+                progress.should_stop.store(true, Ordering::Relaxed);
             }
             None => {}
         };
     }
 
-    return (result_to_return, attempt_count);
+    reports.sort_by_key(|report| report.thread);
+    (result_to_return, reports)
 }
 
 fn get_puzzle_matching_conditions(
-    rng: &mut SmallRng,
-    difficulty: &Difficulty,
+    seed: u64,
+    difficulty: &DifficultyTier,
     solve_controller: &SolveController,
-    max_attempts: i32,
-    max_hints: i32,
-    should_stop: &AtomicBool,
-    debug: bool
-) -> (Option<(Grid, SolveStatistics, i32)>, i32) {
-    let mut num_attempts = 0;
+    settings: SearchSettings,
+    progress: &Progress,
+    thread: usize,
+    debug: bool,
+) -> (Option<FoundPuzzle>, ThreadReport) {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let mut report = ThreadReport::new(thread);
+    let mut completed_grids = CompletedGridCache::new(settings.digs_per_grid);
 
-    while num_attempts < max_attempts && !should_stop.load(Ordering::Relaxed) {
-        let (grid, num_hints, solve_statistics) =
-            sudoku_solver::generator::generate_grid(rng, &solve_controller);
-        num_attempts += 1;
+    while report.attempts < settings.max_attempts && !progress.should_stop.load(Ordering::Relaxed) {
+        let puzzle = generate_grid_for_tier_from_cache(
+            &mut rng,
+            &mut completed_grids,
+            difficulty,
+            solve_controller,
+            &settings.dig_options,
+        );
+        report.attempts += 1;
+        progress.record_attempt(thread, puzzle.num_hints, &puzzle.statistics);
 
         if debug {
-            println!("Found puzzle with {:#?}", solve_statistics);
+            println!("Found puzzle with {:#?}", puzzle.statistics);
         }
 
-        if difficulty.meets_minimum_requirements(&solve_statistics) && num_hints <= max_hints {
-            return (Some((grid, solve_statistics, num_hints)), num_attempts);
+        if puzzle.meets_criteria() {
+            report.found_after = Some(progress.start.elapsed());
+            return (
+                Some((puzzle.grid, puzzle.statistics, puzzle.num_hints, seed)),
+                report,
+            );
         }
-    }
-
-    return (None, num_attempts);
-}
 
-fn save_grid_csv(grid: &Grid, filename: &str) -> Result<(), Box<dyn Error>> {
-    // Not using the csv crate for writing because it's being difficult and won't accept raw integers
-    let mut file = std::fs::File::create(filename)?;
-
-    for x in 0..9 {
-        for y in 0..9 {
-            let cell = grid.get(x, y).unwrap();
-            let value = &*cell.value.borrow();
-            let digit = match value {
-                CellValue::Fixed(digit) => *digit,
-                CellValue::Unknown(_) => 0,
-            };
-
-            let mut text = digit.to_string();
-            if y < 8 {
-                text.push(',');
-            }
-            file.write(text.as_bytes())?;
+        report.rejections.record(&puzzle.rejections);
+
+        let is_closer = report
+            .near_miss
+            .as_ref()
+            .is_none_or(|near_miss| puzzle.rejections.len() < near_miss.rejections.len());
+        if is_closer {
+            report.near_miss = Some(NearMiss {
+                grid: puzzle.grid.snapshot(),
+                statistics: puzzle.statistics,
+                num_hints: puzzle.num_hints,
+                rejections: puzzle.rejections,
+                seed,
+            });
         }
-        file.write(b"\n")?;
     }
 
-    Ok(())
+    (None, report)
 }
@@ -0,0 +1,165 @@
+use std::error::Error;
+use std::io::Write;
+use std::str::FromStr;
+
+use sudoku_solver::difficulty::{built_in_tiers, DifficultyTier};
+use sudoku_solver::grading::{rate_batch, DistributionReport, Grader, RatedPuzzle};
+use sudoku_solver::io::{format_puzzles, PuzzleFormat};
+use sudoku_solver::solver::{SolveStatistics, SolveStatus, Uniqueness};
+
+fn main() {
+    let mut input = String::new();
+    let mut output: Option<String> = None;
+    let mut from: Option<String> = None;
+    let mut difficulty_file: Option<String> = None;
+    let mut report: Option<String> = None;
+    {
+        // this block limits scope of borrows by ap.refer() method
+        let mut ap = argparse::ArgumentParser::new();
+        ap.set_description(
+            "Rate Sudoku puzzles from another source and place them in the generator's difficulty tiers",
+        );
+
+        ap.refer(&mut input).required().add_option(
+            &["-i", "--input"],
+            argparse::Store,
+            "Path to the puzzles to rate; SDM files can hold any number of them",
+        );
+
+        ap.refer(&mut output).add_option(
+            &["-o", "--output"],
+            argparse::StoreOption,
+            "Path to write the ratings to as CSV; printed if not given",
+        );
+
+        ap.refer(&mut from).add_option(
+            &["--from"],
+            argparse::StoreOption,
+            "Format of the input; guessed from its extension if not given",
+        );
+
+        ap.refer(&mut difficulty_file).add_option(
+            &["--difficulty-file"],
+            argparse::StoreOption,
+            "TOML file of difficulty tiers, from easiest to hardest, to use instead of the built-in ones",
+        );
+
+        ap.refer(&mut report).add_option(
+            &["--report"],
+            argparse::StoreOption,
+            "Path to save a summary of how the puzzles spread across tiers, hint counts and techniques; as JSON if it ends in .json, otherwise as text",
+        );
+
+        ap.parse_args_or_exit();
+    }
+
+    let format = match from {
+        Some(format) => PuzzleFormat::from_str(&format),
+        None => PuzzleFormat::from_filename(&input).ok_or(format!(
+            "Unable to tell the format of {}; use --from",
+            input
+        )),
+    };
+    let format = match format {
+        Ok(format) => format,
+        Err(e) => exit_with_error(&e),
+    };
+
+    let tiers = match load_tiers(difficulty_file) {
+        Ok(tiers) => tiers,
+        Err(e) => exit_with_error(&e),
+    };
+
+    let file = match std::fs::File::open(&input) {
+        Ok(file) => file,
+        Err(e) => exit_with_error(&format!("Error while reading {}: \"{}\"", input, e)),
+    };
+    let rated = match rate_batch(file, format, &Grader::default(), &tiers) {
+        Ok(rated) => rated,
+        Err(e) => exit_with_error(&format!("Error while reading grid: \"{}\"", e)),
+    };
+
+    let result = match &output {
+        Some(output) => std::fs::File::create(output)
+            .map_err(|e| e.into())
+            .and_then(|mut file| write_ratings(&mut file, &rated)),
+        None => write_ratings(&mut std::io::stdout(), &rated),
+    };
+
+    match result {
+        Ok(()) => {
+            if let Some(output) = output {
+                println!("Rated {} puzzle(s) into {}", rated.len(), output);
+
+                let statistics: Vec<SolveStatistics> =
+                    rated.iter().map(|puzzle| puzzle.statistics).collect();
+                println!("{}", SolveStatistics::mean(&statistics));
+            }
+        }
+        Err(e) => exit_with_error(&format!("Error while writing ratings: \"{}\"", e)),
+    }
+
+    if let Some(report) = report {
+        if let Err(e) = DistributionReport::from_rated(&rated, &tiers).save(&report) {
+            exit_with_error(&format!(
+                "Error while saving report to {}: \"{}\"",
+                report, e
+            ));
+        }
+    }
+}
+
+fn load_tiers(difficulty_file: Option<String>) -> Result<Vec<DifficultyTier>, String> {
+    match difficulty_file {
+        #[cfg(feature = "config")]
+        Some(filename) => sudoku_solver::difficulty::load_tiers_from_file(&filename)
+            .map_err(|error| format!("Unable to load {}: {}", filename, error)),
+        #[cfg(not(feature = "config"))]
+        Some(_) => Err("--difficulty-file requires the config feature".to_string()),
+        None => Ok(built_in_tiers()),
+    }
+}
+
+fn write_ratings(writer: &mut dyn Write, rated: &[RatedPuzzle]) -> Result<(), Box<dyn Error>> {
+    writeln!(
+        writer,
+        "puzzle,hints,status,score,stars,tier,singles,hidden_singles,naked_subsets,hidden_subsets,possibility_groups,useful_constraints,guesses"
+    )?;
+
+    for puzzle in rated {
+        let line = format_puzzles(std::slice::from_ref(&puzzle.puzzle), PuzzleFormat::Line)?;
+        let status = match puzzle.status {
+            SolveStatus::Complete(Some(Uniqueness::Unique)) => "unique",
+            SolveStatus::Complete(_) => "not unique",
+            SolveStatus::Unfinished => "unfinished",
+            SolveStatus::Invalid => "invalid",
+            SolveStatus::Aborted(_) => "aborted",
+        };
+        let statistics = &puzzle.statistics;
+
+        writeln!(
+            writer,
+            "{},{},{},{:.1},{},{},{},{},{},{},{},{},{}",
+            line.trim_end(),
+            puzzle.num_hints,
+            status,
+            puzzle.grade.score,
+            puzzle.grade.stars,
+            puzzle.tier.as_deref().unwrap_or(""),
+            statistics.singles,
+            statistics.hidden_singles,
+            statistics.naked_subsets,
+            statistics.hidden_subsets,
+            statistics.possibility_groups,
+            statistics.useful_constraints,
+            statistics.guesses
+        )?;
+    }
+
+    Ok(())
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
@@ -1,92 +1,487 @@
 use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 
-use sudoku_solver::grid::Grid;
-use sudoku_solver::solver::solve_grid;
+use sudoku_solver::difficulty::{built_in_tiers, DifficultyTier};
+use sudoku_solver::grading::{rate_puzzle, Grader};
+use sudoku_solver::grid::{DisplayOptions, Grid};
+use sudoku_solver::io::{load_puzzles, PuzzleFormat, STDIN_PATH};
+use sudoku_solver::pdf::draw_walkthrough;
+use sudoku_solver::solver::{
+    check_uniqueness, find_degeneracy, solution_spread, solve_grid_with_replay,
+    solve_grid_with_solve_controller, EliminationSource, SolutionSpread, SolveController,
+    SolveReplay, SolveStatistics, SolveStatus, Technique, Uniqueness,
+};
+
+// Exit codes, so that scripts can tell how solving went without reading the output. When the file
+// holds several puzzles, the exit code is the one for the worst of them. argparse already uses 2
+// for bad arguments.
+const EXIT_UNIQUE: i32 = 0;
+const EXIT_PARSE_ERROR: i32 = 1;
+const EXIT_NOT_UNIQUE: i32 = 3;
+const EXIT_UNSOLVABLE: i32 = 4;
+const EXIT_SOLVERS_DISAGREE: i32 = 5;
+const EXIT_UNFINISHED: i32 = 6;
+
+// Prints log messages to stderr so they don't get mixed into the grids printed on stdout
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
 
 fn main() {
-    let mut debug = false;
+    let mut verbosity: u8 = 0;
+    let mut verify = false;
+    let mut check_unique = false;
+    let mut count_solutions: Option<usize> = None;
+    let mut quiet = false;
+    let mut watch = false;
+    let mut coordinates = false;
+    let mut walkthrough: Option<String> = None;
     let mut filename = String::new();
+    let mut from: Option<String> = None;
+    let mut trace_grids = 0;
+    let mut techniques: Option<String> = None;
+    #[cfg(feature = "bench")]
+    let mut bench_repetitions: usize = 0;
     {
         // this block limits scope of borrows by ap.refer() method
         let mut ap = argparse::ArgumentParser::new();
-        ap.set_description("Solve Sudoku puzzles");
-        ap.refer(&mut debug)
-            .add_option(&["--debug"], argparse::StoreTrue, "Run in debug mode");
+        ap.set_description(
+            "Solve Sudoku puzzles. Exits with 0 if every puzzle has a unique solution, 1 if the puzzle file can't be read, 3 if a puzzle has more than one solution, 4 if a puzzle has no solution, 5 if --verify finds the two solvers disagree or that tracing a solve changes it, and 6 if the solver stopped before finishing a puzzle",
+        );
+        ap.refer(&mut verbosity).add_option(
+            &["-v", "--verbose"],
+            argparse::IncrBy(1),
+            "Explain what the solver is doing; -v summarizes each solve, -vv adds every strategy that made progress and every guess, and -vvv adds every change to a cell",
+        );
+
+        ap.refer(&mut verify).add_option(
+            &["--verify"],
+            argparse::StoreTrue,
+            "Also solve the puzzle by brute force, and report if the two solutions disagree; then solve it again while recording every step, and report if that changed anything",
+        );
+
+        ap.refer(&mut check_unique).add_option(
+            &["--check-unique"],
+            argparse::StoreTrue,
+            "Only report whether the puzzle has a unique solution, without solving it",
+        );
+
+        ap.refer(&mut count_solutions).add_option(
+            &["--count-solutions"],
+            argparse::StoreOption,
+            "Only count the puzzle's solutions, stopping after N, and list the cells that differ between them",
+        );
+
+        ap.refer(&mut quiet).add_option(
+            &["-q", "--quiet"],
+            argparse::StoreTrue,
+            "Don't print the grids or any other results; only the exit code reports how solving went",
+        );
+
+        ap.refer(&mut coordinates).add_option(
+            &["--coordinates"],
+            argparse::StoreTrue,
+            "Number the rows and columns along the edges of the printed grids",
+        );
+
+        ap.refer(&mut walkthrough).add_option(
+            &["--walkthrough"],
+            argparse::StoreOption,
+            "Write a PDF to this path explaining each step of the solve, one step per page",
+        );
+
+        ap.refer(&mut watch).add_option(
+            &["--watch"],
+            argparse::StoreTrue,
+            "Keep running, and each time the puzzle file is saved report whether its puzzles are valid, unique, and how hard they are",
+        );
 
         ap.refer(&mut filename).required().add_argument(
             "filename",
             argparse::Store,
-            "Path to puzzle CSV file",
+            "Path to the puzzle file, or - to read it from standard input; every puzzle in it is solved",
         );
 
+        ap.refer(&mut from).add_option(
+            &["--from"],
+            argparse::StoreOption,
+            "Format of the puzzle file; guessed from its extension if not given, otherwise CSV",
+        );
+
+        ap.refer(&mut trace_grids).add_option(
+            &["--trace-grids"],
+            argparse::Store,
+            "Print the grid with its candidates after every N strategy runs or guesses that changed it, following only the guesses that led to the solution",
+        );
+
+        ap.refer(&mut techniques).add_option(
+            &["--techniques"],
+            argparse::StoreOption,
+            "Comma separated techniques the solver may use, such as single,hidden_single,guess; every technique if not given",
+        );
+
+        #[cfg(feature = "bench")]
+        ap.refer(&mut bench_repetitions)
+            .add_option(
+                &["--bench"],
+                argparse::StoreConst(1),
+                "Time how long solving the puzzle takes, broken down by solving strategy",
+            )
+            .add_option(
+                &["--benchmark"],
+                argparse::Store,
+                "Like --bench, but solve each puzzle N times from scratch and report the fastest, median and mean times",
+            );
+
         ap.parse_args_or_exit();
     }
 
-    if debug {
-        unsafe {
-            sudoku_solver::grid::DEBUG = true;
-            sudoku_solver::solver::DEBUG = true;
+    log::set_logger(&StderrLogger).unwrap();
+    log::set_max_level(match verbosity {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Info,
+        2 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    });
+
+    let solve_controller = match &techniques {
+        Some(techniques) => match parse_techniques(techniques) {
+            Ok(techniques) => SolveController::with_techniques(&techniques),
+            Err(message) => {
+                eprintln!("{}", message);
+                std::process::exit(EXIT_PARSE_ERROR);
+            }
+        },
+        None => SolveController::all_techniques(),
+    };
+
+    let format = match from {
+        Some(format) => PuzzleFormat::from_str(&format),
+        None => Ok(PuzzleFormat::from_filename(&filename).unwrap_or(PuzzleFormat::Csv)),
+    };
+
+    if watch {
+        match format {
+            Ok(_) if filename == STDIN_PATH => {
+                eprintln!("--watch needs a file to watch, not standard input");
+                std::process::exit(EXIT_PARSE_ERROR);
+            }
+            Ok(format) => watch_file(&filename, format),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(EXIT_PARSE_ERROR);
+            }
         }
     }
 
-    let mut grid = match read_grid(&filename) {
-        Ok(grid) => grid,
+    let loaded = format.and_then(|format| load_puzzles(&filename, format));
+    let grids = match loaded {
+        Ok((grids, warnings)) => {
+            for warning in warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            grids
+        }
         Err(e) => {
-            eprintln!("Error while reading grid: \"{}\"", e);
-            std::process::exit(1);
+            eprintln!("{}", e);
+            std::process::exit(EXIT_PARSE_ERROR);
         }
     };
 
-    println!("Grid to be solved:\n{}", grid);
+    #[cfg(feature = "bench")]
+    {
+        if bench_repetitions > 0 {
+            let repeated_grids: Vec<Grid> = grids
+                .iter()
+                .flat_map(|grid| std::iter::repeat_n(grid, bench_repetitions))
+                .cloned()
+                .collect();
+            let report = sudoku_solver::bench::run_benchmark(&repeated_grids, &solve_controller);
+            println!("{}", report);
+            return;
+        }
+    }
+
+    let num_puzzles = grids.len();
+    if walkthrough.is_some() && num_puzzles > 1 {
+        eprintln!(
+            "--walkthrough can only explain one puzzle, but the file has {}",
+            num_puzzles
+        );
+        std::process::exit(EXIT_PARSE_ERROR);
+    }
+
+    let solve_options = SolveOptions {
+        solve_controller,
+        verify,
+        trace_grids,
+        quiet,
+        display_options: DisplayOptions {
+            coordinates,
+            ..DisplayOptions::default()
+        },
+        walkthrough,
+    };
+
+    let mut exit_code = EXIT_UNIQUE;
+    for (index, mut grid) in grids.into_iter().enumerate() {
+        if num_puzzles > 1 && !quiet {
+            println!("Puzzle {} of {}", index + 1, num_puzzles);
+        }
+
+        let puzzle_exit_code = if check_unique {
+            let uniqueness = check_uniqueness(&grid);
+            if !quiet {
+                match uniqueness {
+                    Some(Uniqueness::Unique) => println!("The puzzle has a unique solution"),
+                    Some(Uniqueness::NotUnique) => match find_degeneracy(&grid) {
+                        Some(degeneracy) => {
+                            println!("The puzzle has more than one solution; {}", degeneracy)
+                        }
+                        None => println!("The puzzle has more than one solution"),
+                    },
+                    None => println!("The puzzle has no solution"),
+                }
+            }
+            match uniqueness {
+                Some(Uniqueness::Unique) => EXIT_UNIQUE,
+                Some(Uniqueness::NotUnique) => EXIT_NOT_UNIQUE,
+                None => EXIT_UNSOLVABLE,
+            }
+        } else if let Some(cap) = count_solutions {
+            let spread = solution_spread(&grid, cap);
+            if !quiet {
+                print_spread(&spread);
+            }
+            match spread.solutions {
+                0 if !spread.capped => EXIT_UNSOLVABLE,
+                1 if !spread.capped => EXIT_UNIQUE,
+                _ => EXIT_NOT_UNIQUE,
+            }
+        } else {
+            exit_code_for(solve_puzzle(&mut grid, &solve_options))
+        };
+
+        // The codes are ordered so that a worse outcome has a larger code
+        exit_code = exit_code.max(puzzle_exit_code);
+    }
 
-    println!("Solving grid");
-    solve_grid(&mut grid);
+    std::process::exit(exit_code);
+}
 
-    println!("Solved grid:\n{}", grid);
+fn exit_code_for(solve_status: SolveStatus) -> i32 {
+    match solve_status {
+        SolveStatus::Complete(Some(Uniqueness::NotUnique)) => EXIT_NOT_UNIQUE,
+        SolveStatus::Complete(_) => EXIT_UNIQUE,
+        SolveStatus::Invalid => EXIT_UNSOLVABLE,
+        SolveStatus::Unfinished | SolveStatus::Aborted(_) => EXIT_UNFINISHED,
+    }
 }
 
-fn read_grid(filename: &str) -> Result<Grid, String> {
-    let reader_result = csv::ReaderBuilder::new()
-        .has_headers(false)
-        .from_path(filename);
+// Re-rate the puzzles in `filename` whenever its modification time changes. Never returns; the
+// user stops it with Ctrl-C.
+fn watch_file(filename: &str, format: PuzzleFormat) -> ! {
+    let tiers = built_in_tiers();
+    let grader = Grader::default();
+    let mut last_modified: Option<SystemTime> = None;
 
-    let mut reader = match reader_result {
-        Ok(reader) => reader,
+    loop {
+        // The file may briefly disappear while an editor saves it, so errors just mean try later
+        let modified = std::fs::metadata(filename).and_then(|metadata| metadata.modified());
+        if let Ok(modified) = modified {
+            if last_modified != Some(modified) {
+                last_modified = Some(modified);
+                report_puzzles(filename, format, &grader, &tiers);
+            }
+        }
+
+        std::thread::sleep(Duration::from_millis(500));
+    }
+}
+
+fn report_puzzles(filename: &str, format: PuzzleFormat, grader: &Grader, tiers: &[DifficultyTier]) {
+    println!("Checking {}", filename);
+    let grids = match load_puzzles(filename, format) {
+        Ok((grids, warnings)) => {
+            for warning in warnings {
+                println!("Warning: {}", warning);
+            }
+            grids
+        }
         Err(e) => {
-            let e = e.to_string();
-            return Err(e);
+            println!("{}", e);
+            return;
         }
     };
 
-    let grid = Grid::new();
-    let mut row = 0;
-    for result in reader.records() {
-        if row > 8 {
-            return Err("Hit row limit".to_string());
-        }
-
-        let record = result.unwrap();
-
-        for column in 0..9 {
-            let value = record.get(column);
-            match value {
-                Some(x) => {
-                    let digit_result = u8::from_str(x);
-                    match digit_result {
-                        Ok(digit) => {
-                            if digit > 0 {
-                                grid.get(row, column).unwrap().set(digit);
-                            }
-                        }
-                        Err(_error) => return Err("Invalid cell value".to_string()),
-                    };
+    let num_puzzles = grids.len();
+    for (index, grid) in grids.into_iter().enumerate() {
+        if num_puzzles > 1 {
+            println!("Puzzle {} of {}", index + 1, num_puzzles);
+        }
+
+        let rated = rate_puzzle(grid, grader, tiers);
+        match rated.status {
+            SolveStatus::Complete(Some(Uniqueness::NotUnique)) => {
+                println!("Valid, but has more than one solution")
+            }
+            SolveStatus::Complete(_) => println!("Valid with a unique solution"),
+            SolveStatus::Unfinished | SolveStatus::Invalid => {
+                println!("Invalid; it has no solution")
+            }
+            SolveStatus::Aborted(reason) => println!("Gave up on solving it; {}", reason),
+        }
+        println!(
+            "{} clues, score {:.1}, {} stars, tier {}",
+            rated.num_hints,
+            rated.grade.score,
+            rated.grade.stars,
+            rated.tier.as_deref().unwrap_or("none")
+        );
+    }
+    println!();
+}
+
+// Print how many solutions --count-solutions found, and how each cell that differs between them
+// is filled in
+fn print_spread(spread: &SolutionSpread) {
+    if spread.capped {
+        println!(
+            "The puzzle has at least {} solutions; only the first {} are compared",
+            spread.solutions, spread.solutions
+        );
+    } else {
+        println!("The puzzle has {} solution(s)", spread.solutions);
+    }
+
+    if !spread.varying_cells.is_empty() {
+        println!("Cells that differ, with how many of the solutions have each digit there:");
+    }
+    for cell in spread.varying_cells.iter() {
+        let digits: Vec<String> = cell
+            .digits
+            .iter()
+            .map(|(digit, count)| format!("{} ({})", digit, count))
+            .collect();
+        println!("r{}c{}: {}", cell.x + 1, cell.y + 1, digits.join(", "));
+    }
+}
+
+// Split --techniques' comma separated list into the techniques it names
+fn parse_techniques(techniques: &str) -> Result<Vec<Technique>, String> {
+    techniques.split(',').map(Technique::from_str).collect()
+}
+
+// How each puzzle is solved, and what's printed about it
+struct SolveOptions {
+    solve_controller: SolveController,
+    verify: bool,
+    trace_grids: usize,
+    quiet: bool,
+    display_options: DisplayOptions,
+    walkthrough: Option<String>,
+}
+
+fn solve_puzzle(grid: &mut Grid, options: &SolveOptions) -> SolveStatus {
+    let quiet = options.quiet;
+    let display_options = &options.display_options;
+
+    if !quiet {
+        println!(
+            "Grid to be solved ({} clues, {} empty cells):\n{}",
+            grid.clue_count(),
+            grid.empty_count(),
+            grid.render(display_options)
+        );
+    }
+
+    if options.verify {
+        if !quiet {
+            println!("Verifying grid");
+        }
+        match sudoku_solver::solver::cross_check(grid) {
+            Ok(status) => {
+                if !quiet {
+                    println!("Both solvers agree; the grid is {:?}", status)
                 }
-                None => {}
+            }
+            Err(disagreement) => {
+                eprintln!("{}", disagreement);
+                std::process::exit(EXIT_SOLVERS_DISAGREE);
+            }
+        }
+        if let Err(irreproducible) =
+            sudoku_solver::solver::check_reproducible(grid, &options.solve_controller)
+        {
+            eprintln!("{}", irreproducible);
+            std::process::exit(EXIT_SOLVERS_DISAGREE);
+        }
+    }
+
+    if !quiet {
+        println!("Solving grid");
+    }
+    let trace = options.trace_grids > 0 && !quiet;
+    let solve_status = if trace || options.walkthrough.is_some() {
+        let (status, _statistics, replay) = solve_grid_with_replay(grid, &options.solve_controller);
+        if trace {
+            print_trace(&replay, options.trace_grids, display_options);
+        }
+        if let Some(filename) = &options.walkthrough {
+            if let Err(e) = draw_walkthrough(&replay, filename, display_options) {
+                eprintln!(
+                    "Error while saving the walkthrough to {}: \"{}\"",
+                    filename, e
+                );
             }
         }
+        status
+    } else {
+        let mut statistics = SolveStatistics::new();
+        solve_grid_with_solve_controller(grid, &options.solve_controller, &mut statistics)
+    };
 
-        row = row + 1;
+    if !quiet {
+        println!("Solved grid:\n{}", grid.render(display_options));
     }
 
-    return Ok(grid);
+    solve_status
+}
+
+// Print every `every`th frame of the replay, along with the last one
+fn print_trace(replay: &SolveReplay, every: usize, display_options: &DisplayOptions) {
+    let frames = replay.frames();
+    for (index, step) in replay.steps.iter().enumerate() {
+        let step_number = index + 1;
+        if step_number % every != 0 && step_number != replay.steps.len() {
+            continue;
+        }
+
+        let source = match step.source {
+            EliminationSource::Section(section_type, index) => {
+                format!("{:?} {}", section_type, index + 1)
+            }
+            EliminationSource::Cell(x, y) => format!("the cell at row {} column {}", x + 1, y + 1),
+        };
+        println!(
+            "After step {} of {} ({} in {}):\n{}",
+            step_number,
+            replay.steps.len(),
+            step.technique,
+            source,
+            frames[step_number].to_grid().render(display_options)
+        );
+    }
 }
@@ -0,0 +1,170 @@
+use rand::prelude::*;
+use std::str::FromStr;
+
+use sudoku_solver::grid::DisplayOptions;
+use sudoku_solver::io::{format_puzzles, load_puzzles, write_puzzles, PuzzleFormat};
+use sudoku_solver::pdf::PdfOptions;
+use sudoku_solver::transform::Transformation;
+
+fn main() {
+    let mut input = String::new();
+    let mut output: Option<String> = None;
+    let mut from: Option<String> = None;
+    let mut shuffle = false;
+    let mut seed: Option<u64> = None;
+    let mut mirror = false;
+    let mut flip = false;
+    let mut transpose = false;
+    {
+        // this block limits scope of borrows by ap.refer() method
+        let mut ap = argparse::ArgumentParser::new();
+        ap.set_description(
+            "Make equivalent variants of Sudoku puzzles by relabelling digits and rearranging or reflecting the grid",
+        );
+
+        ap.refer(&mut input).required().add_argument(
+            "input",
+            argparse::Store,
+            "Path to the puzzle file to read, or - for standard input",
+        );
+
+        ap.refer(&mut output).add_argument(
+            "output",
+            argparse::StoreOption,
+            "Path to write the transformed puzzles to; printed in the input's format if not given",
+        );
+
+        ap.refer(&mut from).add_option(
+            &["--from"],
+            argparse::StoreOption,
+            "Format of the input; guessed from its extension if not given",
+        );
+
+        ap.refer(&mut shuffle).add_option(
+            &["--shuffle"],
+            argparse::StoreTrue,
+            "Randomly relabel the digits and reorder the rows, columns, bands, and stacks",
+        );
+
+        ap.refer(&mut seed).add_option(
+            &["--seed"],
+            argparse::StoreOption,
+            "Seed for --shuffle, so the same variant can be made again",
+        );
+
+        ap.refer(&mut mirror).add_option(
+            &["--mirror"],
+            argparse::StoreTrue,
+            "Reflect the grid left to right",
+        );
+
+        ap.refer(&mut flip).add_option(
+            &["--flip"],
+            argparse::StoreTrue,
+            "Reflect the grid top to bottom",
+        );
+
+        ap.refer(&mut transpose).add_option(
+            &["--transpose"],
+            argparse::StoreTrue,
+            "Swap the rows and columns",
+        );
+
+        ap.parse_args_or_exit();
+    }
+
+    let from = match from {
+        Some(format) => PuzzleFormat::from_str(&format),
+        None => PuzzleFormat::from_filename(&input).ok_or(format!(
+            "Unable to tell the format of {}; use --from",
+            input
+        )),
+    };
+    let from = match from {
+        Ok(format) => format,
+        Err(e) => exit_with_error(&e),
+    };
+    let to = match &output {
+        Some(output) => match PuzzleFormat::from_filename(output) {
+            Some(format) => format,
+            None => exit_with_error(&format!("Unable to tell the format of {}", output)),
+        },
+        None => from,
+    };
+
+    let grids = match load_puzzles(&input, from) {
+        Ok((grids, warnings)) => {
+            for warning in warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            grids
+        }
+        Err(e) => exit_with_error(&e),
+    };
+
+    let mut rng = match seed {
+        Some(seed) => SmallRng::seed_from_u64(seed),
+        None => SmallRng::from_entropy(),
+    };
+
+    let mut transformations = Vec::new();
+    if mirror {
+        transformations.push(Transformation::mirror());
+    }
+    if flip {
+        transformations.push(Transformation::flip());
+    }
+    if transpose {
+        transformations.push(Transformation::transposition());
+    }
+
+    let transformed: Vec<_> = grids
+        .iter()
+        .map(|grid| {
+            // Each puzzle of a file gets its own random variant
+            let mut grid = if shuffle {
+                Transformation::random(&mut rng).apply(grid)
+            } else {
+                grid.snapshot().to_grid()
+            };
+            for transformation in transformations.iter() {
+                grid = transformation.apply(&grid);
+            }
+            grid
+        })
+        .collect();
+
+    let result = match &output {
+        Some(output) => {
+            let pdf_options = PdfOptions {
+                display: DisplayOptions {
+                    show_candidates: false,
+                    ..DisplayOptions::default()
+                },
+                ..PdfOptions::default()
+            };
+            write_puzzles(&transformed, output, to, &pdf_options)
+        }
+        None => format_puzzles(&transformed, to)
+            .map(|text| print!("{}", text))
+            .map_err(|e| e.into()),
+    };
+
+    match result {
+        Ok(()) => {
+            if let Some(output) = output {
+                println!(
+                    "Transformed {} puzzle(s) into {}",
+                    transformed.len(),
+                    output
+                );
+            }
+        }
+        Err(e) => exit_with_error(&format!("Error while writing puzzles: \"{}\"", e)),
+    }
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
@@ -0,0 +1,624 @@
+//! Difficulty tiers for generated puzzles, defined by which solving strategies a puzzle may need
+//! and how often it needs them. With the `config` feature tiers can be loaded from TOML, e.g.
+//!
+//! ```toml
+//! [[tiers]]
+//! name = "Tough"
+//! techniques = ["Single", "HiddenSingle", "PossibilityGroup", "UsefulConstraints"]
+//! max_subset_size = "Triples"
+//! min_hints = 25
+//!
+//! [tiers.minimum]
+//! possibility_groups = 6
+//! useful_constraints = 6
+//!
+//! [tiers.maximum]
+//! useful_constraints = 15
+//! ```
+
+use crate::solver::{
+    HouseOrder, SolveAction, SolveController, SolveLimits, SolveStatistics, SubsetSize,
+    TechniqueCaps,
+};
+
+/// Limits on the `SolveStatistics` of a puzzle; `None` means there's no limit.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StatisticBounds {
+    pub singles: Option<u32>,
+    pub hidden_singles: Option<u32>,
+    pub naked_subsets: Option<u32>,
+    pub hidden_subsets: Option<u32>,
+    pub possibility_groups: Option<u32>,
+    pub useful_constraints: Option<u32>,
+    pub pointing_constraints: Option<u32>,
+    pub claiming_constraints: Option<u32>,
+    pub guesses: Option<u32>,
+}
+
+impl StatisticBounds {
+    // Each bound with the name and value of the statistic it applies to
+    fn bounds(&self, solve_statistics: &SolveStatistics) -> [(&'static str, Option<u32>, u32); 9] {
+        [
+            ("singles", self.singles, solve_statistics.singles),
+            (
+                "hidden_singles",
+                self.hidden_singles,
+                solve_statistics.hidden_singles,
+            ),
+            (
+                "naked_subsets",
+                self.naked_subsets,
+                solve_statistics.naked_subsets,
+            ),
+            (
+                "hidden_subsets",
+                self.hidden_subsets,
+                solve_statistics.hidden_subsets,
+            ),
+            (
+                "possibility_groups",
+                self.possibility_groups,
+                solve_statistics.possibility_groups,
+            ),
+            (
+                "useful_constraints",
+                self.useful_constraints,
+                solve_statistics.useful_constraints,
+            ),
+            (
+                "pointing_constraints",
+                self.pointing_constraints,
+                solve_statistics.pointing_constraints,
+            ),
+            (
+                "claiming_constraints",
+                self.claiming_constraints,
+                solve_statistics.claiming_constraints,
+            ),
+            ("guesses", self.guesses, solve_statistics.guesses),
+        ]
+    }
+}
+
+/// A criterion of a `DifficultyTier` that a puzzle didn't meet. Statistics are named after their
+/// `SolveStatistics` fields.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Rejection {
+    /// The tier's techniques couldn't solve the dug out puzzle, or found it has several solutions,
+    /// without passing any maximum on the way.
+    NotUnique,
+    TooFewHints,
+    TooManyHints,
+    BelowMinimum(&'static str),
+    AboveMaximum(&'static str),
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::NotUnique => write!(f, "no unique solution with the tier's techniques"),
+            Rejection::TooFewHints => write!(f, "too few hints"),
+            Rejection::TooManyHints => write!(f, "too many hints"),
+            Rejection::BelowMinimum(statistic) => write!(f, "too few {}", statistic),
+            Rejection::AboveMaximum(statistic) => write!(f, "too many {}", statistic),
+        }
+    }
+}
+
+/// How often each `Rejection` came up over many generated puzzles, to show which of a tier's
+/// criteria are the hardest to meet.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RejectionCounts {
+    // In the order they were first seen
+    counts: Vec<(Rejection, usize)>,
+}
+
+impl RejectionCounts {
+    pub fn new() -> RejectionCounts {
+        RejectionCounts::default()
+    }
+
+    /// Count each of a rejected puzzle's rejections once.
+    pub fn record(&mut self, rejections: &[Rejection]) {
+        for &rejection in rejections.iter() {
+            match self
+                .counts
+                .iter_mut()
+                .find(|(seen, _count)| *seen == rejection)
+            {
+                Some((_rejection, count)) => *count += 1,
+                None => self.counts.push((rejection, 1)),
+            }
+        }
+    }
+
+    /// Add the counts from `other`, such as those of another thread.
+    pub fn add(&mut self, other: &RejectionCounts) {
+        for &(rejection, count) in other.counts.iter() {
+            match self
+                .counts
+                .iter_mut()
+                .find(|(seen, _count)| *seen == rejection)
+            {
+                Some((_rejection, total)) => *total += count,
+                None => self.counts.push((rejection, count)),
+            }
+        }
+    }
+
+    /// How often `rejection` was recorded.
+    pub fn count(&self, rejection: Rejection) -> usize {
+        self.counts
+            .iter()
+            .find(|(seen, _count)| *seen == rejection)
+            .map_or(0, |&(_rejection, count)| count)
+    }
+
+    /// Each rejection with how often it was recorded, most common first.
+    pub fn most_common(&self) -> Vec<(Rejection, usize)> {
+        let mut counts = self.counts.clone();
+        counts.sort_by(|(_first, a), (_second, b)| b.cmp(a));
+        counts
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+}
+
+impl std::fmt::Display for RejectionCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counts: Vec<String> = self
+            .most_common()
+            .iter()
+            .map(|(rejection, count)| format!("{} x {}", count, rejection))
+            .collect();
+        write!(f, "{}", counts.join(", "))
+    }
+}
+
+/// One difficulty level a puzzle can be generated at.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DifficultyTier {
+    pub name: String,
+
+    /// The solving strategies a puzzle in this tier may require.
+    pub techniques: Vec<SolveAction>,
+
+    /// The largest subsets or possibility groups a puzzle in this tier may require.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_subset_size: SubsetSize,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub minimum: StatisticBounds,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub maximum: StatisticBounds,
+
+    /// The fewest and most hints a puzzle in this tier may have.
+    pub min_hints: Option<i32>,
+    pub max_hints: Option<i32>,
+}
+
+impl DifficultyTier {
+    /// The `SolveController` to generate puzzles for this tier with. Only the allowed techniques
+    /// are enabled, and those with a maximum are capped at it.
+    pub fn solve_controller(&self) -> SolveController {
+        let allowed = |action: SolveAction| self.techniques.contains(&action);
+
+        SolveController {
+            determine_uniqueness: true,
+            search_singles: allowed(SolveAction::Single),
+            search_hidden_singles: allowed(SolveAction::HiddenSingle),
+            search_naked_subsets: allowed(SolveAction::NakedSubset),
+            search_hidden_subsets: allowed(SolveAction::HiddenSubset),
+            find_possibility_groups: allowed(SolveAction::PossibilityGroup),
+            max_subset_size: self.max_subset_size,
+            search_useful_constraint: allowed(SolveAction::UsefulConstraints),
+            search_pointing_constraints: true,
+            search_claiming_constraints: true,
+            make_guesses: allowed(SolveAction::Guess),
+            parallel_guesses: false,
+            technique_caps: TechniqueCaps {
+                singles: self.maximum.singles,
+                hidden_singles: self.maximum.hidden_singles,
+                naked_subsets: self.maximum.naked_subsets,
+                hidden_subsets: self.maximum.hidden_subsets,
+                possibility_groups: self.maximum.possibility_groups,
+                useful_constraints: self.maximum.useful_constraints,
+                guesses: self.maximum.guesses,
+            },
+            house_order: HouseOrder::Changed,
+            limits: SolveLimits::none(),
+            trust_candidates: false,
+            collect_statistics: true,
+        }
+    }
+
+    /// Whether a puzzle with `num_hints` hints, solved with these statistics, is within the
+    /// tier's bounds.
+    pub fn accepts(&self, solve_statistics: &SolveStatistics, num_hints: i32) -> bool {
+        self.rejections(solve_statistics, num_hints).is_empty()
+    }
+
+    /// Whether statistics gathered partway through a solve are already over one of the tier's
+    /// maximums. Solving further only adds to them, so such a puzzle can be given up on early.
+    pub fn exceeds_maximum(&self, solve_statistics: &SolveStatistics) -> bool {
+        self.maximum
+            .bounds(solve_statistics)
+            .iter()
+            .any(|&(_statistic, bound, count)| bound.is_some_and(|bound| count > bound))
+    }
+
+    /// Whether the tier's minimums call for some strategy besides singles and hidden singles, in
+    /// which case a puzzle that those two can solve on their own is sure to fall short.
+    pub fn needs_more_than_singles(&self) -> bool {
+        let minimum = &self.minimum;
+        [
+            minimum.naked_subsets,
+            minimum.hidden_subsets,
+            minimum.possibility_groups,
+            minimum.useful_constraints,
+            minimum.pointing_constraints,
+            minimum.claiming_constraints,
+            minimum.guesses,
+        ]
+        .iter()
+        .any(|bound| bound.is_some_and(|bound| bound > 0))
+    }
+
+    /// Every bound of the tier that a puzzle with `num_hints` hints, solved with these
+    /// statistics, falls outside of.
+    pub fn rejections(&self, solve_statistics: &SolveStatistics, num_hints: i32) -> Vec<Rejection> {
+        let mut rejections = Vec::new();
+
+        if self
+            .min_hints
+            .is_some_and(|min_hints| num_hints < min_hints)
+        {
+            rejections.push(Rejection::TooFewHints);
+        }
+        if self
+            .max_hints
+            .is_some_and(|max_hints| num_hints > max_hints)
+        {
+            rejections.push(Rejection::TooManyHints);
+        }
+
+        for &(statistic, bound, count) in self.minimum.bounds(solve_statistics).iter() {
+            if bound.is_some_and(|bound| count < bound) {
+                rejections.push(Rejection::BelowMinimum(statistic));
+            }
+        }
+        for &(statistic, bound, count) in self.maximum.bounds(solve_statistics).iter() {
+            if bound.is_some_and(|bound| count > bound) {
+                rejections.push(Rejection::AboveMaximum(statistic));
+            }
+        }
+
+        rejections
+    }
+}
+
+// The solving strategies used by every tier above Easy
+const LOGIC: [SolveAction; 2] = [
+    SolveAction::PossibilityGroup,
+    SolveAction::UsefulConstraints,
+];
+const LOGIC_AND_GUESSES: [SolveAction; 3] = [
+    SolveAction::PossibilityGroup,
+    SolveAction::UsefulConstraints,
+    SolveAction::Guess,
+];
+
+// Note that built-in tiers only need minimum requirements because easier difficulities have turned
+// off parts of the solver. For example, Easy cannot have any guesses simply by virtue of not
+// allowing guesses during the generation process.
+fn preset(
+    name: &str,
+    extra_techniques: &[SolveAction],
+    max_subset_size: SubsetSize,
+    minimum: StatisticBounds,
+) -> DifficultyTier {
+    let mut techniques = vec![SolveAction::Single, SolveAction::HiddenSingle];
+    techniques.extend_from_slice(extra_techniques);
+
+    DifficultyTier {
+        name: name.to_string(),
+        techniques,
+        max_subset_size,
+        minimum,
+        maximum: StatisticBounds::default(),
+        min_hints: None,
+        max_hints: None,
+    }
+}
+
+impl DifficultyTier {
+    /// Puzzles that singles and hidden singles alone can solve. There's no minimum.
+    pub fn easy() -> DifficultyTier {
+        preset("Easy", &[], SubsetSize::Triples, StatisticBounds::default())
+    }
+
+    /// Puzzles that need possibility groups and useful constraints, but no quads.
+    pub fn medium() -> DifficultyTier {
+        preset(
+            "Medium",
+            &LOGIC,
+            // Quads are too advanced for anything below Hard
+            SubsetSize::Triples,
+            StatisticBounds {
+                possibility_groups: Some(6),
+                useful_constraints: Some(6),
+                ..StatisticBounds::default()
+            },
+        )
+    }
+
+    /// Like Medium, but with more useful constraints and subsets of any size.
+    pub fn hard() -> DifficultyTier {
+        preset(
+            "Hard",
+            &LOGIC,
+            SubsetSize::Unlimited,
+            StatisticBounds {
+                possibility_groups: Some(6),
+                useful_constraints: Some(11),
+                ..StatisticBounds::default()
+            },
+        )
+    }
+
+    /// Puzzles that need at least one guess on top of plenty of everything else.
+    pub fn challenge() -> DifficultyTier {
+        preset(
+            "Challenge",
+            &LOGIC_AND_GUESSES,
+            SubsetSize::Unlimited,
+            StatisticBounds {
+                possibility_groups: Some(21),
+                useful_constraints: Some(21),
+                guesses: Some(1),
+                ..StatisticBounds::default()
+            },
+        )
+    }
+
+    /// The built-in tier with the given name, ignoring case, if there is one.
+    pub fn built_in(name: &str) -> Option<DifficultyTier> {
+        built_in_tiers()
+            .into_iter()
+            .find(|tier| tier.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// The tiers the generator offers by default, from easiest to hardest: Easy, Medium, Hard, and
+/// Challenge. These are the same ones the command line tools use, so a front end that generates
+/// puzzles with them stays in step with the CLI.
+pub fn built_in_tiers() -> Vec<DifficultyTier> {
+    vec![
+        DifficultyTier::easy(),
+        DifficultyTier::medium(),
+        DifficultyTier::hard(),
+        DifficultyTier::challenge(),
+    ]
+}
+
+/// Split a book's comma separated list of sections, like `EASY:20,MEDIUM:20`, into the name of
+/// each section's difficulty tier and how many puzzles it holds. Whitespace around the names and
+/// counts is ignored. The names aren't checked against any tiers, as they may come from a tiers
+/// file, but a section with no name or no puzzles is an error.
+pub fn parse_book(book: &str) -> Result<Vec<(String, usize)>, String> {
+    book.split(',')
+        .map(|section| {
+            let mut parts = section.trim().splitn(2, ':');
+            let difficulty = parts.next().unwrap_or("").trim();
+            let count = parts
+                .next()
+                .and_then(|count| count.trim().parse().ok())
+                .filter(|&count| count > 0 && !difficulty.is_empty())
+                .ok_or_else(|| {
+                    format!(
+                        "{} is not a valid book section; use DIFFICULTY:COUNT with a COUNT of at least 1",
+                        section.trim()
+                    )
+                })?;
+            Ok((difficulty.to_string(), count))
+        })
+        .collect()
+}
+
+#[cfg(feature = "config")]
+#[derive(serde::Deserialize)]
+struct TierFile {
+    tiers: Vec<DifficultyTier>,
+}
+
+/// Parse the difficulty tiers listed in a TOML document.
+#[cfg(feature = "config")]
+pub fn load_tiers(contents: &str) -> Result<Vec<DifficultyTier>, toml::de::Error> {
+    let file: TierFile = toml::from_str(contents)?;
+    Ok(file.tiers)
+}
+
+/// Read and parse the difficulty tiers in a TOML file.
+#[cfg(feature = "config")]
+pub fn load_tiers_from_file(
+    filename: &str,
+) -> Result<Vec<DifficultyTier>, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(filename)?;
+    Ok(load_tiers(&contents)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::difficulty::*;
+
+    fn tough_tier() -> DifficultyTier {
+        DifficultyTier {
+            name: "Tough".to_string(),
+            techniques: vec![
+                SolveAction::Single,
+                SolveAction::HiddenSingle,
+                SolveAction::PossibilityGroup,
+                SolveAction::UsefulConstraints,
+            ],
+            max_subset_size: SubsetSize::Triples,
+            minimum: StatisticBounds {
+                possibility_groups: Some(6),
+                useful_constraints: Some(6),
+                ..StatisticBounds::default()
+            },
+            maximum: StatisticBounds {
+                useful_constraints: Some(15),
+                ..StatisticBounds::default()
+            },
+            min_hints: Some(25),
+            max_hints: None,
+        }
+    }
+
+    #[test]
+    fn test_tier() {
+        let tier = tough_tier();
+
+        let solve_controller = tier.solve_controller();
+        assert!(solve_controller.search_singles);
+        assert!(solve_controller.find_possibility_groups);
+        assert!(!solve_controller.make_guesses);
+        assert_eq!(solve_controller.technique_caps.useful_constraints, Some(15));
+        assert_eq!(solve_controller.technique_caps.possibility_groups, None);
+        assert_eq!(solve_controller.max_subset_size, SubsetSize::Triples);
+
+        let mut solve_statistics = SolveStatistics::new();
+        solve_statistics.possibility_groups = 6;
+        solve_statistics.useful_constraints = 5;
+        assert!(!tier.accepts(&solve_statistics, 30));
+
+        solve_statistics.useful_constraints = 15;
+        assert!(tier.accepts(&solve_statistics, 30));
+        assert!(!tier.accepts(&solve_statistics, 24));
+
+        solve_statistics.useful_constraints = 16;
+        assert!(!tier.accepts(&solve_statistics, 30));
+        assert_eq!(
+            tier.rejections(&solve_statistics, 24),
+            vec![
+                Rejection::TooFewHints,
+                Rejection::AboveMaximum("useful_constraints")
+            ]
+        );
+        assert_eq!(
+            Rejection::AboveMaximum("useful_constraints").to_string(),
+            "too many useful_constraints"
+        );
+        assert!(tier.exceeds_maximum(&solve_statistics));
+        assert!(!tier.exceeds_maximum(&SolveStatistics::new()));
+
+        assert!(tier.needs_more_than_singles());
+        assert!(!built_in_tiers()[0].needs_more_than_singles());
+    }
+
+    #[test]
+    fn test_rejection_counts() {
+        let mut counts = RejectionCounts::new();
+        assert!(counts.is_empty());
+        counts.record(&[Rejection::TooManyHints]);
+        counts.record(&[Rejection::TooManyHints, Rejection::BelowMinimum("guesses")]);
+        counts.record(&[Rejection::BelowMinimum("guesses")]);
+        counts.record(&[Rejection::BelowMinimum("guesses")]);
+
+        let mut other = RejectionCounts::new();
+        other.record(&[Rejection::NotUnique, Rejection::TooManyHints]);
+        counts.add(&other);
+
+        assert_eq!(counts.count(Rejection::TooManyHints), 3);
+        assert_eq!(counts.count(Rejection::TooFewHints), 0);
+        assert_eq!(
+            counts.to_string(),
+            "3 x too many hints, 3 x too few guesses, 1 x no unique solution with the tier's techniques"
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        let challenge = DifficultyTier::built_in("challenge").unwrap();
+        assert_eq!(challenge.name, DifficultyTier::challenge().name);
+        assert_eq!(challenge.minimum.guesses, Some(1));
+        assert!(challenge.solve_controller().make_guesses);
+        assert!(!DifficultyTier::easy().solve_controller().make_guesses);
+        assert_eq!(
+            DifficultyTier::medium().max_subset_size,
+            SubsetSize::Triples
+        );
+        assert_eq!(
+            DifficultyTier::hard().max_subset_size,
+            SubsetSize::Unlimited
+        );
+        assert!(DifficultyTier::built_in("Impossible").is_none());
+    }
+
+    #[test]
+    fn test_parse_book() {
+        assert_eq!(
+            parse_book("EASY:20,MEDIUM:5").unwrap(),
+            vec![("EASY".to_string(), 20), ("MEDIUM".to_string(), 5)]
+        );
+        assert_eq!(
+            parse_book(" EASY : 20 ,\tHard:1 ").unwrap(),
+            vec![("EASY".to_string(), 20), ("Hard".to_string(), 1)]
+        );
+
+        // An empty section would only be a title page
+        assert!(parse_book("EASY:0").is_err());
+        assert!(parse_book("EASY:20,HARD:0").is_err());
+
+        for &book in [
+            "",
+            "EASY",
+            "EASY:",
+            ":20",
+            "EASY:-1",
+            "EASY:twenty",
+            "EASY:20,",
+        ]
+        .iter()
+        {
+            assert!(parse_book(book).is_err(), "{}", book);
+        }
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_load_tiers() {
+        let contents = r#"
+            [[tiers]]
+            name = "Gentle"
+            techniques = ["Single", "HiddenSingle"]
+
+            [[tiers]]
+            name = "Tough"
+            techniques = ["Single", "HiddenSingle", "PossibilityGroup", "UsefulConstraints"]
+            max_subset_size = "Triples"
+            min_hints = 25
+
+            [tiers.minimum]
+            possibility_groups = 6
+            useful_constraints = 6
+
+            [tiers.maximum]
+            useful_constraints = 15
+        "#;
+
+        let tiers = load_tiers(contents).unwrap();
+        assert_eq!(tiers.len(), 2);
+        assert_eq!(tiers[0].name, "Gentle");
+        assert_eq!(tiers[0].minimum, StatisticBounds::default());
+        assert_eq!(tiers[0].max_subset_size, SubsetSize::Unlimited);
+        assert_eq!(tiers[1], tough_tier());
+
+        assert!(load_tiers("[[tiers]]\nname = \"Missing techniques\"").is_err());
+    }
+}
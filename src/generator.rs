@@ -1,49 +1,19 @@
-use crate::grid::{Cell, CellValue, Grid, Section};
+use crate::difficulty::{DifficultyTier, Rejection};
+use crate::grid::{Cell, CellValue, Coord, Grid, Section};
+use crate::solver::dlx::{self, count_solutions};
 use crate::solver::{
-    evaluate_grid_with_solve_controller, SolveController, SolveStatistics, SolveStatus, Uniqueness,
+    check_uniqueness, count_solutions_at_most_2, evaluate_grid_with_early_exit,
+    evaluate_grid_with_solve_controller, find_degeneracy, sample_solutions, SolveController,
+    SolveStatistics, SolveStatus, Solver, Uniqueness,
 };
+use crate::topology;
 use rand::prelude::*;
 use std::rc::Rc;
-
-pub static mut DEBUG: bool = false;
-
-impl Grid {
-    fn get_random_empty_cell(&self, rng: &mut SmallRng) -> Result<Rc<Cell>, &str> {
-        // Idea - put all empty cells into a vector and choose one at random
-        // If vector is empty we return an error
-
-        let mut empty_cells = Vec::new();
-        for x in 0..9 {
-            for y in 0..9 {
-                let cell = self.get(x, y).unwrap();
-                let add_cell = {
-                    let cell_value = &*cell.value.borrow();
-                    match cell_value {
-                        // May cause issues with borrow rules
-                        CellValue::Fixed(_) => false,
-                        CellValue::Unknown(_) => true,
-                    }
-                };
-                if add_cell {
-                    empty_cells.push(cell);
-                }
-            }
-        }
-
-        match empty_cells.iter().choose(rng) {
-            Some(cell) => Ok(Rc::clone(cell)),
-            None => Err("Unable to find an empty cell"),
-        }
-    }
-}
+use std::str::FromStr;
 
 impl Cell {
     fn delete_value(&self) {
-        unsafe {
-            if DEBUG {
-                println!("Cell {}, {} had its value deleted.", self.x, self.y);
-            }
-        }
+        log::trace!("Cell {}, {} had its value deleted.", self.x, self.y);
 
         self.set_value_exact(CellValue::Unknown(vec![])); // placeholder
 
@@ -135,153 +105,662 @@ pub fn generate_grid(
     rng: &mut SmallRng,
     solve_controller: &SolveController,
 ) -> (Grid, i32, SolveStatistics) {
-    let mut grid = generate_completed_grid(rng);
-    let mut num_hints = 81;
+    generate_grid_with_min_hints(rng, solve_controller, 0)
+}
 
-    // We now trim down cells; first going to put them in a vector and shuffle them
-    let mut non_empty_cells = Vec::new();
-    for x in 0..9 {
-        for y in 0..9 {
-            let cell = grid.get(x, y).unwrap();
-            non_empty_cells.push(Rc::clone(&cell));
+/// A puzzle made by `generate_grid_for_tier`, and which of the tier's criteria it didn't meet.
+pub struct GeneratedPuzzle {
+    pub grid: Grid,
+    pub num_hints: i32,
+    pub statistics: SolveStatistics,
+    pub rejections: Vec<Rejection>,
+}
+
+impl GeneratedPuzzle {
+    /// Whether the puzzle met all of the tier's criteria.
+    pub fn meets_criteria(&self) -> bool {
+        self.rejections.is_empty()
+    }
+}
+
+/// Generate a puzzle for `tier`. Hints stop being removed once the tier's `min_hints` is reached,
+/// and the finished puzzle is checked against the rest of the tier's criteria. `solve_controller`
+/// would usually be `tier.solve_controller()`, possibly with `parallel_guesses` turned on.
+pub fn generate_grid_for_tier(
+    rng: &mut SmallRng,
+    tier: &DifficultyTier,
+    solve_controller: &SolveController,
+) -> GeneratedPuzzle {
+    generate_grid_for_tier_from_cache(
+        rng,
+        &mut CompletedGridCache::new(1),
+        tier,
+        solve_controller,
+        &DigOptions::default(),
+    )
+}
+
+/// Which clues digging tries to remove first; see `DigOptions`.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum DigOrder {
+    /// Every clue is equally likely to be tried at any point.
+    #[default]
+    Random,
+
+    /// Clues nearer the center of the grid tend to be tried first, leaving more around the edges.
+    CenterFirst,
+
+    /// Clues nearer the edges of the grid tend to be tried first, leaving more in the center.
+    EdgesFirst,
+
+    /// Clues whose position has more weight tend to be tried first. A weight of 0 or less means a
+    /// clue is only tried after all the others.
+    Weighted(fn(Coord) -> f64),
+}
+
+impl DigOrder {
+    /// How strongly the clue at `coord` is preferred for removal.
+    pub fn weight(self, coord: Coord) -> f64 {
+        // 0 for the center cell, up to 4 for the outermost ring of cells
+        let ring = |coord: Coord| {
+            let distance = |index: usize| (index as f64 - 4.0).abs();
+            distance(coord.row()).max(distance(coord.column()))
+        };
+
+        match self {
+            DigOrder::Random => 1.0,
+            DigOrder::CenterFirst => 3f64.powf(4.0 - ring(coord)),
+            DigOrder::EdgesFirst => 3f64.powf(ring(coord)),
+            DigOrder::Weighted(weight) => weight(coord),
         }
     }
-    // Need to randomly reorder non_empty_cells
-    non_empty_cells.shuffle(rng);
+}
 
-    let mut statistics_option = None;
+impl FromStr for DigOrder {
+    // Needed for argparse
+    type Err = String;
 
-    for (_index, cell) in non_empty_cells.iter().enumerate() {
-        let mut grid_clone = grid.clone();
-        let cell_clone = grid_clone.get(cell.x, cell.y).unwrap();
-        let cell_clone = &*cell_clone;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "random" => Ok(DigOrder::Random),
+            "center" => Ok(DigOrder::CenterFirst),
+            "edges" => Ok(DigOrder::EdgesFirst),
+            _ => Err(format!("{} is not a valid dig order", s)),
+        }
+    }
+}
 
-        cell_clone.delete_value();
+/// How clues are removed while digging out a puzzle, so that they can be spread out in a pleasing
+/// way instead of leaving some regions empty.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DigOptions {
+    pub order: DigOrder,
 
-        let (status, statistics) =
-            evaluate_grid_with_solve_controller(&mut grid_clone, solve_controller);
-        match status {
-            SolveStatus::Complete(uniqueness) => {
-                let uniqueness = uniqueness.unwrap();
-                match uniqueness {
-                    Uniqueness::Unique => {
-                        num_hints = num_hints - 1;
-                        grid = grid_clone;
-                    }
-                    Uniqueness::NotUnique => continue, // We can't remove this cell; continue onto the next one (note that grid hasn't been modified because of solve_controller)
+    /// A clue is never removed from a row, column, or square that has this many clues or fewer.
+    pub min_clues_per_house: usize,
+
+    pub strategy: DigStrategy,
+}
+
+/// How hard digging tries to remove clues; see `DigOptions`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DigStrategy {
+    /// Try each clue once, in order, and remove it if the puzzle stays unique. This is quick, but
+    /// rarely gets below 24 clues, since removing one clue can stop several others from going.
+    #[default]
+    Greedy,
+
+    /// Also go back and try leaving in clues that were removed, to find a puzzle with fewer clues
+    /// than the greedy pass would. The greedy pass's puzzle is the first one it reaches, and the
+    /// search carries on until it has tried to remove a clue `node_budget` times in all, or
+    /// `min_hints` is reached. A budget smaller than the 81 tries of the greedy pass does worse.
+    Exhaustive { node_budget: usize },
+}
+
+/// Hands out completed grids to dig puzzles from, only making a new one every `digs_per_grid`
+/// times. How hard a puzzle is comes from which clues are dug out rather than from the completed
+/// grid, so digging the same grid several times, in a different order each time, finds acceptable
+/// puzzles for less work.
+pub struct CompletedGridCache {
+    digs_per_grid: usize,
+    grid: Option<Grid>,
+    uses: usize,
+}
+
+impl CompletedGridCache {
+    /// A cache that makes a new grid every `digs_per_grid` times; 1 makes a new one every time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `digs_per_grid` is 0.
+    pub fn new(digs_per_grid: usize) -> CompletedGridCache {
+        assert!(
+            digs_per_grid > 0,
+            "Each completed grid must be dug at least once"
+        );
+        CompletedGridCache {
+            digs_per_grid,
+            grid: None,
+            uses: 0,
+        }
+    }
+
+    /// A copy of the current completed grid, which is replaced first if it's already been handed
+    /// out `digs_per_grid` times.
+    pub fn next_grid(&mut self, rng: &mut SmallRng) -> Grid {
+        if self.grid.is_none() || self.uses == self.digs_per_grid {
+            self.grid = Some(generate_full_grid(rng));
+            self.uses = 0;
+        }
+        self.uses += 1;
+        self.grid.clone().unwrap()
+    }
+}
+
+/// Like `generate_grid_for_tier`, but dig a completed grid from `cache` instead of always making a
+/// new one, and remove clues as `dig_options` says.
+pub fn generate_grid_for_tier_from_cache(
+    rng: &mut SmallRng,
+    cache: &mut CompletedGridCache,
+    tier: &DifficultyTier,
+    solve_controller: &SolveController,
+    dig_options: &DigOptions,
+) -> GeneratedPuzzle {
+    let grid = cache.next_grid(rng);
+    let min_hints = tier.min_hints.unwrap_or(0);
+    let grid = remove_clues(rng, grid, solve_controller, min_hints, dig_options);
+    evaluate_for_tier(grid, tier, solve_controller)
+}
+
+/// Carry on removing clues from `puzzle` for `tier`, such as to turn an Easy puzzle into a Hard
+/// one. Only clues are removed, so the result has the same solution as `puzzle`; a series of
+/// puzzles of increasing difficulty can share one grid this way. `puzzle` must have a unique
+/// solution, and clues that `tier`'s techniques need are kept just like in
+/// `generate_grid_for_tier`.
+pub fn continue_digging_for_tier(
+    rng: &mut SmallRng,
+    puzzle: &Grid,
+    tier: &DifficultyTier,
+    solve_controller: &SolveController,
+) -> GeneratedPuzzle {
+    // Rebuild the puzzle from its clues, since its empty cells' candidates may have been narrowed
+    // down by solving it
+    let grid = puzzle.snapshot().to_grid();
+    let min_hints = tier.min_hints.unwrap_or(0);
+    let grid = remove_clues(
+        rng,
+        grid,
+        solve_controller,
+        min_hints,
+        &DigOptions::default(),
+    );
+    evaluate_for_tier(grid, tier, solve_controller)
+}
+
+/// Dig a puzzle for `tier` out of `solution`, a completed grid chosen by the setter, such as one
+/// that spells out a date. Clues are removed just like in `generate_grid_for_tier`, so the puzzle's
+/// only solution is `solution`, though whether the tier's criteria can be met depends on the grid.
+/// `dig_options` can keep clues where the setter wants them. Returns an error if `solution` isn't a
+/// solved grid.
+pub fn dig(
+    rng: &mut SmallRng,
+    solution: &Grid,
+    tier: &DifficultyTier,
+    solve_controller: &SolveController,
+    dig_options: &DigOptions,
+) -> Result<GeneratedPuzzle, String> {
+    if !solution.is_complete() {
+        return Err(format!(
+            "The solution has {} empty cells",
+            solution.empty_count()
+        ));
+    }
+    if !solution.is_solved() {
+        return Err("The solution repeats a digit in a row, column, or square".to_string());
+    }
+
+    let grid = solution.snapshot().to_grid();
+    let min_hints = tier.min_hints.unwrap_or(0);
+    let grid = remove_clues(rng, grid, solve_controller, min_hints, dig_options);
+    Ok(evaluate_for_tier(grid, tier, solve_controller))
+}
+
+/// Generate a puzzle with exactly `solutions` solutions, for novelty puzzles where the solver has
+/// to find all of them, and return it along with its solutions. Clues are removed as `dig_options`
+/// says for as long as the puzzle has no more than `solutions` solutions, and a completed grid is
+/// only used if that ends on exactly `solutions`; None is returned if none of `max_attempts` grids
+/// do. Counting the solutions gets slow quickly, so `solutions` should be small, like 2 or 3.
+///
+/// # Panics
+///
+/// Panics if `solutions` is 0.
+pub fn generate_grid_with_solutions(
+    rng: &mut SmallRng,
+    solutions: usize,
+    dig_options: &DigOptions,
+    max_attempts: usize,
+) -> Option<(Grid, Vec<Grid>)> {
+    assert!(solutions > 0, "A puzzle needs at least one solution");
+
+    for _attempt in 0..max_attempts {
+        let mut grid = generate_full_grid(rng);
+        let mut count = 1;
+
+        for cell in dig_order(rng, &grid, dig_options) {
+            if fewest_house_clues(&grid, cell.coord()) <= dig_options.min_clues_per_house {
+                continue;
+            }
+
+            let grid_clone = grid.clone();
+            grid_clone.get(cell.x, cell.y).unwrap().delete_value();
+
+            // Removing a clue never takes a solution away, so a count past `solutions` means the
+            // clue has to stay
+            let clone_count = count_solutions(&grid_clone, solutions + 1);
+            if clone_count <= solutions {
+                grid = grid_clone;
+                count = clone_count;
+            }
+        }
+
+        if count == solutions {
+            let found = sample_solutions(&grid, solutions, rng);
+            return Some((grid, found));
+        }
+    }
+
+    None
+}
+
+// Solve a dug out puzzle to see whether `tier` accepts it, giving up as soon as it's clear that it
+// won't. A tier that needs more than singles can turn away a puzzle that singles alone solve
+// without trying anything harder, and the full solve stops once any maximum is passed. The
+// statistics of a puzzle that's turned away early only go as far as the solve did.
+fn evaluate_for_tier(
+    grid: Grid,
+    tier: &DifficultyTier,
+    solve_controller: &SolveController,
+) -> GeneratedPuzzle {
+    let num_hints = grid.clue_count() as i32;
+
+    let singles_only = SolveController {
+        search_naked_subsets: false,
+        search_hidden_subsets: false,
+        find_possibility_groups: false,
+        search_useful_constraint: false,
+        make_guesses: false,
+        ..*solve_controller
+    };
+    let solved_by_singles = if tier.needs_more_than_singles() {
+        match evaluate_grid_with_solve_controller(&grid, &singles_only) {
+            (SolveStatus::Complete(Some(Uniqueness::Unique)), statistics) => Some(statistics),
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    let (status, statistics) = match solved_by_singles {
+        Some(statistics) => (SolveStatus::Complete(Some(Uniqueness::Unique)), statistics),
+        None => evaluate_grid_with_early_exit(&grid, solve_controller, &|statistics| {
+            tier.exceeds_maximum(statistics)
+        }),
+    };
+
+    // A solve given up on early is already rejected for the maximum it passed
+    let mut rejections = Vec::new();
+    let solved = matches!(
+        status,
+        SolveStatus::Complete(None) | SolveStatus::Complete(Some(Uniqueness::Unique))
+    );
+    if !solved && !tier.exceeds_maximum(&statistics) {
+        rejections.push(Rejection::NotUnique);
+    }
+    rejections.extend(tier.rejections(&statistics, num_hints));
+
+    GeneratedPuzzle {
+        rejections,
+        grid,
+        num_hints,
+        statistics,
+    }
+}
+
+fn generate_grid_with_min_hints(
+    rng: &mut SmallRng,
+    solve_controller: &SolveController,
+    min_hints: i32,
+) -> (Grid, i32, SolveStatistics) {
+    let grid = generate_full_grid(rng);
+    let grid = remove_clues(
+        rng,
+        grid,
+        solve_controller,
+        min_hints,
+        &DigOptions::default(),
+    );
+
+    // Only now do we run the full solver, to get the statistics for the puzzle we settled on
+    let (_status, statistics) = evaluate_grid_with_solve_controller(&grid, solve_controller);
+    let num_hints = grid.clue_count() as i32;
+
+    (grid, num_hints, statistics)
+}
+
+// Remove clues from `grid` in a random order, weighted as `dig_options` says, for as long as
+// `solve_controller` can still solve it uniquely, stopping once only `min_hints` are left
+fn remove_clues(
+    rng: &mut SmallRng,
+    mut grid: Grid,
+    solve_controller: &SolveController,
+    min_hints: i32,
+    dig_options: &DigOptions,
+) -> Grid {
+    let non_empty_cells = dig_order(rng, &grid, dig_options);
+    // Only the status of each try matters
+    let mut solver = Solver::new(SolveController {
+        collect_statistics: false,
+        ..*solve_controller
+    });
+
+    match dig_options.strategy {
+        DigStrategy::Greedy => {
+            for cell in non_empty_cells.iter() {
+                if grid.clue_count() as i32 <= min_hints {
+                    break;
+                }
+                if fewest_house_clues(&grid, cell.coord()) <= dig_options.min_clues_per_house {
+                    continue;
+                }
+
+                let grid_clone = grid.clone();
+                grid_clone.get(cell.x, cell.y).unwrap().delete_value();
+                if stays_unique(&grid_clone, &mut solver) {
+                    grid = grid_clone;
                 }
             }
+        }
+        DigStrategy::Exhaustive { node_budget } => {
+            let mut search = ExhaustiveDig {
+                solver,
+                min_hints,
+                min_clues_per_house: dig_options.min_clues_per_house,
+                nodes_left: node_budget,
+                best: grid.clone(),
+            };
+            search.search(&grid, &non_empty_cells);
+            grid = search.best;
+        }
+    }
+
+    // Only clues that leave a unique solution are removed, which a degenerate puzzle can't have.
+    // Checked in debug builds only, as it would otherwise run on every dig.
+    debug_assert!(
+        find_degeneracy(&grid).is_none(),
+        "Digging left a puzzle that can't be unique: {}",
+        find_degeneracy(&grid).unwrap()
+    );
+
+    grid
+}
+
+// Whether `solver` can still solve `grid`, which just had a clue removed, uniquely
+fn stays_unique(grid: &Grid, solver: &mut Solver) -> bool {
+    // When guesses are allowed any puzzle with a single solution is acceptable, so the cheap
+    // solution counter is enough. Otherwise the restricted solve_controller has to be able to
+    // finish the puzzle on its own.
+    let uniqueness = if solver.solve_controller.make_guesses {
+        match count_solutions_at_most_2(grid) {
+            0 => panic!("Removing constraints should not have set the # of solutions to zero"),
+            1 => Uniqueness::Unique,
+            _ => Uniqueness::NotUnique,
+        }
+    } else {
+        let (status, _statistics) = solver.evaluate(grid);
+        match status {
+            SolveStatus::Complete(uniqueness) => uniqueness.unwrap(),
             SolveStatus::Unfinished => {
-                panic!("evaluate_grid_with_solve_controller should never return UNFINISHED")
+                panic!("Solver::evaluate should never return UNFINISHED")
             }
             SolveStatus::Invalid => {
                 panic!("Removing constraints should not have set the # of solutions to zero")
             }
+            // Without a full solve there's no telling whether the clue can go, so it stays
+            SolveStatus::Aborted(_) => Uniqueness::NotUnique,
         }
-        statistics_option = Some(statistics);
-    }
+    };
 
-    return (grid, num_hints, statistics_option.unwrap());
+    uniqueness == Uniqueness::Unique
 }
 
-// We generate a completed grid with no mind for difficulty; afterward generate_puzzle will take out as many fields as it can with regards to the difficulty
-fn generate_completed_grid(rng: &mut SmallRng) -> Grid {
-    let solve_controller = SolveController {
-        determine_uniqueness: true,
-        search_singles: true,
-        search_hidden_singles: true,
-        find_possibility_groups: true,
-        search_useful_constraint: true,
-        make_guesses: true,
-    };
+// A depth first search over which clues to remove, for `DigStrategy::Exhaustive`. Clues are tried
+// in order, and each puzzle only tries the clues after the one that was removed to make it, so no
+// set of clues is tried twice. The first puzzle it reaches is the one the greedy pass would give,
+// after the same number of tries.
+struct ExhaustiveDig {
+    solver: Solver,
+    min_hints: i32,
+    min_clues_per_house: usize,
 
-    let mut grid: Grid = loop {
-        // First step; randomly assign 8 different digits to different empty cells and see if there's a possible solution
-        // We have to ensure that 8 of the digits appear at least once, otherwise the solution can't be unique because you could interchange the two missing digits throughout the puzzle
-        // We do this in a loop so that if we are really unlucky and our guesses stop there from being any solution, we can easily re-run it
-        let grid = Grid::new();
+    // How many more times a clue may be tried
+    nodes_left: usize,
+
+    // The puzzle with the fewest clues found so far
+    best: Grid,
+}
+
+impl ExhaustiveDig {
+    fn search(&mut self, grid: &Grid, candidates: &[Rc<Cell>]) {
+        let clue_count = grid.clue_count();
+        if clue_count < self.best.clue_count() {
+            self.best = grid.clone();
+        }
+        if clue_count as i32 <= self.min_hints {
+            return;
+        }
 
-        let digit_excluded = rng.gen_range(1, 10);
+        for (position, cell) in candidates.iter().enumerate() {
+            // Stop once even removing every clue that's left to try can't beat the best puzzle
+            let could_remove = candidates.len() - position;
+            if self.nodes_left == 0
+                || clue_count.saturating_sub(could_remove) >= self.best.clue_count()
+            {
+                return;
+            }
+            if fewest_house_clues(grid, cell.coord()) <= self.min_clues_per_house {
+                continue;
+            }
 
-        for digit in 1..10 {
-            if digit != digit_excluded {
-                let cell = grid.get_random_empty_cell(rng);
-                cell.unwrap().set(digit);
+            self.nodes_left -= 1;
+            let grid_clone = grid.clone();
+            grid_clone.get(cell.x, cell.y).unwrap().delete_value();
+            if stays_unique(&grid_clone, &mut self.solver) {
+                self.search(&grid_clone, &candidates[position + 1..]);
             }
         }
+    }
+}
 
-        let (status, _statistics) = evaluate_grid_with_solve_controller(&grid, &solve_controller);
-        match status {
-            SolveStatus::Complete(uniqueness) => {
-                let uniqueness = uniqueness.unwrap();
-                match uniqueness {
-                    Uniqueness::Unique => {
-                        eprintln!("Wow! A puzzle with only 8 guesses have been found");
-                        return grid;
-                    }
-                    Uniqueness::NotUnique => {break grid;} // What we expect
-                }
+// The clues of `grid` in the order they should be tried for removal, random but weighted as
+// `dig_options` says
+fn dig_order(rng: &mut SmallRng, grid: &Grid, dig_options: &DigOptions) -> Vec<Rc<Cell>> {
+    // Put the clues in a vector so that they can be shuffled
+    let mut non_empty_cells = Vec::new();
+    for x in 0..9 {
+        for y in 0..9 {
+            let cell = grid.get(x, y).unwrap();
+            if let CellValue::Fixed(_) = cell.get_value_copy() {
+                non_empty_cells.push(Rc::clone(&cell));
             }
-            SolveStatus::Unfinished => {panic!("evaluate_grid_with_solve_controller should never return UNFINISHED if we are making guesses")}
-            SolveStatus::Invalid => {continue;} // unlucky; try again
         }
+    }
+    // Need to randomly reorder non_empty_cells
+    match dig_options.order {
+        DigOrder::Random => non_empty_cells.shuffle(rng),
+        order => {
+            // Each cell draws a random key that tends to be larger the more weight it has, and the
+            // largest keys go first
+            let mut keyed_cells: Vec<(f64, Rc<Cell>)> = non_empty_cells
+                .drain(..)
+                .map(|cell| {
+                    let weight = order.weight(cell.coord()).max(0.0);
+                    (rng.gen::<f64>().powf(1.0 / weight), cell)
+                })
+                .collect();
+            keyed_cells.sort_by(|(first, _), (second, _)| second.total_cmp(first));
+            non_empty_cells = keyed_cells.into_iter().map(|(_key, cell)| cell).collect();
+        }
+    }
+
+    non_empty_cells
+}
+
+// The fewest clues in any of the row, column, and square that `coord` is in
+fn fewest_house_clues(grid: &Grid, coord: Coord) -> usize {
+    topology::houses_of(coord)
+        .iter()
+        .map(|&(section_type, index)| {
+            let section = grid.get_section(section_type, index).unwrap().borrow();
+            section
+                .vec
+                .iter()
+                .filter(|cell| matches!(&*cell.value.borrow(), CellValue::Fixed(_)))
+                .count()
+        })
+        .min()
+        .unwrap()
+}
+
+/// A clue that could be added to a puzzle with several solutions; see `rank_clue_suggestions`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClueSuggestion {
+    pub x: usize,
+    pub y: usize,
+    pub digit: u8,
+
+    /// How many solutions the puzzle would have with the clue added, counted up to
+    /// `SUGGESTION_SOLUTION_LIMIT`.
+    pub solutions: usize,
+}
+
+/// Solutions are only counted up to this many when ranking clue suggestions.
+pub const SUGGESTION_SOLUTION_LIMIT: usize = 1000;
+
+/// For a puzzle with more than one solution, find clues that would rule some of them out, best
+/// first. Two of the solutions are found and every cell where they differ is suggested with the
+/// digit from each, ranked by how many solutions would remain. Returns an empty list if the puzzle
+/// already has a unique solution or has none.
+pub fn rank_clue_suggestions(puzzle: &Grid) -> Vec<ClueSuggestion> {
+    let (first, second) = match find_two_solutions(puzzle) {
+        Some(solutions) => solutions,
+        None => return Vec::new(),
     };
 
-    // Alright, we now have a grid that we can start adding more guesses onto until we find a unique solution
-    grid = 'outer: loop {
-        let cell = grid.get_random_empty_cell(rng).unwrap(); // We unwrap because if somehow we're filled each cell without finding a solution, that's reason for a panic
-        let cell = &*cell;
-        let mut cell_possibilities = cell
-            .get_value_possibilities()
-            .expect("An empty cell has no possibilities");
-
-        // Let's scramble the order
-        cell_possibilities.shuffle(rng);
-
-        for (_index, digit) in cell_possibilities.iter().enumerate() {
-            let mut grid_clone = grid.clone();
-            let cell = &*grid_clone.get(cell.x, cell.y).unwrap();
-
-            cell.set(*digit);
-
-            let (status, _statistics) =
-                evaluate_grid_with_solve_controller(&mut grid_clone, &solve_controller);
-            match status {
-                    SolveStatus::Complete(uniqueness) => {
-                        let uniqueness = uniqueness.unwrap();
-                        match uniqueness {
-                            Uniqueness::Unique => {break 'outer grid_clone;} // We're done!
-                            Uniqueness::NotUnique => {// We need more guesses
-                                grid = grid_clone;
-                                continue 'outer;
-                            }
-                        }
-                    }
-                    SolveStatus::Unfinished => panic!("evaluate_grid_with_solve_controller should never return UNFINISHED if making guesses"),
-                    SolveStatus::Invalid => continue // Try another guess
-                }
+    let mut suggestions = Vec::new();
+    for coord in Coord::all() {
+        let (x, y) = (coord.row(), coord.column());
+        let first_value = first.value_at(x, y).unwrap();
+        let second_value = second.value_at(x, y).unwrap();
+        if first_value == second_value {
+            continue;
         }
 
-        // If we reach this point in the loop, then none of the possibilities for cell provided any solution
-        // Which means something serious happened before in the solving process - reason for panic
-        eprint!(
-            "No valid hints were found for puzzle\n{} at cell ({}, {})",
-            grid, cell.x, cell.y
-        );
-        panic!("Unable to continue as puzzle is invalid");
-    };
+        for value in [first_value, second_value].iter() {
+            if let CellValue::Fixed(digit) = value {
+                let trial = puzzle.snapshot().to_grid();
+                trial.cell(coord).set(*digit);
+
+                suggestions.push(ClueSuggestion {
+                    x,
+                    y,
+                    digit: *digit,
+                    solutions: count_solutions(&trial, SUGGESTION_SOLUTION_LIMIT),
+                });
+            }
+        }
+    }
 
-    crate::solver::solve_grid(&mut grid);
+    suggestions.sort_by_key(|suggestion| (suggestion.solutions, suggestion.x, suggestion.y));
+    suggestions
+}
 
-    return grid;
+/// Find clues that together give `puzzle` a unique solution, by repeatedly adding the best of
+/// `rank_clue_suggestions`. The set is usually small but isn't guaranteed to be the smallest
+/// possible. Returns an empty list if the puzzle is already unique, or None if it has no solution.
+pub fn suggest_clues(puzzle: &Grid) -> Option<Vec<ClueSuggestion>> {
+    let grid = puzzle.snapshot().to_grid();
+    let mut clues = Vec::new();
+
+    loop {
+        match check_uniqueness(&grid) {
+            None => return None,
+            Some(Uniqueness::Unique) => return Some(clues),
+            Some(Uniqueness::NotUnique) => {}
+        }
+
+        // Each clue rules out at least one of the two solutions it was found from, so this ends
+        let best = rank_clue_suggestions(&grid)[0];
+        grid.get(best.x, best.y).unwrap().set(best.digit);
+        clues.push(best);
+    }
+}
+
+// Two different solutions of the puzzle, or None if it doesn't have two
+fn find_two_solutions(puzzle: &Grid) -> Option<(Grid, Grid)> {
+    let mut first = puzzle.snapshot().to_grid();
+    if dlx::solve_grid(&mut first) != SolveStatus::Complete(Some(Uniqueness::NotUnique)) {
+        return None;
+    }
+
+    // Some empty cell has a different digit in another solution; find it by ruling out the first
+    // solution's digit in one cell at a time
+    for coord in Coord::all() {
+        if let CellValue::Fixed(_) = puzzle.value_at(coord.row(), coord.column()).unwrap() {
+            continue;
+        }
+        let digit = match first.value_at(coord.row(), coord.column()).unwrap() {
+            CellValue::Fixed(digit) => digit,
+            CellValue::Unknown(_) => continue,
+        };
+
+        let mut second = puzzle.snapshot().to_grid();
+        if second.cell(coord).eliminate(digit).is_err() {
+            continue;
+        }
+        if let SolveStatus::Complete(_) = dlx::solve_grid(&mut second) {
+            return Some((first, second));
+        }
+    }
+
+    None
+}
+
+/// Generate a random, completely solved grid, such as for teaching, for `transform` to work on, or
+/// as the starting point of another generator. Every valid grid can come up, though not all
+/// equally often.
+pub fn generate_full_grid(rng: &mut SmallRng) -> Grid {
+    // Filling in an empty grid by backtracking, trying each cell's digits in a random order, hardly
+    // ever has to back up, so this is much quicker than placing random clues and solving
+    let mut solutions = crate::solver::sample_solutions(&Grid::new(), 1, rng);
+    solutions
+        .pop()
+        .expect("An empty grid should always have a solution")
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::generator::generate_grid;
+    use crate::difficulty::{DifficultyTier, Rejection, StatisticBounds};
+    use crate::generator::{
+        continue_digging_for_tier, dig, evaluate_for_tier, generate_full_grid, generate_grid,
+        generate_grid_for_tier, generate_grid_for_tier_from_cache, generate_grid_with_solutions,
+        rank_clue_suggestions, remove_clues, suggest_clues, CompletedGridCache, DigOptions,
+        DigOrder, DigStrategy,
+    };
     use crate::grid::*;
+    use crate::solver::dlx::count_solutions;
     use crate::solver::{
-        solve_grid_with_solve_controller, SolveController, SolveStatistics, SolveStatus, Uniqueness,
+        check_uniqueness, solve_grid_with_solve_controller, SolveAction, SolveStatistics,
+        SolveStatus, SubsetSize, Uniqueness,
     };
     use rand::prelude::SmallRng;
     use rand::SeedableRng;
@@ -326,14 +805,7 @@ mod tests {
 
         let status = solve_grid_with_solve_controller(
             &mut grid,
-            &SolveController {
-                determine_uniqueness: true,
-                search_singles: true,
-                search_hidden_singles: true,
-                find_possibility_groups: true,
-                search_useful_constraint: true,
-                make_guesses: true,
-            },
+            &crate::test_utils::full_solve_controller(),
             &mut SolveStatistics::new(),
         );
 
@@ -343,14 +815,7 @@ mod tests {
     // There was a bug where even though mutate_grid was set to false, the end result was still solved
     #[test]
     fn ensure_grid_not_complete() {
-        let solve_controller = SolveController {
-            determine_uniqueness: true,
-            search_singles: true,
-            search_hidden_singles: true,
-            find_possibility_groups: true,
-            search_useful_constraint: true,
-            make_guesses: true,
-        };
+        let solve_controller = crate::test_utils::full_solve_controller();
 
         // Note that the puzzle itself doesn't matter
         let (grid, _num_hints, _statistics) =
@@ -374,4 +839,303 @@ mod tests {
 
         assert!(observed_empty_cell);
     }
+
+    #[test]
+    fn test_generate_full_grid() {
+        let grid = generate_full_grid(&mut SmallRng::seed_from_u64(422));
+        assert!(grid.is_complete());
+        assert_eq!(check_uniqueness(&grid), Some(Uniqueness::Unique));
+    }
+
+    #[test]
+    fn test_generate_grid_for_tier() {
+        let tier = DifficultyTier {
+            name: "Plenty of hints".to_string(),
+            techniques: vec![SolveAction::Single, SolveAction::HiddenSingle],
+            max_subset_size: SubsetSize::Unlimited,
+            minimum: StatisticBounds::default(),
+            maximum: StatisticBounds::default(),
+            min_hints: Some(40),
+            max_hints: None,
+        };
+
+        let puzzle = generate_grid_for_tier(
+            &mut SmallRng::seed_from_u64(380),
+            &tier,
+            &tier.solve_controller(),
+        );
+        assert!(puzzle.meets_criteria());
+        assert_eq!(puzzle.num_hints, 40);
+
+        assert_eq!(puzzle.grid.clue_count(), 40);
+
+        // A puzzle can't have both at least 40 and at most 30 hints
+        let tier = DifficultyTier {
+            max_hints: Some(30),
+            ..tier
+        };
+        let puzzle = generate_grid_for_tier(
+            &mut SmallRng::seed_from_u64(380),
+            &tier,
+            &tier.solve_controller(),
+        );
+        assert_eq!(puzzle.rejections, vec![Rejection::TooManyHints]);
+
+        // Singles alone can't get anywhere on an empty grid
+        let easy = DifficultyTier::easy();
+        let puzzle = evaluate_for_tier(Grid::new(), &easy, &easy.solve_controller());
+        assert_eq!(puzzle.rejections, vec![Rejection::NotUnique]);
+    }
+
+    #[test]
+    fn test_completed_grid_cache() {
+        let mut rng = SmallRng::seed_from_u64(438);
+        let easy = DifficultyTier::easy();
+        let mut cache = CompletedGridCache::new(3);
+
+        let solutions: Vec<[[u8; 9]; 9]> = (0..4)
+            .map(|_| {
+                let puzzle = generate_grid_for_tier_from_cache(
+                    &mut rng,
+                    &mut cache,
+                    &easy,
+                    &easy.solve_controller(),
+                    &DigOptions::default(),
+                );
+                let mut solution = puzzle.grid.clone();
+                crate::solver::solve_grid(&mut solution);
+                solution.to_digits()
+            })
+            .collect();
+
+        // The first three puzzles are dug from the same grid, and the fourth from a new one
+        assert_eq!(solutions[0], solutions[1]);
+        assert_eq!(solutions[0], solutions[2]);
+        assert_ne!(solutions[0], solutions[3]);
+    }
+
+    #[test]
+    fn test_dig() {
+        let mut rng = SmallRng::seed_from_u64(439);
+        let medium = DifficultyTier::medium();
+        let solution = generate_full_grid(&mut rng);
+
+        let controller = medium.solve_controller();
+        let options = DigOptions::default();
+        let puzzle = dig(&mut rng, &solution, &medium, &controller, &options).unwrap();
+        assert!(puzzle.num_hints < 81);
+        assert_eq!(check_uniqueness(&puzzle.grid), Some(Uniqueness::Unique));
+        let mut solved = puzzle.grid.clone();
+        crate::solver::solve_grid(&mut solved);
+        assert_eq!(solved.to_digits(), solution.to_digits());
+
+        let mut digits = solution.to_digits();
+        digits[4][4] = 0;
+        assert!(dig(
+            &mut rng,
+            &Grid::from_digits(digits),
+            &medium,
+            &controller,
+            &options
+        )
+        .is_err());
+        digits[4][4] = digits[4][5];
+        assert!(dig(
+            &mut rng,
+            &Grid::from_digits(digits),
+            &medium,
+            &controller,
+            &options
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_generate_grid_with_solutions() {
+        let mut rng = SmallRng::seed_from_u64(445);
+
+        for &solutions in [2, 3].iter() {
+            let options = DigOptions::default();
+            let (puzzle, found) =
+                generate_grid_with_solutions(&mut rng, solutions, &options, 20).unwrap();
+            assert_eq!(count_solutions(&puzzle, usize::MAX), solutions);
+            assert_eq!(found.len(), solutions);
+            for (index, solution) in found.iter().enumerate() {
+                assert!(solution.is_solved());
+                assert!(found[..index].iter().all(|other| other != solution));
+                for x in 0..9 {
+                    for y in 0..9 {
+                        if let Some(CellValue::Fixed(digit)) = puzzle.value_at(x, y) {
+                            assert_eq!(solution.value_at(x, y), Some(CellValue::Fixed(digit)));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Every clue has to stay for a full grid's one solution, which isn't two
+        let keep_everything = DigOptions {
+            min_clues_per_house: 9,
+            ..DigOptions::default()
+        };
+        assert!(generate_grid_with_solutions(&mut rng, 2, &keep_everything, 1).is_none());
+    }
+
+    #[test]
+    fn test_dig_strategy() {
+        let mut rng = SmallRng::seed_from_u64(446);
+        let controller = DifficultyTier::challenge().solve_controller();
+
+        let mut fewer_clues = 0;
+        for _ in 0..3 {
+            let solution = generate_full_grid(&mut rng);
+            let dig_rng = rng.clone();
+            let dig_with = |strategy| {
+                let options = DigOptions {
+                    strategy,
+                    ..DigOptions::default()
+                };
+                remove_clues(
+                    &mut dig_rng.clone(),
+                    solution.clone(),
+                    &controller,
+                    0,
+                    &options,
+                )
+            };
+
+            let greedy = dig_with(DigStrategy::Greedy);
+            let exhaustive = dig_with(DigStrategy::Exhaustive { node_budget: 2000 });
+            assert_eq!(check_uniqueness(&exhaustive), Some(Uniqueness::Unique));
+            assert!(exhaustive.clue_count() <= greedy.clue_count());
+            if exhaustive.clue_count() < greedy.clue_count() {
+                fewer_clues += 1;
+            }
+
+            // Without any budget nothing is removed
+            let untouched = dig_with(DigStrategy::Exhaustive { node_budget: 0 });
+            assert_eq!(untouched.clue_count(), 81);
+        }
+        assert!(fewer_clues > 0);
+    }
+
+    #[test]
+    fn test_dig_options() {
+        let mut rng = SmallRng::seed_from_u64(440);
+        let hard = DifficultyTier::hard();
+        let controller = hard.solve_controller();
+        let solution = generate_full_grid(&mut rng);
+        let square_clues = |puzzle: &Grid, square: usize| {
+            Coord::all()
+                .filter(|coord| coord.square() == square)
+                .filter(|&coord| matches!(puzzle.cell(coord).get_value_copy(), CellValue::Fixed(_)))
+                .count()
+        };
+
+        let options = DigOptions {
+            min_clues_per_house: 3,
+            ..DigOptions::default()
+        };
+        let puzzle = dig(&mut rng, &solution, &hard, &controller, &options).unwrap();
+        for index in 0..9 {
+            let row = (0..9).filter(|&y| puzzle.grid.candidates_at(index, y).is_none());
+            let column = (0..9).filter(|&x| puzzle.grid.candidates_at(x, index).is_none());
+            assert!(row.count() >= 3);
+            assert!(column.count() >= 3);
+            assert!(square_clues(&puzzle.grid, index) >= 3);
+        }
+
+        // Digging the center first leaves it with fewer clues than the corners
+        let options = DigOptions {
+            order: DigOrder::CenterFirst,
+            ..DigOptions::default()
+        };
+        let puzzle = dig(&mut rng, &solution, &hard, &controller, &options).unwrap();
+        let corners: usize = [0, 2, 6, 8]
+            .iter()
+            .map(|&square| square_clues(&puzzle.grid, square))
+            .sum();
+        assert!(square_clues(&puzzle.grid, 4) * 4 < corners);
+        let center = Coord::new(4, 4).unwrap();
+        let edge = Coord::new(0, 4).unwrap();
+        assert!(DigOrder::EdgesFirst.weight(edge) > DigOrder::EdgesFirst.weight(center));
+        assert_eq!(
+            DigOrder::Random.weight(edge),
+            DigOrder::Random.weight(center)
+        );
+        assert!(matches!("Center".parse(), Ok(DigOrder::CenterFirst)));
+    }
+
+    #[test]
+    fn test_continue_digging_for_tier() {
+        let tiers = crate::difficulty::built_in_tiers();
+        let easy = &tiers[0];
+        let hard = tiers.iter().find(|tier| tier.name == "Hard").unwrap();
+
+        let mut rng = SmallRng::seed_from_u64(420);
+        let easy_puzzle = generate_grid_for_tier(&mut rng, easy, &easy.solve_controller());
+        let hard_puzzle =
+            continue_digging_for_tier(&mut rng, &easy_puzzle.grid, hard, &hard.solve_controller());
+
+        assert!(hard_puzzle.num_hints <= easy_puzzle.num_hints);
+        assert_eq!(
+            check_uniqueness(&hard_puzzle.grid),
+            Some(Uniqueness::Unique)
+        );
+
+        // Every clue left was one of the easy puzzle's, so both share a solution
+        let easy_snapshot = easy_puzzle.grid.snapshot();
+        let hard_snapshot = hard_puzzle.grid.snapshot();
+        for x in 0..9 {
+            for y in 0..9 {
+                if let Some(CellValue::Fixed(digit)) = hard_snapshot.get(x, y) {
+                    assert_eq!(easy_snapshot.get(x, y), Some(&CellValue::Fixed(*digit)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_suggest_clues() {
+        let digits = [
+            [5, 3, 0, 0, 7, 0, 0, 0, 0],
+            [6, 0, 0, 1, 9, 5, 0, 0, 0],
+            [0, 9, 8, 0, 0, 0, 0, 6, 0],
+            [8, 0, 0, 0, 6, 0, 0, 0, 3],
+            [4, 0, 0, 8, 0, 3, 0, 0, 1],
+            [7, 0, 0, 0, 2, 0, 0, 0, 6],
+            [0, 6, 0, 0, 0, 0, 2, 8, 0],
+            [0, 0, 0, 4, 1, 9, 0, 0, 5],
+            [0, 0, 0, 0, 8, 0, 0, 7, 9],
+        ];
+        let unique = Grid::from_digits(digits);
+        assert!(rank_clue_suggestions(&unique).is_empty());
+        assert_eq!(suggest_clues(&unique), Some(Vec::new()));
+
+        // Losing its first two rows leaves the puzzle with many solutions
+        let mut digits = digits;
+        for row in digits.iter_mut().take(2) {
+            *row = [0; 9];
+        }
+        let puzzle = Grid::from_digits(digits);
+
+        let suggestions = rank_clue_suggestions(&puzzle);
+        assert!(!suggestions.is_empty());
+        assert!(suggestions
+            .windows(2)
+            .all(|pair| pair[0].solutions <= pair[1].solutions));
+
+        let clues = suggest_clues(&puzzle).unwrap();
+        assert!(!clues.is_empty());
+        for clue in clues.iter() {
+            puzzle.get(clue.x, clue.y).unwrap().set(clue.digit);
+        }
+        assert_eq!(check_uniqueness(&puzzle), Some(Uniqueness::Unique));
+
+        puzzle
+            .get(0, 0)
+            .unwrap()
+            .set_value_exact(CellValue::Unknown(vec![]));
+        assert_eq!(suggest_clues(&puzzle), None);
+    }
 }
@@ -0,0 +1,572 @@
+//! Converts the strategies a solve relied on into ratings that can be compared with the ones
+//! published elsewhere: a numeric score in the style of Sudoku Explainer, where a puzzle is as
+//! hard as the hardest technique it needs, and a 1 to 5 star rating.
+
+use crate::difficulty::DifficultyTier;
+use crate::grid::Grid;
+use crate::io::{parse_puzzles, PuzzleFormat};
+use crate::solver::{
+    evaluate_grid_with_solve_controller, solve_grid, SolveStatistics, SolveStatus, Uniqueness,
+};
+use std::io::Read;
+
+/// How hard each solving strategy is considered to be on the numeric scale. The defaults are
+/// close to the ratings Sudoku Explainer gives the equivalent techniques.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradingWeights {
+    pub hidden_singles: f64,
+    pub singles: f64,
+    pub pointing_constraints: f64,
+    pub claiming_constraints: f64,
+    pub possibility_groups: f64,
+
+    /// Naked and hidden subsets are weighted by the largest one of each kind that was needed.
+    pub naked_pairs: f64,
+    pub naked_triples: f64,
+    pub naked_quads: f64,
+    pub hidden_pairs: f64,
+    pub hidden_triples: f64,
+    pub hidden_quads: f64,
+    pub guesses: f64,
+
+    /// Added to `guesses` for every level of guessing beyond the first.
+    pub guess_depth: f64,
+
+    /// Added to the score for every round of the puzzle's longest bottleneck beyond the first;
+    /// see `SolveStatistics::bottleneck`.
+    pub bottleneck: f64,
+}
+
+impl Default for GradingWeights {
+    fn default() -> Self {
+        GradingWeights {
+            hidden_singles: 1.5,
+            singles: 2.3,
+            pointing_constraints: 2.6,
+            claiming_constraints: 2.8,
+            possibility_groups: 3.4,
+            naked_pairs: 3.0,
+            naked_triples: 3.6,
+            naked_quads: 5.0,
+            hidden_pairs: 3.4,
+            hidden_triples: 4.0,
+            hidden_quads: 5.4,
+            guesses: 7.0,
+            guess_depth: 0.5,
+            bottleneck: 0.0,
+        }
+    }
+}
+
+/// A puzzle's rating on both scales.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grade {
+    /// The weight of the hardest strategy that was needed, or 0 if nothing was needed.
+    pub score: f64,
+
+    /// From 1 (easiest) to 5 (hardest).
+    pub stars: u8,
+}
+
+/// Rates `SolveStatistics` using configurable weights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grader {
+    pub weights: GradingWeights,
+
+    /// The minimum scores for 2, 3, 4, and 5 stars, in increasing order.
+    pub star_thresholds: [f64; 4],
+}
+
+impl Default for Grader {
+    fn default() -> Self {
+        Grader {
+            weights: GradingWeights::default(),
+            star_thresholds: [2.0, 2.5, 3.0, 6.0],
+        }
+    }
+}
+
+impl Grader {
+    /// Grade a puzzle from the statistics of solving it. Guesses are only counted if the solution
+    /// couldn't be reached without them, so the statistics should come from a solve with guessing
+    /// allowed and every other strategy the puzzle is meant to be solved with.
+    pub fn grade(&self, solve_statistics: &SolveStatistics) -> Grade {
+        let weights = &self.weights;
+        let mut score: f64 = 0.0;
+
+        let used = [
+            (solve_statistics.hidden_singles, weights.hidden_singles),
+            (solve_statistics.singles, weights.singles),
+            (
+                solve_statistics.pointing_constraints,
+                weights.pointing_constraints,
+            ),
+            (
+                solve_statistics.claiming_constraints,
+                weights.claiming_constraints,
+            ),
+            (
+                solve_statistics.possibility_groups,
+                weights.possibility_groups,
+            ),
+        ];
+        for &(count, weight) in used.iter() {
+            if count > 0 {
+                score = score.max(weight);
+            }
+        }
+
+        let subsets = [
+            (
+                solve_statistics.largest_naked_subset,
+                [
+                    weights.naked_pairs,
+                    weights.naked_triples,
+                    weights.naked_quads,
+                ],
+            ),
+            (
+                solve_statistics.largest_hidden_subset,
+                [
+                    weights.hidden_pairs,
+                    weights.hidden_triples,
+                    weights.hidden_quads,
+                ],
+            ),
+        ];
+        for &(largest, weights) in subsets.iter() {
+            if let Some(&weight) = (largest as usize)
+                .checked_sub(2)
+                .and_then(|index| weights.get(index))
+            {
+                score = score.max(weight);
+            }
+        }
+
+        if solve_statistics.solved_with_guesses {
+            let extra_depth = solve_statistics.max_guess_depth.saturating_sub(1);
+            score = score.max(weights.guesses + weights.guess_depth * extra_depth as f64);
+        }
+
+        score += weights.bottleneck * solve_statistics.bottleneck.saturating_sub(1) as f64;
+
+        let stars = 1 + self
+            .star_thresholds
+            .iter()
+            .filter(|&&threshold| score >= threshold)
+            .count() as u8;
+
+        Grade { score, stars }
+    }
+}
+
+/// A puzzle read by `rate_batch`, along with how it solved and rated.
+pub struct RatedPuzzle {
+    pub puzzle: Grid,
+    pub num_hints: i32,
+
+    /// The result of solving the puzzle with every strategy enabled.
+    pub status: SolveStatus,
+    pub statistics: SolveStatistics,
+    pub grade: Grade,
+
+    /// The name of the first tier that accepts the puzzle, if any do.
+    pub tier: Option<String>,
+}
+
+/// Read every puzzle in `reader` and rate each of them. Puzzles are placed in the first of `tiers`
+/// whose techniques solve them uniquely within its bounds, the same test the generator applies,
+/// so `tiers` should be ordered from easiest to hardest.
+pub fn rate_batch<R: Read>(
+    mut reader: R,
+    format: PuzzleFormat,
+    grader: &Grader,
+    tiers: &[DifficultyTier],
+) -> Result<Vec<RatedPuzzle>, String> {
+    let mut contents = String::new();
+    reader
+        .read_to_string(&mut contents)
+        .map_err(|e| e.to_string())?;
+
+    let puzzles = parse_puzzles(&contents, format)?;
+
+    Ok(puzzles
+        .into_iter()
+        .map(|puzzle| rate_puzzle(puzzle, grader, tiers))
+        .collect())
+}
+
+/// Solve and rate a single puzzle, placing it in a tier the same way as `rate_batch`.
+pub fn rate_puzzle(puzzle: Grid, grader: &Grader, tiers: &[DifficultyTier]) -> RatedPuzzle {
+    let num_hints = puzzle.clue_count() as i32;
+
+    let (status, statistics) = solve_grid(&mut puzzle.clone());
+    let grade = grader.grade(&statistics);
+
+    let tier = tiers
+        .iter()
+        .find(|tier| {
+            let (tier_status, tier_statistics) =
+                evaluate_grid_with_solve_controller(&puzzle, &tier.solve_controller());
+            tier_status == SolveStatus::Complete(Some(Uniqueness::Unique))
+                && tier.accepts(&tier_statistics, num_hints)
+        })
+        .map(|tier| tier.name.clone());
+
+    RatedPuzzle {
+        puzzle,
+        num_hints,
+        status,
+        statistics,
+        grade,
+        tier,
+    }
+}
+
+/// How a batch of puzzles is spread across difficulty tiers, hint counts and techniques, to check
+/// a book's difficulty curve before it's printed. `Display` lays it out as text, and with the json
+/// feature `to_json` writes it as JSON.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistributionReport {
+    pub puzzles: usize,
+
+    /// Each tier's name and how many of the puzzles are in it, with the tiers the report was made
+    /// with first, even if they're empty.
+    pub tiers: Vec<(String, usize)>,
+
+    /// How many puzzles weren't in any tier.
+    pub untiered: usize,
+
+    /// Each number of hints and how many puzzles have it, from the fewest hints up.
+    pub hints: Vec<(i32, usize)>,
+
+    /// How much each technique was used, in the order of `rate`'s columns.
+    pub techniques: Vec<TechniqueUsage>,
+}
+
+/// How many times a technique was used across the puzzles of a `DistributionReport`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TechniqueUsage {
+    /// The name of the statistic, like "hidden_singles".
+    pub technique: &'static str,
+
+    /// How many puzzles used it at all.
+    pub used_by: usize,
+
+    /// Percentiles of the number of uses per puzzle, by the nearest rank.
+    pub median: u32,
+    pub p90: u32,
+    pub max: u32,
+}
+
+// The statistics a DistributionReport looks at, under the names `rate` uses for them
+fn technique_counts(statistics: &SolveStatistics) -> [(&'static str, u32); 7] {
+    [
+        ("singles", statistics.singles),
+        ("hidden_singles", statistics.hidden_singles),
+        ("naked_subsets", statistics.naked_subsets),
+        ("hidden_subsets", statistics.hidden_subsets),
+        ("possibility_groups", statistics.possibility_groups),
+        ("useful_constraints", statistics.useful_constraints),
+        ("guesses", statistics.guesses),
+    ]
+}
+
+// The smallest value that at least `percent` of `sorted` is no more than, or 0 if it's empty
+fn percentile(sorted: &[u32], percent: usize) -> u32 {
+    let rank = (sorted.len() * percent).div_ceil(100);
+    sorted.get(rank.saturating_sub(1)).copied().unwrap_or(0)
+}
+
+impl DistributionReport {
+    /// Summarize puzzles given as the name of the tier each one is in, if any, its number of
+    /// hints, and how solving it went. `tier_names` puts tiers in order and lists them even if no
+    /// puzzle is in them; any other tier is added after them as it's first seen.
+    pub fn new<'a, I>(tier_names: &[String], puzzles: I) -> DistributionReport
+    where
+        I: IntoIterator<Item = (Option<&'a str>, i32, &'a SolveStatistics)>,
+    {
+        let mut tiers: Vec<(String, usize)> =
+            tier_names.iter().map(|name| (name.clone(), 0)).collect();
+        let mut untiered = 0;
+        let mut hints = std::collections::BTreeMap::new();
+        let mut counts: Vec<Vec<u32>> = vec![Vec::new(); 7];
+
+        for (tier, num_hints, statistics) in puzzles {
+            match tier {
+                Some(tier) => match tiers.iter_mut().find(|(name, _count)| name == tier) {
+                    Some((_name, count)) => *count += 1,
+                    None => tiers.push((tier.to_string(), 1)),
+                },
+                None => untiered += 1,
+            }
+            *hints.entry(num_hints).or_insert(0) += 1;
+            for (uses, &(_name, count)) in
+                counts.iter_mut().zip(technique_counts(statistics).iter())
+            {
+                uses.push(count);
+            }
+        }
+
+        let names = technique_counts(&SolveStatistics::new());
+        let techniques = names
+            .iter()
+            .zip(counts.iter_mut())
+            .map(|(&(technique, _zero), uses)| {
+                uses.sort_unstable();
+                TechniqueUsage {
+                    technique,
+                    used_by: uses.iter().filter(|&&count| count > 0).count(),
+                    median: percentile(uses, 50),
+                    p90: percentile(uses, 90),
+                    max: percentile(uses, 100),
+                }
+            })
+            .collect();
+
+        DistributionReport {
+            puzzles: counts[0].len(),
+            tiers,
+            untiered,
+            hints: hints.into_iter().collect(),
+            techniques,
+        }
+    }
+
+    /// Summarize the puzzles rated by `rate_batch`, listing `tiers` in order.
+    pub fn from_rated(rated: &[RatedPuzzle], tiers: &[DifficultyTier]) -> DistributionReport {
+        let tier_names: Vec<String> = tiers.iter().map(|tier| tier.name.clone()).collect();
+        DistributionReport::new(
+            &tier_names,
+            rated
+                .iter()
+                .map(|puzzle| (puzzle.tier.as_deref(), puzzle.num_hints, &puzzle.statistics)),
+        )
+    }
+
+    /// Write the report as JSON.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self)
+            .expect("A DistributionReport can always be written as JSON")
+    }
+
+    /// Save the report to `filename`, as JSON if it ends in .json and as text otherwise.
+    pub fn save(&self, filename: &str) -> Result<(), String> {
+        let contents = if filename.to_ascii_lowercase().ends_with(".json") {
+            #[cfg(feature = "json")]
+            {
+                self.to_json()
+            }
+            #[cfg(not(feature = "json"))]
+            return Err("Saving a report as JSON requires the json feature".to_string());
+        } else {
+            self.to_string()
+        };
+        std::fs::write(filename, contents).map_err(|e| e.to_string())
+    }
+}
+
+impl std::fmt::Display for DistributionReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let share = |count: usize| count as f64 * 100.0 / self.puzzles.max(1) as f64;
+
+        writeln!(f, "{} puzzles", self.puzzles)?;
+
+        writeln!(f, "Tiers:")?;
+        for (name, count) in self.tiers.iter() {
+            writeln!(f, "\t{}: {} ({:.0}%)", name, count, share(*count))?;
+        }
+        if self.untiered > 0 {
+            writeln!(
+                f,
+                "\tNo tier: {} ({:.0}%)",
+                self.untiered,
+                share(self.untiered)
+            )?;
+        }
+
+        writeln!(f, "Hints:")?;
+        for (num_hints, count) in self.hints.iter() {
+            writeln!(f, "\t{}: {} {}", num_hints, count, "#".repeat(*count))?;
+        }
+
+        writeln!(
+            f,
+            "Techniques (puzzles using it; median, 90th percentile and most uses):"
+        )?;
+        for usage in self.techniques.iter() {
+            writeln!(
+                f,
+                "\t{}: {}; {}, {}, {}",
+                usage.technique, usage.used_by, usage.median, usage.p90, usage.max
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grading::*;
+
+    #[test]
+    fn test_grade() {
+        let grader = Grader::default();
+
+        let statistics = SolveStatistics::new();
+        assert_eq!(
+            grader.grade(&statistics),
+            Grade {
+                score: 0.0,
+                stars: 1
+            }
+        );
+
+        let mut statistics = SolveStatistics::new();
+        statistics.singles = 30;
+        statistics.hidden_singles = 10;
+        statistics.pointing_constraints = 2;
+        statistics.useful_constraints = 2;
+        let grade = grader.grade(&statistics);
+        assert_eq!(grade.score, 2.6);
+        assert_eq!(grade.stars, 3);
+
+        // Wrong guesses that were backtracked out of don't make a puzzle harder
+        statistics.guesses = 3;
+        assert_eq!(grader.grade(&statistics), grade);
+
+        statistics.solved_with_guesses = true;
+        statistics.max_guess_depth = 3;
+        let grade = grader.grade(&statistics);
+        assert_eq!(grade.score, 8.0);
+        assert_eq!(grade.stars, 5);
+
+        let grader = Grader {
+            weights: GradingWeights {
+                guesses: 4.0,
+                guess_depth: 0.0,
+                ..GradingWeights::default()
+            },
+            star_thresholds: [1.0, 2.0, 3.0, 4.5],
+        };
+        assert_eq!(
+            grader.grade(&statistics),
+            Grade {
+                score: 4.0,
+                stars: 4
+            }
+        );
+
+        // Bottlenecks only count when they're given a weight
+        statistics.bottleneck = 5;
+        assert_eq!(grader.grade(&statistics).score, 4.0);
+        let grader = Grader {
+            weights: GradingWeights {
+                bottleneck: 0.25,
+                ..grader.weights
+            },
+            ..grader
+        };
+        assert_eq!(
+            grader.grade(&statistics),
+            Grade {
+                score: 5.0,
+                stars: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_rate_batch() {
+        let puzzles = "\
+            53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79\n\
+            5................................................................................\n";
+        let tiers = crate::difficulty::built_in_tiers();
+
+        let rated = rate_batch(
+            puzzles.as_bytes(),
+            PuzzleFormat::Sdm,
+            &Grader::default(),
+            &tiers,
+        )
+        .unwrap();
+        assert_eq!(rated.len(), 2);
+
+        assert_eq!(rated[0].num_hints, 30);
+        assert_eq!(
+            rated[0].status,
+            SolveStatus::Complete(Some(Uniqueness::Unique))
+        );
+        assert_eq!(
+            rated[0].grade,
+            Grader::default().grade(&rated[0].statistics)
+        );
+        assert_eq!(rated[0].tier.as_deref(), Some("Easy"));
+
+        // Far too few hints to have a single solution, so no tier will take it
+        assert_eq!(rated[1].num_hints, 1);
+        assert_eq!(
+            rated[1].status,
+            SolveStatus::Complete(Some(Uniqueness::NotUnique))
+        );
+        assert_eq!(rated[1].tier, None);
+
+        assert!(rate_batch(
+            "not a puzzle".as_bytes(),
+            PuzzleFormat::Sdm,
+            &Grader::default(),
+            &tiers
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_distribution_report() {
+        let puzzles = "\
+            53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79\n\
+            53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79\n\
+            5................................................................................\n";
+        let tiers = crate::difficulty::built_in_tiers();
+        let rated = rate_batch(
+            puzzles.as_bytes(),
+            PuzzleFormat::Sdm,
+            &Grader::default(),
+            &tiers,
+        )
+        .unwrap();
+
+        let report = DistributionReport::from_rated(&rated, &tiers);
+        assert_eq!(report.puzzles, 3);
+        assert_eq!(report.tiers.len(), tiers.len());
+        assert_eq!(report.tiers[0], ("Easy".to_string(), 2));
+        assert_eq!(report.tiers[1].1, 0);
+        assert_eq!(report.untiered, 1);
+        assert_eq!(report.hints, vec![(1, 1), (30, 2)]);
+
+        // Only the puzzle with one hint needs guesses
+        let guesses = report
+            .techniques
+            .iter()
+            .find(|usage| usage.technique == "guesses")
+            .unwrap();
+        assert_eq!(guesses.used_by, 1);
+        assert_eq!(guesses.median, 0);
+        assert_eq!(guesses.max, rated[2].statistics.guesses);
+        assert_eq!(guesses.p90, guesses.max);
+
+        let text = report.to_string();
+        assert!(text.starts_with("3 puzzles\n"));
+        assert!(text.contains("\tEasy: 2 (67%)\n"));
+        assert!(text.contains("\tNo tier: 1 (33%)\n"));
+        assert!(text.contains("\t30: 2 ##\n"));
+
+        let other = DistributionReport::new(&[], vec![(Some("Custom"), 25, &rated[0].statistics)]);
+        assert_eq!(other.tiers, vec![("Custom".to_string(), 1)]);
+
+        #[cfg(feature = "json")]
+        assert!(report.to_json().contains("\"untiered\": 1"));
+    }
+}
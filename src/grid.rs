@@ -1,15 +1,89 @@
+use crate::topology;
 use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::Formatter;
 use std::rc::{Rc, Weak};
+use std::sync::Arc;
 
-pub static mut DEBUG: bool = false;
+thread_local! {
+    // Number of possibility Vecs allocated on this thread since the solver last took the count
+    static CANDIDATE_ALLOCATIONS: std::cell::Cell<u32> = const { std::cell::Cell::new(0) };
+}
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) fn count_candidate_allocation() {
+    CANDIDATE_ALLOCATIONS.with(|count| count.set(count.get().wrapping_add(1)));
+}
+
+/// Returns how many possibility `Vec`s have been allocated on this thread since the last call,
+/// and resets the count to zero.
+pub(crate) fn take_candidate_allocations() -> u32 {
+    CANDIDATE_ALLOCATIONS.with(|count| count.replace(0))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum CellValue {
     Fixed(u8),
     Unknown(Vec<u8>),
 }
 
+/// A change that was refused because it would leave a `Grid` without a solution.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SudokuError {
+    /// Only the digits 1 to 9 can be placed.
+    InvalidDigit(u8),
+    /// The digit is already `Fixed` in the `Cell` at `peer`, in the same row, column, or square.
+    ConflictsWithFixed { digit: u8, peer: Coord },
+    /// The digit is the only possibility left for the `Cell` at `peer`, in the same row, column,
+    /// or square, so placing it would leave that `Cell` with none.
+    EmptiesCell { digit: u8, peer: Coord },
+    /// The digit is the last possibility for the `Cell` at `cell`, or the digit it's `Fixed` to, so
+    /// it can't be removed.
+    LastCandidate { digit: u8, cell: Coord },
+}
+
+impl std::fmt::Display for SudokuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SudokuError::InvalidDigit(digit) => write!(f, "{} is not a digit from 1 to 9", digit),
+            SudokuError::ConflictsWithFixed { digit, peer } => write!(
+                f,
+                "{} is already placed at ({}, {})",
+                digit,
+                peer.row(),
+                peer.column()
+            ),
+            SudokuError::EmptiesCell { digit, peer } => write!(
+                f,
+                "{} is the only possibility left at ({}, {})",
+                digit,
+                peer.row(),
+                peer.column()
+            ),
+            SudokuError::LastCandidate { digit, cell } => write!(
+                f,
+                "{} is the last possibility at ({}, {})",
+                digit,
+                cell.row(),
+                cell.column()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SudokuError {}
+
+/// What `Cell::eliminate` did to the `Cell`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EliminationOutcome {
+    /// The digit wasn't one of the `Cell`'s possibilities, so nothing changed.
+    Unchanged,
+    /// The digit was removed and more than one possibility is left.
+    Removed,
+    /// The digit was removed and only this digit is left; the `Cell` isn't `Fixed` to it yet.
+    BecameSingle(u8),
+}
+
 /// A representation of a single cell in a Sudoku grid. Don't make this directly; make a Grid.
 pub struct Cell {
     pub x: usize,
@@ -43,13 +117,40 @@ impl Cell {
     ///
     /// ```
     pub fn set(&self, digit: u8) {
-        unsafe {
-            if DEBUG {
-                println!("Cell {}, {} was set with digit {}", self.x, self.y, digit);
-            }
-        }
+        self.set_and_visit_changes(digit, &mut |_cell| {});
+    }
+
+    /// Like `set`, but returns the positions of the other `Cell`s whose possibilities changed,
+    /// which are those in the same row, column, or square that had `digit` as a possibility. Each
+    /// is listed once, with those in the row first, then the column, then the square.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sudoku_solver::grid::{Coord, Grid};
+    /// let grid = Grid::new();
+    /// grid.get(0,1).unwrap().set(1);
+    ///
+    /// let changed = grid.get(0,0).unwrap().set_with_changes(2);
+    ///
+    /// // Every peer but the one that's already fixed
+    /// assert_eq!(changed.len(), 19);
+    /// assert!(!changed.contains(&Coord::new(0,1).unwrap()));
+    /// assert_eq!(grid.get(0,0).unwrap().set_with_changes(2), vec![]);
+    /// ```
+    pub fn set_with_changes(&self, digit: u8) -> Vec<Coord> {
+        let mut changed = Vec::new();
+        self.set_and_visit_changes(digit, &mut |cell| changed.push(cell.coord()));
+        changed
+    }
+
+    fn set_and_visit_changes(&self, digit: u8, on_change: &mut dyn FnMut(&Cell)) {
+        log::trace!("Cell {}, {} was set with digit {}", self.x, self.y, digit);
 
         self.value.replace(CellValue::Fixed(digit));
+        // This cell lost its other possibilities, which can matter to its Sections even if none
+        // of its peers had digit
+        self.mark_updates();
 
         // We fully expect our row, column, and section to still be here even though the Rust compiler won't guarantee it
         // Panic-ing if they're not present is perfectly reasonable
@@ -63,14 +164,132 @@ impl Cell {
         let section = &*self.section.upgrade().unwrap();
         let section = &*section.borrow();
 
-        Cell::process_possibilities(row, digit);
-        Cell::process_possibilities(column, digit);
-        Cell::process_possibilities(section, digit);
+        Cell::process_possibilities(row, digit, on_change);
+        Cell::process_possibilities(column, digit, on_change);
+        Cell::process_possibilities(section, digit, on_change);
+    }
+
+    /// The `Cell`'s position in its `Grid`.
+    pub fn coord(&self) -> Coord {
+        Coord::new(self.x, self.y).unwrap()
+    }
+
+    /// Like `set`, but first checks that the digit doesn't contradict the `Cell`s in the same row,
+    /// column, or square; if it does, nothing is changed and the first `Cell` it contradicts is
+    /// reported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sudoku_solver::grid::{Coord, Grid, SudokuError};
+    /// let grid = Grid::new();
+    ///
+    /// grid.get(0,0).unwrap().try_set(1).unwrap();
+    ///
+    /// assert_eq!(
+    ///     grid.get(0,5).unwrap().try_set(1),
+    ///     Err(SudokuError::ConflictsWithFixed { digit: 1, peer: Coord::new(0,0).unwrap() })
+    /// );
+    /// ```
+    pub fn try_set(&self, digit: u8) -> Result<(), SudokuError> {
+        if digit == 0 || digit > 9 {
+            return Err(SudokuError::InvalidDigit(digit));
+        }
+
+        for section in [&self.row, &self.column, &self.section].iter() {
+            let section = &*section.upgrade().unwrap();
+            let section = &*section.borrow();
+
+            for peer in section.vec.iter() {
+                if std::ptr::eq(&**peer, self) {
+                    continue;
+                }
+
+                match &*peer.value.borrow() {
+                    CellValue::Fixed(fixed) if *fixed == digit => {
+                        return Err(SudokuError::ConflictsWithFixed {
+                            digit,
+                            peer: peer.coord(),
+                        });
+                    }
+                    CellValue::Unknown(possibilities) if possibilities.as_slice() == [digit] => {
+                        return Err(SudokuError::EmptiesCell {
+                            digit,
+                            peer: peer.coord(),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.set(digit);
+        Ok(())
+    }
+
+    /// Remove a digit from the `Cell`'s possibilities, as when crossing out a pencil mark. The
+    /// `Cell`'s `Section`s are marked for the solver if anything changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sudoku_solver::grid::{CellValue, EliminationOutcome, Grid};
+    /// let grid = Grid::new();
+    /// let cell = grid.get(0,0).unwrap();
+    /// cell.set_value_exact(CellValue::Unknown(vec![1,2]));
+    ///
+    /// assert_eq!(cell.eliminate(3), Ok(EliminationOutcome::Unchanged));
+    /// assert_eq!(cell.eliminate(1), Ok(EliminationOutcome::BecameSingle(2)));
+    /// assert!(cell.eliminate(2).is_err());
+    /// ```
+    pub fn eliminate(&self, digit: u8) -> Result<EliminationOutcome, SudokuError> {
+        if digit == 0 || digit > 9 {
+            return Err(SudokuError::InvalidDigit(digit));
+        }
+
+        let new_possibilities = match &*self.value.borrow() {
+            CellValue::Fixed(fixed) if *fixed == digit => None,
+            CellValue::Fixed(_) => return Ok(EliminationOutcome::Unchanged),
+            CellValue::Unknown(possibilities) => {
+                if !possibilities.contains(&digit) {
+                    return Ok(EliminationOutcome::Unchanged);
+                }
+                count_candidate_allocation();
+                Some(
+                    possibilities
+                        .iter()
+                        .copied()
+                        .filter(|&possibility| possibility != digit)
+                        .collect::<Vec<u8>>(),
+                )
+            }
+        };
+
+        let new_possibilities = match new_possibilities {
+            Some(possibilities) if !possibilities.is_empty() => possibilities,
+            _ => {
+                return Err(SudokuError::LastCandidate {
+                    digit,
+                    cell: self.coord(),
+                })
+            }
+        };
+
+        let outcome = match new_possibilities.as_slice() {
+            [single] => EliminationOutcome::BecameSingle(*single),
+            _ => EliminationOutcome::Removed,
+        };
+        self.set_value_exact(CellValue::Unknown(new_possibilities));
+
+        Ok(outcome)
     }
 
     /// Get a copy of the `CellValue`
     pub fn get_value_copy(&self) -> CellValue {
         let value = &*self.value.borrow();
+        if let CellValue::Unknown(_) = value {
+            count_candidate_allocation();
+        }
         return value.clone();
     }
 
@@ -109,14 +328,12 @@ impl Cell {
     ///
     /// ```
     pub fn set_value_exact(&self, value: CellValue) {
-        unsafe {
-            if DEBUG {
-                println!(
-                    "Cell {}, {} was set with CellValue exact {:?}",
-                    self.x, self.y, value
-                );
-            }
-        }
+        log::trace!(
+            "Cell {}, {} was set with CellValue exact {:?}",
+            self.x,
+            self.y,
+            value
+        );
 
         self.value.replace(value);
         self.mark_updates();
@@ -127,7 +344,10 @@ impl Cell {
         let value = &*self.value.borrow();
         match value {
             CellValue::Fixed(_) => None,
-            CellValue::Unknown(x) => Some(x.clone()),
+            CellValue::Unknown(x) => {
+                count_candidate_allocation();
+                Some(x.clone())
+            }
         }
     }
 
@@ -137,50 +357,42 @@ impl Cell {
         {
             let row = &*self.row.upgrade().unwrap();
             let row = &*row.borrow();
-            row.do_update.replace(true);
+            row.mark_for_update();
         }
         {
             let column = &*self.column.upgrade().unwrap();
             let column = &*column.borrow();
-            column.do_update.replace(true);
+            column.mark_for_update();
         }
         {
             let section = &*self.section.upgrade().unwrap();
             let section = &*section.borrow();
-            section.do_update.replace(true);
+            section.mark_for_update();
         }
     }
 
     // Go through and remove digit from the Section's Cells' possibilities
-    fn process_possibilities(line: &Section, digit: u8) {
+    fn process_possibilities(line: &Section, digit: u8, on_change: &mut dyn FnMut(&Cell)) {
         for (_index, cell) in line.vec.iter().enumerate() {
             let cell = &**cell;
 
-            // Find the new CellValue to set; may be None if the cell was already fixed or had no possibilities remaining
+            // Find the new CellValue to set; may be None if the cell was already fixed or never had
+            // digit as a possibility. Leaving those cells alone means only the Sections of cells
+            // that actually changed get queued up for the solver.
             let new_value_option: Option<CellValue> = {
                 let value = &*cell.value.borrow();
 
                 match value {
                     CellValue::Unknown(possibilities) => {
-                        let mut new_possibilities = possibilities.clone();
-
-                        match new_possibilities.binary_search(&digit) {
+                        match possibilities.binary_search(&digit) {
                             Ok(index_remove) => {
+                                count_candidate_allocation();
+                                let mut new_possibilities = possibilities.clone();
                                 new_possibilities.remove(index_remove);
+                                Some(CellValue::Unknown(new_possibilities))
                             }
-                            _ => {}
-                        };
-
-                        Some(CellValue::Unknown(new_possibilities))
-                        /*
-                        if new_possibilities.len() == 1 {
-                            let remaining_digit = new_possibilities.first().unwrap().clone();
-                            Some(CellValue::FIXED(remaining_digit))
-                        } else if new_possibilities.len() == 0 {
-                            None
-                        } else {
-                            Some(CellValue::UNKNOWN(new_possibilities))
-                        }*/
+                            Err(_) => None,
+                        }
                     }
                     CellValue::Fixed(_) => None,
                 }
@@ -189,6 +401,7 @@ impl Cell {
             match new_value_option {
                 Some(new_value) => {
                     cell.set_value(new_value);
+                    on_change(cell);
                 }
                 None => {}
             }
@@ -202,12 +415,15 @@ pub struct Section {
     /// Sections needs to have ownership of the Cells but then the others have to have a different
     /// signature.
     pub vec: Vec<Rc<Cell>>,
+    /// Whether this `Section` is waiting in its `Grid`'s queue for the solver to work on it.
     pub do_update: RefCell<bool>,
     pub index: usize,
     pub section_type: SectionType,
+    update_queue: Weak<RefCell<UpdateQueue>>,
 }
 
-#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum SectionType {
     Row,
     Column,
@@ -224,12 +440,29 @@ impl Section {
         self.vec.get(index)
     }
 
-    fn new(index: usize, line_type: SectionType) -> Section {
+    fn new(
+        index: usize,
+        line_type: SectionType,
+        update_queue: Weak<RefCell<UpdateQueue>>,
+    ) -> Section {
         Section {
             vec: Vec::new(),
             do_update: RefCell::new(false),
             index,
             section_type: line_type,
+            update_queue,
+        }
+    }
+
+    // Mark this Section for the solver to work on, adding it to the Grid's queue if it isn't
+    // already waiting there
+    fn mark_for_update(&self) {
+        if !self.do_update.replace(true) {
+            if let Some(update_queue) = self.update_queue.upgrade() {
+                update_queue
+                    .borrow_mut()
+                    .push_back((self.section_type, self.index));
+            }
         }
     }
 
@@ -244,11 +477,103 @@ impl Section {
 
 type MultiMut<T> = Rc<RefCell<T>>;
 
+// Sections that have changed since the solver last looked at them, in the order they changed
+type UpdateQueue = VecDeque<(SectionType, usize)>;
+
+/// The position of a `Cell` in a `Grid`. A `Coord` can only be made for a position on the grid,
+/// so looking a `Cell` up with one, through `Grid::cell`, can't fail. Its parts are read through
+/// named methods, so the row and column can't be swapped by accident after it's made.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Coord {
+    row: u8,
+    column: u8,
+}
+
+impl Coord {
+    /// Returns None if `row` or `column` is 9 or more.
+    pub fn new(row: usize, column: usize) -> Option<Coord> {
+        if row >= 9 || column >= 9 {
+            return None;
+        }
+        Some(Coord {
+            row: row as u8,
+            column: column as u8,
+        })
+    }
+
+    /// The position of the `index`th `Cell` when read row by row, or None if `index` is 81 or more.
+    pub fn from_index(index: usize) -> Option<Coord> {
+        if index >= 81 {
+            return None;
+        }
+        Coord::new(index / 9, index % 9)
+    }
+
+    /// Every position on the grid, row by row.
+    pub fn all() -> impl Iterator<Item = Coord> {
+        (0..81).map(|index| Coord::from_index(index).unwrap())
+    }
+
+    pub fn row(self) -> usize {
+        self.row as usize
+    }
+
+    pub fn column(self) -> usize {
+        self.column as usize
+    }
+
+    /// The index of the 3x3 `Square` section the position is in, numbered row by row.
+    pub fn square(self) -> usize {
+        topology::square_index(self.row(), self.column())
+    }
+
+    /// The inverse of `from_index`.
+    pub fn index(self) -> usize {
+        self.row() * 9 + self.column()
+    }
+}
+
+/// How `Grid::most_constrained_cell` chooses between empty cells with equally few possibilities.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TieBreak {
+    /// The first of them, reading row by row. This is what the solver guesses on.
+    #[default]
+    First,
+
+    /// The last of them, reading row by row.
+    Last,
+
+    /// The one whose row, column, and square have the most empty cells between them, so that a
+    /// digit placed there narrows down the most other cells. Ties between those go to the first.
+    MostEmptyPeers,
+}
+
+/// Application state kept with a `Cell`, like a color the user gave it or a note about it. The
+/// solver never looks at it, but it's copied along with the `Grid` by `clone`, `snapshot`, and
+/// `checkpoint`, so it can live on the same `Grid` instead of in a structure beside it.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CellAnnotation {
+    /// A color tag whose meaning is up to the application, such as an index into its palette.
+    pub color: Option<u8>,
+    pub note: String,
+}
+
+impl CellAnnotation {
+    /// Whether there's nothing in the annotation.
+    pub fn is_empty(&self) -> bool {
+        self.color.is_none() && self.note.is_empty()
+    }
+}
+
 /// A representation of a Sudoku grid.
 pub struct Grid {
     pub rows: Vec<MultiMut<Section>>, // Read from top to bottom
     pub columns: Vec<MultiMut<Section>>,
     pub sections: Vec<MultiMut<Section>>,
+    update_queue: MultiMut<UpdateQueue>,
+    annotations: RefCell<BTreeMap<Coord, CellAnnotation>>,
 }
 
 impl Grid {
@@ -257,11 +582,27 @@ impl Grid {
         let mut rows: Vec<MultiMut<Section>> = Vec::new();
         let mut columns: Vec<MultiMut<Section>> = Vec::new();
         let mut sections: Vec<MultiMut<Section>> = Vec::new();
+        let update_queue: MultiMut<UpdateQueue> = Rc::new(RefCell::new(VecDeque::new()));
 
         for i in 0..9 {
-            rows.push(Rc::new(RefCell::new(Section::new(i, SectionType::Row))));
-            columns.push(Rc::new(RefCell::new(Section::new(i, SectionType::Column))));
-            sections.push(Rc::new(RefCell::new(Section::new(i, SectionType::Square))));
+            let queue = Rc::downgrade(&update_queue);
+            rows.push(Rc::new(RefCell::new(Section::new(
+                i,
+                SectionType::Row,
+                queue,
+            ))));
+            let queue = Rc::downgrade(&update_queue);
+            columns.push(Rc::new(RefCell::new(Section::new(
+                i,
+                SectionType::Column,
+                queue,
+            ))));
+            let queue = Rc::downgrade(&update_queue);
+            sections.push(Rc::new(RefCell::new(Section::new(
+                i,
+                SectionType::Square,
+                queue,
+            ))));
         }
 
         for row_index in 0..9 {
@@ -270,7 +611,7 @@ impl Grid {
             let row_ref = &mut *row_rc.borrow_mut();
 
             for column_index in 0..9 {
-                let section_index = (row_index / 3) * 3 + column_index / 3;
+                let section_index = topology::square_index(row_index, column_index);
                 let (column_rc, section_rc) = unsafe {
                     (
                         columns.get_unchecked_mut(column_index),
@@ -286,6 +627,8 @@ impl Grid {
 
                 let row_weak = Rc::downgrade(row_rc);
 
+                count_candidate_allocation();
+
                 let cell = Cell {
                     x: row_index,
                     y: column_index,
@@ -309,9 +652,105 @@ impl Grid {
             rows,
             columns,
             sections,
+            update_queue,
+            annotations: RefCell::new(BTreeMap::new()),
         };
     }
 
+    /// Build a `Grid` from its digits, listed row by row, with 0 for empty cells. Digits are placed
+    /// with `Cell::set`, so the empty cells' possibilities are narrowed down to match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sudoku_solver::grid::{Grid, CellValue};
+    /// let mut digits = [[0; 9]; 9];
+    /// digits[0][0] = 1;
+    ///
+    /// let grid = Grid::from_digits(digits);
+    ///
+    /// assert_eq!(grid.get(0,0).unwrap().get_value_copy(), CellValue::Fixed(1));
+    /// assert_eq!(grid.get(0,1).unwrap().get_value_copy(), CellValue::Unknown(vec![2,3,4,5,6,7,8,9]));
+    /// assert_eq!(grid.to_digits(), digits);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a digit is larger than 9.
+    pub fn from_digits(digits: [[u8; 9]; 9]) -> Grid {
+        let grid = Grid::new();
+        for (x, row) in digits.iter().enumerate() {
+            for (y, &digit) in row.iter().enumerate() {
+                assert!(digit <= 9, "{} is not a valid digit", digit);
+                if digit > 0 {
+                    grid.get(x, y).unwrap().set(digit);
+                }
+            }
+        }
+        grid
+    }
+
+    /// Returns the `Grid`'s digits row by row, with 0 for `Unknown` cells.
+    pub fn to_digits(&self) -> [[u8; 9]; 9] {
+        let mut digits = [[0; 9]; 9];
+        for (x, row) in digits.iter_mut().enumerate() {
+            for (y, digit) in row.iter_mut().enumerate() {
+                if let CellValue::Fixed(fixed) = &*self.get(x, y).unwrap().value.borrow() {
+                    *digit = *fixed;
+                }
+            }
+        }
+        digits
+    }
+
+    /// Returns the `Section` of the given type and index, or None if the index is out of bounds.
+    pub fn get_section(
+        &self,
+        section_type: SectionType,
+        index: usize,
+    ) -> Option<&MultiMut<Section>> {
+        match section_type {
+            SectionType::Row => self.rows.get(index),
+            SectionType::Column => self.columns.get(index),
+            SectionType::Square => self.sections.get(index),
+        }
+    }
+
+    // Put the Sections waiting for the solver in a fixed order, rows first, then columns, then
+    // squares, each from first to last. Returns how many are waiting.
+    pub(crate) fn sort_updates(&self) -> usize {
+        let update_queue = &mut *self.update_queue.borrow_mut();
+        update_queue
+            .make_contiguous()
+            .sort_by_key(|&(section_type, index)| {
+                let rank = match section_type {
+                    SectionType::Row => 0,
+                    SectionType::Column => 1,
+                    SectionType::Square => 2,
+                };
+                (rank, index)
+            });
+        update_queue.len()
+    }
+
+    // Unmark every Section, so that the solver only looks at those that change from now on
+    pub(crate) fn clear_updates(&self) {
+        while let Some((section_type, index)) = self.pop_update() {
+            let section = self.get_section(section_type, index).unwrap();
+            section.borrow().do_update.replace(false);
+        }
+    }
+
+    // How many Sections are waiting for the solver to look at them
+    pub(crate) fn pending_updates(&self) -> usize {
+        self.update_queue.borrow().len()
+    }
+
+    // Take the Section that has been waiting the longest for the solver to look at it
+    pub(crate) fn pop_update(&self) -> Option<(SectionType, usize)> {
+        self.update_queue.borrow_mut().pop_front()
+    }
+
     /// Returns the `Cell` (in an `Rc`) at the specified coordinates.
     /// * `r` is the row coordinate (first row starting at 0)
     /// * `c` is the column coordinate (first column starting at 0)
@@ -333,38 +772,630 @@ impl Grid {
         return Some(Rc::clone(cell));
     }
 
-    fn process_unknown(x: &Vec<u8>, digit: u8, row: &mut String) {
-        if x.contains(&digit) {
-            row.push('*');
+    /// Returns the `Cell` (in an `Rc`) at `coord`. Unlike `get` this can't fail, as a `Coord` is
+    /// always on the grid.
+    pub fn cell(&self, coord: Coord) -> Rc<Cell> {
+        let row = &*self.rows[coord.row()].borrow();
+        Rc::clone(&row.vec[coord.column()])
+    }
+
+    /// Returns a copy of the annotation of the `Cell` at `coord`, or None if it doesn't have one.
+    pub fn annotation(&self, coord: Coord) -> Option<CellAnnotation> {
+        self.annotations.borrow().get(&coord).cloned()
+    }
+
+    /// Replace the annotation of the `Cell` at `coord`; an empty `annotation` removes it. This
+    /// doesn't change anything the solver sees.
+    pub fn set_annotation(&self, coord: Coord, annotation: CellAnnotation) {
+        let mut annotations = self.annotations.borrow_mut();
+        if annotation.is_empty() {
+            annotations.remove(&coord);
         } else {
-            row.push(' ');
+            annotations.insert(coord, annotation);
         }
     }
 
-    /// Find the smallest empty `Cell` in terms of possibilities; returns `None` if all Cells have
-    /// `Fixed` `CellValue`s.
-    pub fn find_smallest_cell(&self) -> Option<Rc<Cell>> {
-        let mut smallest_cell: Option<Rc<Cell>> = None;
-        let mut smallest_size = usize::MAX;
+    /// Returns a copy of the value at the specified coordinates, or None if they are out of bounds.
+    /// Along with `candidates_at` and `set_at`, this lets a `Grid` be used without going through
+    /// the `Rc`s and `RefCell`s that hold its `Cell`s.
+    pub fn value_at(&self, r: usize, c: usize) -> Option<CellValue> {
+        self.get(r, c).map(|cell| cell.get_value_copy())
+    }
 
-        for x in 0..9 {
-            for y in 0..9 {
-                let cell_rc = self.get(x, y).unwrap();
-                let cell = &*self.get(x, y).unwrap();
-                let cell_value = &*cell.value.borrow();
+    /// Returns the possibilities left at the specified coordinates, or None if the `Cell` there is
+    /// `Fixed` or the coordinates are out of bounds.
+    pub fn candidates_at(&self, r: usize, c: usize) -> Option<Vec<u8>> {
+        self.get(r, c)?.get_value_possibilities()
+    }
 
-                match cell_value {
-                    CellValue::Unknown(possibilities) => {
-                        if (possibilities.len() < smallest_size) && (possibilities.len() > 0) {
-                            smallest_size = possibilities.len();
-                            smallest_cell = Some(cell_rc);
-                        }
+    /// Set the value at the specified coordinates like `Cell::set_value` does, so a `Fixed` digit
+    /// is removed from the possibilities of the `Cell`s in the same row, column, and square.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are out of bounds.
+    pub fn set_at(&self, r: usize, c: usize, value: CellValue) {
+        match self.get(r, c) {
+            Some(cell) => cell.set_value(value),
+            None => panic!("({}, {}) is not on the grid", r, c),
+        }
+    }
+
+    /// Draw the grid as text, the way `options` asks. Each cell takes up three lines of three
+    /// characters, with empty cells marking their candidates in the positions of a phone keypad.
+    /// The `Display` implementation is the same as rendering with the default options.
+    pub fn render(&self, options: &DisplayOptions) -> String {
+        let lines = options.charset.lines();
+        let label_margin = if options.coordinates { "  " } else { "" };
+        let mut output = String::new();
+
+        if options.coordinates {
+            output.push_str(label_margin);
+            for c in 0..9 {
+                output.push_str(&format!(" {} ", c + 1));
+                if c < 8 {
+                    output.push(' ');
+                }
+            }
+            output.push('\n');
+        }
+
+        for r in 0..9 {
+            // Each row corresponds to 3 rows since we leave room for guesses
+            let mut rows = [
+                label_margin.to_string(),
+                label_margin.to_string(),
+                label_margin.to_string(),
+            ];
+            if options.coordinates {
+                rows[1] = format!("{} ", r + 1);
+            }
+
+            for c in 0..9 {
+                let highlighted = options.highlights.contains(&Coord::new(r, c).unwrap());
+                let blank = if highlighted { lines.highlight } else { ' ' };
+                // Not counted as a candidate allocation, so that printing mid-solve doesn't change it
+                let value = self.get(r, c).unwrap().value.borrow().clone();
+
+                for (sub_row, row) in rows.iter_mut().enumerate() {
+                    for sub_column in 0..3 {
+                        let candidate = (sub_row * 3 + sub_column + 1) as u8;
+                        let symbol = match &value {
+                            CellValue::Fixed(digit) if sub_row == 1 && sub_column == 1 => {
+                                std::char::from_digit(*digit as u32, 10).unwrap_or('?')
+                            }
+                            CellValue::Unknown(candidates)
+                                if options.show_candidates && candidates.contains(&candidate) =>
+                            {
+                                '*'
+                            }
+                            _ => blank,
+                        };
+                        row.push(symbol);
                     }
-                    _ => {}
+
+                    if (c % 3 == 2) && (c < 8) {
+                        row.push(lines.thick_vertical);
+                    } else if c < 8 {
+                        row.push(lines.thin_vertical);
+                    }
+                }
+            }
+
+            for row in rows.iter() {
+                output.push_str(row);
+                output.push('\n');
+            }
+
+            if r < 8 {
+                let (horizontal, thin_crossing, thick_crossing) = if r % 3 == 2 {
+                    (
+                        lines.thick_horizontal,
+                        lines.thick_horizontal_thin_vertical,
+                        lines.thick_crossing,
+                    )
+                } else {
+                    (
+                        lines.thin_horizontal,
+                        lines.thin_crossing,
+                        lines.thin_horizontal_thick_vertical,
+                    )
+                };
+
+                output.push_str(label_margin);
+                for c in 0..9 {
+                    for _ in 0..3 {
+                        output.push(horizontal);
+                    }
+                    if (c % 3 == 2) && (c < 8) {
+                        output.push(thick_crossing);
+                    } else if c < 8 {
+                        output.push(thin_crossing);
+                    }
+                }
+                output.push('\n');
+            }
+        }
+
+        output
+    }
+
+    /// Find the empty `Cell` with the fewest possibilities, choosing between those with equally
+    /// few by `tie_break`. Cells with no possibilities left are passed over, so this returns `None`
+    /// if every other `Cell` is `Fixed`.
+    pub fn most_constrained_cell(&self, tie_break: TieBreak) -> Option<Rc<Cell>> {
+        // The best position so far, its number of possibilities, and its empty peers
+        let mut best: Option<(Coord, usize, usize)> = None;
+
+        for coord in Coord::all() {
+            let size = match &*self.cell(coord).value.borrow() {
+                CellValue::Unknown(possibilities) if !possibilities.is_empty() => {
+                    possibilities.len()
+                }
+                _ => continue,
+            };
+            let empty_peers = match tie_break {
+                TieBreak::MostEmptyPeers => self.empty_peers(coord),
+                TieBreak::First | TieBreak::Last => 0,
+            };
+
+            let is_better = match best {
+                None => true,
+                Some((_coord, best_size, best_empty_peers)) => {
+                    size < best_size
+                        || size == best_size
+                            && match tie_break {
+                                TieBreak::First => false,
+                                TieBreak::Last => true,
+                                TieBreak::MostEmptyPeers => empty_peers > best_empty_peers,
+                            }
+                }
+            };
+            if is_better {
+                best = Some((coord, size, empty_peers));
+            }
+        }
+
+        best.map(|(coord, _size, _empty_peers)| self.cell(coord))
+    }
+
+    // How many empty cells share a row, column, or square with `coord`, counting those that share
+    // two of them twice
+    fn empty_peers(&self, coord: Coord) -> usize {
+        let empty = |section: &MultiMut<Section>| {
+            section
+                .borrow()
+                .vec
+                .iter()
+                .filter(|cell| cell.coord() != coord)
+                .filter(|cell| matches!(&*cell.value.borrow(), CellValue::Unknown(_)))
+                .count()
+        };
+        empty(&self.rows[coord.row()])
+            + empty(&self.columns[coord.column()])
+            + empty(&self.sections[coord.square()])
+    }
+
+    /// Returns true if every `Cell` has a `Fixed` value, whether or not those values conflict.
+    pub fn is_complete(&self) -> bool {
+        self.rows.iter().all(|row| {
+            row.borrow()
+                .vec
+                .iter()
+                .all(|cell| matches!(&*cell.value.borrow(), CellValue::Fixed(_)))
+        })
+    }
+
+    /// Returns true if the `Grid` is complete and no row, column, or square repeats a digit.
+    pub fn is_solved(&self) -> bool {
+        self.is_complete() && !self.has_repeated_digits()
+    }
+
+    /// Returns true if a row, column, or square has the same `Fixed` digit in more than one
+    /// `Cell`. Such a `Grid` has no solution. Digits placed with `Cell::set` are allowed to clash,
+    /// so it can happen even though `Cell::try_set` refuses to.
+    pub fn has_repeated_digits(&self) -> bool {
+        let repeats = |section: &MultiMut<Section>| {
+            let mut seen = [false; 9];
+            for cell in section.borrow().vec.iter() {
+                if let CellValue::Fixed(digit) = &*cell.value.borrow() {
+                    let index = (*digit as usize) - 1;
+                    if seen[index] {
+                        return true;
+                    }
+                    seen[index] = true;
+                }
+            }
+            false
+        };
+
+        self.rows.iter().any(repeats)
+            || self.columns.iter().any(repeats)
+            || self.sections.iter().any(repeats)
+    }
+
+    /// Returns the number of `Cell`s with a `Fixed` value.
+    pub fn clue_count(&self) -> usize {
+        self.rows
+            .iter()
+            .map(|row| {
+                row.borrow()
+                    .vec
+                    .iter()
+                    .filter(|cell| matches!(&*cell.value.borrow(), CellValue::Fixed(_)))
+                    .count()
+            })
+            .sum()
+    }
+
+    /// Returns the number of `Cell`s with an `Unknown` value.
+    pub fn empty_count(&self) -> usize {
+        81 - self.clue_count()
+    }
+
+    /// Returns the total number of possibilities remaining across all `Unknown` `Cell`s.
+    pub fn candidate_count(&self) -> usize {
+        self.rows
+            .iter()
+            .map(|row| {
+                row.borrow()
+                    .vec
+                    .iter()
+                    .map(|cell| match &*cell.value.borrow() {
+                        CellValue::Fixed(_) => 0,
+                        CellValue::Unknown(possibilities) => possibilities.len(),
+                    })
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
+    /// Returns every `Cell` whose value differs between this `Grid` and `other`, including `Unknown`
+    /// cells with different possibilities, in row by row order.
+    pub fn diff(&self, other: &Grid) -> Vec<CellDiff> {
+        let mut diffs = Vec::new();
+        for coord in Coord::all() {
+            let (before_cell, after_cell) = (self.cell(coord), other.cell(coord));
+            let before = &*before_cell.value.borrow();
+            let after = &*after_cell.value.borrow();
+            if before != after {
+                diffs.push(CellDiff {
+                    x: coord.row(),
+                    y: coord.column(),
+                    before: before.clone(),
+                    after: after.clone(),
+                });
+            }
+        }
+
+        diffs
+    }
+
+    /// Returns the cells of the given row, column, or square where `digit` can still go, in the
+    /// `Section`'s order: `Unknown` cells that have it as a possibility, and any cell already
+    /// `Fixed` to it. Returns an empty list if the `Section` index is out of bounds.
+    pub fn positions_of(&self, digit: u8, house: (SectionType, usize)) -> Vec<Coord> {
+        let (section_type, index) = house;
+        let section = match self.get_section(section_type, index) {
+            Some(section) => section.borrow(),
+            None => return Vec::new(),
+        };
+
+        section
+            .vec
+            .iter()
+            .filter(|cell| match &*cell.value.borrow() {
+                CellValue::Fixed(fixed) => *fixed == digit,
+                CellValue::Unknown(possibilities) => possibilities.contains(&digit),
+            })
+            .map(|cell| cell.coord())
+            .collect()
+    }
+
+    /// Find everywhere `digit` is placed or can still go, and the houses where it's confined, for
+    /// showing a player all of a digit at once. See `DigitHighlight`.
+    pub fn highlight_digit(&self, digit: u8) -> DigitHighlight {
+        let mut placed = Vec::new();
+        let mut candidates = Vec::new();
+        for coord in Coord::all() {
+            match &*self.cell(coord).value.borrow() {
+                CellValue::Fixed(fixed) if *fixed == digit => placed.push(coord),
+                CellValue::Unknown(possibilities) if possibilities.contains(&digit) => {
+                    candidates.push(coord)
+                }
+                _ => {}
+            }
+        }
+
+        let mut confined = Vec::new();
+        for house in 0..topology::HOUSES {
+            let house = topology::house(house);
+            let cells = self.positions_of(digit, house);
+            if cells.is_empty() || cells.iter().any(|coord| placed.contains(coord)) {
+                continue;
+            }
+
+            let shared =
+                |of: fn(Coord) -> usize| cells.iter().all(|&coord| of(coord) == of(cells[0]));
+            let within = match house.0 {
+                SectionType::Row | SectionType::Column if shared(Coord::square) => {
+                    Some((SectionType::Square, cells[0].square()))
+                }
+                SectionType::Square if shared(Coord::row) => {
+                    Some((SectionType::Row, cells[0].row()))
+                }
+                SectionType::Square if shared(Coord::column) => {
+                    Some((SectionType::Column, cells[0].column()))
                 }
+                _ => None,
+            };
+            if let Some(within) = within {
+                confined.push(Confinement {
+                    house,
+                    within,
+                    cells,
+                });
+            }
+        }
+
+        DigitHighlight {
+            digit,
+            placed,
+            candidates,
+            confined,
+        }
+    }
+
+    /// Save the `Grid` part way through a solve. See `GridCheckpoint`.
+    pub fn checkpoint(&self) -> GridCheckpoint {
+        GridCheckpoint {
+            values: self.snapshot(),
+            pending_sections: self.update_queue.borrow().iter().copied().collect(),
+        }
+    }
+
+    /// Take a copy of every `Cell`'s value. See `GridSnapshot`.
+    pub fn snapshot(&self) -> GridSnapshot {
+        let mut values = Vec::with_capacity(81);
+        for coord in Coord::all() {
+            // Not counted as a candidate allocation, so that recording a solve doesn't change it
+            values.push(self.cell(coord).value.borrow().clone());
+        }
+
+        let annotations = self
+            .annotations
+            .borrow()
+            .iter()
+            .map(|(coord, annotation)| (coord.index(), annotation.clone()))
+            .collect();
+
+        GridSnapshot {
+            values,
+            annotations,
+        }
+    }
+}
+
+/// Where one digit is and can still go in a `Grid`, from `Grid::highlight_digit`. All of the
+/// lists are in row by row order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DigitHighlight {
+    pub digit: u8,
+
+    /// The cells that are `Fixed` to the digit.
+    pub placed: Vec<Coord>,
+
+    /// The `Unknown` cells that still have the digit as a possibility.
+    pub candidates: Vec<Coord>,
+
+    /// The houses without the digit that can only have it where they overlap one other house,
+    /// by their order in `topology`: rows, then columns, then squares.
+    pub confined: Vec<Confinement>,
+}
+
+impl DigitHighlight {
+    /// Options for `Grid::render` that shade in the cells where the digit can still go.
+    pub fn display_options(&self) -> DisplayOptions {
+        DisplayOptions {
+            highlights: self.candidates.clone(),
+            ..DisplayOptions::default()
+        }
+    }
+}
+
+/// A row, column, or square where a digit can only go in the cells it shares with another house,
+/// which then can't have the digit anywhere else; see `DigitHighlight`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Confinement {
+    pub house: (SectionType, usize),
+
+    /// The other house, which is a square for a row or column, and a row (or failing that a
+    /// column) for a square.
+    pub within: (SectionType, usize),
+
+    /// Where the digit can go in `house`.
+    pub cells: Vec<Coord>,
+}
+
+/// A `Cell` whose value differs between two `Grid`s; see `Grid::diff`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CellDiff {
+    pub x: usize,
+    pub y: usize,
+    /// The value in the `Grid` `diff` was called on.
+    pub before: CellValue,
+    /// The value in the other `Grid`.
+    pub after: CellValue,
+}
+
+/// A plain copy of the `CellValue`s of a `Grid`, read row by row, and of its `CellAnnotation`s.
+/// Unlike `Grid` it contains no `Rc`s or `RefCell`s, so it can be sent across threads and turned
+/// back into a `Grid` there.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct GridSnapshot {
+    values: Vec<CellValue>,
+
+    // Each annotated cell's index, read row by row, and its annotation
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    annotations: Vec<(usize, CellAnnotation)>,
+}
+
+impl GridSnapshot {
+    /// Returns the `CellValue` at the specified coordinates, or None if they are out of bounds.
+    pub fn get(&self, r: usize, c: usize) -> Option<&CellValue> {
+        if r >= 9 || c >= 9 {
+            return None;
+        }
+        self.values.get(r * 9 + c)
+    }
+
+    pub(crate) fn set(&mut self, r: usize, c: usize, value: CellValue) {
+        self.values[r * 9 + c] = value;
+    }
+
+    /// Build a new `Grid` with the snapshot's values. Every `Section` of the new `Grid` is marked
+    /// for the solver to look at.
+    pub fn to_grid(&self) -> Grid {
+        let grid = Grid::new();
+        for coord in Coord::all() {
+            let value = self.values[coord.index()].clone();
+            grid.cell(coord).set_value_exact(value);
+        }
+        for (index, annotation) in self.annotations.iter() {
+            // Only a snapshot read from a file could have a cell off the grid
+            if let Some(coord) = Coord::from_index(*index) {
+                grid.set_annotation(coord, annotation.clone());
+            }
+        }
+
+        grid
+    }
+}
+
+/// Everything needed to carry on solving a `Grid` later: each `Cell`'s value, including the
+/// possibilities of `Unknown` ones, and the `Section`s that were waiting for the solver to look at
+/// them, in the order they were marked. A `GridSnapshot` alone can't be resumed exactly, since
+/// `GridSnapshot::to_grid` marks every `Section`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GridCheckpoint {
+    pub values: GridSnapshot,
+    pub pending_sections: Vec<(SectionType, usize)>,
+}
+
+impl GridCheckpoint {
+    /// Build a `Grid` in the state the checkpoint was taken in. Returns an error if the checkpoint
+    /// doesn't describe a `Grid`, which can only happen if it was edited or read from a file.
+    pub fn restore(&self) -> Result<Grid, String> {
+        if self.values.values.len() != 81 {
+            return Err(format!(
+                "Expected 81 cell values but found {}",
+                self.values.values.len()
+            ));
+        }
+
+        for (index, value) in self.values.values.iter().enumerate() {
+            let digits = match value {
+                CellValue::Fixed(digit) => std::slice::from_ref(digit),
+                CellValue::Unknown(possibilities) => possibilities.as_slice(),
+            };
+            if let Some(digit) = digits.iter().find(|&&digit| digit == 0 || digit > 9) {
+                return Err(format!(
+                    "Cell {} holds {}, which isn't a digit",
+                    index, digit
+                ));
+            }
+        }
+
+        let grid = Grid::new();
+        for coord in Coord::all() {
+            // Replacing the value directly leaves the Sections unmarked, unlike set_value_exact
+            grid.cell(coord)
+                .value
+                .replace(self.values.values[coord.index()].clone());
+        }
+
+        for (index, annotation) in self.values.annotations.iter() {
+            match Coord::from_index(*index) {
+                Some(coord) => grid.set_annotation(coord, annotation.clone()),
+                None => return Err(format!("There is no cell {} to annotate", index)),
+            }
+        }
+
+        for &(section_type, index) in self.pending_sections.iter() {
+            match grid.get_section(section_type, index) {
+                Some(section) => section.borrow().mark_for_update(),
+                None => return Err(format!("There is no {:?} {}", section_type, index)),
             }
         }
-        smallest_cell
+
+        Ok(grid)
+    }
+
+    /// Write the checkpoint as JSON, to be read back with `from_json`.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("A GridCheckpoint can always be written as JSON")
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<GridCheckpoint, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// A `Grid` that can be shared between threads, for example by request handlers that all start
+/// from the same puzzle. The values are held in an `Arc`, so cloning a `SharedGrid` is cheap.
+/// It can't be changed in place; `update` makes a changed copy and leaves the original alone.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SharedGrid {
+    snapshot: Arc<GridSnapshot>,
+}
+
+impl SharedGrid {
+    pub fn new(grid: &Grid) -> SharedGrid {
+        SharedGrid::from(grid.snapshot())
+    }
+
+    /// Returns the `CellValue` at the specified coordinates, or None if they are out of bounds.
+    pub fn value_at(&self, r: usize, c: usize) -> Option<&CellValue> {
+        self.snapshot.get(r, c)
+    }
+
+    pub fn snapshot(&self) -> &GridSnapshot {
+        &self.snapshot
+    }
+
+    /// Build a new `Grid` with the shared values, which the current thread is free to modify or
+    /// solve.
+    pub fn to_grid(&self) -> Grid {
+        self.snapshot.to_grid()
+    }
+
+    /// Returns a new `SharedGrid` with the values of this one after `change` has been applied to a
+    /// copy of them.
+    pub fn update<F: FnOnce(&Grid)>(&self, change: F) -> SharedGrid {
+        let grid = self.to_grid();
+        change(&grid);
+        SharedGrid::new(&grid)
+    }
+}
+
+impl From<GridSnapshot> for SharedGrid {
+    fn from(snapshot: GridSnapshot) -> Self {
+        SharedGrid {
+            snapshot: Arc::new(snapshot),
+        }
+    }
+}
+
+impl From<&Grid> for SharedGrid {
+    fn from(grid: &Grid) -> Self {
+        SharedGrid::new(grid)
     }
 }
 
@@ -377,13 +1408,22 @@ impl Clone for Grid {
     }
 
     fn clone_from(&mut self, source: &Self) {
-        for x in 0..9 {
-            for y in 0..9 {
-                let source_value = source.get(x, y).unwrap().get_value_copy();
-                self.get(x, y).unwrap().set_value_exact(source_value);
-            }
+        for coord in Coord::all() {
+            // Copying into the existing CellValue lets it reuse its possibilities' allocation
+            let source_cell = source.cell(coord);
+            let cell = self.cell(coord);
+            cell.value
+                .borrow_mut()
+                .clone_from(&source_cell.value.borrow());
         }
 
+        self.update_queue
+            .borrow_mut()
+            .clone_from(&source.update_queue.borrow());
+        self.annotations
+            .borrow_mut()
+            .clone_from(&source.annotations.borrow());
+
         for i in 0..9 {
             let new_row = &*self.rows.get(i).unwrap().borrow();
             let source_row = &*source.rows.get(i).unwrap().borrow();
@@ -400,66 +1440,805 @@ impl Clone for Grid {
     }
 }
 
+impl From<[[u8; 9]; 9]> for Grid {
+    fn from(digits: [[u8; 9]; 9]) -> Self {
+        Grid::from_digits(digits)
+    }
+}
+
+impl From<&Grid> for [[u8; 9]; 9] {
+    fn from(grid: &Grid) -> Self {
+        grid.to_digits()
+    }
+}
+
+// Grids are equal when their Cells' values are; which Sections are waiting for the solver to look
+// at them, and the Cells' annotations, don't matter.
+impl PartialEq for Grid {
+    fn eq(&self, other: &Self) -> bool {
+        self.rows
+            .iter()
+            .zip(other.rows.iter())
+            .all(|(row, other_row)| {
+                let row = &*row.borrow();
+                let other_row = &*other_row.borrow();
+                row.vec
+                    .iter()
+                    .zip(other_row.vec.iter())
+                    .all(|(cell, other_cell)| *cell.value.borrow() == *other_cell.value.borrow())
+            })
+    }
+}
+
+impl Eq for Grid {}
+
+impl std::hash::Hash for Grid {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        for row in self.rows.iter() {
+            for cell in row.borrow().vec.iter() {
+                cell.value.borrow().hash(state);
+            }
+        }
+    }
+}
+
+/// Builds a `Grid` by reading a digit (0 meaning empty) for each of the 81 cells. Digits are placed
+/// with `Cell::try_set`, and ones that clash with an earlier digit are left out, so no house
+/// repeats a digit. The puzzle can still have no solution.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Grid {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let grid = Grid::new();
+
+        for r in 0..9 {
+            for c in 0..9 {
+                let digit = u.int_in_range(0..=9)?;
+                if digit > 0 {
+                    // A clashing digit is skipped rather than failing the whole grid
+                    let _ = grid.get(r, c).unwrap().try_set(digit);
+                }
+            }
+        }
+
+        Ok(grid)
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (0, Some(81))
+    }
+}
+
+/// The characters `Grid::render` draws the lines between cells with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Charset {
+    /// Box-drawing characters, with heavier lines around each square.
+    Unicode,
+    /// Plain ASCII, for terminals and logs that can't show box-drawing characters.
+    Ascii,
+}
+
+// Every character used to draw one Charset
+struct Lines {
+    thin_vertical: char,
+    thick_vertical: char,
+    thin_horizontal: char,
+    thick_horizontal: char,
+    thin_crossing: char,
+    thin_horizontal_thick_vertical: char,
+    thick_horizontal_thin_vertical: char,
+    thick_crossing: char,
+    highlight: char,
+}
+
+impl Charset {
+    fn lines(self) -> Lines {
+        match self {
+            Charset::Unicode => Lines {
+                thin_vertical: '┆',
+                thick_vertical: '\u{2503}',
+                thin_horizontal: '┄',
+                thick_horizontal: '━',
+                thin_crossing: '┼',
+                thin_horizontal_thick_vertical: '╂',
+                thick_horizontal_thin_vertical: '┿',
+                thick_crossing: '╋',
+                highlight: '░',
+            },
+            Charset::Ascii => Lines {
+                thin_vertical: ':',
+                thick_vertical: '|',
+                thin_horizontal: '-',
+                thick_horizontal: '=',
+                thin_crossing: '+',
+                thin_horizontal_thick_vertical: '+',
+                thick_horizontal_thin_vertical: '#',
+                thick_crossing: '#',
+                highlight: '.',
+            },
+        }
+    }
+}
+
+/// Options for `Grid::render`. The default options draw the same thing as `Display`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DisplayOptions {
+    pub charset: Charset,
+
+    /// Whether empty cells mark their remaining candidates, or are left blank.
+    pub show_candidates: bool,
+
+    /// Cells to shade in, such as the ones a hint is about.
+    pub highlights: Vec<Coord>,
+
+    /// Whether to number the rows down the left side and the columns across the top, from 1 to 9.
+    pub coordinates: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            charset: Charset::Unicode,
+            show_candidates: true,
+            highlights: Vec::new(),
+            coordinates: false,
+        }
+    }
+}
+
 impl std::fmt::Display for Grid {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for r in 0..9 {
-            // Each row corresponds to 3 rows since we leave room for guesses
-            let mut row1 = String::new();
-            let mut row2 = String::new();
-            let mut row3 = String::new();
+        write!(f, "{}", self.render(&DisplayOptions::default()))
+    }
+}
 
-            for c in 0..9 {
-                let cell = &*self.get(r, c).unwrap();
-                let value = &*cell.value.borrow();
+#[cfg(test)]
+mod tests {
+    use crate::grid::*;
+    use crate::solver::dlx;
+
+    #[test]
+    fn test_is_complete_and_solved() {
+        let mut grid = Grid::new();
+        assert!(!grid.is_complete());
+        assert!(!grid.is_solved());
+
+        // A single empty cell is enough to leave it incomplete
+        let mut almost = grid.clone();
+        dlx::solve_grid(&mut almost);
+        almost
+            .get(8, 8)
+            .unwrap()
+            .set_value_exact(CellValue::Unknown(vec![1]));
+        assert!(!almost.is_complete());
+
+        dlx::solve_grid(&mut grid);
+        assert!(grid.is_complete());
+        assert!(grid.is_solved());
+
+        // Still complete, but now the first row has two of the same digit
+        let digit = match grid.get(0, 0).unwrap().get_value_copy() {
+            CellValue::Fixed(digit) => digit,
+            CellValue::Unknown(_) => unreachable!(),
+        };
+        grid.get(0, 1)
+            .unwrap()
+            .set_value_exact(CellValue::Fixed(digit));
+        assert!(grid.is_complete());
+        assert!(!grid.is_solved());
+        assert!(grid.has_repeated_digits());
+    }
 
-                match value {
-                    CellValue::Fixed(x) => {
-                        row1.push_str("   ");
-                        row2.push(' ');
-                        row2.push_str(&x.to_string());
-                        row2.push(' ');
-                        row3.push_str("   ");
-                    }
-                    CellValue::Unknown(x) => {
-                        Grid::process_unknown(&x, 1, &mut row1);
-                        Grid::process_unknown(&x, 2, &mut row1);
-                        Grid::process_unknown(&x, 3, &mut row1);
-
-                        Grid::process_unknown(&x, 4, &mut row2);
-                        Grid::process_unknown(&x, 5, &mut row2);
-                        Grid::process_unknown(&x, 6, &mut row2);
-
-                        Grid::process_unknown(&x, 7, &mut row3);
-                        Grid::process_unknown(&x, 8, &mut row3);
-                        Grid::process_unknown(&x, 9, &mut row3);
-                    }
-                };
+    #[test]
+    fn test_has_repeated_digits() {
+        let grid = Grid::new();
+        assert!(!grid.has_repeated_digits());
+        grid.get(0, 0).unwrap().set(9);
+        grid.get(4, 4).unwrap().set(9);
+        assert!(!grid.has_repeated_digits());
+
+        // Each kind of house on its own
+        for &(x, y) in [(0, 8), (8, 0), (2, 2)].iter() {
+            let clashing = grid.clone();
+            clashing.get(x, y).unwrap().set(9);
+            assert!(clashing.has_repeated_digits(), "{}, {}", x, y);
+        }
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary() {
+        use crate::solver::{solve_grid, SolveStatus};
+        use arbitrary::{Arbitrary, Unstructured};
+        use rand::prelude::*;
+
+        let mut rng = SmallRng::seed_from_u64(357);
+        for _ in 0..50 {
+            // Short inputs run out part way through, leaving the rest of the grid empty
+            let mut data = vec![0u8; rng.gen_range(0, 120)];
+            rng.fill_bytes(&mut data);
+            let mut grid = Grid::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            assert!(!grid.has_repeated_digits());
+
+            if let (SolveStatus::Complete(_), _) = solve_grid(&mut grid) {
+                assert!(grid.is_solved());
+            }
+        }
+    }
 
-                if (c % 3 == 2) && (c < 8) {
-                    row1.push('\u{2503}');
-                    row2.push('\u{2503}');
-                    row3.push('\u{2503}');
-                } else if c < 8 {
-                    row1.push('┆');
-                    row2.push('┆');
-                    row3.push('┆');
+    #[test]
+    fn test_from_and_to_digits() {
+        let digits = [
+            [9, 0, 0, 0, 0, 0, 0, 4, 0],
+            [0, 0, 0, 5, 0, 0, 8, 0, 0],
+            [2, 4, 0, 0, 7, 0, 0, 0, 0],
+            [0, 0, 8, 0, 2, 0, 9, 0, 0],
+            [0, 0, 0, 6, 0, 0, 0, 7, 0],
+            [0, 0, 0, 0, 0, 5, 0, 0, 1],
+            [3, 0, 0, 0, 8, 0, 4, 0, 7],
+            [7, 0, 0, 0, 1, 9, 2, 0, 0],
+            [0, 0, 6, 0, 0, 0, 0, 0, 0],
+        ];
+        let grid = Grid::from_digits(digits);
+        assert_eq!(grid.to_digits(), digits);
+
+        // The same as setting each digit by hand, possibilities included
+        let by_hand = Grid::new();
+        for (x, row) in digits.iter().enumerate() {
+            for (y, &digit) in row.iter().enumerate() {
+                if digit > 0 {
+                    by_hand.get(x, y).unwrap().set(digit);
                 }
             }
+        }
+        assert!(grid == by_hand);
+        assert_eq!(
+            grid.get(0, 1).unwrap().get_value_copy(),
+            CellValue::Unknown(vec![1, 3, 5, 6, 7, 8])
+        );
+
+        assert!(Grid::from_digits([[0; 9]; 9]) == Grid::new());
+        assert_eq!(Grid::new().to_digits(), [[0; 9]; 9]);
+    }
 
-            write!(f, "{}", row1)?;
-            write!(f, "\n")?;
-            write!(f, "{}", row2)?;
-            write!(f, "\n")?;
-            write!(f, "{}", row3)?;
-            write!(f, "\n")?;
+    #[test]
+    fn test_highlight_digit() {
+        let grid = Grid::new();
+        for y in 0..8 {
+            grid.get(0, y).unwrap().set(y as u8 + 1);
+        }
+
+        let nines = grid.highlight_digit(9);
+        assert!(nines.placed.is_empty());
+        assert_eq!(nines.candidates.len(), 73);
+        assert_eq!(
+            nines.confined,
+            vec![Confinement {
+                house: (SectionType::Row, 0),
+                within: (SectionType::Square, 2),
+                cells: vec![Coord::new(0, 8).unwrap()],
+            }]
+        );
+
+        let ones = grid.highlight_digit(1);
+        assert_eq!(ones.placed, vec![Coord::new(0, 0).unwrap()]);
+        assert!(!ones.candidates.contains(&Coord::new(1, 1).unwrap()));
+        assert!(ones.candidates.contains(&Coord::new(1, 3).unwrap()));
+        assert!(ones.confined.is_empty());
+
+        assert_ne!(grid.render(&nines.display_options()), grid.to_string());
+
+        // The 9s of the next two rows leave the top left square only its first row for one
+        let grid = Grid::new();
+        grid.get(1, 5).unwrap().set(9);
+        grid.get(2, 7).unwrap().set(9);
+        let top_left = grid
+            .highlight_digit(9)
+            .confined
+            .into_iter()
+            .find(|confinement| confinement.house == (SectionType::Square, 0))
+            .unwrap();
+        assert_eq!(top_left.within, (SectionType::Row, 0));
+        assert_eq!(top_left.cells.len(), 3);
+    }
 
-            if (r % 3 == 2) && (r < 8) {
-                write!(f, "━━━┿━━━┿━━━╋━━━┿━━━┿━━━╋━━━┿━━━┿━━━\n")?;
-            } else if r < 8 {
-                write!(f, "┄┄┄┼┄┄┄┼┄┄┄╂┄┄┄┼┄┄┄┼┄┄┄╂┄┄┄┼┄┄┄┼┄┄┄\n")?;
+    #[test]
+    fn test_counts() {
+        let grid = Grid::new();
+        assert_eq!(grid.clue_count(), 0);
+        assert_eq!(grid.empty_count(), 81);
+        assert_eq!(grid.candidate_count(), 81 * 9);
+
+        // Removes 1 from the 20 cells that share a row, column, or square with (0, 0)
+        grid.get(0, 0).unwrap().set(1);
+        assert_eq!(grid.clue_count(), 1);
+        assert_eq!(grid.empty_count(), 80);
+        assert_eq!(grid.candidate_count(), 80 * 9 - 20);
+    }
+
+    #[test]
+    fn test_equality_and_hash() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let hash = |grid: &Grid| {
+            let mut hasher = DefaultHasher::new();
+            grid.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let grid = Grid::new();
+        grid.get(4, 4).unwrap().set(5);
+
+        // The copy has every Section marked for the solver, unlike the original, but the same values
+        let copy = grid.snapshot().to_grid();
+        assert!(copy == grid);
+        assert_eq!(hash(&copy), hash(&grid));
+
+        let mut solved = grid.clone();
+        dlx::solve_grid(&mut solved);
+        assert!(solved != grid);
+        assert_ne!(hash(&solved), hash(&grid));
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = Grid::new();
+        let after = before.clone();
+        assert!(before.diff(&after).is_empty());
+
+        after.get(0, 0).unwrap().set(1);
+        after
+            .get(8, 8)
+            .unwrap()
+            .set_value_exact(CellValue::Unknown(vec![2, 3]));
+
+        let diffs = before.diff(&after);
+        // (0, 0) itself, the 20 cells that lost 1 as a possibility, and (8, 8)
+        assert_eq!(diffs.len(), 22);
+        assert_eq!(
+            diffs[0],
+            CellDiff {
+                x: 0,
+                y: 0,
+                before: CellValue::Unknown(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]),
+                after: CellValue::Fixed(1),
+            }
+        );
+        assert_eq!(
+            diffs[1].after,
+            CellValue::Unknown(vec![2, 3, 4, 5, 6, 7, 8, 9])
+        );
+        assert_eq!(
+            diffs.last().unwrap(),
+            &CellDiff {
+                x: 8,
+                y: 8,
+                before: CellValue::Unknown(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]),
+                after: CellValue::Unknown(vec![2, 3]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_coord() {
+        assert_eq!(Coord::new(9, 0), None);
+        assert_eq!(Coord::new(0, 9), None);
+        assert_eq!(Coord::from_index(81), None);
+
+        let coord = Coord::new(4, 7).unwrap();
+        assert_eq!((coord.row(), coord.column()), (4, 7));
+        assert_eq!(coord.square(), 5);
+        assert_eq!(Coord::from_index(coord.index()), Some(coord));
+
+        let coords: Vec<Coord> = Coord::all().collect();
+        assert_eq!(coords.len(), 81);
+        assert_eq!(coords[10], Coord::new(1, 1).unwrap());
+
+        let grid = Grid::new();
+        let cell = grid.cell(coord);
+        assert_eq!((cell.x, cell.y), (4, 7));
+        assert_eq!(cell.coord(), coord);
+        assert!(Rc::ptr_eq(&cell, &grid.get(4, 7).unwrap()));
+    }
+
+    #[test]
+    fn test_value_facade() {
+        let grid = Grid::new();
+        grid.set_at(0, 0, CellValue::Fixed(1));
+        grid.set_at(8, 8, CellValue::Unknown(vec![2, 3]));
+
+        assert_eq!(grid.value_at(0, 0), Some(CellValue::Fixed(1)));
+        assert_eq!(grid.candidates_at(0, 0), None);
+        assert_eq!(grid.candidates_at(0, 1), Some(vec![2, 3, 4, 5, 6, 7, 8, 9]));
+        assert_eq!(grid.value_at(8, 8), Some(CellValue::Unknown(vec![2, 3])));
+        assert_eq!(grid.value_at(9, 0), None);
+        assert_eq!(grid.candidates_at(0, 9), None);
+    }
+
+    #[test]
+    fn test_shared_grid() {
+        let grid = Grid::new();
+        grid.set_at(0, 0, CellValue::Fixed(1));
+        let shared = SharedGrid::new(&grid);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    let mut grid = shared.to_grid();
+                    dlx::solve_grid(&mut grid);
+                    grid.is_solved() && grid.value_at(0, 0) == Some(CellValue::Fixed(1))
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().unwrap());
+        }
+
+        let updated = shared.update(|grid| grid.set_at(0, 1, CellValue::Fixed(2)));
+        assert_eq!(updated.value_at(0, 1), Some(&CellValue::Fixed(2)));
+        assert_eq!(
+            shared.value_at(0, 1),
+            Some(&CellValue::Unknown(vec![2, 3, 4, 5, 6, 7, 8, 9]))
+        );
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let grid = Grid::new();
+        grid.get(0, 0).unwrap().set(1);
+        grid.get(4, 4)
+            .unwrap()
+            .set_value_exact(CellValue::Unknown(vec![2, 3]));
+        // Pretend the solver has already looked at the first Section
+        let (section_type, index) = grid.pop_update().unwrap();
+        grid.get_section(section_type, index)
+            .unwrap()
+            .borrow()
+            .do_update
+            .replace(false);
+
+        let checkpoint = grid.checkpoint();
+        assert!(!checkpoint.pending_sections.is_empty());
+        assert!(!checkpoint.pending_sections.contains(&(section_type, index)));
+
+        let restored = checkpoint.restore().unwrap();
+        assert!(restored == grid);
+        assert_eq!(restored.checkpoint(), checkpoint);
+        for &(section_type, index) in checkpoint.pending_sections.iter() {
+            assert!(restored
+                .get_section(section_type, index)
+                .unwrap()
+                .borrow()
+                .do_update());
+        }
+        assert!(!restored
+            .get_section(section_type, index)
+            .unwrap()
+            .borrow()
+            .do_update());
+
+        let mut bad_checkpoint = checkpoint.clone();
+        bad_checkpoint.pending_sections.push((SectionType::Row, 9));
+        assert!(bad_checkpoint.restore().is_err());
+
+        // Digits outside 1 to 9 are refused rather than left for the solver to trip over
+        for bad_value in [
+            CellValue::Fixed(10),
+            CellValue::Fixed(0),
+            CellValue::Unknown(vec![1, 10]),
+            CellValue::Unknown(vec![0, 2]),
+        ]
+        .iter()
+        {
+            let mut bad_checkpoint = checkpoint.clone();
+            bad_checkpoint.values.values[8] = bad_value.clone();
+            assert!(bad_checkpoint.restore().is_err(), "{:?}", bad_value);
+        }
+
+        #[cfg(feature = "json")]
+        {
+            assert_eq!(
+                GridCheckpoint::from_json(&checkpoint.to_json()).unwrap(),
+                checkpoint
+            );
+            let json = checkpoint
+                .to_json()
+                .replacen("{\"Fixed\":1}", "{\"Fixed\":10}", 1);
+            let bad_checkpoint = GridCheckpoint::from_json(&json).unwrap();
+            assert_eq!(
+                bad_checkpoint.restore().err(),
+                Some("Cell 0 holds 10, which isn't a digit".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_queues_changed_sections() {
+        // Every peer of the centre cell except one has already ruled out 5
+        let grid = Grid::new();
+        for x in 0..9 {
+            for y in 0..9 {
+                let peer = x == 4 || y == 4 || (x / 3 == 1 && y / 3 == 1);
+                if peer && (x, y) != (4, 4) && (x, y) != (4, 0) {
+                    grid.get(x, y)
+                        .unwrap()
+                        .set_value_exact(CellValue::Unknown(vec![1, 2, 3]));
+                }
             }
         }
+        grid.clear_updates();
+
+        grid.get(4, 4).unwrap().set(5);
+        let mut queued = Vec::new();
+        while let Some(update) = grid.pop_update() {
+            queued.push(update);
+        }
+
+        // The cell's own houses plus those of the one peer that lost 5, each once; the houses of
+        // the peers that never had 5 are left alone
+        let mut expected = vec![
+            (SectionType::Row, 4),
+            (SectionType::Column, 4),
+            (SectionType::Square, 4),
+            (SectionType::Column, 0),
+            (SectionType::Square, 3),
+        ];
+        queued.sort_by_key(|&(section_type, index)| (section_type as usize, index));
+        expected.sort_by_key(|&(section_type, index)| (section_type as usize, index));
+        assert_eq!(queued, expected);
+        assert_eq!(
+            grid.get(4, 0).unwrap().get_value_copy(),
+            CellValue::Unknown(vec![1, 2, 3, 4, 6, 7, 8, 9])
+        );
+    }
+
+    #[test]
+    fn test_try_set() {
+        let grid = Grid::new();
+        assert_eq!(
+            grid.get(0, 0).unwrap().try_set(10),
+            Err(SudokuError::InvalidDigit(10))
+        );
+        assert_eq!(grid.get(0, 0).unwrap().try_set(1), Ok(()));
+        assert_eq!(grid.value_at(0, 0), Some(CellValue::Fixed(1)));
+
+        // Same square as (0, 0)
+        assert_eq!(
+            grid.get(2, 2).unwrap().try_set(1),
+            Err(SudokuError::ConflictsWithFixed {
+                digit: 1,
+                peer: Coord::new(0, 0).unwrap()
+            })
+        );
+
+        grid.set_at(8, 4, CellValue::Unknown(vec![5]));
+        let error = grid.get(3, 4).unwrap().try_set(5).unwrap_err();
+        assert_eq!(
+            error,
+            SudokuError::EmptiesCell {
+                digit: 5,
+                peer: Coord::new(8, 4).unwrap()
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "5 is the only possibility left at (8, 4)"
+        );
+        // Nothing was changed
+        assert_eq!(
+            grid.value_at(3, 4),
+            Some(CellValue::Unknown(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]))
+        );
+    }
+
+    #[test]
+    fn test_set_with_changes() {
+        let grid = Grid::new();
+        grid.get(0, 0).unwrap().set(5);
+        grid.get(1, 1).unwrap().set(6);
+
+        // The row and column have already lost 5 where they cross the top left square
+        let changed = grid.get(4, 4).unwrap().set_with_changes(5);
+        let expected: Vec<Coord> = (1..9)
+            .map(|column| Coord::new(4, column).unwrap())
+            .filter(|&coord| coord != Coord::new(4, 4).unwrap())
+            .chain((1..9).map(|row| Coord::new(row, 4).unwrap()))
+            .filter(|&coord| coord != Coord::new(4, 4).unwrap())
+            .chain(
+                [(3, 3), (3, 5), (5, 3), (5, 5)]
+                    .iter()
+                    .map(|&(row, column)| Coord::new(row, column).unwrap()),
+            )
+            .collect();
+        assert_eq!(changed, expected);
+
+        // The solver sees the same thing as after set
+        let plain = Grid::new();
+        plain.get(0, 0).unwrap().set(5);
+        plain.get(1, 1).unwrap().set(6);
+        plain.get(4, 4).unwrap().set(5);
+        assert!(grid == plain);
+
+        // 6 is already gone from the rest of its square
+        let changed = grid.get(2, 2).unwrap().set_with_changes(6);
+        assert_eq!(changed.len(), 12);
+        assert!(changed.iter().all(|coord| coord.square() != 0));
+    }
+
+    #[test]
+    fn test_eliminate() {
+        let grid = Grid::new();
+        grid.get(0, 0).unwrap().set(1);
+        let cell = grid.get(0, 1).unwrap();
+
+        assert_eq!(cell.eliminate(1), Ok(EliminationOutcome::Unchanged));
+        assert_eq!(cell.eliminate(0), Err(SudokuError::InvalidDigit(0)));
+        for digit in 2..8 {
+            assert_eq!(cell.eliminate(digit), Ok(EliminationOutcome::Removed));
+        }
+        assert_eq!(cell.eliminate(8), Ok(EliminationOutcome::BecameSingle(9)));
+        assert_eq!(
+            cell.eliminate(9),
+            Err(SudokuError::LastCandidate {
+                digit: 9,
+                cell: Coord::new(0, 1).unwrap()
+            })
+        );
+        assert_eq!(grid.candidates_at(0, 1), Some(vec![9]));
+
+        // A Fixed cell keeps its digit
+        let fixed = grid.get(0, 0).unwrap();
+        assert_eq!(fixed.eliminate(2), Ok(EliminationOutcome::Unchanged));
+        assert!(fixed.eliminate(1).is_err());
+    }
+
+    #[test]
+    fn test_positions_of() {
+        let grid = Grid::new();
+        grid.get(0, 0).unwrap().set(1);
+        grid.get(4, 1).unwrap().set(1);
+        grid.get(1, 2)
+            .unwrap()
+            .set_value_exact(CellValue::Unknown(vec![2, 3]));
+
+        let coords = |positions: &[(usize, usize)]| -> Vec<Coord> {
+            positions
+                .iter()
+                .map(|&(x, y)| Coord::new(x, y).unwrap())
+                .collect()
+        };
+
+        // Once a digit is placed, it's the only position left in each of its Sections
+        assert_eq!(
+            grid.positions_of(1, (SectionType::Square, 0)),
+            coords(&[(0, 0)])
+        );
+        assert_eq!(
+            grid.positions_of(1, (SectionType::Square, 3)),
+            coords(&[(4, 1)])
+        );
+        assert_eq!(grid.positions_of(2, (SectionType::Column, 2)).len(), 9);
+        assert_eq!(
+            grid.positions_of(4, (SectionType::Row, 1)),
+            coords(&[
+                (1, 0),
+                (1, 1),
+                (1, 3),
+                (1, 4),
+                (1, 5),
+                (1, 6),
+                (1, 7),
+                (1, 8)
+            ])
+        );
+        assert!(grid.positions_of(1, (SectionType::Row, 9)).is_empty());
+    }
+
+    #[test]
+    fn test_most_constrained_cell() {
+        let grid = Grid::new();
+        let coord_of = |tie_break: TieBreak| grid.most_constrained_cell(tie_break).unwrap().coord();
+        grid.set_at(1, 2, CellValue::Unknown(vec![2, 3]));
+        grid.set_at(7, 7, CellValue::Unknown(vec![4, 5]));
+        // A cell with no possibilities is passed over
+        grid.set_at(8, 8, CellValue::Unknown(vec![]));
+
+        assert_eq!(coord_of(TieBreak::First), Coord::new(1, 2).unwrap());
+        assert_eq!(coord_of(TieBreak::Last), Coord::new(7, 7).unwrap());
+
+        grid.get(7, 0).unwrap().set(1);
+        assert_eq!(
+            coord_of(TieBreak::MostEmptyPeers),
+            Coord::new(1, 2).unwrap()
+        );
+        grid.get(1, 0).unwrap().set(6);
+        grid.get(0, 2).unwrap().set(7);
+        assert_eq!(
+            coord_of(TieBreak::MostEmptyPeers),
+            Coord::new(7, 7).unwrap()
+        );
+
+        let mut solved = Grid::new();
+        dlx::solve_grid(&mut solved);
+        assert!(solved.most_constrained_cell(TieBreak::First).is_none());
+    }
+
+    #[test]
+    fn test_render() {
+        let grid = Grid::new();
+        grid.get(0, 0).unwrap().set(5);
+        assert_eq!(grid.render(&DisplayOptions::default()), grid.to_string());
+
+        let options = DisplayOptions {
+            charset: Charset::Ascii,
+            show_candidates: false,
+            highlights: vec![Coord::new(0, 1).unwrap()],
+            coordinates: true,
+        };
+        let rendered = grid.render(&options);
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        // A header, then three lines per row and a line between each pair of rows
+        assert_eq!(lines.len(), 1 + 9 * 3 + 8);
+        assert_eq!(lines[0], "   1   2   3   4   5   6   7   8   9 ");
+        assert_eq!(&lines[1][..10], "     :...:");
+        assert_eq!(&lines[2][..10], "1  5 :...:");
+        assert_eq!(&lines[4][..14], "  ---+---+---+");
+        assert_eq!(&lines[12][..14], "  ===#===#===#");
+        assert!(rendered.is_ascii());
+    }
+
+    #[test]
+    fn test_annotations() {
+        let grid = Grid::new();
+        let corner = Coord::new(0, 0).unwrap();
+        let center = Coord::new(4, 4).unwrap();
+        let note = CellAnnotation {
+            color: Some(3),
+            note: "Only 1 or 7".to_string(),
+        };
+        grid.set_annotation(corner, note.clone());
+        grid.set_annotation(
+            center,
+            CellAnnotation {
+                color: Some(1),
+                ..CellAnnotation::default()
+            },
+        );
+        assert_eq!(grid.annotation(corner), Some(note.clone()));
+        assert_eq!(grid.annotation(Coord::new(0, 1).unwrap()), None);
+
+        // Solving doesn't touch them, and copies keep them
+        let mut solved = grid.clone();
+        crate::solver::solve_grid(&mut solved);
+        assert_eq!(solved.annotation(corner), Some(note.clone()));
+        assert!(grid == Grid::new());
+        assert_eq!(
+            grid.snapshot().to_grid().annotation(corner),
+            Some(note.clone())
+        );
+        let checkpoint = grid.checkpoint();
+        assert_eq!(
+            checkpoint.restore().unwrap().annotation(corner),
+            Some(note.clone())
+        );
+        #[cfg(feature = "json")]
+        {
+            let restored = GridCheckpoint::from_json(&checkpoint.to_json()).unwrap();
+            assert_eq!(
+                restored
+                    .restore()
+                    .unwrap()
+                    .annotation(center)
+                    .unwrap()
+                    .color,
+                Some(1)
+            );
+        }
 
-        return Result::Ok(());
+        grid.set_annotation(corner, CellAnnotation::default());
+        assert_eq!(grid.annotation(corner), None);
+        assert_eq!(solved.annotation(corner), Some(note));
     }
 }
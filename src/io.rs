@@ -0,0 +1,1049 @@
+//! Reading and writing puzzles in the file formats commonly used to share them.
+
+use crate::grid::Grid;
+use crate::pdf::PdfOptions;
+use crate::solver::{find_degeneracy, Degeneracy, SolveStatistics};
+use std::io::Read;
+use std::str::FromStr;
+
+/// The path that `load_puzzle` and `load_puzzles` take to mean standard input.
+pub const STDIN_PATH: &str = "-";
+
+/// A way of storing puzzles in a file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PuzzleFormat {
+    /// Nine rows of nine comma separated digits, with `0` or nothing for empty cells. Several
+    /// puzzles can be stacked in one file, separated by blank lines.
+    Csv,
+
+    /// A single line of 81 characters, with `.` or `0` for empty cells.
+    Line,
+
+    /// Any number of puzzles, each as one line of 81 characters.
+    Sdm,
+
+    /// Nine rows of nine characters with `.` for empty cells, optionally preceded by `#` comments.
+    Sdk,
+
+    /// An array of nine arrays of nine digits, with `0` or `null` for empty cells.
+    Json,
+
+    /// A printable page; puzzles can be written as PDFs but not read from them.
+    Pdf,
+}
+
+impl PuzzleFormat {
+    /// Guess the format of a file from its extension, returning None for unknown extensions.
+    pub fn from_filename(filename: &str) -> Option<PuzzleFormat> {
+        let extension = std::path::Path::new(filename).extension()?.to_str()?;
+        PuzzleFormat::from_str(extension).ok()
+    }
+
+    /// Whether the format can hold more than one puzzle.
+    pub fn holds_many(&self) -> bool {
+        *self == PuzzleFormat::Sdm || *self == PuzzleFormat::Csv
+    }
+}
+
+impl FromStr for PuzzleFormat {
+    // Needed for argparse
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let format = match s.to_ascii_lowercase().as_str() {
+            "csv" => PuzzleFormat::Csv,
+            "line" | "txt" => PuzzleFormat::Line,
+            "sdm" => PuzzleFormat::Sdm,
+            "sdk" => PuzzleFormat::Sdk,
+            "json" => PuzzleFormat::Json,
+            "pdf" => PuzzleFormat::Pdf,
+            _ => return Err(format!("{} is not a valid puzzle format", s)),
+        };
+        Ok(format)
+    }
+}
+
+// Build a Grid from 81 digits listed row by row, with 0 for empty cells
+fn grid_from_digits(digits: &[u8]) -> Grid {
+    let mut rows = [[0; 9]; 9];
+    for (index, &digit) in digits.iter().enumerate() {
+        rows[index / 9][index % 9] = digit;
+    }
+    Grid::from_digits(rows)
+}
+
+fn digits_from_grid(grid: &Grid) -> Vec<u8> {
+    grid.to_digits().iter().flatten().copied().collect()
+}
+
+// Reads a row of single character cells, like the ones in 81 character lines and SDK files
+fn parse_characters(text: &str, expected_length: usize) -> Result<Vec<u8>, String> {
+    let digits = text
+        .chars()
+        .map(|character| match character {
+            '1'..='9' => Ok(character as u8 - b'0'),
+            '0' | '.' | '_' | '-' | '*' => Ok(0),
+            _ => Err(format!("Invalid cell value '{}'", character)),
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+
+    if digits.len() != expected_length {
+        return Err(format!(
+            "Expected {} cells but found {} in \"{}\"",
+            expected_length,
+            digits.len(),
+            text
+        ));
+    }
+    Ok(digits)
+}
+
+/// Something odd about a CSV file that didn't stop it from being read. Lines, columns, and
+/// puzzles are counted from 1.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CsvWarning {
+    /// The line had fewer than 9 columns; the missing cells were left empty.
+    MissingColumns { line: usize, found: usize },
+
+    /// The line had more than 9 columns; the extra ones were ignored.
+    ExtraColumns { line: usize, found: usize },
+
+    /// The cell held a number larger than 9 and was left empty.
+    ValueTooLarge {
+        line: usize,
+        column: usize,
+        value: u32,
+    },
+
+    /// The puzzle has fewer than 17 clues, so it can't have a unique solution.
+    TooFewClues { puzzle: usize, clues: usize },
+
+    /// Two digits appear nowhere in the puzzle, so it can't have a unique solution.
+    MissingDigits {
+        puzzle: usize,
+        first: u8,
+        second: u8,
+    },
+}
+
+impl std::fmt::Display for CsvWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CsvWarning::MissingColumns { line, found } => write!(
+                f,
+                "Line {} has only {} columns; the rest were left empty",
+                line, found
+            ),
+            CsvWarning::ExtraColumns { line, found } => write!(
+                f,
+                "Line {} has {} columns; everything after the 9th was ignored",
+                line, found
+            ),
+            CsvWarning::ValueTooLarge {
+                line,
+                column,
+                value,
+            } => write!(
+                f,
+                "Line {} column {} holds {}, which is larger than 9; it was left empty",
+                line, column, value
+            ),
+            CsvWarning::TooFewClues { puzzle, clues } => write!(
+                f,
+                "Puzzle {} has {} clues; puzzles with fewer than 17 never have a unique solution",
+                puzzle, clues
+            ),
+            CsvWarning::MissingDigits {
+                puzzle,
+                first,
+                second,
+            } => write!(
+                f,
+                "Puzzle {} has no {}s or {}s, so it can't have a unique solution",
+                puzzle, first, second
+            ),
+        }
+    }
+}
+
+/// Read the puzzles in a CSV file like `parse_puzzles`, but rather than rejecting or silently
+/// accepting rows with the wrong number of columns and numbers larger than 9, read what can be
+/// read and report them as warnings. Anything that isn't a number is still an error.
+pub fn parse_csv_with_warnings(contents: &str) -> Result<(Vec<Grid>, Vec<CsvWarning>), String> {
+    let mut grids = Vec::new();
+    let mut warnings = Vec::new();
+    let mut digits = vec![0; 81];
+    let mut row = 0;
+
+    for (line_index, line) in contents.lines().enumerate() {
+        let line_number = line_index + 1;
+        // Comments hold metadata such as how the puzzle was generated
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        // A blank line after some rows ends a puzzle; so does the end of the file
+        if line.trim().is_empty() {
+            if row > 0 {
+                finish_csv_puzzle(&mut digits, &mut grids, &mut warnings);
+                row = 0;
+            }
+            continue;
+        }
+        if row > 8 {
+            return Err(format!(
+                "Hit row limit at line {}; separate puzzles with a blank line",
+                line_number
+            ));
+        }
+
+        let values: Vec<&str> = line.split(',').map(|value| value.trim()).collect();
+        if values.len() < 9 {
+            warnings.push(CsvWarning::MissingColumns {
+                line: line_number,
+                found: values.len(),
+            });
+        } else if values.len() > 9 {
+            warnings.push(CsvWarning::ExtraColumns {
+                line: line_number,
+                found: values.len(),
+            });
+        }
+
+        for (column, value) in values.into_iter().take(9).enumerate() {
+            if value.is_empty() {
+                continue;
+            }
+            match u32::from_str(value) {
+                Ok(digit) if digit <= 9 => digits[row * 9 + column] = digit as u8,
+                Ok(value) => warnings.push(CsvWarning::ValueTooLarge {
+                    line: line_number,
+                    column: column + 1,
+                    value,
+                }),
+                Err(_) => return Err(format!("Invalid cell value '{}'", value)),
+            }
+        }
+        row += 1;
+    }
+
+    // An empty file is read as one empty puzzle
+    if row > 0 || grids.is_empty() {
+        finish_csv_puzzle(&mut digits, &mut grids, &mut warnings);
+    }
+    Ok((grids, warnings))
+}
+
+// Add the puzzle read so far to grids and clear digits for the next one
+fn finish_csv_puzzle(digits: &mut [u8], grids: &mut Vec<Grid>, warnings: &mut Vec<CsvWarning>) {
+    let grid = grid_from_digits(digits);
+    let puzzle = grids.len() + 1;
+    match find_degeneracy(&grid) {
+        Some(Degeneracy::TooFewClues(clues)) => {
+            warnings.push(CsvWarning::TooFewClues { puzzle, clues })
+        }
+        Some(Degeneracy::MissingDigits(first, second)) => {
+            warnings.push(CsvWarning::MissingDigits {
+                puzzle,
+                first,
+                second,
+            })
+        }
+        None => {}
+    }
+    grids.push(grid);
+    digits.iter_mut().for_each(|digit| *digit = 0);
+}
+
+fn parse_sdk(contents: &str) -> Result<Grid, String> {
+    let rows: Vec<&str> = contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('['))
+        .collect();
+
+    if rows.len() != 9 {
+        return Err(format!("Expected 9 rows but found {}", rows.len()));
+    }
+
+    let mut digits = Vec::with_capacity(81);
+    for row in rows {
+        digits.extend(parse_characters(row, 9)?);
+    }
+    Ok(grid_from_digits(&digits))
+}
+
+// Split a JSON object into its keys and the text of their values, which are left unparsed. Keys
+// are compared as written, so one that uses escapes won't match its unescaped name.
+fn json_members(object: &str) -> Result<Vec<(&str, &str)>, String> {
+    let object = object.trim();
+    let inner = object
+        .strip_prefix('{')
+        .and_then(|rest| rest.strip_suffix('}'))
+        .ok_or("Expected a JSON object")?;
+
+    // Commas only separate members outside of strings and nested arrays or objects
+    let mut values = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (offset, character) in inner.char_indices() {
+        if in_string {
+            match character {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match character {
+            '"' => in_string = true,
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                values.push(&inner[start..offset]);
+                start = offset + 1;
+            }
+            _ => {}
+        }
+    }
+    if in_string || depth != 0 {
+        return Err("Unbalanced brackets or quotes".to_string());
+    }
+    if !inner[start..].trim().is_empty() {
+        values.push(&inner[start..]);
+    }
+
+    values
+        .into_iter()
+        .map(|member| {
+            let member = member.trim();
+            let mut escaped = false;
+            let key_end = member
+                .strip_prefix('"')
+                .and_then(|rest| {
+                    rest.find(|character| match character {
+                        _ if escaped => {
+                            escaped = false;
+                            false
+                        }
+                        '\\' => {
+                            escaped = true;
+                            false
+                        }
+                        _ => character == '"',
+                    })
+                })
+                .ok_or(format!("Expected a quoted key in '{}'", member))?;
+            let key = &member[1..=key_end];
+            match member[key_end + 2..].trim_start().strip_prefix(':') {
+                Some(value) => Ok((key, value.trim())),
+                None => Err(format!("Expected ':' after the key \"{}\"", key)),
+            }
+        })
+        .collect()
+}
+
+// Quote a string for JSON, escaping the characters it can't hold as they are
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ if (character as u32) < 0x20 => {
+                quoted.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            _ => quoted.push(character),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn parse_json(contents: &str) -> Result<Grid, String> {
+    // Puzzles written with metadata keep the grid in the "grid" field of an object
+    if contents.trim_start().starts_with('{') {
+        let members = json_members(contents)?;
+        return match members.iter().find(|(key, _)| *key == "grid") {
+            Some((_, grid)) => parse_json(grid),
+            None => Err("Expected a \"grid\" field holding the puzzle".to_string()),
+        };
+    }
+
+    let mut rows: Vec<Vec<u8>> = Vec::new();
+    let mut depth = 0;
+    let mut token = String::new();
+
+    // The only thing we need to read is a nested array of numbers, so rather than pull in a JSON
+    // library we track how deep in the brackets we are and split numbers on everything else.
+    fn end_token(token: &mut String, rows: &mut [Vec<u8>]) -> Result<(), String> {
+        if token.is_empty() {
+            return Ok(());
+        }
+        let digit = match token.as_str() {
+            "null" => 0,
+            _ => match u8::from_str(token) {
+                Ok(digit) if digit <= 9 => digit,
+                _ => return Err(format!("Invalid cell value '{}'", token)),
+            },
+        };
+        match rows.last_mut() {
+            Some(row) => row.push(digit),
+            None => return Err(format!("Value '{}' is outside of a row", token)),
+        }
+        token.clear();
+        Ok(())
+    }
+
+    for character in contents.chars() {
+        match character {
+            '[' => {
+                depth += 1;
+                match depth {
+                    1 => {}
+                    2 => rows.push(Vec::new()),
+                    _ => return Err("Arrays are nested too deeply".to_string()),
+                }
+            }
+            ']' | ',' => {
+                if depth == 2 {
+                    end_token(&mut token, &mut rows)?;
+                }
+                if character == ']' {
+                    if depth == 0 {
+                        return Err("Unbalanced brackets".to_string());
+                    }
+                    depth -= 1;
+                }
+            }
+            _ if character.is_whitespace() => {}
+            _ if depth == 2 => token.push(character),
+            _ => return Err(format!("Unexpected character '{}'", character)),
+        }
+    }
+
+    if depth != 0 {
+        return Err("Unbalanced brackets".to_string());
+    }
+    if rows.len() != 9 || rows.iter().any(|row| row.len() != 9) {
+        return Err("Expected 9 rows of 9 values".to_string());
+    }
+
+    let digits: Vec<u8> = rows.into_iter().flatten().collect();
+    Ok(grid_from_digits(&digits))
+}
+
+/// Read every puzzle stored in `contents`. Formats that hold a single puzzle always return one
+/// `Grid`. Each `Grid`'s empty cells have their possibilities filled in from the given digits.
+pub fn parse_puzzles(contents: &str, format: PuzzleFormat) -> Result<Vec<Grid>, String> {
+    let grid = match format {
+        PuzzleFormat::Csv => return parse_csv_with_warnings(contents).map(|(grids, _)| grids),
+        PuzzleFormat::Line | PuzzleFormat::Sdm => {
+            let lines: Vec<&str> = contents
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .collect();
+            if format == PuzzleFormat::Line && lines.len() != 1 {
+                return Err(format!("Expected 1 line but found {}", lines.len()));
+            }
+
+            return lines
+                .into_iter()
+                .map(|line| parse_characters(line, 81).map(|digits| grid_from_digits(&digits)))
+                .collect();
+        }
+        PuzzleFormat::Sdk => parse_sdk(contents)?,
+        PuzzleFormat::Json => parse_json(contents)?,
+        PuzzleFormat::Pdf => return Err("Puzzles can't be read from PDFs".to_string()),
+    };
+
+    Ok(vec![grid])
+}
+
+/// A puzzle written out in one of the formats `parse_puzzles` reads, for fuzzing the parsers. It
+/// starts out well formed, then has a few characters swapped for ones that mean something in at
+/// least one of the formats, so it's often malformed in ways a parser has to cope with.
+#[cfg(feature = "arbitrary")]
+#[derive(Clone, Debug)]
+pub struct PuzzleText {
+    pub format: PuzzleFormat,
+    pub text: String,
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for PuzzleText {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        const FORMATS: [PuzzleFormat; 5] = [
+            PuzzleFormat::Csv,
+            PuzzleFormat::Line,
+            PuzzleFormat::Sdm,
+            PuzzleFormat::Sdk,
+            PuzzleFormat::Json,
+        ];
+        const SPECIAL: [char; 14] = [
+            '0', '1', '9', '.', ',', '\n', ' ', '#', '[', ']', '{', '}', '"', ':',
+        ];
+
+        let format = *u.choose(&FORMATS)?;
+        let grid: Grid = u.arbitrary()?;
+        let mut characters: Vec<char> = format_puzzles(std::slice::from_ref(&grid), format)
+            .expect("Every text format holds one puzzle")
+            .chars()
+            .collect();
+        for _ in 0..u.int_in_range(0..=3)? {
+            let index = u.int_in_range(0..=characters.len() - 1)?;
+            characters[index] = *u.choose(&SPECIAL)?;
+        }
+
+        Ok(PuzzleText {
+            format,
+            text: characters.into_iter().collect(),
+        })
+    }
+}
+
+// Read the whole of a file, or of standard input for STDIN_PATH
+fn read_input(path: &str) -> Result<String, String> {
+    let result = if path == STDIN_PATH {
+        let mut contents = String::new();
+        std::io::stdin()
+            .read_to_string(&mut contents)
+            .map(|_| contents)
+    } else {
+        std::fs::read_to_string(path)
+    };
+
+    result.map_err(|e| {
+        let name = if path == STDIN_PATH {
+            "standard input"
+        } else {
+            path
+        };
+        format!("Error while reading {}: \"{}\"", name, e)
+    })
+}
+
+/// Read every puzzle in the file at `path`, or on standard input if `path` is `STDIN_PATH`,
+/// along with any warnings about CSV input; other formats never have warnings.
+pub fn load_puzzles(
+    path: &str,
+    format: PuzzleFormat,
+) -> Result<(Vec<Grid>, Vec<CsvWarning>), String> {
+    let contents = read_input(path)?;
+    let result = match format {
+        PuzzleFormat::Csv => parse_csv_with_warnings(&contents),
+        _ => parse_puzzles(&contents, format).map(|grids| (grids, Vec::new())),
+    };
+
+    result.map_err(|e| format!("Error while reading grid: \"{}\"", e))
+}
+
+/// Like `load_puzzles`, but for when exactly one puzzle is expected.
+pub fn load_puzzle(path: &str, format: PuzzleFormat) -> Result<(Grid, Vec<CsvWarning>), String> {
+    let (mut grids, warnings) = load_puzzles(path, format)?;
+    if grids.len() != 1 {
+        return Err(format!(
+            "Expected 1 puzzle but found {} in {}",
+            grids.len(),
+            path
+        ));
+    }
+    Ok((grids.remove(0), warnings))
+}
+
+/// Where a generated puzzle came from, so that a published puzzle can be traced back and generated
+/// again. Fields that aren't known are left as None and aren't written.
+#[derive(Clone, Debug, Default)]
+pub struct PuzzleMetadata {
+    /// The name of the difficulty tier the puzzle was generated for.
+    pub difficulty: Option<String>,
+
+    /// The seed the generator's random number generator started from.
+    pub seed: Option<u64>,
+
+    /// The version of this crate that generated the puzzle.
+    pub generator_version: Option<String>,
+
+    /// How solving the puzzle with the difficulty tier's strategies went.
+    pub statistics: Option<SolveStatistics>,
+}
+
+impl PuzzleMetadata {
+    /// Metadata for a puzzle generated by this version of the crate.
+    pub fn generated(difficulty: &str, seed: u64, statistics: SolveStatistics) -> PuzzleMetadata {
+        PuzzleMetadata {
+            difficulty: Some(difficulty.to_string()),
+            seed: Some(seed),
+            generator_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            statistics: Some(statistics),
+        }
+    }
+
+    /// Every field that's set as a name and a value, in the order they're written. Only the
+    /// difficulty and version are text; the rest are numbers.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+        if let Some(difficulty) = &self.difficulty {
+            fields.push(("difficulty", difficulty.clone()));
+        }
+        if let Some(seed) = self.seed {
+            fields.push(("seed", seed.to_string()));
+        }
+        if let Some(generator_version) = &self.generator_version {
+            fields.push(("generator_version", generator_version.clone()));
+        }
+        if let Some(statistics) = &self.statistics {
+            fields.push(("singles", statistics.singles.to_string()));
+            fields.push(("hidden_singles", statistics.hidden_singles.to_string()));
+            fields.push(("naked_subsets", statistics.naked_subsets.to_string()));
+            fields.push(("hidden_subsets", statistics.hidden_subsets.to_string()));
+            fields.push((
+                "possibility_groups",
+                statistics.possibility_groups.to_string(),
+            ));
+            fields.push((
+                "useful_constraints",
+                statistics.useful_constraints.to_string(),
+            ));
+            fields.push((
+                "pointing_constraints",
+                statistics.pointing_constraints.to_string(),
+            ));
+            fields.push((
+                "claiming_constraints",
+                statistics.claiming_constraints.to_string(),
+            ));
+            fields.push(("guesses", statistics.guesses.to_string()));
+        }
+        fields
+    }
+}
+
+/// Write puzzles out as text in `format`. Fails for PDFs, which aren't text, and when given more
+/// than one puzzle for a format that only holds one.
+pub fn format_puzzles(grids: &[Grid], format: PuzzleFormat) -> Result<String, String> {
+    format_puzzles_with_metadata(grids, &[], format)
+}
+
+/// Like `format_puzzles`, but also writes the metadata of each puzzle, matched up by position, in
+/// formats that have room for it: as `#` comments before each CSV puzzle, `#C` comments in SDK
+/// files, and as fields next to the grid in JSON. The other formats leave it out.
+pub fn format_puzzles_with_metadata(
+    grids: &[Grid],
+    metadata: &[PuzzleMetadata],
+    format: PuzzleFormat,
+) -> Result<String, String> {
+    if grids.len() != 1 && !format.holds_many() {
+        return Err(format!(
+            "{:?} files hold one puzzle, but {} were given",
+            format,
+            grids.len()
+        ));
+    }
+
+    let mut text = String::new();
+    for (index, grid) in grids.iter().enumerate() {
+        let digits = digits_from_grid(grid);
+        let fields = metadata
+            .get(index)
+            .map(|metadata| metadata.fields())
+            .unwrap_or_default();
+        match format {
+            PuzzleFormat::Csv => {
+                if index > 0 {
+                    text.push('\n');
+                }
+                for (name, value) in fields.iter() {
+                    text.push_str(&format!("# {}: {}\n", name, value));
+                }
+                for row in digits.chunks(9) {
+                    let row: Vec<String> = row.iter().map(|digit| digit.to_string()).collect();
+                    text.push_str(&row.join(","));
+                    text.push('\n');
+                }
+            }
+            PuzzleFormat::Line | PuzzleFormat::Sdk => {
+                if format == PuzzleFormat::Sdk {
+                    for (name, value) in fields.iter() {
+                        text.push_str(&format!("#C {}: {}\n", name, value));
+                    }
+                }
+                for (index, &digit) in digits.iter().enumerate() {
+                    text.push(if digit == 0 {
+                        '.'
+                    } else {
+                        (b'0' + digit) as char
+                    });
+                    if format == PuzzleFormat::Sdk && index % 9 == 8 {
+                        text.push('\n');
+                    }
+                }
+                if format == PuzzleFormat::Line {
+                    text.push('\n');
+                }
+            }
+            PuzzleFormat::Sdm => {
+                for &digit in digits.iter() {
+                    text.push((b'0' + digit) as char);
+                }
+                text.push('\n');
+            }
+            PuzzleFormat::Json => {
+                let rows: Vec<String> = digits
+                    .chunks(9)
+                    .map(|row| {
+                        let row: Vec<String> = row.iter().map(|digit| digit.to_string()).collect();
+                        format!("  [{}]", row.join(", "))
+                    })
+                    .collect();
+                if fields.is_empty() {
+                    text.push_str(&format!("[\n{}\n]\n", rows.join(",\n")));
+                } else {
+                    text.push_str("{\n");
+                    for (name, value) in fields.iter() {
+                        let value = match *name {
+                            "difficulty" | "generator_version" => json_string(value),
+                            _ => value.clone(),
+                        };
+                        text.push_str(&format!("  \"{}\": {},\n", name, value));
+                    }
+                    let rows: Vec<String> = rows.iter().map(|row| format!("  {}", row)).collect();
+                    text.push_str(&format!("  \"grid\": [\n{}\n  ]\n}}\n", rows.join(",\n")));
+                }
+            }
+            PuzzleFormat::Pdf => return Err("PDFs can't be written as text".to_string()),
+        }
+    }
+
+    Ok(text)
+}
+
+/// Write puzzles to `filename` in `format`. `pdf_options` controls how the page is laid out, and
+/// only applies to PDFs.
+pub fn write_puzzles(
+    grids: &[Grid],
+    filename: &str,
+    format: PuzzleFormat,
+    pdf_options: &PdfOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_puzzles_with_metadata(grids, &[], filename, format, pdf_options)
+}
+
+/// Like `write_puzzles`, but also writes each puzzle's metadata where the format has room for
+/// it; see `format_puzzles_with_metadata`. PDFs are titled with the difficulty and list the rest
+/// under the grid.
+pub fn write_puzzles_with_metadata(
+    grids: &[Grid],
+    metadata: &[PuzzleMetadata],
+    filename: &str,
+    format: PuzzleFormat,
+    pdf_options: &PdfOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if format == PuzzleFormat::Pdf {
+        if grids.len() != 1 {
+            return Err(format!("PDFs hold one puzzle, but {} were given", grids.len()).into());
+        }
+        let metadata = metadata.first().cloned().unwrap_or_default();
+        return crate::pdf::draw_page(&grids[0], filename, pdf_options, &metadata);
+    }
+
+    let text = format_puzzles_with_metadata(grids, metadata, format)?;
+    std::fs::write(filename, text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::CellValue;
+    use crate::io::*;
+    use crate::test_utils::random_puzzle;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = SmallRng::seed_from_u64(377);
+        let (puzzle, _uniqueness) = random_puzzle(&mut rng, 30);
+
+        for &format in [
+            PuzzleFormat::Csv,
+            PuzzleFormat::Line,
+            PuzzleFormat::Sdm,
+            PuzzleFormat::Sdk,
+            PuzzleFormat::Json,
+        ]
+        .iter()
+        {
+            let text = format_puzzles(std::slice::from_ref(&puzzle), format).unwrap();
+            let grids = parse_puzzles(&text, format).unwrap();
+            assert_eq!(grids.len(), 1);
+            assert_eq!(grids[0].snapshot(), puzzle.snapshot(), "{:?}", format);
+        }
+    }
+
+    const LINE: &str =
+        "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    #[test]
+    fn test_parse_puzzles() {
+        let line = LINE;
+        let grid = &parse_puzzles(line, PuzzleFormat::Line).unwrap()[0];
+        assert_eq!(
+            grid.get(0, 0).unwrap().get_value_copy(),
+            CellValue::Fixed(5)
+        );
+        assert_eq!(
+            grid.get(0, 2).unwrap().get_value_copy(),
+            CellValue::Unknown(vec![1, 2, 4])
+        );
+
+        let sdm = format!("{}\n{}\n", line, line.replace('.', "0"));
+        let grids = parse_puzzles(&sdm, PuzzleFormat::Sdm).unwrap();
+        assert_eq!(grids.len(), 2);
+        assert_eq!(grids[0].snapshot(), grids[1].snapshot());
+        assert!(parse_puzzles(&sdm, PuzzleFormat::Line).is_err());
+        assert!(format_puzzles(&grids, PuzzleFormat::Sdk).is_err());
+
+        let sdk = "#A comment\n53..7....\n6..195...\n.98....6.\n8...6...3\n4..8.3..1\n7...2...6\n.6....28.\n...419..5\n....8..79\n";
+        assert_eq!(
+            parse_puzzles(sdk, PuzzleFormat::Sdk).unwrap()[0].snapshot(),
+            grid.snapshot()
+        );
+
+        assert!(parse_puzzles("[[1, 2]]", PuzzleFormat::Json).is_err());
+        assert!(parse_puzzles("1,2,x", PuzzleFormat::Csv).is_err());
+        assert!(parse_puzzles(&line[1..], PuzzleFormat::Line).is_err());
+
+        assert_eq!(
+            PuzzleFormat::from_filename("puzzles/book.SDM"),
+            Some(PuzzleFormat::Sdm)
+        );
+        assert_eq!(PuzzleFormat::from_filename("puzzle"), None);
+    }
+
+    #[test]
+    fn test_load_puzzles() {
+        let path = std::env::temp_dir().join("sudoku_solver_test_load_puzzles.sdm");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, format!("{}\n{}\n", LINE, LINE)).unwrap();
+
+        let (grids, warnings) = load_puzzles(path, PuzzleFormat::Sdm).unwrap();
+        assert_eq!(grids.len(), 2);
+        assert!(warnings.is_empty());
+        assert!(load_puzzle(path, PuzzleFormat::Sdm).is_err());
+        assert!(load_puzzle(path, PuzzleFormat::Line).is_err());
+
+        std::fs::write(path, "1,2,3\n").unwrap();
+        let (grid, warnings) = load_puzzle(path, PuzzleFormat::Csv).unwrap();
+        assert_eq!(grid.clue_count(), 3);
+        assert_eq!(warnings.len(), 2);
+
+        std::fs::remove_file(path).unwrap();
+        assert!(load_puzzle(path, PuzzleFormat::Csv).is_err());
+    }
+
+    #[test]
+    fn test_csv_warnings() {
+        let text = format_puzzles(
+            &parse_puzzles(LINE, PuzzleFormat::Line).unwrap(),
+            PuzzleFormat::Csv,
+        )
+        .unwrap();
+        let (grids, warnings) = parse_csv_with_warnings(&text).unwrap();
+        assert!(warnings.is_empty());
+        let grid = &grids[0];
+
+        // Drop the last cell of the first row, add a tenth to the second, and make the first
+        // cell of the third row too large
+        let mut rows: Vec<String> = text.lines().map(|row| row.to_string()).collect();
+        let last_comma = rows[0].rfind(',').unwrap();
+        rows[0].truncate(last_comma);
+        rows[1].push_str(",4");
+        rows[2].replace_range(0..1, "10");
+        let (modified, warnings) = parse_csv_with_warnings(&rows.join("\n")).unwrap();
+        let modified = &modified[0];
+        assert_eq!(
+            warnings,
+            vec![
+                CsvWarning::MissingColumns { line: 1, found: 8 },
+                CsvWarning::ExtraColumns { line: 2, found: 10 },
+                CsvWarning::ValueTooLarge {
+                    line: 3,
+                    column: 1,
+                    value: 10
+                },
+            ]
+        );
+        assert_eq!(modified.to_digits()[1], grid.to_digits()[1]);
+        assert_eq!(modified.to_digits()[2][0], 0);
+
+        let (_grid, warnings) = parse_csv_with_warnings("1,2,3\n").unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                CsvWarning::MissingColumns { line: 1, found: 3 },
+                CsvWarning::TooFewClues {
+                    puzzle: 1,
+                    clues: 3
+                }
+            ]
+        );
+
+        // Without its 8s and 9s the puzzle still has enough clues, but they could be swapped
+        let without_8_or_9 = text.replace(['8', '9'], "0");
+        let (_grids, warnings) = parse_csv_with_warnings(&without_8_or_9).unwrap();
+        assert_eq!(
+            warnings,
+            vec![CsvWarning::MissingDigits {
+                puzzle: 1,
+                first: 8,
+                second: 9
+            }]
+        );
+        assert_eq!(find_degeneracy(grid), None);
+    }
+
+    #[test]
+    fn test_stacked_csv() {
+        let grid = parse_puzzles(LINE, PuzzleFormat::Line).unwrap().remove(0);
+        let empty = Grid::new();
+        let text = format_puzzles(&[grid.clone(), empty, grid], PuzzleFormat::Csv).unwrap();
+
+        let (grids, warnings) = parse_csv_with_warnings(&format!("\n{}\n\n", text)).unwrap();
+        assert_eq!(grids.len(), 3);
+        assert_eq!(grids[0].snapshot(), grids[2].snapshot());
+        assert_eq!(grids[1].clue_count(), 0);
+        assert_eq!(
+            warnings,
+            vec![CsvWarning::TooFewClues {
+                puzzle: 2,
+                clues: 0
+            }]
+        );
+
+        // Without the blank lines the puzzles run together
+        let joined: String = text
+            .lines()
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(parse_csv_with_warnings(&joined).is_err());
+    }
+
+    #[test]
+    fn test_metadata() {
+        let grid = parse_puzzles(LINE, PuzzleFormat::Line).unwrap().remove(0);
+        let metadata = PuzzleMetadata {
+            difficulty: Some("Hard".to_string()),
+            seed: Some(418),
+            generator_version: Some("1.2.3".to_string()),
+            statistics: None,
+        };
+
+        let csv = format_puzzles_with_metadata(
+            &[grid.clone(), grid.clone()],
+            std::slice::from_ref(&metadata),
+            PuzzleFormat::Csv,
+        )
+        .unwrap();
+        assert!(csv.starts_with("# difficulty: Hard\n# seed: 418\n# generator_version: 1.2.3\n"));
+        let grids = parse_puzzles(&csv, PuzzleFormat::Csv).unwrap();
+        assert_eq!(grids.len(), 2);
+        assert_eq!(grids[0].snapshot(), grid.snapshot());
+
+        for &format in [PuzzleFormat::Sdk, PuzzleFormat::Json].iter() {
+            let text = format_puzzles_with_metadata(
+                std::slice::from_ref(&grid),
+                std::slice::from_ref(&metadata),
+                format,
+            )
+            .unwrap();
+            assert!(
+                text.contains("seed: 418") || text.contains("\"seed\": 418"),
+                "{}",
+                text
+            );
+            let grids = parse_puzzles(&text, format).unwrap();
+            assert_eq!(grids[0].snapshot(), grid.snapshot(), "{:?}", format);
+        }
+
+        // Strings are escaped for JSON, and text that looks like a grid field doesn't confuse parsing
+        let tricky = PuzzleMetadata {
+            difficulty: Some("\"grid\": [[1]], \\ \u{301}\n".to_string()),
+            generator_version: Some("{[".to_string()),
+            ..metadata.clone()
+        };
+        let text = format_puzzles_with_metadata(
+            std::slice::from_ref(&grid),
+            std::slice::from_ref(&tricky),
+            PuzzleFormat::Json,
+        )
+        .unwrap();
+        assert!(
+            text.contains("\"difficulty\": \"\\\"grid\\\": [[1]], \\\\ \u{301}\\n\","),
+            "{}",
+            text
+        );
+        let grids = parse_puzzles(&text, PuzzleFormat::Json).unwrap();
+        assert_eq!(grids[0].snapshot(), grid.snapshot());
+        #[cfg(feature = "json")]
+        {
+            let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+            assert_eq!(value["difficulty"], tricky.difficulty.as_deref().unwrap());
+        }
+        assert!(parse_puzzles("{\"seed\": 1}", PuzzleFormat::Json).is_err());
+        assert!(parse_puzzles("{\"grid\": [[1, 2]", PuzzleFormat::Json).is_err());
+
+        // Every technique counted gets written, including the subsets and both kinds of constraint
+        let mut statistics = SolveStatistics::new();
+        statistics.naked_subsets = 2;
+        statistics.hidden_subsets = 3;
+        statistics.pointing_constraints = 4;
+        statistics.claiming_constraints = 5;
+        let fields = PuzzleMetadata {
+            statistics: Some(statistics),
+            ..metadata
+        }
+        .fields();
+        for &(name, value) in [
+            ("naked_subsets", "2"),
+            ("hidden_subsets", "3"),
+            ("pointing_constraints", "4"),
+            ("claiming_constraints", "5"),
+        ]
+        .iter()
+        {
+            assert!(fields.contains(&(name, value.to_string())), "{}", name);
+        }
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_puzzle_text() {
+        use crate::solver::solve_grid;
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let mut rng = SmallRng::seed_from_u64(357);
+        let mut parsed = 0;
+        for _ in 0..100 {
+            let mut data = vec![0u8; rng.gen_range(0, 200)];
+            rng.fill_bytes(&mut data);
+            let puzzle = PuzzleText::arbitrary(&mut Unstructured::new(&data)).unwrap();
+
+            // Whatever the parsers make of it has to be safe to solve, repeated digits and all
+            if let Ok(grids) = parse_puzzles(&puzzle.text, puzzle.format) {
+                for mut grid in grids {
+                    solve_grid(&mut grid);
+                    parsed += 1;
+                }
+            }
+        }
+        assert!(parsed > 0);
+    }
+}
@@ -1,4 +1,12 @@
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod difficulty;
 pub mod generator;
+pub mod grading;
 pub mod grid;
+pub mod io;
 pub mod pdf;
 pub mod solver;
+pub mod test_utils;
+pub mod topology;
+pub mod transform;
@@ -1,23 +1,416 @@
-use crate::grid::{CellValue, Grid};
+use crate::grid::{CellValue, Coord, DisplayOptions, Grid};
+use crate::io::PuzzleMetadata;
+use crate::solver::SolveReplay;
 use printpdf::*;
 use std::fs::File;
 use std::io::BufWriter;
+use std::str::FromStr;
 
+const A4: (Mm, Mm) = (Mm(215.0), Mm(279.0));
+
+// Where a single grid goes on the page
 const BOTTOM_LEFT_X: f64 = 10.0;
 const BOTTOM_LEFT_Y: f64 = 279.0 - 200.0 - 10.0;
 const GRID_DIMENSION: f64 = 190.0;
 
-const A4: (Mm, Mm) = (Mm(215.0), Mm(279.0));
+// The space left between the page's edges and each grid when two share a page side by side
+const TWO_COLUMN_MARGIN: f64 = 7.5;
+
+// Captions go under a single grid, wrapped to fit the page
+const CAPTION_FONT_SIZE: i64 = 12;
+const CAPTION_LINE_HEIGHT: f64 = 6.0;
+const CAPTION_LINE_LENGTH: usize = 85;
+
+// Metadata goes in small print at the bottom of the page
+const FOOTER_FONT_SIZE: i64 = 8;
+const FOOTER_Y: f64 = 10.0;
+
+// A book's section titles go on a page of their own, part way down
+const DIVIDER_FONT_SIZE: i64 = 36;
+const DIVIDER_Y: f64 = 160.0;
+const DIVIDER_LINE_HEIGHT: f64 = 14.0;
+
+// Everything below is worked out from the size of the board and of the space it's drawn in, so
+// that only these need to change for boards of other sizes
+const BOX_SIZE: usize = 3; // Cells along each side of a square section
+const BOARD_SIZE: usize = BOX_SIZE * BOX_SIZE;
+
+// Font sizes in points for a given height in mm, chosen so that a 9x9 board filling the page uses
+// 45pt digits, 12pt candidates, and 14pt coordinates
+const FIXED_VALUE_POINTS_PER_MM: f64 = 2.13;
+const CANDIDATE_POINTS_PER_MM: f64 = 1.7;
+const LABEL_POINTS_PER_MM: f64 = 0.66;
+
+// Where text starts inside its cell (or candidate's cell), as a fraction of that cell's size
+// from its left and top edges
+const FIXED_VALUE_OFFSET: (f64, f64) = (0.29, 0.78);
+const CANDIDATE_OFFSET: (f64, f64) = (0.3, 0.7);
 
-pub fn draw_grid(grid: &Grid, filename: &str, print_possibilities: bool) -> Result<(), Box<dyn std::error::Error>> {
-    let (doc, page1, layer1) = PdfDocument::new("Sudoku Puzzle", A4.0, A4.1, "Layer 1");
+/// What goes on the page besides the puzzle itself.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PdfLayout {
+    /// The puzzle on its own, filling the page.
+    Single,
+    /// The puzzle on the left, and a grid to work in on the right, both scaled down to fit.
+    WithWorkingGrid(WorkingGrid),
+}
+
+/// The grid printed next to the puzzle by `PdfLayout::WithWorkingGrid`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WorkingGrid {
+    /// An empty grid, for copying the puzzle into.
+    Blank,
+    /// The puzzle again, with every empty cell's candidates filled in.
+    Candidates,
+}
+
+impl FromStr for WorkingGrid {
+    // Needed for argparse
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "blank" => Ok(WorkingGrid::Blank),
+            "candidates" => Ok(WorkingGrid::Candidates),
+            _ => Err(format!(
+                "{} is not a valid working grid; use blank or candidates",
+                s
+            )),
+        }
+    }
+}
+
+/// How puzzles are laid out when written as PDFs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PdfOptions {
+    /// How each grid is drawn; the charset doesn't apply to PDFs, and highlighted cells are
+    /// shaded grey.
+    pub display: DisplayOptions,
+    pub layout: PdfLayout,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        PdfOptions {
+            display: DisplayOptions::default(),
+            layout: PdfLayout::Single,
+        }
+    }
+}
+
+/// A run of puzzles in a book, such as all of the easy ones, printed after a page with its title.
+#[derive(Clone, Default)]
+pub struct BookSection {
+    pub title: String,
+    pub puzzles: Vec<Grid>,
+    /// The metadata of each puzzle, matched up by position; puzzles without any leave the footer
+    /// blank.
+    pub metadata: Vec<PuzzleMetadata>,
+}
+
+// Where a grid is drawn on the page, and how big it is
+#[derive(Clone, Copy)]
+struct Placement {
+    left: f64,
+    bottom: f64,
+    size: f64,
+}
+
+impl Placement {
+    fn cell_size(self) -> f64 {
+        self.size / BOARD_SIZE as f64
+    }
+
+    // Candidates are drawn in a BOX_SIZE by BOX_SIZE grid of their own inside each cell
+    fn candidate_size(self) -> f64 {
+        self.cell_size() / BOX_SIZE as f64
+    }
+
+    // The y position of the top edge of row `r`
+    fn cell_top(self, r: usize) -> f64 {
+        self.bottom + self.cell_size() * ((BOARD_SIZE - r) as f64)
+    }
+
+    // The x position of the left edge of column `c`
+    fn cell_left(self, c: usize) -> f64 {
+        self.left + self.cell_size() * (c as f64)
+    }
+}
+
+pub fn draw_grid(
+    grid: &Grid,
+    filename: &str,
+    print_possibilities: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let options = DisplayOptions {
+        show_candidates: print_possibilities,
+        ..DisplayOptions::default()
+    };
+    draw_grid_with_options(grid, filename, &options)
+}
+
+/// Like `draw_grid`, but following `options` like `Grid::render` does. The charset doesn't apply
+/// to PDFs; highlighted cells are shaded grey.
+pub fn draw_grid_with_options(
+    grid: &Grid,
+    filename: &str,
+    options: &DisplayOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pdf_options = PdfOptions {
+        display: options.clone(),
+        layout: PdfLayout::Single,
+    };
+    draw_page(grid, filename, &pdf_options, &PuzzleMetadata::default())
+}
+
+/// Write `grid` to a one page PDF laid out the way `options` asks. The document is titled with the
+/// difficulty in `metadata`, and any other metadata is listed at the bottom of the page.
+pub fn draw_page(
+    grid: &Grid,
+    filename: &str,
+    options: &PdfOptions,
+    metadata: &PuzzleMetadata,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let title = match &metadata.difficulty {
+        Some(difficulty) => format!("Sudoku Puzzle ({})", difficulty),
+        None => "Sudoku Puzzle".to_string(),
+    };
+    let (doc, page1, layer1) = PdfDocument::new(title.as_str(), A4.0, A4.1, "Layer 1");
     let layer = doc.get_page(page1).get_layer(layer1);
 
     let font = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
-    let fixed_value_font_size = 45;
-    let possibility_font_size = 12;
+    let label_font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+
+    draw_layout(&layer, &font, &label_font, grid, options);
+    draw_footer(&layer, &label_font, metadata);
+
+    doc.save(&mut BufWriter::new(File::create(filename)?))?;
+
+    Ok(())
+}
+
+/// Write a PDF walking through a solve one step at a time. Each page shows the grid with its
+/// candidates just before a step, with the cells the step changes shaded and a caption explaining
+/// it; the last page shows where the solve ended up. `options` applies to every page, except that
+/// candidates are always shown.
+pub fn draw_walkthrough(
+    replay: &SolveReplay,
+    filename: &str,
+    options: &DisplayOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (doc, first_page, first_layer) =
+        PdfDocument::new("Sudoku Walkthrough", A4.0, A4.1, "Layer 1");
+
+    let font = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+    let label_font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+
+    let frames = replay.frames();
+    for (index, frame) in frames.iter().enumerate() {
+        let layer = if index == 0 {
+            doc.get_page(first_page).get_layer(first_layer)
+        } else {
+            let (page, layer) = doc.add_page(A4.0, A4.1, "Layer 1");
+            doc.get_page(page).get_layer(layer)
+        };
+
+        let (highlights, caption) = match replay.steps.get(index) {
+            Some(step) => (
+                step.changes
+                    .iter()
+                    .map(|(x, y, _)| Coord::new(*x, *y).unwrap())
+                    .collect(),
+                format!(
+                    "Step {} of {}. {}",
+                    index + 1,
+                    replay.steps.len(),
+                    step.describe(frame)
+                ),
+            ),
+            None => (
+                Vec::new(),
+                format!("After all {} steps.", replay.steps.len()),
+            ),
+        };
+
+        let page_options = PdfOptions {
+            display: DisplayOptions {
+                show_candidates: true,
+                highlights,
+                ..options.clone()
+            },
+            layout: PdfLayout::Single,
+        };
+        draw_layout(&layer, &font, &label_font, &frame.to_grid(), &page_options);
+        draw_caption(&layer, &label_font, &caption);
+    }
+
+    doc.save(&mut BufWriter::new(File::create(filename)?))?;
+
+    Ok(())
+}
+
+/// Write a book of puzzles to one PDF. Each section starts with a page holding its title and
+/// how many puzzles it has, followed by one page per puzzle laid out the way `options` asks.
+/// Puzzles are numbered from 1 within each section, and captioned with the section's title and
+/// their number. Fails without writing anything if there are no sections, as the PDF would only
+/// hold a blank page.
+pub fn draw_book(
+    sections: &[BookSection],
+    filename: &str,
+    options: &PdfOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if sections.is_empty() {
+        return Err("A book needs at least one section".into());
+    }
+
+    let (doc, first_page, first_layer) = PdfDocument::new("Sudoku Puzzles", A4.0, A4.1, "Layer 1");
+
+    let font = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+    let label_font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
 
-    draw_empty_grid(&layer);
+    let mut first_page = Some((first_page, first_layer));
+    let mut next_layer = || {
+        let (page, layer) = first_page
+            .take()
+            .unwrap_or_else(|| doc.add_page(A4.0, A4.1, "Layer 1"));
+        doc.get_page(page).get_layer(layer)
+    };
+
+    for section in sections.iter() {
+        let layer = next_layer();
+        layer.use_text(
+            section.title.clone(),
+            DIVIDER_FONT_SIZE,
+            Mm(BOTTOM_LEFT_X),
+            Mm(DIVIDER_Y),
+            &font,
+        );
+        let count = match section.puzzles.len() {
+            1 => "1 puzzle".to_string(),
+            count => format!("{} puzzles", count),
+        };
+        layer.use_text(
+            count,
+            CAPTION_FONT_SIZE,
+            Mm(BOTTOM_LEFT_X),
+            Mm(DIVIDER_Y - DIVIDER_LINE_HEIGHT),
+            &label_font,
+        );
+
+        for (index, grid) in section.puzzles.iter().enumerate() {
+            let layer = next_layer();
+            draw_layout(&layer, &font, &label_font, grid, options);
+            draw_caption(
+                &layer,
+                &label_font,
+                &format!(
+                    "{} {} of {}",
+                    section.title,
+                    index + 1,
+                    section.puzzles.len()
+                ),
+            );
+            if let Some(metadata) = section.metadata.get(index) {
+                draw_footer(&layer, &label_font, metadata);
+            }
+        }
+    }
+
+    doc.save(&mut BufWriter::new(File::create(filename)?))?;
+
+    Ok(())
+}
+
+fn draw_layout(
+    layer: &PdfLayerReference,
+    font: &IndirectFontRef,
+    label_font: &IndirectFontRef,
+    grid: &Grid,
+    options: &PdfOptions,
+) {
+    match options.layout {
+        PdfLayout::Single => {
+            let placement = Placement {
+                left: BOTTOM_LEFT_X,
+                bottom: BOTTOM_LEFT_Y,
+                size: GRID_DIMENSION,
+            };
+            draw_placed_grid(layer, font, label_font, grid, &options.display, placement);
+        }
+        PdfLayout::WithWorkingGrid(working_grid) => {
+            let size = (A4.0 .0 - 3.0 * TWO_COLUMN_MARGIN) / 2.0;
+            // Both grids line up with the top of where a single grid would be
+            let bottom = BOTTOM_LEFT_Y + GRID_DIMENSION - size;
+            let left_placement = Placement {
+                left: TWO_COLUMN_MARGIN,
+                bottom,
+                size,
+            };
+            let right_placement = Placement {
+                left: 2.0 * TWO_COLUMN_MARGIN + size,
+                ..left_placement
+            };
+
+            draw_placed_grid(
+                layer,
+                font,
+                label_font,
+                grid,
+                &options.display,
+                left_placement,
+            );
+
+            let (working_grid, working_options) = match working_grid {
+                WorkingGrid::Blank => (
+                    Grid::new(),
+                    DisplayOptions {
+                        show_candidates: false,
+                        highlights: Vec::new(),
+                        ..options.display.clone()
+                    },
+                ),
+                WorkingGrid::Candidates => (
+                    grid.clone(),
+                    DisplayOptions {
+                        show_candidates: true,
+                        ..options.display.clone()
+                    },
+                ),
+            };
+            draw_placed_grid(
+                layer,
+                font,
+                label_font,
+                &working_grid,
+                &working_options,
+                right_placement,
+            );
+        }
+    }
+}
+
+fn draw_placed_grid(
+    layer: &PdfLayerReference,
+    font: &IndirectFontRef,
+    label_font: &IndirectFontRef,
+    grid: &Grid,
+    options: &DisplayOptions,
+    placement: Placement,
+) {
+    let cell_size = placement.cell_size();
+    let candidate_size = placement.candidate_size();
+    let fixed_value_font_size = (cell_size * FIXED_VALUE_POINTS_PER_MM).round() as i64;
+    let possibility_font_size = (candidate_size * CANDIDATE_POINTS_PER_MM).round() as i64;
+
+    for coord in options.highlights.iter() {
+        shade_cell(layer, placement, *coord);
+    }
+
+    draw_empty_grid(layer, placement);
+
+    if options.coordinates {
+        draw_coordinates(layer, label_font, placement);
+    }
 
     // x represents position on left-right scale
     // y represents position on up-down scale
@@ -26,101 +419,174 @@ pub fn draw_grid(grid: &Grid, filename: &str, print_possibilities: bool) -> Resu
     // One thing to note - higher y values are associated with the top of the page, while for my grid
     //   higher row values are associated with the bottom of the page.
 
-    let x_offset = 6.1;
-    let y_offset = -16.5;
+    for r in 0..BOARD_SIZE {
+        let top = placement.cell_top(r);
 
-    for r in 0..9 {
-        let y = Mm(BOTTOM_LEFT_Y + (GRID_DIMENSION / 9.0) * (9.0 - r as f64) + y_offset);
+        for c in 0..BOARD_SIZE {
+            let left = placement.cell_left(c);
 
-        for c in 0..9 {
-            let x = Mm(BOTTOM_LEFT_X + (GRID_DIMENSION / 9.0) * (c as f64) + x_offset);
-
-            let cell = grid.get(r, c).unwrap();
-            let value = &*cell.value.borrow();
-            match value {
+            let value = grid.value_at(r, c).unwrap();
+            match &value {
                 CellValue::Fixed(digit) => {
-                    let text = digit.to_string();
-                    layer.use_text(text, fixed_value_font_size, x, y, &font);
+                    let (x, y) = text_position(left, top, cell_size, FIXED_VALUE_OFFSET);
+                    layer.use_text(digit.to_string(), fixed_value_font_size, x, y, font);
                 }
                 CellValue::Unknown(possibilities) => {
-                    if print_possibilities {
-                        for (_, possibility) in possibilities.iter().enumerate() {
-
-                            let sub_row = (possibility - 1) / 3;
-                            let sub_column = (possibility - 1) % 3;
-                            // Need to adjust x & y
-                            let x = Mm(x.0 - 4.0 + (GRID_DIMENSION / 27.0) * (sub_column as f64));
-                            let y = Mm(y.0 - 9.5 + (GRID_DIMENSION / 27.0) * (3.0 - sub_row as f64));
+                    if options.show_candidates {
+                        for possibility in possibilities.iter() {
+                            let sub_row = (*possibility as usize - 1) / BOX_SIZE;
+                            let sub_column = (*possibility as usize - 1) % BOX_SIZE;
 
-                            let text = possibility.to_string();
-                            layer.use_text(text, possibility_font_size, x, y, &font);
+                            let (x, y) = text_position(
+                                left + candidate_size * (sub_column as f64),
+                                top - candidate_size * (sub_row as f64),
+                                candidate_size,
+                                CANDIDATE_OFFSET,
+                            );
+                            layer.use_text(
+                                possibility.to_string(),
+                                possibility_font_size,
+                                x,
+                                y,
+                                font,
+                            );
                         }
                     }
                 }
             }
         }
     }
+}
 
-    doc.save(&mut BufWriter::new(File::create(filename)?))?;
+// Write `caption` under a single grid, breaking it into lines between words
+fn draw_caption(layer: &PdfLayerReference, font: &IndirectFontRef, caption: &str) {
+    let mut lines: Vec<String> = Vec::new();
+    for word in caption.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + word.len() <= CAPTION_LINE_LENGTH => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
 
-    return Ok(());
+    for (index, line) in lines.into_iter().enumerate() {
+        let y = BOTTOM_LEFT_Y - CAPTION_LINE_HEIGHT * (index + 1) as f64;
+        layer.use_text(line, CAPTION_FONT_SIZE, Mm(BOTTOM_LEFT_X), Mm(y), font);
+    }
 }
 
-fn draw_empty_grid(layer: &PdfLayerReference) {
+// List every field of `metadata` but the difficulty, which titles the page or section, in small
+// print at the bottom of the page
+fn draw_footer(layer: &PdfLayerReference, font: &IndirectFontRef, metadata: &PuzzleMetadata) {
+    let fields: Vec<String> = metadata
+        .fields()
+        .into_iter()
+        .filter(|(name, _value)| *name != "difficulty")
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect();
+    if !fields.is_empty() {
+        layer.use_text(
+            fields.join(", "),
+            FOOTER_FONT_SIZE,
+            Mm(BOTTOM_LEFT_X),
+            Mm(FOOTER_Y),
+            font,
+        );
+    }
+}
+
+// Where to start text in a square of side `size` whose top left corner is at (left, top)
+fn text_position(left: f64, top: f64, size: f64, offset: (f64, f64)) -> (Mm, Mm) {
+    (Mm(left + size * offset.0), Mm(top - size * offset.1))
+}
+
+// Fill in the background of a cell in light grey; must be done before anything is drawn over it
+fn shade_cell(layer: &PdfLayerReference, placement: Placement, coord: Coord) {
+    let cell_size = placement.cell_size();
+    let left = placement.cell_left(coord.column());
+    let top = placement.cell_top(coord.row());
+
+    let points = vec![
+        (Point::new(Mm(left), Mm(top - cell_size)), false),
+        (Point::new(Mm(left + cell_size), Mm(top - cell_size)), false),
+        (Point::new(Mm(left + cell_size), Mm(top)), false),
+        (Point::new(Mm(left), Mm(top)), false),
+    ];
+
+    layer.set_fill_color(Color::Greyscale(Greyscale::new(0.85, None)));
+    layer.add_shape(Line {
+        points,
+        is_closed: true,
+        has_fill: true,
+        has_stroke: false,
+        is_clipping_path: false,
+    });
+    layer.set_fill_color(Color::Greyscale(Greyscale::new(0.0, None)));
+}
+
+// Number the rows in the left margin and the columns above the grid, starting from 1
+fn draw_coordinates(layer: &PdfLayerReference, font: &IndirectFontRef, placement: Placement) {
+    let cell_size = placement.cell_size();
+    let label_font_size = (cell_size * LABEL_POINTS_PER_MM).round() as i64;
+    // Roughly half a label's width and height, to centre them on their row or column
+    let half_label = cell_size * 0.085;
+
+    for i in 0..BOARD_SIZE {
+        let text = (i + 1).to_string();
+
+        let row_y = Mm(placement.cell_top(i) - cell_size / 2.0 - half_label);
+        layer.use_text(
+            text.clone(),
+            label_font_size,
+            Mm(placement.left - cell_size * 0.31),
+            row_y,
+            font,
+        );
+
+        let column_x = Mm(placement.cell_left(i) + cell_size / 2.0 - half_label);
+        layer.use_text(
+            text,
+            label_font_size,
+            column_x,
+            Mm(placement.bottom + placement.size + cell_size * 0.14),
+            font,
+        );
+    }
+}
+
+fn draw_empty_grid(layer: &PdfLayerReference, placement: Placement) {
     // x represents position on left-right scale
     // y represents position on up-down scale
 
-    // Thick lines first
+    let cell_size = placement.cell_size();
+    let starting_x = Mm(placement.left);
+    let ending_x = Mm(placement.left + placement.size);
+    let starting_y = Mm(placement.bottom);
+    let ending_y = Mm(placement.bottom + placement.size);
+
+    // Thick lines first, around each square section
 
     layer.set_outline_thickness(2.0);
-    // Horizontal first
-    {
-        let starting_x = Mm(BOTTOM_LEFT_X);
-        let ending_x = Mm(BOTTOM_LEFT_X + GRID_DIMENSION);
-        let y_increment = GRID_DIMENSION / 3.0;
-        for i in 0..4 {
-            let y = Mm(BOTTOM_LEFT_Y + (i as f64) * y_increment);
-            draw_line(layer, Point::new(starting_x, y), Point::new(ending_x, y));
-        }
-    }
+    for i in (0..=BOARD_SIZE).step_by(BOX_SIZE) {
+        let y = Mm(placement.bottom + (i as f64) * cell_size);
+        draw_line(layer, Point::new(starting_x, y), Point::new(ending_x, y));
 
-    // Vertical lines next
-    {
-        let starting_y = Mm(BOTTOM_LEFT_Y);
-        let ending_y = Mm(BOTTOM_LEFT_Y + GRID_DIMENSION);
-        let x_increment = GRID_DIMENSION / 3.0;
-        for i in 0..4 {
-            let x = Mm(BOTTOM_LEFT_X + (i as f64) * x_increment);
-            draw_line(layer, Point::new(x, starting_y), Point::new(x, ending_y));
-        }
+        let x = Mm(placement.left + (i as f64) * cell_size);
+        draw_line(layer, Point::new(x, starting_y), Point::new(x, ending_y));
     }
 
-    // Thin lines next
+    // Thin lines next, between the cells inside each section
 
     layer.set_outline_thickness(0.0); // Special value to make line be 1px on all devices and zoom levels
-                                      // Horizontal first
-    {
-        let starting_x = Mm(BOTTOM_LEFT_X);
-        let ending_x = Mm(BOTTOM_LEFT_X + GRID_DIMENSION);
-        let y_increment = GRID_DIMENSION / 9.0;
-        for i in 1..9 {
-            if i % 3 != 0 {
-                let y = Mm(BOTTOM_LEFT_Y + (i as f64) * y_increment);
-                draw_line(layer, Point::new(starting_x, y), Point::new(ending_x, y));
-            }
-        }
-    }
+    for i in 1..BOARD_SIZE {
+        if i % BOX_SIZE != 0 {
+            let y = Mm(placement.bottom + (i as f64) * cell_size);
+            draw_line(layer, Point::new(starting_x, y), Point::new(ending_x, y));
 
-    // Vertical lines next
-    {
-        let starting_y = Mm(BOTTOM_LEFT_Y);
-        let ending_y = Mm(BOTTOM_LEFT_Y + GRID_DIMENSION);
-        let x_increment = GRID_DIMENSION / 9.0;
-        for i in 1..9 {
-            if i % 3 != 0 {
-                let x = Mm(BOTTOM_LEFT_X + (i as f64) * x_increment);
-                draw_line(layer, Point::new(x, starting_y), Point::new(x, ending_y));
-            }
+            let x = Mm(placement.left + (i as f64) * cell_size);
+            draw_line(layer, Point::new(x, starting_y), Point::new(x, ending_y));
         }
     }
 }
@@ -138,3 +604,18 @@ fn draw_line(layer: &PdfLayerReference, point1: Point, point2: Point) {
 
     layer.add_shape(line);
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::pdf::*;
+
+    #[test]
+    fn test_draw_book() {
+        let path = std::env::temp_dir().join("sudoku_solver_test_draw_book.pdf");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        assert!(draw_book(&[], path, &PdfOptions::default()).is_err());
+        assert!(std::fs::metadata(path).is_err());
+    }
+}
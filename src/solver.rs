@@ -1,7 +1,33 @@
-use crate::grid::{Cell, CellValue, Grid, Section};
+use crate::grid::{
+    take_candidate_allocations, Cell, CellValue, Coord, Grid, GridSnapshot, Section, SectionType,
+    TieBreak,
+};
+use crate::topology;
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
 use std::rc::Rc;
 
-pub static mut DEBUG: bool = false;
+pub mod dlx;
+mod hints;
+mod line;
+mod pencil_marks;
+mod progress;
+mod recording;
+mod subsets;
+mod technique;
+mod what_if;
+
+pub use hints::{find_all, next_hint, Hint};
+pub use line::apply_technique;
+pub use pencil_marks::{apply_pencil_marks, check_pencil_marks, PencilMarkError};
+pub use progress::{progress, Progress};
+use recording::Recorder;
+pub use recording::{
+    Determination, Elimination, EliminationHistory, EliminationSource, GuessNode, GuessTree,
+    SolvabilityTimeline, SolveProgress, SolveReplay, SolveStep,
+};
+pub use technique::Technique;
+pub use what_if::{what_if, WhatIf};
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Uniqueness {
@@ -9,17 +35,42 @@ pub enum Uniqueness {
     NotUnique,
 }
 
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub enum SolveStatus {
     Complete(Option<Uniqueness>),
     Unfinished,
     Invalid,
+
+    /// The solve passed one of the `SolveController`'s `SolveLimits` and was given up on, so
+    /// nothing is known about the puzzle.
+    Aborted(AbortReason),
+}
+
+/// Which of the `SolveLimits` a solve passed.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum AbortReason {
+    TooManyIterations,
+    TooDeep,
+}
+
+impl std::fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AbortReason::TooManyIterations => write!(f, "the strategies ran too many times"),
+            AbortReason::TooDeep => write!(f, "the guesses went too deep"),
+        }
+    }
 }
 
-/// See `SolveController` for a description of the solving strategies.
-enum SolveAction {
+/// The solving strategies, plus guessing. See `SolveController` for a description of each, and
+/// `techniques` for the `Technique`s each of them applies.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SolveAction {
     Single,
     HiddenSingle,
+    NakedSubset,
+    HiddenSubset,
     PossibilityGroup,
     UsefulConstraints,
     Guess,
@@ -27,6 +78,10 @@ enum SolveAction {
 
 impl SolveStatus {
     fn increment(self, additional_status: SolveStatus) -> SolveStatus {
+        if let SolveStatus::Aborted(_) = additional_status {
+            return additional_status;
+        }
+
         match self {
             SolveStatus::Complete(uniqueness_option) => {
                 if uniqueness_option.is_none() {
@@ -41,7 +96,9 @@ impl SolveStatus {
                             SolveStatus::Unfinished => {
                                 SolveStatus::Complete(Some(Uniqueness::Unique))
                             }
-                            SolveStatus::Invalid => SolveStatus::Complete(Some(Uniqueness::Unique)),
+                            SolveStatus::Invalid | SolveStatus::Aborted(_) => {
+                                SolveStatus::Complete(Some(Uniqueness::Unique))
+                            }
                         },
                     }
                 }
@@ -53,6 +110,7 @@ impl SolveStatus {
             SolveStatus::Invalid => {
                 panic!("increment() shouldn't be called on SolveStatus::Invalid")
             }
+            SolveStatus::Aborted(_) => self,
         }
     }
 }
@@ -71,42 +129,378 @@ pub struct SolveController {
     /// once in that `Section` should be set to that value, is enabled. Has never been tested with a `false` value.
     pub search_hidden_singles: bool,
 
+    /// Whether the solving strategy where 2 to 4 cells of a `Section` whose possibilities only have as many
+    /// digits between them (a naked pair, triple, or quad) remove those digits from the rest of the Section,
+    /// is enabled.
+    pub search_naked_subsets: bool,
+
+    /// Whether the solving strategy where 2 to 4 digits that are only possible in as many cells of a `Section`
+    /// (a hidden pair, triple, or quad) remove every other possibility from those cells, is enabled.
+    pub search_hidden_subsets: bool,
+
     /// Whether the solving strategy where, in a given `Section`, the solver tries to divide the un-set cells up
-    /// into two or more exclusive groups based on their possibilities, is enabled.
+    /// into two or more exclusive groups based on their possibilities, is enabled. This is an advanced
+    /// partitioning that finds everything the subset strategies do and more, but what it finds doesn't always
+    /// have a standard name; it runs after them, so it's only credited with what they miss.
     pub find_possibility_groups: bool,
 
+    /// The largest naked or hidden subset, or group of cells a possibility group splits off, that
+    /// the solver may rely on. See `SubsetSize`.
+    pub max_subset_size: SubsetSize,
+
     /// Whether the solving strategy where if you know that a digit must occur in a part of Section A that overlaps
     /// entirely with Section B, that you can then determine that that digit cannot occur in the rest of
     /// Section B, is enabled.
     pub search_useful_constraint: bool,
 
+    /// When `search_useful_constraint` is enabled, whether it may use a square to remove possibilities
+    /// from a row or column (a 'pointing' constraint).
+    pub search_pointing_constraints: bool,
+
+    /// When `search_useful_constraint` is enabled, whether it may use a row or column to remove
+    /// possibilities from a square (a 'claiming' constraint).
+    pub search_claiming_constraints: bool,
+
     /// Whether the solver can make guesses as a last resort to try to solve the puzzle.
     pub make_guesses: bool,
+
+    /// Whether, when determining uniqueness, the solver may explore the candidates of a guess on a
+    /// thread pool instead of one after another. Only has an effect when the crate is built with
+    /// the `rayon` feature. The status, solution, and statistics don't depend on which threads
+    /// finish first.
+    pub parallel_guesses: bool,
+
+    /// Limits on how many times each solving strategy may be relied on. See `TechniqueCaps`.
+    pub technique_caps: TechniqueCaps,
+
+    /// The order to work through the rows, columns, and squares in. See `HouseOrder`.
+    pub house_order: HouseOrder,
+
+    /// When to give up on a solve that's running away. See `SolveLimits`.
+    pub limits: SolveLimits,
+
+    /// Whether to take the possibilities of the grid's `Unknown` cells as they are when the solve
+    /// starts, instead of running the strategies over every row, column, and square that was marked
+    /// when the grid was filled in. Turn this on to carry on from a saved state that the strategies
+    /// had already run as far as they could on, such as a `GridSnapshot` taken between guesses,
+    /// which `to_grid` marks all over; the solve then goes straight to guessing. Use a
+    /// `GridCheckpoint` instead for a state saved part way through the strategies.
+    pub trust_candidates: bool,
+
+    /// Whether to fill in the `SolveStatistics`. Turning this off saves the bookkeeping in hot
+    /// loops that only need the `SolveStatus`; the statistics are then left at zero, except for
+    /// the counts that `technique_caps` and `limits` need to work. Progress reports and early
+    /// exits rely on the statistics, so leave it on for them.
+    pub collect_statistics: bool,
+}
+
+/// How large a subset the solver may look for, counted in cells. A possibility group that splits
+/// a `Section` in two counts as the smaller of its two parts, since either one is a subset of the
+/// Section: the larger part's cells are then a hidden subset of the digits left over.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SubsetSize {
+    Pairs,
+    Triples,
+
+    /// Naked and hidden subsets up to quads, and possibility groups of any size.
+    #[default]
+    Unlimited,
+}
+
+impl SubsetSize {
+    /// The most cells a subset may have, or None if there's no limit.
+    pub fn max_cells(self) -> Option<usize> {
+        match self {
+            SubsetSize::Pairs => Some(2),
+            SubsetSize::Triples => Some(3),
+            SubsetSize::Unlimited => None,
+        }
+    }
+}
+
+/// The order in which the solver works through the rows, columns, and squares that changed since
+/// it last looked at them. Each of them always runs the strategies in the same order: singles,
+/// hidden singles, naked subsets, hidden subsets, possibility groups, then useful constraints.
+///
+/// Either way a solve is repeatable: grids with the same values are solved the same way, with the
+/// same `SolveStatistics` and `SolveReplay`, whatever order their cells were filled in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HouseOrder {
+    /// Take them in the order they changed, so that the solver follows up on its own deductions
+    /// first. Those already waiting when the solve starts go rows first, then columns, then
+    /// squares.
+    Changed,
+
+    /// Sweep over the grid in rounds, each taking every one that's waiting rows first, then
+    /// columns, then squares; whatever changes during a round waits for the next one.
+    Sweep,
+}
+
+/// The most times each solving strategy may be relied on during a single solve, as counted by
+/// `SolveStatistics`; `None` means there's no limit. Once a strategy reaches its cap the solver
+/// carries on as if it were disabled in the `SolveController`, so if the remaining strategies
+/// can't finish the puzzle the solve stops there.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TechniqueCaps {
+    pub singles: Option<u32>,
+    pub hidden_singles: Option<u32>,
+    pub naked_subsets: Option<u32>,
+    pub hidden_subsets: Option<u32>,
+    pub possibility_groups: Option<u32>,
+    pub useful_constraints: Option<u32>,
+    pub guesses: Option<u32>,
+}
+
+impl TechniqueCaps {
+    /// Create a new TechniqueCaps that doesn't limit any strategy.
+    pub fn none() -> TechniqueCaps {
+        TechniqueCaps {
+            singles: None,
+            hidden_singles: None,
+            naked_subsets: None,
+            hidden_subsets: None,
+            possibility_groups: None,
+            useful_constraints: None,
+            guesses: None,
+        }
+    }
+
+    // Whether action can still be used given how often it's been used so far
+    fn allows(&self, action: &SolveAction, solve_statistics: &SolveStatistics) -> bool {
+        let (cap, count) = match action {
+            SolveAction::Single => (self.singles, solve_statistics.singles),
+            SolveAction::HiddenSingle => (self.hidden_singles, solve_statistics.hidden_singles),
+            SolveAction::NakedSubset => (self.naked_subsets, solve_statistics.naked_subsets),
+            SolveAction::HiddenSubset => (self.hidden_subsets, solve_statistics.hidden_subsets),
+            SolveAction::PossibilityGroup => {
+                (self.possibility_groups, solve_statistics.possibility_groups)
+            }
+            SolveAction::UsefulConstraints => {
+                (self.useful_constraints, solve_statistics.useful_constraints)
+            }
+            SolveAction::Guess => (self.guesses, solve_statistics.guesses),
+        };
+
+        cap.is_none_or(|cap| count < cap)
+    }
+}
+
+/// Hard limits on a single solve, so that a badly configured solver or a bug in a strategy can't
+/// keep it running forever; `None` means there's no limit. A solve that passes one stops with
+/// `SolveStatus::Aborted`. Setting either limit turns off `parallel_guesses`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SolveLimits {
+    /// The most times the strategies may be run on a row, column, or square, counted by
+    /// `SolveStatistics::iterations` across every guess.
+    pub max_iterations: Option<u32>,
+
+    /// The most guesses that may be in place at once.
+    pub max_guess_depth: Option<u32>,
+}
+
+impl SolveLimits {
+    /// Create a new SolveLimits that never aborts a solve.
+    pub fn none() -> SolveLimits {
+        SolveLimits {
+            max_iterations: None,
+            max_guess_depth: None,
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    fn is_none(&self) -> bool {
+        *self == SolveLimits::none()
+    }
 }
 
 impl SolveController {
+    /// Create a SolveController that determines uniqueness and only uses the given techniques.
+    /// Any of the naked (or hidden) subsets enables the strategy that looks for all of them, up to
+    /// the largest subset given unless `PossibilityGroup` is too, and `PointingPair` enables only
+    /// the pointing half of useful constraints.
+    pub fn with_techniques(techniques: &[Technique]) -> SolveController {
+        let uses = |technique: Technique| techniques.contains(&technique);
+        let pointing = uses(Technique::PointingPair) || uses(Technique::UsefulConstraints);
+        let largest_subset = techniques
+            .iter()
+            .filter_map(|technique| technique.subset_size())
+            .max();
+        SolveController {
+            determine_uniqueness: true,
+            search_singles: uses(Technique::Single),
+            search_hidden_singles: uses(Technique::HiddenSingle),
+            search_naked_subsets: SolveAction::NakedSubset
+                .techniques()
+                .iter()
+                .any(|&technique| uses(technique)),
+            search_hidden_subsets: SolveAction::HiddenSubset
+                .techniques()
+                .iter()
+                .any(|&technique| uses(technique)),
+            find_possibility_groups: uses(Technique::PossibilityGroup),
+            max_subset_size: match largest_subset {
+                Some(2) if !uses(Technique::PossibilityGroup) => SubsetSize::Pairs,
+                Some(3) if !uses(Technique::PossibilityGroup) => SubsetSize::Triples,
+                _ => SubsetSize::Unlimited,
+            },
+            search_useful_constraint: pointing,
+            search_pointing_constraints: pointing,
+            search_claiming_constraints: uses(Technique::UsefulConstraints),
+            make_guesses: uses(Technique::Guess),
+            parallel_guesses: false,
+            technique_caps: TechniqueCaps::none(),
+            house_order: HouseOrder::Changed,
+            limits: SolveLimits::none(),
+            trust_candidates: false,
+            collect_statistics: true,
+        }
+    }
+
+    /// Whether the solver may use `technique`, the other way around from `with_techniques`. A
+    /// naked or hidden subset needs its strategy and a `max_subset_size` that's large enough.
+    /// Caps aren't taken into account.
+    pub fn enables(&self, technique: Technique) -> bool {
+        let fits = |size: usize| {
+            self.max_subset_size
+                .max_cells()
+                .is_none_or(|max| size <= max)
+        };
+        match technique {
+            Technique::Single => self.search_singles,
+            Technique::HiddenSingle => self.search_hidden_singles,
+            Technique::NakedPair | Technique::NakedTriple | Technique::NakedQuad => {
+                self.search_naked_subsets && fits(technique.subset_size().unwrap())
+            }
+            Technique::HiddenPair | Technique::HiddenTriple | Technique::HiddenQuad => {
+                self.search_hidden_subsets && fits(technique.subset_size().unwrap())
+            }
+            Technique::PointingPair => {
+                self.search_useful_constraint && self.search_pointing_constraints
+            }
+            Technique::PossibilityGroup => self.find_possibility_groups,
+            Technique::UsefulConstraints => {
+                self.search_useful_constraint
+                    && (self.search_pointing_constraints || self.search_claiming_constraints)
+            }
+            Technique::Guess => self.make_guesses,
+        }
+    }
+
+    /// Create a SolveController with every strategy enabled that determines uniqueness, as
+    /// `solve_grid` uses.
+    pub fn all_techniques() -> SolveController {
+        SolveController {
+            determine_uniqueness: true,
+            search_singles: true,
+            search_hidden_singles: true,
+            search_naked_subsets: true,
+            search_hidden_subsets: true,
+            find_possibility_groups: true,
+            max_subset_size: SubsetSize::Unlimited,
+            search_useful_constraint: true,
+            search_pointing_constraints: true,
+            search_claiming_constraints: true,
+            make_guesses: true,
+            parallel_guesses: false,
+            technique_caps: TechniqueCaps::none(),
+            house_order: HouseOrder::Changed,
+            limits: SolveLimits::none(),
+            trust_candidates: false,
+            collect_statistics: true,
+        }
+    }
+
+    /// Create a SolveController for when any solution will do: every strategy is enabled, but
+    /// uniqueness isn't determined, so guessing stops at the first solution found and a solve that
+    /// needed guesses reports `SolveStatus::Complete(None)`. This is the fastest way to fill in a
+    /// puzzle.
+    pub fn first_solution() -> SolveController {
+        SolveController {
+            determine_uniqueness: false,
+            ..SolveController::all_techniques()
+        }
+    }
+
     fn determine_uniqueness(&self) -> bool {
         self.determine_uniqueness
     }
 
-    fn search_singles(&self) -> bool {
+    // Whether to count how often each strategy is used, either for the caller or for the caps
+    fn counts_actions(&self) -> bool {
+        self.collect_statistics || self.technique_caps != TechniqueCaps::none()
+    }
+
+    // Whether to count the houses processed, either for the caller or for the limit on them
+    fn counts_iterations(&self) -> bool {
+        self.collect_statistics || self.limits.max_iterations.is_some()
+    }
+
+    fn search_singles(&self, solve_statistics: &SolveStatistics) -> bool {
         self.search_singles
+            && self
+                .technique_caps
+                .allows(&SolveAction::Single, solve_statistics)
     }
 
-    fn search_hidden_singles(&self) -> bool {
+    fn search_hidden_singles(&self, solve_statistics: &SolveStatistics) -> bool {
         self.search_hidden_singles
+            && self
+                .technique_caps
+                .allows(&SolveAction::HiddenSingle, solve_statistics)
+    }
+
+    fn search_naked_subsets(&self, solve_statistics: &SolveStatistics) -> bool {
+        self.search_naked_subsets
+            && self
+                .technique_caps
+                .allows(&SolveAction::NakedSubset, solve_statistics)
+    }
+
+    fn search_hidden_subsets(&self, solve_statistics: &SolveStatistics) -> bool {
+        self.search_hidden_subsets
+            && self
+                .technique_caps
+                .allows(&SolveAction::HiddenSubset, solve_statistics)
     }
 
-    fn find_possibility_groups(&self) -> bool {
+    fn find_possibility_groups(&self, solve_statistics: &SolveStatistics) -> bool {
         self.find_possibility_groups
+            && self
+                .technique_caps
+                .allows(&SolveAction::PossibilityGroup, solve_statistics)
     }
 
-    fn search_useful_constraint(&self) -> bool {
+    // Pointing constraints are searched for from squares, claiming constraints from rows and columns
+    fn search_useful_constraint(
+        &self,
+        line_type: &SectionType,
+        solve_statistics: &SolveStatistics,
+    ) -> bool {
+        let direction_enabled = match line_type {
+            SectionType::Square => self.search_pointing_constraints,
+            SectionType::Row | SectionType::Column => self.search_claiming_constraints,
+        };
+
         self.search_useful_constraint
+            && direction_enabled
+            && self
+                .technique_caps
+                .allows(&SolveAction::UsefulConstraints, solve_statistics)
     }
 
-    fn make_guesses(&self) -> bool {
+    fn make_guesses(&self, solve_statistics: &SolveStatistics) -> bool {
         self.make_guesses
+            && self
+                .technique_caps
+                .allows(&SolveAction::Guess, solve_statistics)
+    }
+
+    // Branches explored in parallel each count their own guesses and iterations, so neither a
+    // guess cap nor the limits can be enforced
+    #[cfg(feature = "rayon")]
+    fn parallel_guesses(&self) -> bool {
+        self.parallel_guesses && self.technique_caps.guesses.is_none() && self.limits.is_none()
     }
 }
 
@@ -114,13 +508,65 @@ impl SolveController {
 /// that the method make at least one change to the line it was originally called on, whether that
 /// be setting a value or adjusting the possibilities in a cell. Multiple contributions in one call
 /// of the strategy on a `Section` are only counted as one contribution.
-#[derive(Copy, Clone, Debug)]
+///
+/// Statistics of several solves can be added up with `sum`, or averaged with `mean`, to report on
+/// a batch of puzzles at once.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct SolveStatistics {
     pub singles: u32,
     pub hidden_singles: u32,
+    pub naked_subsets: u32,
+    pub hidden_subsets: u32,
     pub possibility_groups: u32,
     pub useful_constraints: u32,
+
+    /// The number of times a cell had to be guessed in; cells with a single possibility left
+    /// don't count.
     pub guesses: u32,
+
+    /// The number of digits tried across all of those guesses, including those that led to dead
+    /// ends.
+    pub guess_candidates: u32,
+
+    /// `useful_constraints` split up by whether a square removed possibilities from a row or
+    /// column (pointing) or the other way around (claiming).
+    pub pointing_constraints: u32,
+    pub claiming_constraints: u32,
+
+    /// Whether the solution that was found could only be reached by guessing. Unlike `guesses`,
+    /// this isn't affected by guesses that turned out to be wrong.
+    pub solved_with_guesses: bool,
+
+    /// The most guesses that were in place at once.
+    pub max_guess_depth: u32,
+
+    /// The size of the largest naked (or hidden) subset that was relied on, from 2 for a pair to
+    /// 4 for a quad, or 0 if there weren't any.
+    pub largest_naked_subset: u32,
+    pub largest_hidden_subset: u32,
+
+    /// How many of the naked (or hidden) subsets were pairs, triples, and quads, in that order.
+    pub naked_subset_sizes: [u32; 3],
+    pub hidden_subset_sizes: [u32; 3],
+
+    /// How many times the strategies were run on a row, column, or square, whether or not they
+    /// changed anything.
+    pub iterations: u32,
+
+    /// The longest run of consecutive rounds in which the strategies only made progress once, as
+    /// counted by the actions above, where a round takes every row, column, and square that was
+    /// waiting when it started. Long runs mean the puzzle only opens up one step at a time, which
+    /// makes it harder for people even when every step is easy.
+    pub bottleneck: u32,
+
+    // Memory usage during the solve
+    pub grid_clones: u32,
+    pub candidate_allocations: u32,
+
+    // How many guesses deep the solver currently is; used to track max_guess_depth
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) guess_depth: u32,
 }
 
 impl SolveStatistics {
@@ -129,9 +575,25 @@ impl SolveStatistics {
         SolveStatistics {
             singles: 0,
             hidden_singles: 0,
+            naked_subsets: 0,
+            hidden_subsets: 0,
             possibility_groups: 0,
             useful_constraints: 0,
             guesses: 0,
+            guess_candidates: 0,
+            pointing_constraints: 0,
+            claiming_constraints: 0,
+            solved_with_guesses: false,
+            max_guess_depth: 0,
+            largest_naked_subset: 0,
+            largest_hidden_subset: 0,
+            naked_subset_sizes: [0; 3],
+            hidden_subset_sizes: [0; 3],
+            iterations: 0,
+            bottleneck: 0,
+            grid_clones: 0,
+            candidate_allocations: 0,
+            guess_depth: 0,
         }
     }
 
@@ -139,11 +601,273 @@ impl SolveStatistics {
         match action {
             SolveAction::Single => self.singles = self.singles + 1,
             SolveAction::HiddenSingle => self.hidden_singles = self.hidden_singles + 1,
+            SolveAction::NakedSubset => self.naked_subsets += 1,
+            SolveAction::HiddenSubset => self.hidden_subsets += 1,
             SolveAction::PossibilityGroup => self.possibility_groups = self.possibility_groups + 1,
             SolveAction::UsefulConstraints => self.useful_constraints = self.useful_constraints + 1,
             SolveAction::Guess => self.guesses = self.guesses + 1,
         }
     }
+
+    // How many times the strategies have made progress, not counting guesses
+    fn deductions(&self) -> u32 {
+        self.singles
+            + self.hidden_singles
+            + self.naked_subsets
+            + self.hidden_subsets
+            + self.possibility_groups
+            + self.useful_constraints
+    }
+
+    /// How many times `technique` was used. The naked and hidden subsets are counted by their
+    /// size, and `PointingPair` counts the pointing constraints.
+    pub fn count(&self, technique: Technique) -> u32 {
+        match technique {
+            Technique::Single => self.singles,
+            Technique::HiddenSingle => self.hidden_singles,
+            Technique::NakedPair | Technique::NakedTriple | Technique::NakedQuad => {
+                self.naked_subset_sizes[technique.subset_size().unwrap() - 2]
+            }
+            Technique::HiddenPair | Technique::HiddenTriple | Technique::HiddenQuad => {
+                self.hidden_subset_sizes[technique.subset_size().unwrap() - 2]
+            }
+            Technique::PointingPair => self.pointing_constraints,
+            Technique::PossibilityGroup => self.possibility_groups,
+            Technique::UsefulConstraints => self.useful_constraints,
+            Technique::Guess => self.guesses,
+        }
+    }
+
+    /// The average of each statistic over a batch of solves; all zeros for an empty batch.
+    pub fn mean(batch: &[SolveStatistics]) -> MeanStatistics {
+        let total: SolveStatistics = batch.iter().sum();
+        let count = batch.len().max(1) as f64;
+        let mean = |total: u32| f64::from(total) / count;
+
+        MeanStatistics {
+            solves: batch.len(),
+            singles: mean(total.singles),
+            hidden_singles: mean(total.hidden_singles),
+            naked_subsets: mean(total.naked_subsets),
+            hidden_subsets: mean(total.hidden_subsets),
+            possibility_groups: mean(total.possibility_groups),
+            useful_constraints: mean(total.useful_constraints),
+            guesses: mean(total.guesses),
+            guess_candidates: mean(total.guess_candidates),
+            pointing_constraints: mean(total.pointing_constraints),
+            claiming_constraints: mean(total.claiming_constraints),
+            solved_with_guesses: batch
+                .iter()
+                .filter(|statistics| statistics.solved_with_guesses)
+                .count() as f64
+                / count,
+            max_guess_depth: mean(
+                batch
+                    .iter()
+                    .map(|statistics| statistics.max_guess_depth)
+                    .sum(),
+            ),
+            bottleneck: mean(batch.iter().map(|statistics| statistics.bottleneck).sum()),
+            grid_clones: mean(total.grid_clones),
+            candidate_allocations: mean(total.candidate_allocations),
+        }
+    }
+
+    // Add in the statistics of a solve that started at the current guess depth
+    pub(crate) fn add(&mut self, other: &SolveStatistics) {
+        self.singles += other.singles;
+        self.hidden_singles += other.hidden_singles;
+        self.naked_subsets += other.naked_subsets;
+        self.hidden_subsets += other.hidden_subsets;
+        self.possibility_groups += other.possibility_groups;
+        self.useful_constraints += other.useful_constraints;
+        self.guesses += other.guesses;
+        self.guess_candidates += other.guess_candidates;
+        self.pointing_constraints += other.pointing_constraints;
+        self.claiming_constraints += other.claiming_constraints;
+        self.solved_with_guesses |= other.solved_with_guesses;
+        self.max_guess_depth = self
+            .max_guess_depth
+            .max(self.guess_depth + other.max_guess_depth);
+        self.largest_naked_subset = self.largest_naked_subset.max(other.largest_naked_subset);
+        self.largest_hidden_subset = self.largest_hidden_subset.max(other.largest_hidden_subset);
+        for size in 0..3 {
+            self.naked_subset_sizes[size] += other.naked_subset_sizes[size];
+            self.hidden_subset_sizes[size] += other.hidden_subset_sizes[size];
+        }
+        self.iterations += other.iterations;
+        self.bottleneck = self.bottleneck.max(other.bottleneck);
+        self.grid_clones += other.grid_clones;
+        self.candidate_allocations += other.candidate_allocations;
+    }
+}
+
+/// Adds up the counts of several solves. `solved_with_guesses` is true if any of them needed
+/// guesses, and `max_guess_depth` is the deepest of them.
+impl<'a> std::iter::Sum<&'a SolveStatistics> for SolveStatistics {
+    fn sum<I: Iterator<Item = &'a SolveStatistics>>(iter: I) -> SolveStatistics {
+        iter.fold(SolveStatistics::new(), |mut total, statistics| {
+            total.add(statistics);
+            total
+        })
+    }
+}
+
+impl std::iter::Sum for SolveStatistics {
+    fn sum<I: Iterator<Item = SolveStatistics>>(iter: I) -> SolveStatistics {
+        iter.fold(SolveStatistics::new(), |mut total, statistics| {
+            total.add(&statistics);
+            total
+        })
+    }
+}
+
+impl std::fmt::Display for SolveStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} {} actions", self.singles, label(SolveAction::Single))?;
+        writeln!(
+            f,
+            "{} {} actions",
+            self.hidden_singles,
+            label(SolveAction::HiddenSingle)
+        )?;
+        writeln!(
+            f,
+            "{} {} actions (largest {})",
+            self.naked_subsets,
+            label(SolveAction::NakedSubset),
+            self.largest_naked_subset
+        )?;
+        writeln!(
+            f,
+            "{} {} actions (largest {})",
+            self.hidden_subsets,
+            label(SolveAction::HiddenSubset),
+            self.largest_hidden_subset
+        )?;
+        writeln!(
+            f,
+            "{} {} actions ({} pointing, {} claiming)",
+            self.useful_constraints,
+            label(SolveAction::UsefulConstraints),
+            self.pointing_constraints,
+            self.claiming_constraints
+        )?;
+        writeln!(
+            f,
+            "{} {} actions",
+            self.possibility_groups,
+            label(SolveAction::PossibilityGroup)
+        )?;
+        writeln!(
+            f,
+            "{} {} actions, trying {} digits",
+            self.guesses,
+            label(SolveAction::Guess),
+            self.guess_candidates
+        )?;
+        writeln!(
+            f,
+            "{} rounds in the longest single-deduction bottleneck",
+            self.bottleneck
+        )?;
+        write!(
+            f,
+            "{} deepest guess, {} grid clones, {} candidate allocations",
+            self.max_guess_depth, self.grid_clones, self.candidate_allocations
+        )
+    }
+}
+
+// How statistics and timings name a strategy, like "HIDDEN_SINGLE"
+pub(crate) fn label(action: SolveAction) -> String {
+    action.name().to_ascii_uppercase()
+}
+
+/// The average `SolveStatistics` of a batch of solves, from `SolveStatistics::mean`.
+/// `solved_with_guesses` is the fraction of the solves that needed guesses.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MeanStatistics {
+    /// How many solves were averaged.
+    pub solves: usize,
+    pub singles: f64,
+    pub hidden_singles: f64,
+    pub naked_subsets: f64,
+    pub hidden_subsets: f64,
+    pub possibility_groups: f64,
+    pub useful_constraints: f64,
+    pub guesses: f64,
+    pub guess_candidates: f64,
+    pub pointing_constraints: f64,
+    pub claiming_constraints: f64,
+    pub solved_with_guesses: f64,
+    pub max_guess_depth: f64,
+    pub bottleneck: f64,
+    pub grid_clones: f64,
+    pub candidate_allocations: f64,
+}
+
+impl std::fmt::Display for MeanStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Averaged over {} solves:", self.solves)?;
+        writeln!(
+            f,
+            "{:.1} {} actions",
+            self.singles,
+            label(SolveAction::Single)
+        )?;
+        writeln!(
+            f,
+            "{:.1} {} actions",
+            self.hidden_singles,
+            label(SolveAction::HiddenSingle)
+        )?;
+        writeln!(
+            f,
+            "{:.1} {} actions",
+            self.naked_subsets,
+            label(SolveAction::NakedSubset)
+        )?;
+        writeln!(
+            f,
+            "{:.1} {} actions",
+            self.hidden_subsets,
+            label(SolveAction::HiddenSubset)
+        )?;
+        writeln!(
+            f,
+            "{:.1} {} actions ({:.1} pointing, {:.1} claiming)",
+            self.useful_constraints,
+            label(SolveAction::UsefulConstraints),
+            self.pointing_constraints,
+            self.claiming_constraints
+        )?;
+        writeln!(
+            f,
+            "{:.1} {} actions",
+            self.possibility_groups,
+            label(SolveAction::PossibilityGroup)
+        )?;
+        writeln!(
+            f,
+            "{:.1} {} actions, trying {:.1} digits; {:.0}% of solves needed guesses",
+            self.guesses,
+            label(SolveAction::Guess),
+            self.guess_candidates,
+            self.solved_with_guesses * 100.0
+        )?;
+        writeln!(
+            f,
+            "{:.1} rounds in the longest single-deduction bottleneck",
+            self.bottleneck
+        )?;
+        write!(
+            f,
+            "{:.1} deepest guess, {:.1} grid clones, {:.1} candidate allocations",
+            self.max_guess_depth, self.grid_clones, self.candidate_allocations
+        )
+    }
 }
 
 // Code for identify_and_process_possibility_groups (it uses it's own structs)
@@ -184,20 +908,19 @@ mod process_possibility_groups {
 
     // See if there's a set of cells with possibilities that exclude those possibilities from other cells.
     // Runs recursively on each group to identify all groups in case there's more than 2.
-    pub fn identify_and_process_possibility_groups(line: &Section) -> bool {
-        unsafe {
-            if super::DEBUG {
-                println!(
-                    "Looking for possibility groups on line {:?} {}",
-                    line.section_type, line.index
-                );
-            }
-        }
-
-        bisect_possibility_groups(line, vec![0, 1, 2, 3, 4, 5, 6, 7, 8])
+    // A split is only used if the smaller side has at most max_cells cells (when there's a limit).
+    pub fn identify_and_process_possibility_groups(
+        line: &Section,
+        max_cells: Option<usize>,
+    ) -> bool {
+        bisect_possibility_groups(line, vec![0, 1, 2, 3, 4, 5, 6, 7, 8], max_cells)
     }
 
-    fn bisect_possibility_groups(line: &Section, cells_of_interest: Vec<usize>) -> bool {
+    fn bisect_possibility_groups(
+        line: &Section,
+        cells_of_interest: Vec<usize>,
+        max_cells: Option<usize>,
+    ) -> bool {
         /*
            Algorithm -
                Setup - Let count = 0
@@ -302,6 +1025,12 @@ mod process_possibility_groups {
                 }
             }
 
+            // Too large a split to use; its groups can't be split further without applying it first
+            let smaller_side = faux_line.num_in_group().min(faux_line.num_out_group());
+            if max_cells.is_some_and(|max_cells| smaller_side > max_cells) {
+                return made_change;
+            }
+
             // Now we have to see if this was worth it
             if faux_line.num_out_group() > 0 {
                 // Worth it
@@ -328,7 +1057,10 @@ mod process_possibility_groups {
                     let mut possibilities = {
                         let value = &*real_cell.value.borrow();
                         match value {
-                            CellValue::Unknown(possibilities) => possibilities.clone(),
+                            CellValue::Unknown(possibilities) => {
+                                crate::grid::count_candidate_allocation();
+                                possibilities.clone()
+                            }
                             CellValue::Fixed(_) => {
                                 panic!("Faux_cell shouldn't have linked to fixed cell")
                             }
@@ -380,8 +1112,8 @@ mod process_possibility_groups {
 
         // Out of scope of everything; we need to check again if it was worth it.
         if run_recursion {
-            bisect_possibility_groups(line, in_group_indices);
-            bisect_possibility_groups(line, out_group_indices);
+            bisect_possibility_groups(line, in_group_indices, max_cells);
+            bisect_possibility_groups(line, out_group_indices, max_cells);
         }
 
         return made_change;
@@ -390,15 +1122,6 @@ mod process_possibility_groups {
 
 // Search for a cell with only one possibility so that we can set it to FIXED
 fn search_single_possibility(line: &Section) -> bool {
-    unsafe {
-        if DEBUG {
-            println!(
-                "search_single_possibility on line {:?} {}",
-                line.section_type, line.index
-            );
-        }
-    }
-
     let mut made_change = false;
 
     for (_index, cell) in line.vec.iter().enumerate() {
@@ -476,6 +1199,62 @@ fn search_hidden_single(line: &Section) -> bool {
     return made_change;
 }
 
+// Search for naked (or hidden) pairs, then triples, then quads, up to max_size, and remove the
+// possibilities they rule out. Returns the technique of the largest subset that made a change.
+fn search_subsets(line: &Section, naked: bool, max_size: SubsetSize) -> Option<Technique> {
+    let mut largest = None;
+
+    for size in 2..=max_size.max_cells().unwrap_or(4) {
+        // Read the possibilities again, as the smaller subsets may have removed some
+        let mut masks = [0u16; 9];
+        for (mask, cell) in masks.iter_mut().zip(line.vec.iter()) {
+            if let CellValue::Unknown(possibilities) = &*cell.value.borrow() {
+                *mask = subsets::to_mask(possibilities);
+            }
+        }
+
+        let found = match naked {
+            true => subsets::naked_subsets(&masks, size),
+            false => subsets::hidden_subsets(&masks, size),
+        };
+
+        for subset in found {
+            for (index, cell) in line.vec.iter().enumerate() {
+                // A naked subset's digits leave the other cells, while a hidden subset's cells
+                // keep only its digits
+                let to_remove = match (naked, subset.cells & 1 << index != 0) {
+                    (true, false) => subset.digits,
+                    (false, true) => !subset.digits,
+                    _ => continue,
+                };
+
+                let possibilities = match cell.get_value_possibilities() {
+                    Some(possibilities) => possibilities,
+                    None => continue,
+                };
+                if subsets::to_mask(&possibilities) & to_remove == 0 {
+                    continue;
+                }
+
+                crate::grid::count_candidate_allocation();
+                let mut remaining: Vec<u8> = possibilities
+                    .into_iter()
+                    .filter(|&digit| to_remove & 1 << digit == 0)
+                    .collect();
+                let new_value = if remaining.len() == 1 {
+                    CellValue::Fixed(remaining.pop().unwrap())
+                } else {
+                    CellValue::Unknown(remaining)
+                };
+                cell.set_value(new_value);
+                largest = Some(Technique::subset(naked, size));
+            }
+        }
+    }
+
+    largest
+}
+
 mod search_useful_constraint {
     use crate::grid::{CellValue, Grid, Section, SectionType};
     use std::cell::RefCell;
@@ -501,15 +1280,6 @@ mod search_useful_constraint {
     // from row 0 across the other sections. Conversely, if the possibility only occurs in the first section
     // for row 0, then you can remove the possibility from the rest of section 0.
     pub fn search_useful_constraint(grid: &Grid, line: &Section) -> bool {
-        unsafe {
-            if super::DEBUG {
-                println!(
-                    "Searching for a useful constraint on line {:?} {}",
-                    line.section_type, line.index
-                );
-            }
-        }
-
         let mut made_change = false;
 
         let (check_row, check_column, check_section) = match line.section_type {
@@ -634,6 +1404,7 @@ mod search_useful_constraint {
                         let new_possibilities = match possibilities.binary_search(&digit_to_remove)
                         {
                             Ok(x) => {
+                                crate::grid::count_candidate_allocation();
                                 let mut new_possibilities = possibilities.clone();
                                 new_possibilities.remove(x);
                                 new_possibilities
@@ -688,73 +1459,131 @@ mod search_useful_constraint {
     }
 }
 
+// Runs a solving strategy on line and returns the technique it made a change with, if it made one.
+// Any changes are passed on to recorder, and when benchmarking the time it took is also recorded.
+fn run_strategy<F: FnOnce() -> Option<Technique>>(
+    action: SolveAction,
+    grid: &Grid,
+    line: &Section,
+    recorder: &mut Recorder,
+    strategy: F,
+) -> Option<Technique> {
+    let before = recorder.before_step(grid);
+
+    #[cfg(feature = "bench")]
+    let technique = {
+        let start = std::time::Instant::now();
+        let technique = strategy();
+        crate::bench::record_strategy_time(&action, start.elapsed());
+        technique
+    };
+
+    #[cfg(not(feature = "bench"))]
+    let technique = strategy();
+
+    if let Some(technique) = technique {
+        log::debug!(
+            "{:?} made progress on {:?} {}",
+            technique,
+            line.section_type,
+            line.index
+        );
+        let source = EliminationSource::Section(line.section_type, line.index);
+        recorder.after_step(before, grid, action, technique, source);
+    }
+
+    technique
+}
+
 fn solve_line(
     grid: &Grid,
     line: &Section,
     solve_controller: &SolveController,
     solve_statistics: &mut SolveStatistics,
+    recorder: &mut Recorder,
 ) {
-    unsafe {
-        if DEBUG {
-            println!("Solving {:?} {}", line.section_type, line.index);
-        }
+    line.do_update.replace(false);
+    let counts_actions = solve_controller.counts_actions();
+
+    if solve_controller.search_singles(solve_statistics)
+        && run_strategy(SolveAction::Single, grid, line, recorder, || {
+            search_single_possibility(line).then_some(Technique::Single)
+        })
+        .is_some()
+        && counts_actions
+    {
+        solve_statistics.increment(&SolveAction::Single);
     }
 
-    line.do_update.replace(false);
+    if solve_controller.search_hidden_singles(solve_statistics)
+        && run_strategy(SolveAction::HiddenSingle, grid, line, recorder, || {
+            search_hidden_single(line).then_some(Technique::HiddenSingle)
+        })
+        .is_some()
+        && counts_actions
+    {
+        solve_statistics.increment(&SolveAction::HiddenSingle);
+    }
 
-    if solve_controller.search_singles() {
-        unsafe {
-            if DEBUG {
-                println!(
-                    "Searching for singles on line {:?} of {}\n{}",
-                    line.section_type, line.index, grid
-                );
+    if solve_controller.search_naked_subsets(solve_statistics) {
+        if let Some(technique) =
+            run_strategy(SolveAction::NakedSubset, grid, line, recorder, || {
+                search_subsets(line, true, solve_controller.max_subset_size)
+            })
+        {
+            if counts_actions {
+                solve_statistics.increment(&SolveAction::NakedSubset);
+                let size = technique.subset_size().unwrap();
+                solve_statistics.naked_subset_sizes[size - 2] += 1;
+                solve_statistics.largest_naked_subset =
+                    solve_statistics.largest_naked_subset.max(size as u32);
             }
         }
-        if search_single_possibility(line) {
-            solve_statistics.increment(&SolveAction::Single);
-        }
     }
 
-    if solve_controller.search_hidden_singles() {
-        unsafe {
-            if DEBUG {
-                println!(
-                    "Searching for hidden singles on line {:?} of {}\n{}",
-                    line.section_type, line.index, grid
-                );
+    if solve_controller.search_hidden_subsets(solve_statistics) {
+        if let Some(technique) =
+            run_strategy(SolveAction::HiddenSubset, grid, line, recorder, || {
+                search_subsets(line, false, solve_controller.max_subset_size)
+            })
+        {
+            if counts_actions {
+                solve_statistics.increment(&SolveAction::HiddenSubset);
+                let size = technique.subset_size().unwrap();
+                solve_statistics.hidden_subset_sizes[size - 2] += 1;
+                solve_statistics.largest_hidden_subset =
+                    solve_statistics.largest_hidden_subset.max(size as u32);
             }
         }
-        if search_hidden_single(line) {
-            solve_statistics.increment(&SolveAction::HiddenSingle);
-        }
     }
 
-    if solve_controller.find_possibility_groups() {
-        unsafe {
-            if DEBUG {
-                println!(
-                    "Searching for possibility groups on line {:?} of {}\n{}",
-                    line.section_type, line.index, grid
-                );
-            }
-        }
-        if process_possibility_groups::identify_and_process_possibility_groups(line) {
-            solve_statistics.increment(&SolveAction::PossibilityGroup);
-        }
+    // The advanced partitioning, for whatever the named subsets above didn't find
+    if solve_controller.find_possibility_groups(solve_statistics)
+        && run_strategy(SolveAction::PossibilityGroup, grid, line, recorder, || {
+            process_possibility_groups::identify_and_process_possibility_groups(
+                line,
+                solve_controller.max_subset_size.max_cells(),
+            )
+            .then_some(Technique::PossibilityGroup)
+        })
+        .is_some()
+        && counts_actions
+    {
+        solve_statistics.increment(&SolveAction::PossibilityGroup);
     }
 
-    if solve_controller.search_useful_constraint() {
-        unsafe {
-            if DEBUG {
-                println!(
-                    "Searching for useful constraints on line {:?} of {}\n{}",
-                    line.section_type, line.index, grid
-                );
-            }
-        }
-        if search_useful_constraint::search_useful_constraint(grid, line) {
-            solve_statistics.increment(&SolveAction::UsefulConstraints);
+    if solve_controller.search_useful_constraint(&line.section_type, solve_statistics)
+        && run_strategy(SolveAction::UsefulConstraints, grid, line, recorder, || {
+            search_useful_constraint::search_useful_constraint(grid, line)
+                .then_some(Technique::UsefulConstraints)
+        })
+        .is_some()
+        && counts_actions
+    {
+        solve_statistics.increment(&SolveAction::UsefulConstraints);
+        match line.section_type {
+            SectionType::Square => solve_statistics.pointing_constraints += 1,
+            SectionType::Row | SectionType::Column => solve_statistics.claiming_constraints += 1,
         }
     }
 }
@@ -764,14 +1593,7 @@ fn solve_line(
 /// call `solve_grid_with_solve_controller` directly, but you also have to input an empty `SolveStatistics`.
 pub fn solve_grid(grid: &mut Grid) -> (SolveStatus, SolveStatistics) {
     // By default we enable everything
-    let solve_controller = SolveController {
-        determine_uniqueness: true,
-        search_singles: true,
-        search_hidden_singles: true,
-        find_possibility_groups: true,
-        search_useful_constraint: true,
-        make_guesses: true,
-    };
+    let solve_controller = SolveController::all_techniques();
 
     let mut solve_statistics = SolveStatistics::new();
     let solve_status =
@@ -785,6 +1607,70 @@ pub fn solve_grid_with_solve_controller(
     grid: &mut Grid,
     solve_controller: &SolveController,
     solve_statistics: &mut SolveStatistics,
+) -> SolveStatus {
+    // Don't count allocations from before the solve started
+    take_candidate_allocations();
+
+    let solve_status = start_solve(
+        grid,
+        solve_controller,
+        solve_statistics,
+        &mut Vec::new(),
+        &mut Recorder::none(),
+    );
+    log_summary(&solve_status, solve_statistics);
+    solve_status
+}
+
+// How often each strategy was relied on, for the lowest level of logging
+fn log_summary(solve_status: &SolveStatus, solve_statistics: &SolveStatistics) {
+    log::info!(
+        "Solve finished as {:?} after {} singles, {} hidden singles, {} naked subsets, {} hidden subsets, {} possibility groups, {} useful constraints, and {} guesses",
+        solve_status,
+        solve_statistics.singles,
+        solve_statistics.hidden_singles,
+        solve_statistics.naked_subsets,
+        solve_statistics.hidden_subsets,
+        solve_statistics.possibility_groups,
+        solve_statistics.useful_constraints,
+        solve_statistics.guesses
+    );
+}
+
+// Run a whole solve of grid. The Sections already waiting are put in a fixed order first, so that
+// the order grid's cells were filled in doesn't change how it's solved.
+fn start_solve(
+    grid: &mut Grid,
+    solve_controller: &SolveController,
+    solve_statistics: &mut SolveStatistics,
+    grid_pool: &mut Vec<Grid>,
+    recorder: &mut Recorder,
+) -> SolveStatus {
+    // The strategies assume the clues don't contradict each other; given a repeated digit they
+    // can guess for a very long time before running into a dead end everywhere
+    if grid.has_repeated_digits() {
+        return SolveStatus::Invalid;
+    }
+    if solve_controller.trust_candidates {
+        grid.clear_updates();
+    }
+    grid.sort_updates();
+    solve_grid_with_grid_pool(
+        grid,
+        solve_controller,
+        solve_statistics,
+        grid_pool,
+        recorder,
+    )
+}
+
+// grid_pool holds spare Grids that guesses can copy into instead of allocating new ones
+fn solve_grid_with_grid_pool(
+    grid: &mut Grid,
+    solve_controller: &SolveController,
+    solve_statistics: &mut SolveStatistics,
+    grid_pool: &mut Vec<Grid>,
+    recorder: &mut Recorder,
 ) -> SolveStatus {
     // Code is kind of messy so here it goes - solve_grid first tries to solve without any guesses
     // If that's not enough and a guess is required, then solve_grid_guess is called
@@ -793,11 +1679,18 @@ pub fn solve_grid_with_solve_controller(
     // solve_grid_no_guess tries to solve without any guesses.
     // Of course this is if the solve_controller lets everything be used for solving it
 
-    let mut status = solve_grid_no_guess(grid, solve_controller, solve_statistics);
+    let mut status = solve_grid_no_guess(grid, solve_controller, solve_statistics, recorder);
     status = match status {
+        SolveStatus::Unfinished if recorder.stopped => SolveStatus::Unfinished,
         SolveStatus::Unfinished => {
-            if solve_controller.make_guesses() {
-                solve_grid_guess(grid, solve_controller, solve_statistics)
+            if solve_controller.make_guesses(solve_statistics) {
+                solve_grid_guess(
+                    grid,
+                    solve_controller,
+                    solve_statistics,
+                    grid_pool,
+                    recorder,
+                )
             } else {
                 SolveStatus::Complete(Some(Uniqueness::NotUnique)) // solve_grid_no_guess couldn't finish and we can't make guesses, so it's 'not unique' in the sense that we need more guesses
             }
@@ -805,6 +1698,10 @@ pub fn solve_grid_with_solve_controller(
         _ => status,
     };
 
+    if solve_controller.collect_statistics {
+        solve_statistics.candidate_allocations += take_candidate_allocations();
+    }
+
     return status;
 }
 
@@ -824,58 +1721,812 @@ pub fn evaluate_grid_with_solve_controller(
     return (solve_status, solve_statistics);
 }
 
-fn solve_grid_no_guess(
+/// Like `evaluate_grid_with_solve_controller`, but gives up as soon as `should_stop` returns true
+/// for the statistics so far, returning `SolveStatus::Unfinished` along with the statistics up to
+/// that point. `should_stop` is checked after each strategy runs and before each guess, so a
+/// puzzle can be turned away as soon as it's clear it won't do, such as when it's already needed
+/// too many guesses. Guesses are always explored one after another, regardless of
+/// `parallel_guesses`.
+pub fn evaluate_grid_with_early_exit(
+    grid: &Grid,
+    solve_controller: &SolveController,
+    should_stop: &dyn Fn(&SolveStatistics) -> bool,
+) -> (SolveStatus, SolveStatistics) {
+    let mut mut_grid = grid.clone();
+    let mut solve_statistics = SolveStatistics::new();
+    take_candidate_allocations();
+
+    let solve_status = start_solve(
+        &mut mut_grid,
+        solve_controller,
+        &mut solve_statistics,
+        &mut Vec::new(),
+        &mut Recorder::with_stop_when(should_stop),
+    );
+    log_summary(&solve_status, &solve_statistics);
+    (solve_status, solve_statistics)
+}
+
+// How many houses are processed between the progress reports of `solve_grid_with_progress`
+const PROGRESS_INTERVAL: u32 = 1000;
+
+/// Solves (and modifies) the input `Grid` & `SolveStatistics` like
+/// `solve_grid_with_solve_controller`, calling `on_progress` every 1000 houses processed and
+/// every time a guess is made, so that a long solve can show how it's going. Guesses are always
+/// explored one after another, regardless of `parallel_guesses`.
+pub fn solve_grid_with_progress(
     grid: &mut Grid,
     solve_controller: &SolveController,
     solve_statistics: &mut SolveStatistics,
+    on_progress: &dyn Fn(&SolveProgress),
 ) -> SolveStatus {
-    loop {
-        let mut ran_something = false;
-        for (_index, line_ref) in grid.rows.iter().enumerate() {
-            //println!("Processing row {}", _index);
-            let line_ref = &*(&**line_ref).borrow();
-            if line_ref.do_update() {
-                solve_line(&grid, line_ref, solve_controller, solve_statistics);
-                ran_something = true;
-            }
-        }
-        for (_index, line_ref) in grid.columns.iter().enumerate() {
-            //println!("Processing column {}", _index);
-            let line_ref = &*(&**line_ref).borrow();
-            if line_ref.do_update() {
-                solve_line(&grid, line_ref, solve_controller, solve_statistics);
-                ran_something = true;
-            }
-        }
-        for (_index, line_ref) in grid.sections.iter().enumerate() {
-            //println!("Processing section {}", _index);
-            let line_ref = &*(&**line_ref).borrow();
-            if line_ref.do_update() {
-                solve_line(&grid, line_ref, solve_controller, solve_statistics);
-                ran_something = true;
-            }
-        }
+    take_candidate_allocations();
+
+    let solve_status = start_solve(
+        grid,
+        solve_controller,
+        solve_statistics,
+        &mut Vec::new(),
+        &mut Recorder::with_progress(on_progress),
+    );
+    log_summary(&solve_status, solve_statistics);
+    solve_status
+}
 
-        if !ran_something {
-            // No lines have changed since we last analyzed them
-            return SolveStatus::Unfinished;
-        }
+/// Solves (and modifies) the input `Grid` like `solve_grid_with_solve_controller`, while recording
+/// every possibility that gets removed from each cell and which strategy removed it. Guesses are
+/// always explored one after another, regardless of `parallel_guesses`.
+pub fn solve_grid_with_history(
+    grid: &mut Grid,
+    solve_controller: &SolveController,
+) -> (SolveStatus, SolveStatistics, EliminationHistory) {
+    let mut solve_statistics = SolveStatistics::new();
+    let mut recorder = Recorder::with_history();
+
+    take_candidate_allocations();
+    let solve_status = start_solve(
+        grid,
+        solve_controller,
+        &mut solve_statistics,
+        &mut Vec::new(),
+        &mut recorder,
+    );
+
+    log_summary(&solve_status, &solve_statistics);
+    let history = recorder
+        .history
+        .expect("Recorder was created with a history");
+    (solve_status, solve_statistics, history)
+}
 
-        // Check if complete or invalid
-        let mut appears_complete = true;
-        for x in 0..9 {
-            for y in 0..9 {
-                let cell = grid.get(x, y).unwrap();
-                let cell = &*cell;
-                let value = &**(&cell.value.borrow());
+/// Solves (and modifies) the input `Grid` like `solve_grid_with_solve_controller`, while recording
+/// the cells changed by every strategy run or guess so the solve can be played back one step at a
+/// time. Guesses are always explored one after another, regardless of `parallel_guesses`.
+pub fn solve_grid_with_replay(
+    grid: &mut Grid,
+    solve_controller: &SolveController,
+) -> (SolveStatus, SolveStatistics, SolveReplay) {
+    let mut solve_statistics = SolveStatistics::new();
+    let mut recorder = Recorder::with_steps();
+    let start = grid.snapshot();
+
+    take_candidate_allocations();
+    let solve_status = start_solve(
+        grid,
+        solve_controller,
+        &mut solve_statistics,
+        &mut Vec::new(),
+        &mut recorder,
+    );
+
+    log_summary(&solve_status, &solve_statistics);
+    let steps = recorder.steps.expect("Recorder was created with steps");
+    (solve_status, solve_statistics, SolveReplay { start, steps })
+}
 
-                match value {
-                    CellValue::Unknown(possibilities) => {
-                        appears_complete = false;
-                        if possibilities.len() == 0 {
-                            return SolveStatus::Invalid;
-                        }
-                    }
+/// Solves (and modifies) the input `Grid` like `solve_grid_with_solve_controller`, while recording
+/// every guess made, whether or not it led to a solution, as a tree. Guesses are always explored
+/// one after another, regardless of `parallel_guesses`.
+pub fn solve_grid_with_guess_tree(
+    grid: &mut Grid,
+    solve_controller: &SolveController,
+) -> (SolveStatus, SolveStatistics, GuessTree) {
+    let mut solve_statistics = SolveStatistics::new();
+    let mut recorder = Recorder::with_guesses();
+
+    take_candidate_allocations();
+    let solve_status = start_solve(
+        grid,
+        solve_controller,
+        &mut solve_statistics,
+        &mut Vec::new(),
+        &mut recorder,
+    );
+
+    log_summary(&solve_status, &solve_statistics);
+    let roots = recorder.guesses.expect("Recorder was created with guesses");
+    (solve_status, solve_statistics, GuessTree { roots })
+}
+
+/// For a puzzle with a unique solution, reports the step at which each cell could first be
+/// narrowed down to one digit and which strategy did it, without modifying the input `Grid`.
+/// Returns None if the puzzle isn't found to have a unique solution, which includes when
+/// `determine_uniqueness` is off.
+pub fn solvability_timeline(
+    grid: &Grid,
+    solve_controller: &SolveController,
+) -> Option<SolvabilityTimeline> {
+    let mut mut_grid = grid.clone();
+    let (solve_status, _solve_statistics, replay) =
+        solve_grid_with_replay(&mut mut_grid, solve_controller);
+
+    match solve_status {
+        SolveStatus::Complete(Some(Uniqueness::Unique)) => Some(replay.solvability_timeline()),
+        _ => None,
+    }
+}
+
+/// Solves each of the input `Grid`s with all solving strategies enabled, like `solve_grid`, but
+/// without modifying them. With the `rayon` feature enabled the puzzles are spread across all cores.
+pub fn solve_all(grids: &[Grid]) -> Vec<(SolveStatus, SolveStatistics)> {
+    fn solve_snapshot(snapshot: &GridSnapshot) -> (SolveStatus, SolveStatistics) {
+        let mut grid = snapshot.to_grid();
+        solve_grid(&mut grid)
+    }
+
+    // Grids can't be sent to other threads, so the workers are handed snapshots instead
+    let snapshots: Vec<GridSnapshot> = grids.iter().map(|grid| grid.snapshot()).collect();
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        snapshots.par_iter().map(solve_snapshot).collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        snapshots.iter().map(solve_snapshot).collect()
+    }
+}
+
+/// A way in which `solve_grid` and `dlx::solve_grid` disagreed about a puzzle; see `cross_check`.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Disagreement {
+    /// The solvers came to different conclusions about whether the puzzle has a unique solution.
+    Status {
+        logic: SolveStatus,
+        dlx: SolveStatus,
+    },
+    /// Both solvers found a unique solution, but not the same one.
+    Solution {
+        logic: GridSnapshot,
+        dlx: GridSnapshot,
+    },
+}
+
+impl std::fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Disagreement::Status { logic, dlx } => write!(
+                f,
+                "Logic solver found {:?} but brute-force solver found {:?}",
+                logic, dlx
+            ),
+            Disagreement::Solution { logic, dlx } => write!(
+                f,
+                "Solvers found different solutions.\nLogic solver:\n{}\nBrute-force solver:\n{}",
+                logic.to_grid(),
+                dlx.to_grid()
+            ),
+        }
+    }
+}
+
+/// Solves `grid` with both `solve_grid` and the independent brute-force `dlx::solve_grid`, without
+/// modifying it, and checks that they agree on whether it can be solved, whether the solution is
+/// unique, and if so what that solution is. Returns the agreed upon `SolveStatus`.
+pub fn cross_check(grid: &Grid) -> Result<SolveStatus, Disagreement> {
+    let mut logic_grid = grid.clone();
+    let (logic_status, _statistics) = solve_grid(&mut logic_grid);
+
+    let mut dlx_grid = grid.clone();
+    let dlx_status = dlx::solve_grid(&mut dlx_grid);
+
+    if logic_status != dlx_status {
+        return Err(Disagreement::Status {
+            logic: logic_status,
+            dlx: dlx_status,
+        });
+    }
+
+    // Non-unique puzzles can legitimately end up with different solutions
+    if logic_status == SolveStatus::Complete(Some(Uniqueness::Unique)) {
+        let logic_solution = logic_grid.snapshot();
+        let dlx_solution = dlx_grid.snapshot();
+        if logic_solution != dlx_solution {
+            return Err(Disagreement::Solution {
+                logic: logic_solution,
+                dlx: dlx_solution,
+            });
+        }
+    }
+
+    Ok(logic_status)
+}
+
+/// How one of the solves compared by `check_reproducible` went.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolveOutcome {
+    pub status: SolveStatus,
+    pub statistics: SolveStatistics,
+
+    /// The grid as the solve left it.
+    pub grid: GridSnapshot,
+}
+
+/// Two solves of the same puzzle that should have gone the same way but didn't; see
+/// `check_reproducible`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Irreproducible {
+    /// The solve with nothing watching it.
+    pub plain: SolveOutcome,
+
+    /// The solve that recorded everything it did along the way.
+    pub observed: SolveOutcome,
+}
+
+impl std::fmt::Display for Irreproducible {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Solving found {:?} with\n{}\nbut recording the solve found {:?} with\n{}",
+            self.plain.status,
+            self.plain.statistics,
+            self.observed.status,
+            self.observed.statistics
+        )
+    }
+}
+
+/// Solves `grid` twice without modifying it, once plainly and once while recording its history,
+/// replay, and guess tree (everything the solver's tracing options watch), and checks that
+/// watching didn't change anything: the status, the statistics, and the grid it was left as must
+/// all match. Returns how the plain solve went.
+///
+/// The solver never draws random numbers, so a solve only depends on the grid and the
+/// `SolveController`; this is how that's tested. `parallel_guesses` is turned off for both solves,
+/// as a recorded solve always explores its guesses one after another.
+pub fn check_reproducible(
+    grid: &Grid,
+    solve_controller: &SolveController,
+) -> Result<SolveOutcome, Box<Irreproducible>> {
+    let solve_controller = SolveController {
+        parallel_guesses: false,
+        ..*solve_controller
+    };
+
+    let mut plain_grid = grid.clone();
+    let mut plain_statistics = SolveStatistics::new();
+    let plain_status =
+        solve_grid_with_solve_controller(&mut plain_grid, &solve_controller, &mut plain_statistics);
+    let plain = SolveOutcome {
+        status: plain_status,
+        statistics: plain_statistics,
+        grid: plain_grid.snapshot(),
+    };
+
+    let mut observed_grid = grid.clone();
+    let mut observed_statistics = SolveStatistics::new();
+    take_candidate_allocations();
+    let observed_status = start_solve(
+        &mut observed_grid,
+        &solve_controller,
+        &mut observed_statistics,
+        &mut Vec::new(),
+        &mut Recorder::with_everything(),
+    );
+    let observed = SolveOutcome {
+        status: observed_status,
+        statistics: observed_statistics,
+        grid: observed_grid.snapshot(),
+    };
+
+    if plain == observed {
+        Ok(plain)
+    } else {
+        Err(Box::new(Irreproducible { plain, observed }))
+    }
+}
+
+/// A reason a puzzle can't have a unique solution that shows without solving it; see
+/// `find_degeneracy`. A degenerate puzzle may also have no solution at all.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Degeneracy {
+    /// The puzzle has this many clues, fewer than 17, and no puzzle with so few has a unique
+    /// solution.
+    TooFewClues(usize),
+
+    /// Neither digit is given anywhere, so swapping them in any solution gives another one.
+    MissingDigits(u8, u8),
+}
+
+impl std::fmt::Display for Degeneracy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Degeneracy::TooFewClues(clues) => write!(
+                f,
+                "it has {} clues; puzzles with fewer than 17 never have a unique solution",
+                clues
+            ),
+            Degeneracy::MissingDigits(first, second) => write!(
+                f,
+                "neither {} nor {} is given, so they can be swapped in any solution",
+                first, second
+            ),
+        }
+    }
+}
+
+/// Check the clues of `grid` for a sign that it can't have a unique solution, which is much
+/// quicker than looking for a second solution: a unique puzzle needs at least 17 clues, and at
+/// least 8 of the 9 digits among them.
+pub fn find_degeneracy(grid: &Grid) -> Option<Degeneracy> {
+    let clues = grid.clue_count();
+    if clues < 17 {
+        return Some(Degeneracy::TooFewClues(clues));
+    }
+
+    let mut given = [false; 10];
+    for coord in Coord::all() {
+        if let CellValue::Fixed(digit) = &*grid.cell(coord).value.borrow() {
+            given[*digit as usize] = true;
+        }
+    }
+    let mut missing = (1..10u8).filter(|&digit| !given[digit as usize]);
+    match (missing.next(), missing.next()) {
+        (Some(first), Some(second)) => Some(Degeneracy::MissingDigits(first, second)),
+        _ => None,
+    }
+}
+
+/// Find out whether `grid` has a unique solution without solving it, returning None if it has no
+/// solution at all. The search stops as soon as a second solution turns up and no statistics are
+/// kept, so this is much cheaper than `evaluate_grid_with_solve_controller` when only uniqueness
+/// matters. `Unknown` cells are restricted to their current possibilities.
+pub fn check_uniqueness(grid: &Grid) -> Option<Uniqueness> {
+    match count_solutions_at_most_2(grid) {
+        0 => None,
+        1 => Some(Uniqueness::Unique),
+        _ => Some(Uniqueness::NotUnique),
+    }
+}
+
+/// Count the solutions of `grid`, stopping as soon as a second one is found (so the result is 0, 1
+/// or 2). This skips all of the technique and statistics bookkeeping of the main solver and just
+/// backtracks over bitmasks, making it much cheaper when all we need to know is whether a puzzle is
+/// unique. `Unknown` cells are restricted to their current possibilities.
+pub(crate) fn count_solutions_at_most_2(grid: &Grid) -> usize {
+    fn count(bitmasks: &mut Bitmasks) -> usize {
+        let (index, options) = match bitmasks.most_constrained_cell() {
+            Ok(Some(best)) => best,
+            Ok(None) => return 1, // No empty cells left
+            Err(()) => return 0,
+        };
+
+        let mut solutions = 0;
+        for digit in 1..10 {
+            if options & 1 << digit == 0 {
+                continue;
+            }
+
+            bitmasks.place(index, digit);
+            solutions += count(bitmasks);
+            bitmasks.unplace(index, digit);
+
+            if solutions >= 2 {
+                break;
+            }
+        }
+
+        solutions.min(2)
+    }
+
+    match Bitmasks::new(grid) {
+        Some(mut bitmasks) => count(&mut bitmasks),
+        None => 0,
+    }
+}
+
+/// Find up to `n` different solutions of `grid`, trying each cell's digits in a random order so
+/// that different calls turn up different solutions. Fewer than `n` are returned only when `grid`
+/// doesn't have that many, so an invalid puzzle gives none. This is handy for seeing how far a
+/// puzzle is from having a unique solution, or for filling in a pattern of clues. `Unknown` cells
+/// are restricted to their current possibilities.
+pub fn sample_solutions(grid: &Grid, n: usize, rng: &mut SmallRng) -> Vec<Grid> {
+    // Carries on past the solutions already found, so that every one it returns is new
+    fn search(bitmasks: &mut Bitmasks, found: &[[u8; 81]], rng: &mut SmallRng) -> Option<[u8; 81]> {
+        let (index, options) = match bitmasks.most_constrained_cell() {
+            Ok(Some(best)) => best,
+            Ok(None) if found.contains(&bitmasks.digits) => return None,
+            Ok(None) => return Some(bitmasks.digits),
+            Err(()) => return None,
+        };
+
+        let mut digits: Vec<u8> = (1..10).filter(|digit| options & 1 << digit != 0).collect();
+        digits.shuffle(rng);
+        for digit in digits {
+            bitmasks.place(index, digit);
+            let solution = search(bitmasks, found, rng);
+            bitmasks.unplace(index, digit);
+
+            if solution.is_some() {
+                return solution;
+            }
+        }
+
+        None
+    }
+
+    let mut bitmasks = match Bitmasks::new(grid) {
+        Some(bitmasks) => bitmasks,
+        None => return Vec::new(),
+    };
+
+    let mut found = Vec::new();
+    while found.len() < n {
+        match search(&mut bitmasks, &found, rng) {
+            Some(solution) => found.push(solution),
+            None => break, // Every solution has been found
+        }
+    }
+
+    found
+        .into_iter()
+        .map(|solution| {
+            let mut digits = [[0; 9]; 9];
+            for (index, digit) in solution.iter().enumerate() {
+                digits[index / 9][index % 9] = *digit;
+            }
+            Grid::from_digits(digits)
+        })
+        .collect()
+}
+
+/// How the solutions of a puzzle differ from each other; see `solution_spread`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolutionSpread {
+    /// How many solutions were found; never more than the cap.
+    pub solutions: usize,
+
+    /// Whether the search stopped at the cap with more solutions left, so `solutions` is only a
+    /// lower bound.
+    pub capped: bool,
+
+    /// Every cell that isn't the same in all the solutions found.
+    pub varying_cells: Vec<VaryingCell>,
+}
+
+/// A cell that's filled in differently by some of the solutions in a `SolutionSpread`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VaryingCell {
+    pub x: usize,
+    pub y: usize,
+
+    /// How many of the solutions had each digit in the cell, as `(digit, count)` from the lowest
+    /// digit up.
+    pub digits: Vec<(u8, usize)>,
+}
+
+/// Count the solutions of `grid`, stopping once `cap` have been found, and tally which digits each
+/// cell takes across them. For a hand-made puzzle that's almost unique, the varying cells show
+/// where another clue is needed. An invalid puzzle has no solutions and no varying cells.
+/// `Unknown` cells are restricted to their current possibilities.
+pub fn solution_spread(grid: &Grid, cap: usize) -> SolutionSpread {
+    // Visits solutions in a fixed order until `remaining` reaches 0, tallying all but the last
+    fn search(bitmasks: &mut Bitmasks, remaining: &mut usize, tallies: &mut [[usize; 10]; 81]) {
+        let (index, options) = match bitmasks.most_constrained_cell() {
+            Ok(Some(best)) => best,
+            Ok(None) => {
+                *remaining -= 1;
+                if *remaining > 0 {
+                    for (tally, &digit) in tallies.iter_mut().zip(bitmasks.digits.iter()) {
+                        tally[digit as usize] += 1;
+                    }
+                }
+                return;
+            }
+            Err(()) => return,
+        };
+
+        for digit in 1..10 {
+            if *remaining == 0 {
+                return;
+            }
+            if options & 1 << digit != 0 {
+                bitmasks.place(index, digit);
+                search(bitmasks, remaining, tallies);
+                bitmasks.unplace(index, digit);
+            }
+        }
+    }
+
+    let mut bitmasks = match Bitmasks::new(grid) {
+        Some(bitmasks) => bitmasks,
+        None => {
+            return SolutionSpread {
+                solutions: 0,
+                capped: false,
+                varying_cells: Vec::new(),
+            }
+        }
+    };
+
+    // Look for one more than the cap to tell whether there are any left over; it isn't tallied
+    let mut remaining = cap + 1;
+    let mut tallies = [[0; 10]; 81];
+    search(&mut bitmasks, &mut remaining, &mut tallies);
+    let capped = remaining == 0;
+    let solutions = cap + 1 - remaining - capped as usize;
+
+    let varying_cells = tallies
+        .iter()
+        .enumerate()
+        .filter_map(|(index, tally)| {
+            let digits: Vec<(u8, usize)> = (1..10)
+                .filter(|&digit| tally[digit as usize] > 0)
+                .map(|digit| (digit, tally[digit as usize]))
+                .collect();
+            if digits.len() > 1 {
+                Some(VaryingCell {
+                    x: index / 9,
+                    y: index % 9,
+                    digits,
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    SolutionSpread {
+        solutions,
+        capped,
+        varying_cells,
+    }
+}
+
+// The state of a backtracking search over bitmasks, where bit d stands for digit d
+struct Bitmasks {
+    // Each empty cell's possibilities when the search started
+    candidates: [u16; 81],
+    rows: [u16; 9],
+    columns: [u16; 9],
+    sections: [u16; 9],
+    // Each cell's digit, or 0 for cells that are still empty
+    digits: [u8; 81],
+}
+
+impl Bitmasks {
+    const ALL_DIGITS: u16 = 0b11_1111_1110;
+
+    // None if `grid` already has two of a digit in a Section, or an empty cell that can't hold
+    // anything
+    fn new(grid: &Grid) -> Option<Bitmasks> {
+        let mut bitmasks = Bitmasks {
+            candidates: [0; 81],
+            rows: [0; 9],
+            columns: [0; 9],
+            sections: [0; 9],
+            digits: [0; 81],
+        };
+
+        for x in 0..9 {
+            for y in 0..9 {
+                let cell = grid.get(x, y).unwrap();
+                let value = &*cell.value.borrow();
+                match value {
+                    CellValue::Fixed(digit) => {
+                        if !bitmasks.fits(x * 9 + y, *digit) {
+                            return None;
+                        }
+                        bitmasks.place(x * 9 + y, *digit);
+                    }
+                    CellValue::Unknown(possibilities) => {
+                        let mask = possibilities
+                            .iter()
+                            .fold(0, |mask, digit| mask | 1 << digit)
+                            & Bitmasks::ALL_DIGITS;
+                        if mask == 0 {
+                            return None;
+                        }
+                        bitmasks.candidates[x * 9 + y] = mask;
+                    }
+                }
+            }
+        }
+
+        Some(bitmasks)
+    }
+
+    // The empty cell with the fewest options left along with those options, None if every cell is
+    // filled in, or an error if some empty cell has no options left
+    fn most_constrained_cell(&self) -> Result<Option<(usize, u16)>, ()> {
+        let mut best: Option<(usize, u16)> = None;
+        for index in 0..81 {
+            if self.digits[index] != 0 {
+                continue;
+            }
+            let options = self.candidates[index] & !self.used(index);
+            if options == 0 {
+                return Err(());
+            }
+            if best.is_none_or(|(_, best_options)| options.count_ones() < best_options.count_ones())
+            {
+                best = Some((index, options));
+            }
+        }
+        Ok(best)
+    }
+
+    // The digits already in the row, column and section of the cell at `index`
+    fn used(&self, index: usize) -> u16 {
+        let (x, y) = (index / 9, index % 9);
+        self.rows[x] | self.columns[y] | self.sections[topology::square_index(x, y)]
+    }
+
+    fn fits(&self, index: usize, digit: u8) -> bool {
+        self.used(index) & 1 << digit == 0
+    }
+
+    fn place(&mut self, index: usize, digit: u8) {
+        let (x, y) = (index / 9, index % 9);
+        let bit = 1 << digit;
+        self.rows[x] |= bit;
+        self.columns[y] |= bit;
+        self.sections[topology::square_index(x, y)] |= bit;
+        self.digits[index] = digit;
+    }
+
+    fn unplace(&mut self, index: usize, digit: u8) {
+        let (x, y) = (index / 9, index % 9);
+        let bit = !(1 << digit);
+        self.rows[x] &= bit;
+        self.columns[y] &= bit;
+        self.sections[topology::square_index(x, y)] &= bit;
+        self.digits[index] = 0;
+    }
+}
+
+// The next Section for solve_grid_no_guess to look at. `round_left` is how many Sections are left
+// in the current round of a `HouseOrder::Sweep`.
+fn next_update(
+    grid: &Grid,
+    house_order: HouseOrder,
+    round_left: &mut usize,
+) -> Option<(SectionType, usize)> {
+    if house_order == HouseOrder::Sweep {
+        if *round_left == 0 {
+            *round_left = grid.sort_updates();
+        }
+        *round_left = round_left.saturating_sub(1);
+    }
+    grid.pop_update()
+}
+
+// Splits a solve_grid_no_guess call into rounds to work out SolveStatistics::bottleneck. Each
+// round takes the Sections that were waiting when it started, whatever the HouseOrder.
+struct BottleneckTracker {
+    round_left: usize,
+    round_start: u32,
+    run: u32,
+}
+
+impl BottleneckTracker {
+    fn new(solve_statistics: &SolveStatistics) -> BottleneckTracker {
+        BottleneckTracker {
+            round_left: 0,
+            round_start: solve_statistics.deductions(),
+            run: 0,
+        }
+    }
+
+    // Called with every Section taken off the queue
+    fn next_section(&mut self, grid: &Grid, solve_statistics: &mut SolveStatistics) {
+        if self.round_left == 0 {
+            self.end_round(solve_statistics);
+            // Including the Section that was just taken off
+            self.round_left = grid.pending_updates() + 1;
+        }
+        self.round_left -= 1;
+    }
+
+    fn end_round(&mut self, solve_statistics: &mut SolveStatistics) {
+        match solve_statistics.deductions() - self.round_start {
+            0 => {}
+            1 => {
+                self.run += 1;
+                solve_statistics.bottleneck = solve_statistics.bottleneck.max(self.run);
+            }
+            _ => self.run = 0,
+        }
+        self.round_start = solve_statistics.deductions();
+    }
+}
+
+fn solve_grid_no_guess(
+    grid: &mut Grid,
+    solve_controller: &SolveController,
+    solve_statistics: &mut SolveStatistics,
+    recorder: &mut Recorder,
+) -> SolveStatus {
+    let mut bottleneck = if solve_controller.collect_statistics {
+        Some(BottleneckTracker::new(solve_statistics))
+    } else {
+        None
+    };
+    loop {
+        // Work through the Sections that changed, in the order they changed. Solving a Section
+        // can queue up more; we're done once nothing is waiting anymore.
+        let mut ran_something = false;
+        let mut round_left = 0;
+        while let Some((section_type, index)) =
+            next_update(grid, solve_controller.house_order, &mut round_left)
+        {
+            if let Some(bottleneck) = bottleneck.as_mut() {
+                bottleneck.next_section(grid, solve_statistics);
+            }
+            let line_ref = grid.get_section(section_type, index).unwrap();
+            let line_ref = &*(&**line_ref).borrow();
+            if line_ref.do_update() {
+                solve_line(grid, line_ref, solve_controller, solve_statistics, recorder);
+                ran_something = true;
+
+                if solve_controller.counts_iterations() {
+                    solve_statistics.iterations += 1;
+                    if solve_controller
+                        .limits
+                        .max_iterations
+                        .is_some_and(|max_iterations| solve_statistics.iterations > max_iterations)
+                    {
+                        return SolveStatus::Aborted(AbortReason::TooManyIterations);
+                    }
+                    if solve_statistics
+                        .iterations
+                        .is_multiple_of(PROGRESS_INTERVAL)
+                    {
+                        recorder.report_progress(solve_statistics);
+                    }
+                }
+
+                if recorder.should_stop(solve_statistics) {
+                    return SolveStatus::Unfinished;
+                }
+            }
+        }
+        if let Some(bottleneck) = bottleneck.as_mut() {
+            bottleneck.end_round(solve_statistics);
+        }
+
+        if !ran_something {
+            // No lines have changed since we last analyzed them
+            return SolveStatus::Unfinished;
+        }
+
+        // Check if complete or invalid
+        let mut appears_complete = true;
+        for x in 0..9 {
+            for y in 0..9 {
+                let cell = grid.get(x, y).unwrap();
+                let cell = &*cell;
+                let value = &**(&cell.value.borrow());
+
+                match value {
+                    CellValue::Unknown(possibilities) => {
+                        appears_complete = false;
+                        if possibilities.len() == 0 {
+                            return SolveStatus::Invalid;
+                        }
+                    }
                     CellValue::Fixed(_) => {}
                 }
             }
@@ -893,242 +2544,1689 @@ fn solve_grid_guess(
     grid: &mut Grid,
     solve_controller: &SolveController,
     solve_statistics: &mut SolveStatistics,
+    grid_pool: &mut Vec<Grid>,
+    recorder: &mut Recorder,
+) -> SolveStatus {
+    let smallest_cell = grid.most_constrained_cell(TieBreak::First);
+    let smallest_cell = match smallest_cell {
+        Some(cell) => cell,
+        // The strategies only notice a grid is complete after running, so with them turned off a
+        // grid filled in by the last guess ends up here
+        None if grid.is_complete() => return SolveStatus::Complete(Some(Uniqueness::Unique)),
+        None => return SolveStatus::Invalid,
+    };
+
+    let possibilities = smallest_cell.get_value_possibilities().unwrap();
+
+    // A cell with one possibility left is a single that the strategies weren't allowed to find;
+    // there's nothing to choose between, so it isn't counted as a guess
+    if possibilities.len() == 1 {
+        log::debug!(
+            "Cell {}, {} has only {} left",
+            smallest_cell.x,
+            smallest_cell.y,
+            possibilities[0]
+        );
+        let before = recorder.before_step(grid);
+        smallest_cell.set(possibilities[0]);
+        if solve_controller.counts_actions() {
+            solve_statistics.increment(&SolveAction::Single);
+        }
+        recorder.after_step(
+            before,
+            grid,
+            SolveAction::Single,
+            Technique::Single,
+            EliminationSource::Cell(smallest_cell.x, smallest_cell.y),
+        );
+        return solve_grid_with_grid_pool(
+            grid,
+            solve_controller,
+            solve_statistics,
+            grid_pool,
+            recorder,
+        );
+    }
+
+    if solve_controller
+        .limits
+        .max_guess_depth
+        .is_some_and(|max_guess_depth| solve_statistics.guess_depth >= max_guess_depth)
+    {
+        return SolveStatus::Aborted(AbortReason::TooDeep);
+    }
+
+    if solve_controller.counts_actions() {
+        solve_statistics.increment(&SolveAction::Guess);
+    }
+    solve_statistics.guess_depth += 1;
+    if solve_controller.collect_statistics {
+        solve_statistics.max_guess_depth = solve_statistics
+            .max_guess_depth
+            .max(solve_statistics.guess_depth);
+    }
+    recorder.report_progress(solve_statistics);
+
+    let status = try_possibilities(
+        grid,
+        &smallest_cell,
+        &possibilities,
+        solve_controller,
+        solve_statistics,
+        grid_pool,
+        recorder,
+    );
+
+    solve_statistics.guess_depth -= 1;
+    if solve_controller.collect_statistics && matches!(status, SolveStatus::Complete(_)) {
+        solve_statistics.solved_with_guesses = true;
+    }
+    status
+}
+
+// Solve a copy of grid for each of the cell's possibilities in turn, keeping the solution if any.
+// When uniqueness isn't being determined the last one is tried on grid itself, which is then left
+// partly filled in if there's no solution.
+fn try_possibilities(
+    grid: &mut Grid,
+    smallest_cell: &Cell,
+    possibilities: &[u8],
+    solve_controller: &SolveController,
+    solve_statistics: &mut SolveStatistics,
+    grid_pool: &mut Vec<Grid>,
+    recorder: &mut Recorder,
 ) -> SolveStatus {
-    solve_statistics.increment(&SolveAction::Guess);
+    #[cfg(feature = "rayon")]
+    {
+        // Branches on other threads can't share the recorder
+        if solve_controller.parallel_guesses()
+            && !recorder.is_enabled()
+            && recorder.stop_when.is_none()
+            && recorder.on_progress.is_none()
+            && recorder.guesses.is_none()
+            && solve_controller.determine_uniqueness()
+            && possibilities.len() >= parallel_guess::MIN_POSSIBILITIES
+        {
+            // Other threads' counts are collected by each branch, so settle this thread's first
+            if solve_controller.collect_statistics {
+                solve_statistics.candidate_allocations += take_candidate_allocations();
+            }
+            return parallel_guess::solve_grid_guess_parallel(
+                grid,
+                smallest_cell,
+                possibilities,
+                solve_controller,
+                solve_statistics,
+            );
+        }
+    }
+
+    if !solve_controller.determine_uniqueness()
+        && !recorder.is_enabled()
+        && recorder.guesses.is_none()
+        && recorder.stop_when.is_none()
+    {
+        return try_possibilities_until_solved(
+            grid,
+            smallest_cell,
+            possibilities,
+            solve_controller,
+            solve_statistics,
+            grid_pool,
+            recorder,
+        );
+    }
+
+    let mut current_status = SolveStatus::Unfinished;
+    let mut grid_solution: Option<Grid> = None;
+    let mut solved_in_place = false;
+    let mut solution_recorder: Option<Recorder> = None;
+    let before_guess = recorder.before_step(grid);
+    // Every branch's guesses are kept here, not just the solution's
+    let mut guesses = recorder.guesses.take();
+    let guess_depth = recorder.guess_depth;
+
+    for (index, &digit) in possibilities.iter().enumerate() {
+        if recorder.should_stop(solve_statistics) {
+            break;
+        }
+
+        // When any solution will do, the last possibility is tried on grid itself, the same as in
+        // try_possibilities_until_solved, so that recording a solve doesn't change its statistics
+        let in_place = !solve_controller.determine_uniqueness() && index + 1 == possibilities.len();
+        let mut spare_grid = None;
+        let grid_copy = if in_place {
+            &mut *grid
+        } else {
+            if solve_controller.collect_statistics {
+                solve_statistics.grid_clones += 1;
+            }
+            spare_grid.insert(match grid_pool.pop() {
+                Some(mut spare_grid) => {
+                    spare_grid.clone_from(grid);
+                    spare_grid
+                }
+                None => grid.clone(),
+            })
+        };
+        if solve_controller.collect_statistics {
+            solve_statistics.guess_candidates += 1;
+        }
+        log::debug!(
+            "Guessing {} in cell {}, {} out of {:?}",
+            digit,
+            smallest_cell.x,
+            smallest_cell.y,
+            possibilities
+        );
+        grid_copy
+            .get(smallest_cell.x, smallest_cell.y)
+            .unwrap()
+            .set(digit);
+
+        let mut branch_recorder = recorder.clone();
+        if guesses.is_some() {
+            branch_recorder.guesses = Some(Vec::new());
+            branch_recorder.guess_depth += 1;
+        }
+        branch_recorder.after_step(
+            before_guess.clone(),
+            grid_copy,
+            SolveAction::Guess,
+            Technique::Guess,
+            EliminationSource::Cell(smallest_cell.x, smallest_cell.y),
+        );
+
+        let status = solve_grid_with_grid_pool(
+            grid_copy,
+            solve_controller,
+            solve_statistics,
+            grid_pool,
+            &mut branch_recorder,
+        );
+
+        if let Some(guesses) = &mut guesses {
+            guesses.push(GuessNode {
+                cell: (smallest_cell.x, smallest_cell.y),
+                digit,
+                outcome: status,
+                depth: guess_depth,
+                children: branch_recorder.guesses.take().unwrap_or_default(),
+            });
+        }
+
+        if branch_recorder.stopped {
+            recorder.stopped = true;
+            grid_pool.extend(spare_grid);
+            break;
+        }
+
+        // Keep a copy of grid_copy in case we later mutate grid with it; otherwise it goes back
+        // into the pool
+        match status {
+            SolveStatus::Complete(_) => {
+                match spare_grid {
+                    Some(grid_copy) => {
+                        if let Some(old_solution) = grid_solution.replace(grid_copy) {
+                            grid_pool.push(old_solution);
+                        }
+                    }
+                    None => solved_in_place = true,
+                }
+                solution_recorder = Some(branch_recorder);
+            }
+            _ => grid_pool.extend(spare_grid),
+        }
+
+        current_status = current_status.increment(status);
+
+        match current_status {
+            SolveStatus::Complete(uniqueness) => {
+                if !solve_controller.determine_uniqueness() {
+                    current_status = SolveStatus::Complete(None); // be explicit we don't know
+                    break; // no point in continuing
+                }
+
+                let uniqueness = uniqueness.expect(
+                    "We're looking for uniqueness and yet an earlier function didn't make a claim",
+                );
+                match uniqueness {
+                    Uniqueness::Unique => {
+                        continue;
+                    } // gotta keep on checking
+                    Uniqueness::NotUnique => {
+                        break; // We can stop looking as we already found at least two solutions
+                    }
+                }
+            }
+            SolveStatus::Unfinished => continue, // Keep looking for a solution
+            SolveStatus::Invalid => panic!("current_status should not be INVALID at this point"),
+            SolveStatus::Aborted(_) => break,
+        }
+    }
+
+    // We've finished the for-loop
+    match current_status {
+        // Neither a solution found so far nor running out of possibilities means anything now
+        _ if recorder.stopped => current_status = SolveStatus::Unfinished,
+        SolveStatus::Complete(_) => {
+            if !solved_in_place {
+                grid.clone_from(
+                    grid_solution
+                        .as_ref()
+                        .expect("grid_solution should have value if we found a solution"),
+                );
+            }
+            *recorder = solution_recorder
+                .expect("solution_recorder should have value if we found a solution");
+            recorder.guess_depth = guess_depth;
+        }
+        SolveStatus::Unfinished => {
+            current_status = SolveStatus::Invalid; // We can now say Invalid
+        }
+        SolveStatus::Invalid | SolveStatus::Aborted(_) => {}
+    }
+
+    recorder.guesses = guesses;
+
+    if let Some(grid_solution) = grid_solution {
+        grid_pool.push(grid_solution);
+    }
+
+    return current_status;
+}
+
+// Like try_possibilities, for when any solution will do and nothing is being recorded. The first
+// solution found is swapped into grid, and the last possibility is tried on grid itself instead
+// of on a copy, since no later branch needs the original back. grid is left partly filled in if
+// there's no solution.
+fn try_possibilities_until_solved(
+    grid: &mut Grid,
+    smallest_cell: &Cell,
+    possibilities: &[u8],
+    solve_controller: &SolveController,
+    solve_statistics: &mut SolveStatistics,
+    grid_pool: &mut Vec<Grid>,
+    recorder: &mut Recorder,
+) -> SolveStatus {
+    let (x, y) = (smallest_cell.x, smallest_cell.y);
+    let (&last_digit, other_digits) = possibilities
+        .split_last()
+        .expect("A guess should have at least one possibility");
+
+    for &digit in other_digits {
+        let mut grid_copy = match grid_pool.pop() {
+            Some(mut spare_grid) => {
+                spare_grid.clone_from(grid);
+                spare_grid
+            }
+            None => grid.clone(),
+        };
+        if solve_controller.collect_statistics {
+            solve_statistics.grid_clones += 1;
+            solve_statistics.guess_candidates += 1;
+        }
+        log::debug!(
+            "Guessing {} in cell {}, {} out of {:?}",
+            digit,
+            x,
+            y,
+            possibilities
+        );
+        grid_copy.get(x, y).unwrap().set(digit);
+
+        let status = solve_grid_with_grid_pool(
+            &mut grid_copy,
+            solve_controller,
+            solve_statistics,
+            grid_pool,
+            recorder,
+        );
+        if let SolveStatus::Complete(_) = status {
+            std::mem::swap(grid, &mut grid_copy);
+            grid_pool.push(grid_copy);
+            return SolveStatus::Complete(None);
+        }
+        grid_pool.push(grid_copy);
+        if let SolveStatus::Aborted(_) = status {
+            return status;
+        }
+    }
+
+    if solve_controller.collect_statistics {
+        solve_statistics.guess_candidates += 1;
+    }
+    log::debug!(
+        "Guessing {} in cell {}, {} out of {:?}",
+        last_digit,
+        x,
+        y,
+        possibilities
+    );
+    grid.get(x, y).unwrap().set(last_digit);
+    match solve_grid_with_grid_pool(
+        grid,
+        solve_controller,
+        solve_statistics,
+        grid_pool,
+        recorder,
+    ) {
+        SolveStatus::Complete(_) => SolveStatus::Complete(None),
+        status @ SolveStatus::Aborted(_) => status,
+        _ => SolveStatus::Invalid,
+    }
+}
+
+/// Solves `Grid`s with a fixed `SolveController` while holding on to the `Grid`s it allocates for
+/// guesses, so they can be reused by later calls. Use this instead of `solve_grid_with_solve_controller`
+/// when solving many puzzles in a row. Guesses are always explored one after another, regardless
+/// of `parallel_guesses`.
+pub struct Solver {
+    pub solve_controller: SolveController,
+    grid_pool: Vec<Grid>,
+}
+
+impl Solver {
+    /// Create a new `Solver` with no `Grid`s allocated yet.
+    pub fn new(solve_controller: SolveController) -> Solver {
+        Solver {
+            solve_controller,
+            grid_pool: Vec::new(),
+        }
+    }
+
+    /// Solves (and modifies) the input `Grid` like `solve_grid_with_solve_controller`. Returns a
+    /// `SolveStatus` and `SolveStatistics`.
+    pub fn solve_into(&mut self, grid: &mut Grid) -> (SolveStatus, SolveStatistics) {
+        let mut solve_controller = self.solve_controller;
+        solve_controller.parallel_guesses = false;
+
+        let mut solve_statistics = SolveStatistics::new();
+        take_candidate_allocations();
+        let solve_status = start_solve(
+            grid,
+            &solve_controller,
+            &mut solve_statistics,
+            &mut self.grid_pool,
+            &mut Recorder::none(),
+        );
+
+        log_summary(&solve_status, &solve_statistics);
+        (solve_status, solve_statistics)
+    }
+
+    /// Solves a copy of `grid` like `evaluate_grid_with_solve_controller`, leaving `grid` as it
+    /// was. The copy is made into one of the `Grid`s the `Solver` is holding on to rather than a
+    /// new one, so evaluating puzzles in a tight loop, such as while generating them, stops
+    /// allocating `Grid`s once the `Solver` has enough of them.
+    pub fn evaluate(&mut self, grid: &Grid) -> (SolveStatus, SolveStatistics) {
+        let mut scratch = match self.grid_pool.pop() {
+            Some(mut scratch) => {
+                scratch.clone_from(grid);
+                scratch
+            }
+            None => grid.clone(),
+        };
+        let result = self.solve_into(&mut scratch);
+        self.grid_pool.push(scratch);
+
+        result
+    }
+}
+
+// Parallel version of the loop in solve_grid_guess, used when hunting for a second solution
+#[cfg(feature = "rayon")]
+mod parallel_guess {
+    use super::{
+        solve_grid_with_grid_pool, Recorder, SolveController, SolveStatistics, SolveStatus,
+        Uniqueness,
+    };
+    use crate::grid::{Cell, Grid};
+    use rayon::prelude::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Cells with fewer possibilities than this aren't worth the overhead of going parallel
+    pub const MIN_POSSIBILITIES: usize = 3;
+
+    pub fn solve_grid_guess_parallel(
+        grid: &mut Grid,
+        cell: &Cell,
+        possibilities: &[u8],
+        solve_controller: &SolveController,
+        solve_statistics: &mut SolveStatistics,
+    ) -> SolveStatus {
+        // Grids can't be shared across threads, so each branch rebuilds its own from a snapshot
+        let snapshot = grid.snapshot();
+        let (x, y) = (cell.x, cell.y);
+        // The solutions each branch found, so far as they're known yet
+        let solutions_found: Vec<AtomicUsize> =
+            possibilities.iter().map(|_| AtomicUsize::new(0)).collect();
+
+        let results: Vec<_> = possibilities
+            .par_iter()
+            .enumerate()
+            .map(|(index, &digit)| {
+                // Once the branches before this one have found two solutions, the sequential loop
+                // would have stopped before reaching it, so it can be skipped. Only those earlier
+                // branches count; skipping on a later one's solution would make the statistics and
+                // the solution that's kept depend on which thread finished first.
+                let found_before: usize = solutions_found[..index]
+                    .iter()
+                    .map(|found| found.load(Ordering::Relaxed))
+                    .sum();
+                if found_before >= 2 {
+                    return None;
+                }
+
+                let mut grid_copy = snapshot.to_grid();
+                grid_copy.get(x, y).unwrap().set(digit);
+
+                let mut branch_statistics = SolveStatistics::new();
+                if solve_controller.collect_statistics {
+                    branch_statistics.grid_clones += 1;
+                }
+                let status = solve_grid_with_grid_pool(
+                    &mut grid_copy,
+                    solve_controller,
+                    &mut branch_statistics,
+                    &mut Vec::new(),
+                    &mut Recorder::none(),
+                );
+
+                let solution = match status {
+                    SolveStatus::Complete(uniqueness) => {
+                        let found = match uniqueness {
+                            Some(Uniqueness::Unique) => 1,
+                            _ => 2,
+                        };
+                        solutions_found[index].store(found, Ordering::Relaxed);
+                        Some(grid_copy.snapshot())
+                    }
+                    _ => None,
+                };
+
+                Some((status, branch_statistics, solution))
+            })
+            .collect();
+
+        // Combine the branches in the same order the sequential loop would have visited them. Every
+        // branch up to the one that settles uniqueness was solved, so the result doesn't depend on
+        // how the threads were scheduled.
+        let mut current_status = SolveStatus::Unfinished;
+        let mut grid_solution = None;
+
+        for (status, branch_statistics, solution) in results.into_iter().flatten() {
+            if solve_controller.collect_statistics {
+                solve_statistics.guess_candidates += 1;
+            }
+            if solve_controller.counts_actions() {
+                solve_statistics.add(&branch_statistics);
+            }
+
+            // The sequential loop keeps the last solution it comes across
+            if solution.is_some() {
+                grid_solution = solution;
+            }
+
+            current_status = current_status.increment(status);
+            if current_status == SolveStatus::Complete(Some(Uniqueness::NotUnique)) {
+                break;
+            }
+        }
+
+        match current_status {
+            SolveStatus::Complete(_) => {
+                grid.clone_from(
+                    &grid_solution
+                        .expect("grid_solution should have value if we found a solution")
+                        .to_grid(),
+                );
+            }
+            SolveStatus::Unfinished => {
+                current_status = SolveStatus::Invalid;
+            }
+            SolveStatus::Invalid | SolveStatus::Aborted(_) => {}
+        }
+
+        current_status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::*;
+    use crate::solver::*;
+
+    #[test]
+    fn test_search_single_possibility() {
+        let grid = Grid::new();
+
+        for i in 0..8 {
+            grid.get(0, i).unwrap().set(i as u8 + 1);
+        }
+
+        assert_eq!(
+            CellValue::Unknown(vec![9]),
+            grid.get(0, 8).unwrap().get_value_copy()
+        );
+
+        let line = grid.rows.first().unwrap();
+        let line = &*(**line).borrow();
+
+        search_single_possibility(line);
+
+        assert_eq!(
+            CellValue::Fixed(9),
+            grid.get(0, 8).unwrap().get_value_copy()
+        );
+    }
+
+    #[test]
+    fn test_identify_and_process_possibility_groups() {
+        let grid = Grid::new();
+
+        // Fill up the first row with values, except for the first section
+        for i in 3..6 {
+            grid.get(0, i).unwrap().set(i as u8 + 1);
+        }
+        grid.get(1, 6).unwrap().set(1);
+        grid.get(1, 7).unwrap().set(2);
+        grid.get(1, 8).unwrap().set(3);
+
+        assert_eq!(
+            CellValue::Unknown(vec![1, 2, 3, 7, 8, 9]),
+            grid.get(0, 0).unwrap().get_value_copy()
+        );
+
+        let line = grid.rows.first().unwrap();
+        let line = &*(**line).borrow();
+
+        process_possibility_groups::identify_and_process_possibility_groups(line, None);
+
+        assert_eq!(
+            CellValue::Unknown(vec![1, 2, 3]),
+            grid.get(0, 0).unwrap().get_value_copy()
+        );
+    }
+
+    #[test]
+    fn test_search_useful_constraint_1() {
+        let grid = Grid::new();
+
+        // Fill up the first row with values, except for the first section
+        for i in 3..6 {
+            grid.get(0, i).unwrap().set(i as u8 + 1);
+        }
+        grid.get(1, 6).unwrap().set(1);
+        grid.get(1, 7).unwrap().set(2);
+        grid.get(1, 8).unwrap().set(3);
+
+        assert_eq!(
+            CellValue::Unknown(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]),
+            grid.get(2, 0).unwrap().get_value_copy()
+        );
+
+        let line = grid.rows.first().unwrap();
+        let line = &*(**line).borrow();
+
+        search_useful_constraint::search_useful_constraint(&grid, line);
+
+        assert_eq!(
+            CellValue::Unknown(vec![4, 5, 6, 7, 8, 9]),
+            grid.get(2, 0).unwrap().get_value_copy()
+        );
+    }
+
+    #[test]
+    fn test_search_useful_constraint_2() {
+        let grid = Grid::new();
+
+        // These values come from a specific bug example where a constraint was incorrectly identified
+        grid.get(3, 0).unwrap().set(8);
+        grid.get(3, 1).unwrap().set(5);
+        grid.get(3, 2).unwrap().set(2);
+        grid.get(5, 1).unwrap().set(6);
+        grid.get(6, 1).unwrap().set(8);
+        grid.get(8, 2).unwrap().set(7);
+
+        grid.get(0, 1)
+            .unwrap()
+            .set_value(CellValue::Unknown(vec![1, 3, 4, 7, 9]));
+        grid.get(1, 1)
+            .unwrap()
+            .set_value(CellValue::Unknown(vec![1, 3, 4, 5, 9]));
+        grid.get(2, 1)
+            .unwrap()
+            .set_value(CellValue::Unknown(vec![1, 2]));
+        grid.get(4, 1)
+            .unwrap()
+            .set_value(CellValue::Unknown(vec![1, 3, 4, 7]));
+        grid.get(7, 1)
+            .unwrap()
+            .set_value(CellValue::Unknown(vec![1, 2, 3, 9]));
+        grid.get(8, 1)
+            .unwrap()
+            .set_value(CellValue::Unknown(vec![1, 2, 3, 5, 9]));
+
+        // 5 is wrongly removed here
+        grid.get(1, 0)
+            .unwrap()
+            .set_value(CellValue::Unknown(vec![1, 3, 4, 5, 9]));
+
+        println!("{}", grid);
+
+        let line = grid.columns.get(1).unwrap();
+        let line = &*(**line).borrow();
+
+        search_useful_constraint::search_useful_constraint(&grid, line);
+
+        assert_eq!(
+            CellValue::Unknown(vec![1, 3, 4, 5, 9]),
+            grid.get(1, 0).unwrap().get_value_copy()
+        );
+    }
+
+    #[test]
+    fn test_hidden_single() {
+        let grid = Grid::new();
+
+        // In Row 0 there should be only one spot for 1s and 2s to be set, even though every cell will
+        // have possibilities for values 3 - 9
+
+        grid.get(1, 5).unwrap().set(1);
+        grid.get(2, 6).unwrap().set(1);
+        grid.get(5, 2).unwrap().set(1);
+        grid.get(6, 1).unwrap().set(1);
+
+        grid.get(1, 6).unwrap().set(2);
+        grid.get(2, 5).unwrap().set(2);
+        grid.get(5, 1).unwrap().set(2);
+        grid.get(6, 0).unwrap().set(2);
+
+        let first_row = grid.rows.get(0).unwrap();
+        let first_row = &*(**first_row).borrow();
+
+        search_hidden_single(&first_row);
+
+        assert_eq!(
+            CellValue::Fixed(1),
+            grid.get(0, 0).unwrap().get_value_copy()
+        );
+        assert_eq!(
+            CellValue::Fixed(2),
+            grid.get(0, 2).unwrap().get_value_copy()
+        );
+    }
+
+    #[test]
+    fn test_solve_all() {
+        let grid1 = Grid::new();
+        for i in 0..8 {
+            grid1.get(0, i).unwrap().set(i as u8 + 1);
+        }
+
+        let grid2 = Grid::new();
+        grid2.get(0, 0).unwrap().set(1);
+        grid2.get(0, 1).unwrap().set(1);
+
+        let results = solve_all(&[grid1, grid2]);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[0].0,
+            SolveStatus::Complete(Some(Uniqueness::NotUnique))
+        );
+        assert_eq!(results[1].0, SolveStatus::Invalid);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_guesses() {
+        let digits = [
+            [0, 0, 0, 8, 0, 0, 4, 0, 5],
+            [1, 0, 0, 0, 0, 0, 0, 0, 0],
+            [9, 0, 0, 0, 5, 0, 1, 3, 0],
+            [3, 0, 0, 6, 9, 5, 0, 0, 2],
+            [0, 0, 0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 6, 4, 8, 0, 0, 1, 0],
+            [0, 0, 0, 0, 1, 0, 3, 0, 9],
+            [0, 8, 0, 0, 6, 0, 0, 0, 0],
+            [0, 0, 9, 3, 0, 0, 2, 8, 0],
+        ];
+
+        let mut sequential_grid = Grid::new();
+        for (r, row) in digits.iter().enumerate() {
+            for (c, &digit) in row.iter().enumerate() {
+                if digit > 0 {
+                    sequential_grid.get(r, c).unwrap().set(digit);
+                }
+            }
+        }
+        let mut parallel_grid = sequential_grid.clone();
+
+        let mut solve_controller = crate::test_utils::full_solve_controller();
+        let sequential_status = solve_grid_with_solve_controller(
+            &mut sequential_grid,
+            &solve_controller,
+            &mut SolveStatistics::new(),
+        );
+
+        solve_controller.parallel_guesses = true;
+        let parallel_status = solve_grid_with_solve_controller(
+            &mut parallel_grid,
+            &solve_controller,
+            &mut SolveStatistics::new(),
+        );
+
+        assert_eq!(sequential_status, parallel_status);
+        assert_eq!(sequential_grid.snapshot(), parallel_grid.snapshot());
+
+        // A puzzle with many solutions comes out the same however the branches are scheduled
+        let mut expected_grid = Grid::new();
+        let mut expected_statistics = SolveStatistics::new();
+        let expected_status = solve_grid_with_solve_controller(
+            &mut expected_grid,
+            &solve_controller,
+            &mut expected_statistics,
+        );
+        assert_eq!(
+            expected_status,
+            SolveStatus::Complete(Some(Uniqueness::NotUnique))
+        );
+        for _ in 0..5 {
+            let mut grid = Grid::new();
+            let mut statistics = SolveStatistics::new();
+            let status =
+                solve_grid_with_solve_controller(&mut grid, &solve_controller, &mut statistics);
+            assert_eq!(status, expected_status);
+            assert_eq!(grid.snapshot(), expected_grid.snapshot());
+            assert_eq!(statistics, expected_statistics);
+        }
+
+        solve_controller.parallel_guesses = false;
+        let mut sequential_grid = Grid::new();
+        solve_grid_with_solve_controller(
+            &mut sequential_grid,
+            &solve_controller,
+            &mut SolveStatistics::new(),
+        );
+        assert_eq!(sequential_grid.snapshot(), expected_grid.snapshot());
+    }
+
+    #[test]
+    fn test_count_solutions_at_most_2() {
+        let grid = Grid::new();
+        assert_eq!(count_solutions_at_most_2(&grid), 2);
+
+        let mut solved_grid = grid.clone();
+        solve_grid(&mut solved_grid);
+        assert_eq!(count_solutions_at_most_2(&solved_grid), 1);
+
+        grid.get(0, 0).unwrap().set(1);
+        grid.get(0, 1).unwrap().set(1);
+        assert_eq!(count_solutions_at_most_2(&grid), 0);
+    }
+
+    #[test]
+    fn test_check_uniqueness() {
+        let grid = Grid::new();
+        assert_eq!(check_uniqueness(&grid), Some(Uniqueness::NotUnique));
+
+        let mut solved_grid = grid.clone();
+        solve_grid(&mut solved_grid);
+        assert_eq!(check_uniqueness(&solved_grid), Some(Uniqueness::Unique));
+
+        grid.get(0, 0).unwrap().set(1);
+        grid.get(0, 1).unwrap().set(1);
+        assert_eq!(check_uniqueness(&grid), None);
+    }
+
+    #[test]
+    fn test_solution_spread() {
+        use rand::prelude::*;
+
+        let mut rng = SmallRng::seed_from_u64(429);
+        let solved_grid = crate::test_utils::random_completed_grid(&mut rng);
+        let spread = solution_spread(&solved_grid, 10);
+        assert_eq!(spread.solutions, 1);
+        assert!(!spread.capped && spread.varying_cells.is_empty());
+
+        // Emptying two rows of a band usually leaves a few ways to fill them back in
+        let grid = solved_grid.clone();
+        for x in 0..2 {
+            for y in 0..9 {
+                grid.get(x, y)
+                    .unwrap()
+                    .set_value(CellValue::Unknown((1..10).collect()));
+            }
+        }
+        let spread = solution_spread(&grid, 100);
+        assert_eq!(
+            spread.solutions,
+            sample_solutions(&grid, 100, &mut rng).len()
+        );
+        assert!(!spread.capped);
+        for cell in spread.varying_cells.iter() {
+            assert!(cell.x < 2);
+            assert_eq!(
+                cell.digits.iter().map(|(_, count)| count).sum::<usize>(),
+                spread.solutions
+            );
+        }
+
+        let spread = solution_spread(&Grid::new(), 10);
+        assert_eq!(spread.solutions, 10);
+        assert!(spread.capped && !spread.varying_cells.is_empty());
+
+        // Nothing can go in the top right corner
+        let grid = Grid::new();
+        for y in 0..8 {
+            grid.get(0, y).unwrap().set(y as u8 + 1);
+        }
+        grid.get(1, 8).unwrap().set(9);
+        assert_eq!(solution_spread(&grid, 10).solutions, 0);
+    }
+
+    #[test]
+    fn test_sample_solutions() {
+        use rand::prelude::*;
+
+        let mut rng = SmallRng::seed_from_u64(421);
+        let (puzzle, _uniqueness) = crate::test_utils::random_puzzle(&mut rng, 20);
+        let clues = puzzle.snapshot();
+
+        let solutions = sample_solutions(&puzzle, 5, &mut rng);
+        assert_eq!(solutions.len(), 5);
+        for (index, solution) in solutions.iter().enumerate() {
+            assert!(solution.is_complete());
+            assert_eq!(check_uniqueness(solution), Some(Uniqueness::Unique));
+            assert!(solutions[..index].iter().all(|other| other != solution));
+            for x in 0..9 {
+                for y in 0..9 {
+                    if let Some(CellValue::Fixed(digit)) = clues.get(x, y) {
+                        assert_eq!(solution.value_at(x, y), Some(CellValue::Fixed(*digit)));
+                    }
+                }
+            }
+        }
+
+        // Emptying one row of a solved grid leaves only the one way to fill it back in
+        let mut solved_grid = Grid::new();
+        solve_grid(&mut solved_grid);
+        let grid = Grid::new();
+        for x in 1..9 {
+            for y in 0..9 {
+                if let Some(CellValue::Fixed(digit)) = solved_grid.value_at(x, y) {
+                    grid.get(x, y).unwrap().set(digit);
+                }
+            }
+        }
+        let solutions = sample_solutions(&grid, 5, &mut rng);
+        assert_eq!(solutions.len(), 1);
+        assert!(solutions[0] == solved_grid);
+
+        grid.get(0, 0).unwrap().set(1);
+        grid.get(0, 1).unwrap().set(1);
+        assert!(sample_solutions(&grid, 5, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_grid_with_early_exit() {
+        let grid = Grid::new();
+
+        // An empty grid can't be solved without guessing
+        let (status, statistics) = evaluate_grid_with_early_exit(
+            &grid,
+            &crate::test_utils::full_solve_controller(),
+            &|statistics| statistics.guesses > 0,
+        );
+        assert_eq!(status, SolveStatus::Unfinished);
+        assert_eq!(statistics.guesses, 1);
+        assert!(grid.get(0, 0).unwrap().get_value_possibilities().is_some());
+
+        let (status, statistics) = evaluate_grid_with_early_exit(
+            &grid,
+            &crate::test_utils::full_solve_controller(),
+            &|_statistics| false,
+        );
+        assert_eq!(status, SolveStatus::Complete(Some(Uniqueness::NotUnique)));
+        assert!(statistics.guesses > 1);
+    }
+
+    #[test]
+    fn test_statistics_aggregation() {
+        let mut first = SolveStatistics::new();
+        first.singles = 10;
+        first.guesses = 2;
+        first.max_guess_depth = 2;
+        first.solved_with_guesses = true;
+        let mut second = SolveStatistics::new();
+        second.singles = 20;
+        second.max_guess_depth = 1;
+        let batch = [first, second];
+
+        let total: SolveStatistics = batch.iter().sum();
+        assert_eq!(total.singles, 30);
+        assert_eq!(total.guesses, 2);
+        assert_eq!(total.max_guess_depth, 2);
+        assert!(total.solved_with_guesses);
+
+        let mean = SolveStatistics::mean(&batch);
+        assert_eq!(mean.solves, 2);
+        assert_eq!(mean.singles, 15.0);
+        assert_eq!(mean.guesses, 1.0);
+        assert_eq!(mean.max_guess_depth, 1.5);
+        assert_eq!(mean.solved_with_guesses, 0.5);
+        assert!(mean.to_string().contains("15.0 SINGLE actions"));
+        assert_eq!(SolveStatistics::mean(&[]), MeanStatistics::default());
+
+        assert!(first.to_string().starts_with("10 SINGLE actions\n"));
+        assert_eq!(first.count(Technique::Single), 10);
+        assert_eq!(first.count(Technique::NakedPair), 0);
+
+        #[cfg(feature = "json")]
+        assert_eq!(
+            serde_json::from_str::<SolveStatistics>(&serde_json::to_string(&first).unwrap())
+                .unwrap()
+                .singles,
+            10
+        );
+    }
+
+    #[test]
+    fn test_with_techniques() {
+        let controller = SolveController::with_techniques(&Technique::ALL);
+        assert!(controller.search_singles && controller.search_hidden_singles);
+        assert!(controller.find_possibility_groups && controller.make_guesses);
+        assert!(controller.search_pointing_constraints && controller.search_claiming_constraints);
+
+        let controller =
+            SolveController::with_techniques(&[Technique::Single, Technique::PointingPair]);
+        assert!(controller.search_singles && !controller.search_hidden_singles);
+        assert!(controller.search_useful_constraint && controller.search_pointing_constraints);
+        assert!(!controller.search_claiming_constraints && !controller.make_guesses);
+
+        for &technique in Technique::ALL.iter() {
+            assert!(SolveController::with_techniques(&[technique]).enables(technique));
+            assert!(SolveController::all_techniques().enables(technique));
+        }
+        let pairs = SolveController::with_techniques(&[Technique::HiddenPair]);
+        assert!(pairs.enables(Technique::HiddenPair) && !pairs.enables(Technique::HiddenTriple));
+        assert!(!pairs.enables(Technique::NakedPair));
+    }
+
+    #[test]
+    fn test_search_subsets() {
+        // Three cells of the first row that only have 1, 2, and 3 between them
+        let triple_grid = || {
+            let grid = Grid::new();
+            for &(y, ref digits) in [(0, vec![1, 2, 3]), (4, vec![2, 3]), (8, vec![1, 3])].iter() {
+                grid.get(0, y)
+                    .unwrap()
+                    .set_value_exact(CellValue::Unknown(digits.clone()));
+            }
+            grid
+        };
 
-    let smallest_cell = grid.find_smallest_cell();
-    let smallest_cell = match smallest_cell {
-        Some(cell) => cell,
-        None => return SolveStatus::Invalid,
-    };
+        let grid = triple_grid();
 
-    let possibilities = smallest_cell.get_value_possibilities().unwrap();
+        let row = grid.get_section(SectionType::Row, 0).unwrap();
+        let row = &*row.borrow();
+        assert_eq!(
+            search_subsets(row, true, SubsetSize::Unlimited),
+            Some(Technique::NakedTriple)
+        );
+        assert_eq!(grid.candidates_at(0, 1), Some(vec![4, 5, 6, 7, 8, 9]));
+        assert_eq!(search_subsets(row, true, SubsetSize::Unlimited), None);
+
+        // The six other cells are now a hidden subset of 4 to 9, but it doesn't remove anything
+        assert_eq!(search_subsets(row, false, SubsetSize::Unlimited), None);
+
+        // A solve credits the triple to the subsets, which run before the possibility groups
+        // that would also find it
+        let solve_controller = SolveController::with_techniques(&[
+            Technique::NakedTriple,
+            Technique::PossibilityGroup,
+        ]);
+        let (_status, solve_statistics) =
+            evaluate_grid_with_solve_controller(&triple_grid(), &solve_controller);
+        assert_eq!(solve_statistics.naked_subsets, 1);
+        assert_eq!(solve_statistics.largest_naked_subset, 3);
+        assert_eq!(solve_statistics.count(Technique::NakedTriple), 1);
+        assert_eq!(solve_statistics.count(Technique::NakedPair), 0);
+        assert!(solve_statistics
+            .to_string()
+            .contains("1 NAKED_SUBSET actions (largest 3)"));
+        assert_eq!(solve_statistics.possibility_groups, 0);
+        assert_eq!(
+            crate::grading::Grader::default()
+                .grade(&solve_statistics)
+                .score,
+            3.6
+        );
+    }
 
-    let mut current_status = SolveStatus::Unfinished;
-    let mut grid_solution = None;
+    #[test]
+    fn test_max_subset_size() {
+        // The first four cells of the first row are a naked quad, which leaves the other five as a
+        // hidden subset, so either way the group has at least four cells
+        let grid = Grid::new();
+        for y in 0..4 {
+            grid.get(0, y)
+                .unwrap()
+                .set_value_exact(CellValue::Unknown(vec![1, 2, 3, 4]));
+        }
 
-    for (_index, &digit) in possibilities.iter().enumerate() {
-        let mut grid_copy = grid.clone();
-        grid_copy
-            .get(smallest_cell.x, smallest_cell.y)
-            .unwrap()
-            .set(digit);
-        let status =
-            solve_grid_with_solve_controller(&mut grid_copy, solve_controller, solve_statistics);
+        let count_groups = |max_subset_size: SubsetSize| {
+            let solve_controller = SolveController {
+                max_subset_size,
+                ..SolveController::with_techniques(&[
+                    Technique::NakedQuad,
+                    Technique::PossibilityGroup,
+                ])
+            };
+            let (_status, solve_statistics) =
+                evaluate_grid_with_solve_controller(&grid, &solve_controller);
+            solve_statistics.naked_subsets + solve_statistics.possibility_groups
+        };
+        assert_eq!(count_groups(SubsetSize::Pairs), 0);
+        assert_eq!(count_groups(SubsetSize::Triples), 0);
+        assert!(count_groups(SubsetSize::Unlimited) > 0);
 
-        // Keep a copy of grid_copy in case we later mutate grid with it
-        match status {
-            SolveStatus::Complete(_) => {
-                grid_solution = Some(grid_copy);
+        assert_eq!(
+            SolveController::with_techniques(&[Technique::Single, Technique::NakedPair])
+                .max_subset_size,
+            SubsetSize::Pairs
+        );
+    }
+
+    #[test]
+    fn test_house_order_is_repeatable() {
+        use rand::prelude::*;
+
+        let mut rng = SmallRng::seed_from_u64(431);
+        let puzzle = crate::test_utils::random_unique_puzzle(&mut rng);
+        let digits = puzzle.to_digits();
+
+        // The same puzzle, filled in from the last cell back to the first
+        let reversed = Grid::new();
+        for index in (0..81).rev() {
+            let digit = digits[index / 9][index % 9];
+            if digit != 0 {
+                reversed.get(index / 9, index % 9).unwrap().set(digit);
             }
-            _ => {}
         }
 
-        current_status = current_status.increment(status);
+        for &house_order in [HouseOrder::Changed, HouseOrder::Sweep].iter() {
+            let solve_controller = SolveController {
+                house_order,
+                ..SolveController::with_techniques(&Technique::ALL)
+            };
+            let (forward_status, forward_statistics, forward_replay) =
+                solve_grid_with_replay(&mut puzzle.clone(), &solve_controller);
+            let (backward_status, backward_statistics, backward_replay) =
+                solve_grid_with_replay(&mut reversed.clone(), &solve_controller);
+
+            assert_eq!(
+                forward_status,
+                SolveStatus::Complete(Some(Uniqueness::Unique))
+            );
+            assert_eq!(forward_status, backward_status);
+            assert_eq!(
+                forward_statistics.to_string(),
+                backward_statistics.to_string()
+            );
+            assert_eq!(forward_replay.steps, backward_replay.steps);
+        }
+    }
 
-        match current_status {
-            SolveStatus::Complete(uniqueness) => {
-                if !solve_controller.determine_uniqueness() {
-                    current_status = SolveStatus::Complete(None); // be explicit we don't know
-                    break; // no point in continuing
-                }
+    #[test]
+    fn test_check_reproducible() {
+        use rand::prelude::*;
 
-                let uniqueness = uniqueness.expect(
-                    "We're looking for uniqueness and yet an earlier function didn't make a claim",
+        let mut rng = SmallRng::seed_from_u64(435);
+
+        // When any solution will do, the last digit of a guess is tried without copying the grid,
+        // so solve enough puzzles that some guesses only succeed with their last digit
+        for num_hints in [22, 26].iter().cycle().take(8) {
+            let (puzzle, _uniqueness) = crate::test_utils::random_puzzle(&mut rng, *num_hints);
+            check_reproducible(&puzzle, &SolveController::first_solution()).unwrap();
+        }
+
+        let controllers = [
+            crate::test_utils::full_solve_controller(),
+            SolveController::first_solution(),
+            SolveController::with_techniques(&[Technique::Single, Technique::HiddenSingle]),
+        ];
+        for num_hints in [22, 30, 81].iter() {
+            let (puzzle, _uniqueness) = crate::test_utils::random_puzzle(&mut rng, *num_hints);
+            for solve_controller in controllers.iter() {
+                let outcome = check_reproducible(&puzzle, solve_controller).unwrap();
+                assert_eq!(
+                    outcome.statistics.candidate_allocations > 0,
+                    *num_hints < 81
                 );
-                match uniqueness {
-                    Uniqueness::Unique => {
-                        continue;
-                    } // gotta keep on checking
-                    Uniqueness::NotUnique => {
-                        break; // We can stop looking as we already found at least two solutions
-                    }
-                }
             }
-            SolveStatus::Unfinished => continue, // Keep looking for a solution
-            SolveStatus::Invalid => panic!("current_status should not be INVALID at this point"),
         }
-    }
 
-    // We've finished the for-loop
-    match current_status {
-        SolveStatus::Complete(_) => {
-            grid.clone_from(
-                &grid_solution.expect("grid_solution should have value if we found a solution"),
+        // A 1 that has to go in two cells of the same row
+        let impossible = Grid::new();
+        impossible.get(0, 0).unwrap().set(1);
+        impossible
+            .get(1, 3)
+            .unwrap()
+            .set_value_exact(CellValue::Unknown(vec![1]));
+        impossible
+            .get(1, 6)
+            .unwrap()
+            .set_value_exact(CellValue::Unknown(vec![1]));
+        for solve_controller in controllers.iter() {
+            let outcome = check_reproducible(&impossible, solve_controller).unwrap();
+            assert_ne!(
+                outcome.status,
+                SolveStatus::Complete(Some(Uniqueness::Unique))
             );
         }
-        SolveStatus::Unfinished => {
-            current_status = SolveStatus::Invalid; // We can now say Invalid
-        }
-        SolveStatus::Invalid => {}
     }
 
-    return current_status;
-}
+    #[test]
+    fn test_first_solution() {
+        use rand::prelude::*;
+
+        let mut rng = SmallRng::seed_from_u64(427);
+        let controller = SolveController::first_solution();
+        for _ in 0..5 {
+            let puzzle = crate::test_utils::random_unique_puzzle(&mut rng);
+            let mut expected = puzzle.clone();
+            let (_status, full_statistics) = solve_grid(&mut expected);
+
+            let mut grid = puzzle.clone();
+            let mut statistics = SolveStatistics::new();
+            let status = solve_grid_with_solve_controller(&mut grid, &controller, &mut statistics);
+            assert!(matches!(status, SolveStatus::Complete(_)));
+            assert_eq!(grid.to_digits(), expected.to_digits());
+            assert!(statistics.grid_clones <= full_statistics.grid_clones);
+        }
 
-#[cfg(test)]
-mod tests {
-    use crate::grid::*;
-    use crate::solver::*;
+        // Any solution will do for a puzzle with many
+        let mut grid = Grid::new();
+        let mut statistics = SolveStatistics::new();
+        let status = solve_grid_with_solve_controller(&mut grid, &controller, &mut statistics);
+        assert_eq!(status, SolveStatus::Complete(None));
+        assert!(grid.is_solved());
+
+        // Nothing can go in the top right corner
+        let mut grid = Grid::new();
+        for y in 0..7 {
+            grid.get(0, y).unwrap().set(y as u8 + 1);
+        }
+        grid.get(1, 7).unwrap().set(8);
+        grid.get(1, 8).unwrap().set(9);
+        let status =
+            solve_grid_with_solve_controller(&mut grid, &controller, &mut SolveStatistics::new());
+        assert_eq!(status, SolveStatus::Invalid);
+    }
 
     #[test]
-    fn test_search_single_possibility() {
+    fn test_solver_reuse() {
         let grid = Grid::new();
+        grid.get(0, 0).unwrap().set(1);
+        grid.get(1, 3).unwrap().set(2);
+        grid.get(4, 4).unwrap().set(3);
 
-        for i in 0..8 {
-            grid.get(0, i).unwrap().set(i as u8 + 1);
-        }
+        let mut solver = Solver::new(crate::test_utils::full_solve_controller());
 
-        assert_eq!(
-            CellValue::Unknown(vec![9]),
-            grid.get(0, 8).unwrap().get_value_copy()
-        );
+        let mut expected_grid = grid.clone();
+        let (expected_status, _statistics) = solve_grid(&mut expected_grid);
 
-        let line = grid.rows.first().unwrap();
-        let line = &*(**line).borrow();
+        // Solving the same puzzle twice should reuse the pooled Grids without affecting the result
+        for _ in 0..2 {
+            let mut solver_grid = grid.clone();
+            let (status, _statistics) = solver.solve_into(&mut solver_grid);
 
-        search_single_possibility(line);
+            assert_eq!(status, expected_status);
+            assert_eq!(solver_grid.snapshot(), expected_grid.snapshot());
+        }
 
-        assert_eq!(
-            CellValue::Fixed(9),
-            grid.get(0, 8).unwrap().get_value_copy()
-        );
+        // Evaluating copies into a pooled Grid, so once the pool has grown enough it stays the
+        // same size, and the puzzle is left as it was
+        let (expected_status, expected_statistics) =
+            evaluate_grid_with_solve_controller(&grid, &solver.solve_controller);
+        let mut pooled = Vec::new();
+        for _ in 0..3 {
+            let (status, statistics) = solver.evaluate(&grid);
+            assert_eq!(status, expected_status);
+            assert_eq!(statistics.singles, expected_statistics.singles);
+            assert_eq!(statistics.guesses, expected_statistics.guesses);
+            pooled.push(solver.grid_pool.len());
+        }
+        assert_eq!(pooled[1], pooled[2]);
+        assert_eq!(grid.clue_count(), 3);
     }
 
     #[test]
-    fn test_identify_and_process_possibility_groups() {
-        let grid = Grid::new();
-
-        // Fill up the first row with values, except for the first section
-        for i in 3..6 {
-            grid.get(0, i).unwrap().set(i as u8 + 1);
+    fn test_memory_statistics() {
+        // An empty grid can only be solved by guessing
+        let mut grid = Grid::new();
+        let (_status, statistics) = solve_grid(&mut grid);
+        assert!(statistics.guesses > 0);
+        assert!(statistics.max_guess_depth > 0);
+        assert!(statistics.max_guess_depth <= statistics.guesses);
+        assert!(statistics.grid_clones >= statistics.max_guess_depth);
+        assert!(statistics.candidate_allocations > 0);
+
+        // Whereas a grid missing a single digit needs no guesses, and so no clones
+        let mut puzzle = Grid::new();
+        for x in 0..9 {
+            for y in 0..9 {
+                if (x, y) == (0, 0) {
+                    continue;
+                }
+                if let CellValue::Fixed(digit) = grid.get(x, y).unwrap().get_value_copy() {
+                    puzzle.get(x, y).unwrap().set(digit);
+                }
+            }
         }
-        grid.get(1, 6).unwrap().set(1);
-        grid.get(1, 7).unwrap().set(2);
-        grid.get(1, 8).unwrap().set(3);
+        let (status, statistics) = solve_grid(&mut puzzle);
+        assert_eq!(status, SolveStatus::Complete(Some(Uniqueness::Unique)));
+        assert_eq!(statistics.guesses, 0);
+        assert_eq!(statistics.max_guess_depth, 0);
+        assert_eq!(statistics.grid_clones, 0);
+    }
 
-        assert_eq!(
-            CellValue::Unknown(vec![1, 2, 3, 7, 8, 9]),
-            grid.get(0, 0).unwrap().get_value_copy()
-        );
+    #[test]
+    fn test_guess_counts() {
+        // An empty grid needs guesses within guesses, each trying at least one digit
+        let mut grid = Grid::new();
+        let (_status, statistics) = solve_grid(&mut grid);
+        assert!(statistics.max_guess_depth > 1);
+        assert!(statistics.guess_candidates >= statistics.guesses);
+
+        // With the strategies turned off, a missing digit is left to the guessing stage, but a
+        // cell with one possibility isn't a guess
+        let mut digits = grid.to_digits();
+        digits[4][4] = 0;
+        let mut puzzle = Grid::from_digits(digits);
+        let solve_controller = SolveController {
+            search_singles: false,
+            search_hidden_singles: false,
+            search_naked_subsets: false,
+            search_hidden_subsets: false,
+            find_possibility_groups: false,
+            search_useful_constraint: false,
+            search_pointing_constraints: false,
+            search_claiming_constraints: false,
+            ..crate::test_utils::full_solve_controller()
+        };
+        let mut statistics = SolveStatistics::new();
+        let status =
+            solve_grid_with_solve_controller(&mut puzzle, &solve_controller, &mut statistics);
+        assert_eq!(status, SolveStatus::Complete(Some(Uniqueness::Unique)));
+        assert!(puzzle == grid);
+        assert_eq!(statistics.guesses, 0);
+        assert_eq!(statistics.guess_candidates, 0);
+        assert_eq!(statistics.max_guess_depth, 0);
+        assert!(!statistics.solved_with_guesses);
+    }
 
-        let line = grid.rows.first().unwrap();
-        let line = &*(**line).borrow();
+    #[test]
+    fn test_cross_check() {
+        use rand::prelude::*;
+
+        let mut rng = SmallRng::seed_from_u64(367);
+        for &num_hints in [81, 30, 10].iter() {
+            let (puzzle, uniqueness) = crate::test_utils::random_puzzle(&mut rng, num_hints);
+            assert_eq!(
+                cross_check(&puzzle),
+                Ok(SolveStatus::Complete(Some(uniqueness)))
+            );
+        }
 
-        process_possibility_groups::identify_and_process_possibility_groups(line);
+        // Nothing can go in the top right corner
+        let grid = Grid::new();
+        for y in 0..8 {
+            grid.get(0, y).unwrap().set(y as u8 + 1);
+        }
+        grid.get(1, 8).unwrap().set(9);
+        assert_eq!(cross_check(&grid), Ok(SolveStatus::Invalid));
+    }
 
-        assert_eq!(
-            CellValue::Unknown(vec![1, 2, 3]),
-            grid.get(0, 0).unwrap().get_value_copy()
-        );
+    #[test]
+    fn test_solved_with_guesses() {
+        // An empty grid can't be solved without guessing
+        let mut solved_grid = Grid::new();
+        let (_status, statistics) = solve_grid(&mut solved_grid);
+        assert!(statistics.solved_with_guesses);
+
+        // A grid missing one digit is solved by singles alone
+        let mut puzzle = Grid::new();
+        for x in 0..9 {
+            for y in 0..9 {
+                if (x, y) == (0, 0) {
+                    continue;
+                }
+                if let CellValue::Fixed(digit) = solved_grid.get(x, y).unwrap().get_value_copy() {
+                    puzzle.get(x, y).unwrap().set(digit);
+                }
+            }
+        }
+        let (_status, statistics) = solve_grid(&mut puzzle);
+        assert!(!statistics.solved_with_guesses);
+
+        // Whereas an impossible grid is never solved, however many guesses are made
+        let mut grid = Grid::new();
+        for y in 0..8 {
+            grid.get(0, y).unwrap().set(y as u8 + 1);
+        }
+        grid.get(1, 8).unwrap().set(9);
+        let (status, statistics) = solve_grid(&mut grid);
+        assert_eq!(status, SolveStatus::Invalid);
+        assert!(!statistics.solved_with_guesses);
     }
 
     #[test]
-    fn test_search_useful_constraint_1() {
+    fn test_technique_caps() {
         let grid = Grid::new();
+        let mut solve_controller = crate::test_utils::full_solve_controller();
+
+        solve_controller.technique_caps.guesses = Some(3);
+        let (status, statistics) = evaluate_grid_with_solve_controller(&grid, &solve_controller);
+        assert_eq!(statistics.guesses, 3);
+        assert_eq!(status, SolveStatus::Complete(Some(Uniqueness::NotUnique)));
+
+        solve_controller.technique_caps.guesses = Some(0);
+        solve_controller.technique_caps.singles = Some(1);
+        grid.get(0, 0).unwrap().set(1);
+        let (_status, statistics) = evaluate_grid_with_solve_controller(&grid, &solve_controller);
+        assert_eq!(statistics.guesses, 0);
+        assert!(statistics.singles <= 1);
+    }
 
-        // Fill up the first row with values, except for the first section
-        for i in 3..6 {
-            grid.get(0, i).unwrap().set(i as u8 + 1);
-        }
-        grid.get(1, 6).unwrap().set(1);
-        grid.get(1, 7).unwrap().set(2);
-        grid.get(1, 8).unwrap().set(3);
+    #[test]
+    fn test_pointing_and_claiming_constraints() {
+        use rand::prelude::*;
 
+        let mut rng = SmallRng::seed_from_u64(371);
+        let puzzle = crate::test_utils::random_unique_puzzle(&mut rng);
+
+        let (_status, statistics) = solve_grid(&mut puzzle.clone());
         assert_eq!(
-            CellValue::Unknown(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]),
-            grid.get(2, 0).unwrap().get_value_copy()
+            statistics.pointing_constraints + statistics.claiming_constraints,
+            statistics.useful_constraints
         );
 
-        let line = grid.rows.first().unwrap();
-        let line = &*(**line).borrow();
-
-        search_useful_constraint::search_useful_constraint(&grid, line);
+        let mut solve_controller = SolveController {
+            search_claiming_constraints: false,
+            ..crate::test_utils::full_solve_controller()
+        };
+        let (_status, statistics) = evaluate_grid_with_solve_controller(&puzzle, &solve_controller);
+        assert_eq!(statistics.claiming_constraints, 0);
+        assert_eq!(
+            statistics.pointing_constraints,
+            statistics.useful_constraints
+        );
 
+        solve_controller.search_pointing_constraints = false;
+        solve_controller.search_claiming_constraints = true;
+        let (_status, statistics) = evaluate_grid_with_solve_controller(&puzzle, &solve_controller);
+        assert_eq!(statistics.pointing_constraints, 0);
         assert_eq!(
-            CellValue::Unknown(vec![4, 5, 6, 7, 8, 9]),
-            grid.get(2, 0).unwrap().get_value_copy()
+            statistics.claiming_constraints,
+            statistics.useful_constraints
         );
     }
 
     #[test]
-    fn test_search_useful_constraint_2() {
+    fn test_solve_limits() {
         let grid = Grid::new();
+        let mut solve_controller = crate::test_utils::full_solve_controller();
 
-        // These values come from a specific bug example where a constraint was incorrectly identified
-        grid.get(3, 0).unwrap().set(8);
-        grid.get(3, 1).unwrap().set(5);
-        grid.get(3, 2).unwrap().set(2);
-        grid.get(5, 1).unwrap().set(6);
-        grid.get(6, 1).unwrap().set(8);
-        grid.get(8, 2).unwrap().set(7);
-
-        grid.get(0, 1)
-            .unwrap()
-            .set_value(CellValue::Unknown(vec![1, 3, 4, 7, 9]));
-        grid.get(1, 1)
-            .unwrap()
-            .set_value(CellValue::Unknown(vec![1, 3, 4, 5, 9]));
-        grid.get(2, 1)
-            .unwrap()
-            .set_value(CellValue::Unknown(vec![1, 2]));
-        grid.get(4, 1)
-            .unwrap()
-            .set_value(CellValue::Unknown(vec![1, 3, 4, 7]));
-        grid.get(7, 1)
-            .unwrap()
-            .set_value(CellValue::Unknown(vec![1, 2, 3, 9]));
-        grid.get(8, 1)
-            .unwrap()
-            .set_value(CellValue::Unknown(vec![1, 2, 3, 5, 9]));
-
-        // 5 is wrongly removed here
-        grid.get(1, 0)
-            .unwrap()
-            .set_value(CellValue::Unknown(vec![1, 3, 4, 5, 9]));
-
-        println!("{}", grid);
+        solve_controller.limits.max_iterations = Some(5);
+        let (status, statistics) = evaluate_grid_with_solve_controller(&grid, &solve_controller);
+        assert_eq!(status, SolveStatus::Aborted(AbortReason::TooManyIterations));
+        assert_eq!(statistics.iterations, 6);
 
-        let line = grid.columns.get(1).unwrap();
-        let line = &*(**line).borrow();
+        // An empty grid needs a guess straight away
+        solve_controller.limits = SolveLimits {
+            max_iterations: None,
+            max_guess_depth: Some(0),
+        };
+        let (status, statistics) = evaluate_grid_with_solve_controller(&grid, &solve_controller);
+        assert_eq!(status, SolveStatus::Aborted(AbortReason::TooDeep));
+        assert_eq!(statistics.guesses, 0);
+
+        // Generous limits don't change anything
+        solve_controller.limits = SolveLimits {
+            max_iterations: Some(1_000_000),
+            max_guess_depth: Some(81),
+        };
+        let (status, _statistics) = evaluate_grid_with_solve_controller(&grid, &solve_controller);
+        assert_eq!(status, SolveStatus::Complete(Some(Uniqueness::NotUnique)));
+    }
 
-        search_useful_constraint::search_useful_constraint(&grid, line);
+    #[test]
+    fn test_solve_grid_with_progress() {
+        let events = std::cell::RefCell::new(Vec::new());
+        let mut grid = Grid::new();
+        let mut statistics = SolveStatistics::new();
+        let status = solve_grid_with_progress(
+            &mut grid,
+            &crate::test_utils::full_solve_controller(),
+            &mut statistics,
+            &|progress| events.borrow_mut().push(*progress),
+        );
+        assert_eq!(status, solve_grid(&mut Grid::new()).0);
 
+        // One report for every guess, and one for every thousand houses
+        let events = events.into_inner();
         assert_eq!(
-            CellValue::Unknown(vec![1, 3, 4, 5, 9]),
-            grid.get(1, 0).unwrap().get_value_copy()
+            events.len() as u32,
+            statistics.guesses + statistics.iterations / PROGRESS_INTERVAL
         );
+        assert!(events
+            .windows(2)
+            .all(|pair| pair[0].guesses <= pair[1].guesses
+                && pair[0].houses_processed <= pair[1].houses_processed));
+        assert_eq!(events[0].guesses, 1);
+        assert_eq!(events[0].guess_depth, 1);
+        assert!(events
+            .iter()
+            .all(|event| event.guess_depth <= statistics.max_guess_depth));
     }
 
     #[test]
-    fn test_hidden_single() {
-        let grid = Grid::new();
+    fn test_update_queue_reaches_fixpoint() {
+        // Solving only the Sections that were queued up has to end in the same place as sweeping
+        // every Section over and over until nothing changes; a missed enqueue would stop short
+        use rand::prelude::*;
+
+        let solve_controller = SolveController {
+            make_guesses: false,
+            ..crate::test_utils::full_solve_controller()
+        };
+        let mut rng = SmallRng::seed_from_u64(362);
+        for _ in 0..20 {
+            let (puzzle, _uniqueness) = crate::test_utils::random_puzzle(&mut rng, 24);
+
+            let mut queued = puzzle.clone();
+            solve_grid_no_guess(
+                &mut queued,
+                &solve_controller,
+                &mut SolveStatistics::new(),
+                &mut Recorder::none(),
+            );
 
-        // In Row 0 there should be only one spot for 1s and 2s to be set, even though every cell will
-        // have possibilities for values 3 - 9
+            let swept = puzzle.clone();
+            loop {
+                let before = swept.snapshot();
+                for section in swept
+                    .rows
+                    .iter()
+                    .chain(swept.columns.iter())
+                    .chain(swept.sections.iter())
+                {
+                    solve_line(
+                        &swept,
+                        &section.borrow(),
+                        &solve_controller,
+                        &mut SolveStatistics::new(),
+                        &mut Recorder::none(),
+                    );
+                }
+                if swept.snapshot() == before {
+                    break;
+                }
+            }
 
-        grid.get(1, 5).unwrap().set(1);
-        grid.get(2, 6).unwrap().set(1);
-        grid.get(5, 2).unwrap().set(1);
-        grid.get(6, 1).unwrap().set(1);
+            assert_eq!(queued.snapshot(), swept.snapshot());
+        }
+    }
 
-        grid.get(1, 6).unwrap().set(2);
-        grid.get(2, 5).unwrap().set(2);
-        grid.get(5, 1).unwrap().set(2);
-        grid.get(6, 0).unwrap().set(2);
+    #[test]
+    fn test_contradictory_grids() {
+        // Two 9s in the first row of an otherwise sparse grid used to keep the solver guessing
+        // more or less forever
+        let grid = Grid::new();
+        grid.get(0, 0).unwrap().set(9);
+        grid.get(0, 5).unwrap().set(9);
+        grid.get(2, 7).unwrap().set(4);
+        grid.get(3, 3).unwrap().set(1);
+        grid.get(4, 7).unwrap().set(2);
+        grid.get(6, 1).unwrap().set(7);
+        grid.get(8, 8).unwrap().set(3);
+
+        let clashing_column = Grid::new();
+        clashing_column.get(1, 4).unwrap().set(5);
+        clashing_column.get(7, 4).unwrap().set(5);
+
+        let clashing_square = Grid::new();
+        clashing_square.get(3, 3).unwrap().set(6);
+        clashing_square.get(5, 5).unwrap().set(6);
+
+        for grid in [grid, clashing_column, clashing_square].iter() {
+            let mut solved = grid.clone();
+            let (status, _statistics) = solve_grid(&mut solved);
+            assert_eq!(status, SolveStatus::Invalid);
+
+            let mut solver = Solver::new(SolveController::first_solution());
+            assert_eq!(solver.evaluate(grid).0, SolveStatus::Invalid);
+        }
+    }
 
-        let first_row = grid.rows.get(0).unwrap();
-        let first_row = &*(**first_row).borrow();
+    #[test]
+    fn test_trust_candidates() {
+        use rand::prelude::*;
+
+        let mut rng = SmallRng::seed_from_u64(451);
+        let puzzle = crate::test_utils::random_unique_puzzle(&mut rng);
+        let mut solution = puzzle.clone();
+        solve_grid(&mut solution);
+
+        // Save the puzzle once the strategies have done all they can
+        let logic_only = SolveController {
+            make_guesses: false,
+            ..crate::test_utils::full_solve_controller()
+        };
+        let mut saved = puzzle.clone();
+        solve_grid_with_solve_controller(&mut saved, &logic_only, &mut SolveStatistics::new());
+        let saved = saved.snapshot();
+
+        let mut solve_controller = crate::test_utils::full_solve_controller();
+        let mut cold = saved.to_grid();
+        let mut cold_statistics = SolveStatistics::new();
+        let cold_status =
+            solve_grid_with_solve_controller(&mut cold, &solve_controller, &mut cold_statistics);
+
+        solve_controller.trust_candidates = true;
+        let mut warm = saved.to_grid();
+        let mut warm_statistics = SolveStatistics::new();
+        let warm_status =
+            solve_grid_with_solve_controller(&mut warm, &solve_controller, &mut warm_statistics);
+
+        assert_eq!(warm_status, cold_status);
+        assert_eq!(warm_status, SolveStatus::Complete(Some(Uniqueness::Unique)));
+        assert!(warm == solution);
+        assert!(warm_statistics.iterations < cold_statistics.iterations);
+        assert_eq!(warm_statistics.singles, cold_statistics.singles);
+    }
 
-        search_hidden_single(&first_row);
+    #[test]
+    fn test_bottleneck() {
+        use rand::prelude::*;
+
+        let mut solved_grid = Grid::new();
+        solve_grid(&mut solved_grid);
+        let digits = solved_grid.to_digits();
+        let leave_out = |empty: &dyn Fn(usize, usize) -> bool| {
+            let grid = Grid::new();
+            for (x, row) in digits.iter().enumerate() {
+                for (y, &digit) in row.iter().enumerate() {
+                    if !empty(x, y) {
+                        grid.get(x, y).unwrap().set(digit);
+                    }
+                }
+            }
+            grid
+        };
 
+        // A single missing digit is found in the only round there is
+        let (_, statistics) = solve_grid(&mut leave_out(&|x, y| (x, y) == (4, 4)));
+        assert_eq!(statistics.bottleneck, 1);
+
+        // Digits missing from different rows are found together
+        let (_, statistics) = solve_grid(&mut leave_out(&|x, y| x == y && x.is_multiple_of(4)));
+        assert_eq!(statistics.singles, 3);
+        assert_eq!(statistics.bottleneck, 0);
+
+        let mut rng = SmallRng::seed_from_u64(452);
+        let mut puzzle = crate::test_utils::random_unique_puzzle(&mut rng);
+        let (_, statistics) = solve_grid(&mut puzzle);
+        assert!(statistics.bottleneck <= statistics.deductions());
+        assert!(statistics.to_string().contains(&format!(
+            "{} rounds in the longest single-deduction bottleneck",
+            statistics.bottleneck
+        )));
+
+        let mut other = SolveStatistics::new();
+        other.bottleneck = statistics.bottleneck + 2;
+        let batch = [statistics, other];
+        let total: SolveStatistics = batch.iter().sum();
+        assert_eq!(total.bottleneck, statistics.bottleneck + 2);
         assert_eq!(
-            CellValue::Fixed(1),
-            grid.get(0, 0).unwrap().get_value_copy()
-        );
-        assert_eq!(
-            CellValue::Fixed(2),
-            grid.get(0, 2).unwrap().get_value_copy()
+            SolveStatistics::mean(&batch).bottleneck,
+            f64::from(statistics.bottleneck) + 1.0
         );
     }
+
+    #[test]
+    fn test_collect_statistics() {
+        use rand::prelude::*;
+
+        let mut rng = SmallRng::seed_from_u64(454);
+        let puzzle = crate::test_utils::random_unique_puzzle(&mut rng);
+        let mut solution = puzzle.clone();
+        solve_grid(&mut solution);
+
+        let mut solve_controller = SolveController {
+            collect_statistics: false,
+            ..crate::test_utils::full_solve_controller()
+        };
+        let mut grid = puzzle.clone();
+        let mut statistics = SolveStatistics::new();
+        let status =
+            solve_grid_with_solve_controller(&mut grid, &solve_controller, &mut statistics);
+        assert_eq!(status, SolveStatus::Complete(Some(Uniqueness::Unique)));
+        assert!(grid == solution);
+        assert_eq!(statistics.to_string(), SolveStatistics::new().to_string());
+
+        // Caps and limits still keep the counts they need
+        solve_controller.technique_caps.singles = Some(1000);
+        let (_status, statistics) = evaluate_grid_with_solve_controller(&puzzle, &solve_controller);
+        assert!(statistics.singles > 0);
+        assert_eq!(statistics.iterations, 0);
+
+        solve_controller.limits.max_iterations = Some(1);
+        let (status, _statistics) = evaluate_grid_with_solve_controller(&puzzle, &solve_controller);
+        assert_eq!(status, SolveStatus::Aborted(AbortReason::TooManyIterations));
+    }
 }
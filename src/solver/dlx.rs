@@ -0,0 +1,324 @@
+//! A brute-force solver using Knuth's Algorithm X with dancing links over the standard sudoku
+//! exact cover matrix. It doesn't reason like a person would, so it says nothing about how hard
+//! a puzzle is, but it's much faster at finding or counting solutions than the solver in
+//! `solver`, and shares no code with it.
+
+use crate::grid::{CellValue, Grid};
+use crate::solver::{SolveStatus, Uniqueness};
+use crate::topology;
+
+// Every cell has a digit, every row / column / section has every digit once
+const NUM_COLUMNS: usize = 4 * 81;
+const ROOT: usize = 0;
+
+// One row of the exact cover matrix; placing `digit` at (`x`, `y`)
+#[derive(Clone, Copy)]
+struct Candidate {
+    x: usize,
+    y: usize,
+    digit: u8,
+}
+
+// A toroidal doubly linked list of the matrix's 1s, stored as indices. Node 0 is the root,
+// nodes 1..=NUM_COLUMNS are the column headers and the rest belong to candidate rows.
+struct Matrix {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    column: Vec<usize>,
+    candidate: Vec<usize>, // only meaningful for non-header nodes
+    size: Vec<usize>,      // only meaningful for column headers
+    candidates: Vec<Candidate>,
+}
+
+impl Matrix {
+    fn new(grid: &Grid) -> Matrix {
+        let num_headers = NUM_COLUMNS + 1;
+        let mut matrix = Matrix {
+            left: (0..num_headers)
+                .map(|i| (i + num_headers - 1) % num_headers)
+                .collect(),
+            right: (0..num_headers).map(|i| (i + 1) % num_headers).collect(),
+            up: (0..num_headers).collect(),
+            down: (0..num_headers).collect(),
+            column: (0..num_headers).collect(),
+            candidate: vec![0; num_headers],
+            size: vec![0; num_headers],
+            candidates: Vec::with_capacity(729),
+        };
+
+        for x in 0..9 {
+            for y in 0..9 {
+                let digits = match grid.get(x, y).unwrap().get_value_copy() {
+                    CellValue::Fixed(digit) => vec![digit],
+                    CellValue::Unknown(possibilities) => possibilities,
+                };
+                for digit in digits {
+                    matrix.add_candidate(Candidate { x, y, digit });
+                }
+            }
+        }
+
+        matrix
+    }
+
+    fn add_candidate(&mut self, candidate: Candidate) {
+        let Candidate { x, y, digit } = candidate;
+        let digit_index = (digit - 1) as usize;
+        let section = topology::square_index(x, y);
+        let columns = [
+            1 + x * 9 + y,
+            1 + 81 + x * 9 + digit_index,
+            1 + 2 * 81 + y * 9 + digit_index,
+            1 + 3 * 81 + section * 9 + digit_index,
+        ];
+
+        let candidate_index = self.candidates.len();
+        self.candidates.push(candidate);
+
+        let first_node = self.left.len();
+        for (i, &column) in columns.iter().enumerate() {
+            let node = first_node + i;
+
+            // Link into the row
+            self.left.push(first_node + (i + 3) % 4);
+            self.right.push(first_node + (i + 1) % 4);
+
+            // Link in at the bottom of the column
+            self.up.push(self.up[column]);
+            self.down.push(column);
+            let above = self.up[column];
+            self.down[above] = node;
+            self.up[column] = node;
+
+            self.column.push(column);
+            self.candidate.push(candidate_index);
+            self.size.push(0);
+            self.size[column] += 1;
+        }
+    }
+
+    fn cover(&mut self, column: usize) {
+        let (left, right) = (self.left[column], self.right[column]);
+        self.right[left] = right;
+        self.left[right] = left;
+
+        let mut row_node = self.down[column];
+        while row_node != column {
+            let mut node = self.right[row_node];
+            while node != row_node {
+                let (up, down) = (self.up[node], self.down[node]);
+                self.down[up] = down;
+                self.up[down] = up;
+                self.size[self.column[node]] -= 1;
+                node = self.right[node];
+            }
+            row_node = self.down[row_node];
+        }
+    }
+
+    fn uncover(&mut self, column: usize) {
+        let mut row_node = self.up[column];
+        while row_node != column {
+            let mut node = self.left[row_node];
+            while node != row_node {
+                let (up, down) = (self.up[node], self.down[node]);
+                self.down[up] = node;
+                self.up[down] = node;
+                self.size[self.column[node]] += 1;
+                node = self.left[node];
+            }
+            row_node = self.up[row_node];
+        }
+
+        let (left, right) = (self.left[column], self.right[column]);
+        self.right[left] = column;
+        self.left[right] = column;
+    }
+
+    // Column with the fewest remaining candidates, or None if every column is covered
+    fn smallest_column(&self) -> Option<usize> {
+        let mut smallest: Option<usize> = None;
+        let mut column = self.right[ROOT];
+        while column != ROOT {
+            if smallest.is_none_or(|smallest| self.size[column] < self.size[smallest]) {
+                smallest = Some(column);
+            }
+            column = self.right[column];
+        }
+        smallest
+    }
+
+    // Counts solutions until `limit` is reached, keeping the candidates of the first one found
+    fn search(
+        &mut self,
+        partial: &mut Vec<usize>,
+        limit: usize,
+        count: &mut usize,
+        first_solution: &mut Option<Vec<usize>>,
+    ) {
+        let column = match self.smallest_column() {
+            Some(column) => column,
+            None => {
+                *count += 1;
+                if first_solution.is_none() {
+                    *first_solution = Some(partial.clone());
+                }
+                return;
+            }
+        };
+
+        if self.size[column] == 0 {
+            return;
+        }
+
+        self.cover(column);
+
+        let mut row_node = self.down[column];
+        while row_node != column && *count < limit {
+            partial.push(self.candidate[row_node]);
+
+            let mut node = self.right[row_node];
+            while node != row_node {
+                self.cover(self.column[node]);
+                node = self.right[node];
+            }
+
+            self.search(partial, limit, count, first_solution);
+
+            let mut node = self.left[row_node];
+            while node != row_node {
+                self.uncover(self.column[node]);
+                node = self.left[node];
+            }
+
+            partial.pop();
+            row_node = self.down[row_node];
+        }
+
+        self.uncover(column);
+    }
+}
+
+// Returns the number of solutions found (at most `limit`) and the first solution
+fn search(grid: &Grid, limit: usize) -> (usize, Option<Vec<Candidate>>) {
+    let mut matrix = Matrix::new(grid);
+    let mut count = 0;
+    let mut first_solution = None;
+
+    if limit > 0 {
+        matrix.search(
+            &mut Vec::with_capacity(81),
+            limit,
+            &mut count,
+            &mut first_solution,
+        );
+    }
+
+    let first_solution = first_solution.map(|candidates| {
+        candidates
+            .iter()
+            .map(|&index| matrix.candidates[index])
+            .collect()
+    });
+
+    (count, first_solution)
+}
+
+/// Solves (and modifies) the input `Grid`, setting it to the first solution found. Returns
+/// `Complete` with whether that solution is unique, or `Invalid` if there are no solutions.
+///
+/// Only the `Grid`'s current `CellValue`s are considered; a digit that's no longer one of an
+/// `Unknown` cell's possibilities won't be tried there.
+pub fn solve_grid(grid: &mut Grid) -> SolveStatus {
+    let (count, first_solution) = search(grid, 2);
+
+    match first_solution {
+        Some(solution) => {
+            for candidate in solution {
+                let cell = grid.get(candidate.x, candidate.y).unwrap();
+                if let CellValue::Unknown(_) = cell.get_value_copy() {
+                    cell.set(candidate.digit);
+                }
+            }
+
+            let uniqueness = if count == 1 {
+                Uniqueness::Unique
+            } else {
+                Uniqueness::NotUnique
+            };
+            SolveStatus::Complete(Some(uniqueness))
+        }
+        None => SolveStatus::Invalid,
+    }
+}
+
+/// Counts how many solutions `grid` has, stopping once `limit` have been found. Use a `limit` of
+/// `usize::MAX` for an exact count, but be aware that puzzles with few hints can have an
+/// enormous number of solutions.
+pub fn count_solutions(grid: &Grid, limit: usize) -> usize {
+    search(grid, limit).0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::{CellValue, Grid};
+    use crate::solver::dlx::*;
+    use crate::solver::evaluate_grid_with_solve_controller;
+    use crate::test_utils::random_puzzle;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_solve_grid() {
+        let mut grid = Grid::new();
+        let status = solve_grid(&mut grid);
+        assert_eq!(status, SolveStatus::Complete(Some(Uniqueness::NotUnique)));
+
+        assert!(grid.is_solved());
+
+        // Two 1s in the same row
+        let mut grid = Grid::new();
+        grid.get(0, 0).unwrap().set_value_exact(CellValue::Fixed(1));
+        grid.get(0, 5).unwrap().set_value_exact(CellValue::Fixed(1));
+        assert_eq!(solve_grid(&mut grid), SolveStatus::Invalid);
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let mut rng = SmallRng::seed_from_u64(366);
+        let (puzzle, _uniqueness) = random_puzzle(&mut rng, 81);
+        assert_eq!(count_solutions(&puzzle, usize::MAX), 1);
+
+        let puzzle = Grid::new();
+        assert_eq!(count_solutions(&puzzle, 10), 10);
+        assert_eq!(count_solutions(&puzzle, 0), 0);
+    }
+
+    #[test]
+    fn test_agrees_with_solver() {
+        let solve_controller = crate::test_utils::full_solve_controller();
+
+        let mut rng = SmallRng::seed_from_u64(12345);
+        for num_hints in (20..40).step_by(4) {
+            let (puzzle, uniqueness) = random_puzzle(&mut rng, num_hints);
+
+            let expected_count = match uniqueness {
+                Uniqueness::Unique => 1,
+                Uniqueness::NotUnique => 2,
+            };
+            assert_eq!(count_solutions(&puzzle, 2), expected_count);
+
+            let (solver_status, _statistics) =
+                evaluate_grid_with_solve_controller(&puzzle, &solve_controller);
+            let mut dlx_grid = puzzle.clone();
+            assert_eq!(solve_grid(&mut dlx_grid), solver_status);
+
+            if uniqueness == Uniqueness::Unique {
+                let mut solver_grid = puzzle.clone();
+                crate::solver::solve_grid(&mut solver_grid);
+                assert_eq!(dlx_grid.snapshot(), solver_grid.snapshot());
+            }
+        }
+    }
+}
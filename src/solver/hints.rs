@@ -0,0 +1,374 @@
+use crate::grid::{CellValue, Grid, SectionType};
+use crate::solver::{subsets, SolveAction, SolveController, Technique};
+use crate::topology;
+
+/// One place on the board where a `Technique` can be applied, along with what applying it would
+/// do. A `Hint` is only reported if applying it would change the `Grid`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Hint {
+    pub technique: Technique,
+
+    /// The row, column, or square that the pattern was found in, or None for patterns that only
+    /// involve one cell.
+    pub section: Option<(SectionType, usize)>,
+
+    /// The cells (as `(x, y)`) and digits that make up the pattern.
+    pub cells: Vec<(usize, usize)>,
+    pub digits: Vec<u8>,
+
+    /// Digits that would be set, as `(x, y, digit)`.
+    pub placements: Vec<(usize, usize, u8)>,
+
+    /// Possibilities that would be removed, as `(x, y, digit)`.
+    pub eliminations: Vec<(usize, usize, u8)>,
+}
+
+// Possibilities of every cell, or None for cells that are Fixed
+type Candidates = [[Option<Vec<u8>>; 9]; 9];
+
+fn read_candidates(grid: &Grid) -> Candidates {
+    let mut candidates: Candidates = Default::default();
+    for (x, row) in candidates.iter_mut().enumerate() {
+        for (y, cell_candidates) in row.iter_mut().enumerate() {
+            if let CellValue::Unknown(possibilities) = grid.get(x, y).unwrap().get_value_copy() {
+                *cell_candidates = Some(possibilities);
+            }
+        }
+    }
+    candidates
+}
+
+// The (x, y) coordinates of the cells in a Section, in the same order as the Grid keeps them
+fn section_cells(section_type: SectionType, index: usize) -> Vec<(usize, usize)> {
+    (0..9)
+        .map(|i| topology::house_cell(section_type, index, i))
+        .collect()
+}
+
+fn all_sections() -> Vec<(SectionType, usize)> {
+    let mut sections = Vec::with_capacity(27);
+    for &section_type in [SectionType::Row, SectionType::Column, SectionType::Square].iter() {
+        for index in 0..9 {
+            sections.push((section_type, index));
+        }
+    }
+    sections
+}
+
+fn has_candidate(candidates: &Candidates, (x, y): (usize, usize), digit: u8) -> bool {
+    candidates[x][y]
+        .as_ref()
+        .is_some_and(|possibilities| possibilities.contains(&digit))
+}
+
+/// List every place where `technique` could currently be applied to `grid`, without applying any
+/// of them. Patterns that wouldn't change anything are left out. `PossibilityGroup`,
+/// `UsefulConstraints` and `Guess` aren't looked for, so they never have any hints; use the
+/// naked and hidden subsets and `PointingPair` for their simplest cases.
+pub fn find_all(technique: Technique, grid: &Grid) -> Vec<Hint> {
+    find_in(technique, &read_candidates(grid))
+}
+
+/// The hint a person would most likely spot next: one for the easiest technique that
+/// `solve_controller` enables and that can currently be applied, going by
+/// `Technique::EASIEST_FIRST`. Returns None if none of them can be.
+pub fn next_hint(grid: &Grid, solve_controller: &SolveController) -> Option<Hint> {
+    let candidates = read_candidates(grid);
+    Technique::EASIEST_FIRST
+        .iter()
+        .filter(|&&technique| solve_controller.enables(technique))
+        .find_map(|&technique| find_in(technique, &candidates).into_iter().next())
+}
+
+fn find_in(technique: Technique, candidates: &Candidates) -> Vec<Hint> {
+    match technique {
+        Technique::Single => find_singles(candidates),
+        Technique::HiddenSingle => find_hidden_singles(candidates),
+        Technique::NakedPair
+        | Technique::NakedTriple
+        | Technique::NakedQuad
+        | Technique::HiddenPair
+        | Technique::HiddenTriple
+        | Technique::HiddenQuad => {
+            find_subsets(candidates, technique, technique.subset_size().unwrap())
+        }
+        Technique::PointingPair => find_pointing_pairs(candidates),
+        Technique::PossibilityGroup | Technique::UsefulConstraints | Technique::Guess => Vec::new(),
+    }
+}
+
+fn find_singles(candidates: &Candidates) -> Vec<Hint> {
+    let mut hints = Vec::new();
+    for (x, row) in candidates.iter().enumerate() {
+        for (y, cell_candidates) in row.iter().enumerate() {
+            if let Some(possibilities) = cell_candidates {
+                if possibilities.len() == 1 {
+                    let digit = possibilities[0];
+                    hints.push(Hint {
+                        technique: Technique::Single,
+                        section: None,
+                        cells: vec![(x, y)],
+                        digits: vec![digit],
+                        placements: vec![(x, y, digit)],
+                        eliminations: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+    hints
+}
+
+fn find_hidden_singles(candidates: &Candidates) -> Vec<Hint> {
+    let mut hints = Vec::new();
+    for (section_type, index) in all_sections() {
+        let cells = section_cells(section_type, index);
+        for digit in 1..=9 {
+            let mut possible_cells = cells
+                .iter()
+                .filter(|&&cell| has_candidate(candidates, cell, digit));
+
+            if let (Some(&(x, y)), None) = (possible_cells.next(), possible_cells.next()) {
+                hints.push(Hint {
+                    technique: Technique::HiddenSingle,
+                    section: Some((section_type, index)),
+                    cells: vec![(x, y)],
+                    digits: vec![digit],
+                    placements: vec![(x, y, digit)],
+                    eliminations: Vec::new(),
+                });
+            }
+        }
+    }
+    hints
+}
+
+// Naked or hidden subsets of `size` cells in every section, as hints for `technique`
+fn find_subsets(candidates: &Candidates, technique: Technique, size: usize) -> Vec<Hint> {
+    let naked = SolveAction::NakedSubset.techniques().contains(&technique);
+
+    let mut hints = Vec::new();
+    for (section_type, index) in all_sections() {
+        let cells = section_cells(section_type, index);
+        let mut masks = [0u16; 9];
+        for (mask, &(x, y)) in masks.iter_mut().zip(cells.iter()) {
+            if let Some(possibilities) = &candidates[x][y] {
+                *mask = subsets::to_mask(possibilities);
+            }
+        }
+
+        let found = match naked {
+            true => subsets::naked_subsets(&masks, size),
+            false => subsets::hidden_subsets(&masks, size),
+        };
+
+        for subset in found {
+            let mut eliminations = Vec::new();
+            for (i, &(x, y)) in cells.iter().enumerate() {
+                let to_remove = match (naked, subset.cells & 1 << i != 0) {
+                    (true, false) => subset.digits,
+                    (false, true) => !subset.digits,
+                    _ => continue,
+                };
+                for digit in subsets::from_mask(masks[i] & to_remove) {
+                    eliminations.push((x, y, digit));
+                }
+            }
+
+            hints.push(Hint {
+                technique,
+                section: Some((section_type, index)),
+                cells: (0..9)
+                    .filter(|&i| subset.cells & 1 << i != 0)
+                    .map(|i| cells[i])
+                    .collect(),
+                digits: subsets::from_mask(subset.digits),
+                placements: Vec::new(),
+                eliminations,
+            });
+        }
+    }
+    hints
+}
+
+fn find_pointing_pairs(candidates: &Candidates) -> Vec<Hint> {
+    let mut hints = Vec::new();
+    for square in 0..9 {
+        let cells = section_cells(SectionType::Square, square);
+        for digit in 1..=9 {
+            let possible_cells: Vec<(usize, usize)> = cells
+                .iter()
+                .cloned()
+                .filter(|&cell| has_candidate(candidates, cell, digit))
+                .collect();
+            if possible_cells.len() < 2 {
+                continue; // Nothing to point with; that's a hidden single
+            }
+
+            let (first_x, first_y) = possible_cells[0];
+            let line = if possible_cells.iter().all(|&(x, _y)| x == first_x) {
+                (SectionType::Row, first_x)
+            } else if possible_cells.iter().all(|&(_x, y)| y == first_y) {
+                (SectionType::Column, first_y)
+            } else {
+                continue;
+            };
+
+            let eliminations: Vec<(usize, usize, u8)> = section_cells(line.0, line.1)
+                .into_iter()
+                .filter(|cell| !cells.contains(cell) && has_candidate(candidates, *cell, digit))
+                .map(|(x, y)| (x, y, digit))
+                .collect();
+
+            if !eliminations.is_empty() {
+                hints.push(Hint {
+                    technique: Technique::PointingPair,
+                    section: Some((SectionType::Square, square)),
+                    cells: possible_cells,
+                    digits: vec![digit],
+                    placements: Vec::new(),
+                    eliminations,
+                });
+            }
+        }
+    }
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::*;
+    use crate::solver::hints::*;
+
+    #[test]
+    fn test_find_all_hidden_singles() {
+        let grid = Grid::new();
+        for y in 0..8 {
+            grid.get(0, y).unwrap().set(y as u8 + 1);
+        }
+
+        // The last cell of the first row must be a 9; that's both a single and a hidden single
+        let singles = find_all(Technique::Single, &grid);
+        assert_eq!(singles.len(), 1);
+        assert_eq!(singles[0].placements, vec![(0, 8, 9)]);
+
+        let hidden_singles = find_all(Technique::HiddenSingle, &grid);
+        assert!(hidden_singles
+            .iter()
+            .any(|hint| hint.section == Some((SectionType::Row, 0))
+                && hint.placements == vec![(0, 8, 9)]));
+
+        // Nothing was applied
+        assert_eq!(
+            grid.get(0, 8).unwrap().get_value_copy(),
+            CellValue::Unknown(vec![9])
+        );
+    }
+
+    #[test]
+    fn test_find_all_naked_pairs() {
+        let grid = Grid::new();
+        grid.get(0, 0)
+            .unwrap()
+            .set_value_exact(CellValue::Unknown(vec![1, 2]));
+        grid.get(0, 4)
+            .unwrap()
+            .set_value_exact(CellValue::Unknown(vec![1, 2]));
+
+        let hints = find_all(Technique::NakedPair, &grid);
+        assert_eq!(hints.len(), 1);
+        let hint = &hints[0];
+        assert_eq!(hint.section, Some((SectionType::Row, 0)));
+        assert_eq!(hint.cells, vec![(0, 0), (0, 4)]);
+        assert_eq!(hint.digits, vec![1, 2]);
+        assert_eq!(hint.eliminations.len(), 2 * 7);
+    }
+
+    #[test]
+    fn test_find_all_hidden_subsets() {
+        let grid = Grid::new();
+        // 8 and 9 only fit in the first two cells of the first row
+        for y in 2..9 {
+            grid.get(0, y)
+                .unwrap()
+                .set_value_exact(CellValue::Unknown(vec![1, 2, 3, 4, 5, 6, 7]));
+        }
+
+        let hints = find_all(Technique::HiddenPair, &grid);
+        assert_eq!(hints.len(), 1);
+        let hint = &hints[0];
+        assert_eq!(hint.section, Some((SectionType::Row, 0)));
+        assert_eq!(hint.cells, vec![(0, 0), (0, 1)]);
+        assert_eq!(hint.digits, vec![8, 9]);
+        assert_eq!(hint.eliminations.len(), 2 * 7);
+
+        // Seen from the other side, the other seven cells are a naked subset too large to report
+        assert!(find_all(Technique::NakedQuad, &grid).is_empty());
+    }
+
+    #[test]
+    fn test_find_all_pointing_pairs() {
+        let grid = Grid::new();
+        // 1 can only go in the top row of the first square
+        for x in 1..3 {
+            for y in 0..3 {
+                grid.get(x, y)
+                    .unwrap()
+                    .set_value_exact(CellValue::Unknown(vec![2, 3, 4, 5, 6, 7, 8, 9]));
+            }
+        }
+
+        let hints = find_all(Technique::PointingPair, &grid);
+        assert_eq!(hints.len(), 1);
+        let hint = &hints[0];
+        assert_eq!(hint.section, Some((SectionType::Square, 0)));
+        assert_eq!(hint.cells, vec![(0, 0), (0, 1), (0, 2)]);
+        assert_eq!(
+            hint.eliminations,
+            (3..9).map(|y| (0, y, 1)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_next_hint() {
+        let grid = Grid::new();
+        for y in 0..8 {
+            grid.get(0, y).unwrap().set(y as u8 + 1);
+        }
+
+        // The 9 is both kinds of single, and hidden singles are the easier to spot
+        let everything = SolveController::with_techniques(&Technique::ALL);
+        let hint = next_hint(&grid, &everything).unwrap();
+        assert_eq!(hint.technique, Technique::HiddenSingle);
+        assert_eq!(hint.placements, vec![(0, 8, 9)]);
+
+        let singles = SolveController::with_techniques(&[Technique::Single]);
+        assert_eq!(
+            next_hint(&grid, &singles).unwrap().technique,
+            Technique::Single
+        );
+        assert_eq!(
+            next_hint(&grid, &SolveController::with_techniques(&[])),
+            None
+        );
+
+        // Only a subset is left to find, and only if the controller allows subsets that large
+        let grid = Grid::new();
+        for &y in [0, 4].iter() {
+            grid.get(0, y)
+                .unwrap()
+                .set_value_exact(CellValue::Unknown(vec![1, 2]));
+        }
+        let hint = next_hint(&grid, &everything).unwrap();
+        assert_eq!(hint.technique, Technique::NakedPair);
+        assert!(everything.enables(Technique::NakedQuad));
+        let no_pairs = SolveController {
+            search_naked_subsets: false,
+            ..everything
+        };
+        assert_ne!(
+            next_hint(&grid, &no_pairs).map(|hint| hint.technique),
+            Some(Technique::NakedPair)
+        );
+    }
+}
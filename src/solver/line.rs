@@ -0,0 +1,119 @@
+use crate::grid::{CellDiff, Grid, SectionType};
+use crate::solver::{
+    find_all, process_possibility_groups, search_hidden_single, search_single_possibility,
+    search_useful_constraint, Technique,
+};
+
+/// Apply `technique` to just one row, column, or square of `grid`, the way the solver would, and
+/// return the cells it changed, including those that lost a possibility to a digit it placed.
+/// Nothing else in the grid is looked at, so this shows what a single technique does to one
+/// section right now; pass a clone of `grid` to only preview it.
+///
+/// `UsefulConstraints` uses a square to remove possibilities from rows and columns, or a row or
+/// column to remove them from squares, depending on `section_type`. The naked and hidden subsets
+/// and `PointingPair` apply the hints `find_all` reports for the section. `Guess` doesn't work on
+/// a single section, so it never changes anything.
+///
+/// # Panics
+///
+/// Panics if `index` is 9 or more.
+pub fn apply_technique(
+    grid: &Grid,
+    technique: Technique,
+    section_type: SectionType,
+    index: usize,
+) -> Vec<CellDiff> {
+    let before = grid.clone();
+    let line = grid
+        .get_section(section_type, index)
+        .expect("Section index should be less than 9");
+    let line = &*line.borrow();
+
+    match technique {
+        Technique::Single => {
+            search_single_possibility(line);
+        }
+        Technique::HiddenSingle => {
+            search_hidden_single(line);
+        }
+        Technique::PossibilityGroup => {
+            process_possibility_groups::identify_and_process_possibility_groups(line, None);
+        }
+        Technique::UsefulConstraints => {
+            search_useful_constraint::search_useful_constraint(grid, line);
+        }
+        Technique::NakedPair
+        | Technique::NakedTriple
+        | Technique::NakedQuad
+        | Technique::HiddenPair
+        | Technique::HiddenTriple
+        | Technique::HiddenQuad
+        | Technique::PointingPair => {
+            for hint in find_all(technique, grid) {
+                if hint.section != Some((section_type, index)) {
+                    continue;
+                }
+                for (x, y, digit) in hint.placements {
+                    grid.get(x, y).unwrap().set(digit);
+                }
+                for (x, y, digit) in hint.eliminations {
+                    // A hint never removes a cell's last possibility from a consistent grid
+                    let _ = grid.get(x, y).unwrap().eliminate(digit);
+                }
+            }
+        }
+        Technique::Guess => {}
+    }
+
+    before.diff(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::{CellValue, Grid, SectionType};
+    use crate::solver::line::*;
+
+    #[test]
+    fn test_apply_technique() {
+        let grid = Grid::new();
+        for y in 0..8 {
+            grid.get(0, y).unwrap().set(y as u8 + 1);
+        }
+
+        // Only the first row knows where its 9 goes
+        assert!(apply_technique(&grid, Technique::HiddenSingle, SectionType::Row, 1).is_empty());
+        assert!(apply_technique(&grid, Technique::Guess, SectionType::Row, 0).is_empty());
+
+        // Placing it also removes 9 from the rest of its column and square
+        let changes = apply_technique(&grid, Technique::HiddenSingle, SectionType::Row, 0);
+        assert_eq!(changes.len(), 13);
+        assert_eq!((changes[0].x, changes[0].y), (0, 8));
+        assert_eq!(changes[0].after, CellValue::Fixed(9));
+        assert_eq!(grid.value_at(0, 8), Some(CellValue::Fixed(9)));
+        assert_eq!(grid.candidates_at(4, 8), Some(vec![1, 2, 3, 4, 5, 6, 7, 8]));
+
+        // The 1, 8 and 9 of the square below the top left one can only go in its first column,
+        // which points them out of the rest of that column
+        let grid = Grid::new();
+        let clues = [
+            (3, 1, 2),
+            (3, 2, 3),
+            (4, 1, 4),
+            (4, 2, 5),
+            (5, 1, 6),
+            (5, 2, 7),
+        ];
+        for &(x, y, digit) in clues.iter() {
+            grid.get(x, y).unwrap().set(digit);
+        }
+        let changes = apply_technique(&grid, Technique::PointingPair, SectionType::Square, 3);
+        assert_eq!(changes.len(), 6);
+        for change in changes {
+            assert_eq!(change.y, 0);
+            assert_eq!(
+                grid.candidates_at(change.x, change.y),
+                Some(vec![2, 3, 4, 5, 6, 7])
+            );
+        }
+    }
+}
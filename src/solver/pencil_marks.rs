@@ -0,0 +1,161 @@
+use crate::grid::{CellValue, Coord, Grid, GridSnapshot, SudokuError};
+use crate::solver::{dlx, SolveStatus, Uniqueness};
+use crate::topology;
+
+/// A way in which a player's pencil marks disagree with the puzzle; see `check_pencil_marks`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PencilMarkError {
+    /// The digit was crossed out of the cell, but it's the digit the cell has in the solution.
+    RemovedSolution { coord: Coord, digit: u8 },
+
+    /// The digit is still marked even though it's already `Fixed` in the same row, column, or
+    /// square.
+    ConflictsWithFixed { coord: Coord, digit: u8 },
+}
+
+/// Narrow down the possibilities of `grid`'s `Unknown` cells to the pencil marks in `marks`, so
+/// that solving `grid` afterwards treats the marks as given. Digits that `grid` has already ruled
+/// out stay ruled out even if they're marked, and cells that are `Fixed` in either are left alone.
+///
+/// If the marks would leave a cell with no possibilities an error is returned and `grid` is left
+/// unchanged.
+pub fn apply_pencil_marks(grid: &mut Grid, marks: &GridSnapshot) -> Result<(), SudokuError> {
+    let narrowed = grid.clone();
+
+    for coord in Coord::all() {
+        let marked = match marks.get(coord.row(), coord.column()) {
+            Some(CellValue::Unknown(marked)) => marked,
+            _ => continue,
+        };
+
+        let cell = narrowed.cell(coord);
+        for digit in cell.get_value_possibilities().unwrap_or_default() {
+            if !marked.contains(&digit) {
+                cell.eliminate(digit)?;
+            }
+        }
+    }
+
+    grid.clone_from(&narrowed);
+    Ok(())
+}
+
+/// Compare a player's pencil marks against `puzzle`, reporting marks that include a digit already
+/// placed in the same row, column, or square, and, if `puzzle` has a unique solution, cells whose
+/// solution digit was crossed out. Only cells that are `Unknown` in both are checked. Errors are
+/// listed row by row.
+pub fn check_pencil_marks(puzzle: &Grid, marks: &GridSnapshot) -> Vec<PencilMarkError> {
+    let mut solution = puzzle.snapshot().to_grid();
+    let solution = match dlx::solve_grid(&mut solution) {
+        SolveStatus::Complete(Some(Uniqueness::Unique)) => Some(solution),
+        _ => None,
+    };
+
+    let mut errors = Vec::new();
+    for coord in Coord::all() {
+        let marked = match marks.get(coord.row(), coord.column()) {
+            Some(CellValue::Unknown(marked)) => marked,
+            _ => continue,
+        };
+        if let Some(CellValue::Fixed(_)) = puzzle.value_at(coord.row(), coord.column()) {
+            continue;
+        }
+
+        if let Some(solution) = &solution {
+            if let Some(CellValue::Fixed(digit)) = solution.value_at(coord.row(), coord.column()) {
+                if !marked.contains(&digit) {
+                    errors.push(PencilMarkError::RemovedSolution { coord, digit });
+                }
+            }
+        }
+
+        for &digit in marked.iter() {
+            if fixed_in_peers(puzzle, coord, digit) {
+                errors.push(PencilMarkError::ConflictsWithFixed { coord, digit });
+            }
+        }
+    }
+
+    errors
+}
+
+// Whether digit is Fixed in a cell sharing a row, column, or square with coord
+fn fixed_in_peers(grid: &Grid, coord: Coord, digit: u8) -> bool {
+    topology::peers(coord)
+        .any(|peer| grid.value_at(peer.row(), peer.column()) == Some(CellValue::Fixed(digit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::{CellValue, Coord, Grid, SudokuError};
+    use crate::solver::pencil_marks::*;
+    use crate::solver::{solve_grid, SolveStatus, Uniqueness};
+
+    fn puzzle() -> Grid {
+        Grid::from_digits([
+            [5, 3, 0, 0, 7, 0, 0, 0, 0],
+            [6, 0, 0, 1, 9, 5, 0, 0, 0],
+            [0, 9, 8, 0, 0, 0, 0, 6, 0],
+            [8, 0, 0, 0, 6, 0, 0, 0, 3],
+            [4, 0, 0, 8, 0, 3, 0, 0, 1],
+            [7, 0, 0, 0, 2, 0, 0, 0, 6],
+            [0, 6, 0, 0, 0, 0, 2, 8, 0],
+            [0, 0, 0, 4, 1, 9, 0, 0, 5],
+            [0, 0, 0, 0, 8, 0, 0, 7, 9],
+        ])
+    }
+
+    #[test]
+    fn test_apply_pencil_marks() {
+        // (0, 2) is a 4 in the solution; (0, 3) is a 6
+        let mut marks = puzzle().snapshot();
+        marks.set(0, 2, CellValue::Unknown(vec![1, 4]));
+
+        let mut grid = puzzle();
+        apply_pencil_marks(&mut grid, &marks).unwrap();
+        assert_eq!(grid.candidates_at(0, 2), Some(vec![1, 4]));
+        let (status, _statistics) = solve_grid(&mut grid);
+        assert_eq!(status, SolveStatus::Complete(Some(Uniqueness::Unique)));
+
+        // Trusting marks that cross out the solution leaves a puzzle with none
+        marks.set(0, 3, CellValue::Unknown(vec![2]));
+        let mut grid = puzzle();
+        apply_pencil_marks(&mut grid, &marks).unwrap();
+        let (status, _statistics) = solve_grid(&mut grid);
+        assert_eq!(status, SolveStatus::Invalid);
+
+        // 5 is already in the first row, so isn't a possibility of (0, 3)
+        marks.set(0, 3, CellValue::Unknown(vec![5]));
+        let mut grid = puzzle();
+        assert_eq!(
+            apply_pencil_marks(&mut grid, &marks),
+            Err(SudokuError::LastCandidate {
+                digit: 6,
+                cell: Coord::new(0, 3).unwrap()
+            })
+        );
+        assert!(grid == puzzle());
+    }
+
+    #[test]
+    fn test_check_pencil_marks() {
+        let mut marks = puzzle().snapshot();
+        assert!(check_pencil_marks(&puzzle(), &marks).is_empty());
+
+        marks.set(0, 2, CellValue::Unknown(vec![1, 2]));
+        marks.set(0, 3, CellValue::Unknown(vec![3, 6]));
+        assert_eq!(
+            check_pencil_marks(&puzzle(), &marks),
+            vec![
+                PencilMarkError::RemovedSolution {
+                    coord: Coord::new(0, 2).unwrap(),
+                    digit: 4
+                },
+                PencilMarkError::ConflictsWithFixed {
+                    coord: Coord::new(0, 3).unwrap(),
+                    digit: 3
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::grading::{Grade, Grader};
+use crate::grid::Grid;
+use crate::solver::{
+    evaluate_grid_with_solve_controller, SolveController, SolveStatistics, SolveStatus,
+};
+
+/// How far along a puzzle in progress is; see `progress`.
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    /// How many of the 81 cells are filled in.
+    pub filled_cells: usize,
+
+    /// `filled_cells` as a percentage, from 0 to 100.
+    pub percent_filled: f64,
+
+    /// How solving the rest of the grid went. `SolveStatus::Invalid` means a digit already placed
+    /// is wrong, even if nothing conflicts with it yet.
+    pub status: SolveStatus,
+
+    /// The strategies that finishing the grid relied on.
+    pub remaining: SolveStatistics,
+
+    /// How hard the rest of the grid is by the default `Grader`, which may be much easier than the
+    /// puzzle was at the start. None if the grid can't be finished.
+    pub remaining_grade: Option<Grade>,
+}
+
+/// Report how much of `grid` is filled in, and estimate how hard the rest of it is by solving a
+/// copy of it. Unlike counting filled cells, the estimate drops sharply once the hard part of a
+/// puzzle is done, even with many cells left. `grid` itself isn't changed.
+pub fn progress(grid: &Grid) -> Progress {
+    let filled_cells = 81 - grid.empty_count();
+    let (status, remaining) =
+        evaluate_grid_with_solve_controller(grid, &SolveController::first_solution());
+
+    let remaining_grade = match status {
+        SolveStatus::Complete(_) => Some(Grader::default().grade(&remaining)),
+        SolveStatus::Unfinished | SolveStatus::Invalid | SolveStatus::Aborted(_) => None,
+    };
+
+    Progress {
+        filled_cells,
+        percent_filled: filled_cells as f64 * 100.0 / 81.0,
+        status,
+        remaining,
+        remaining_grade,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::{CellValue, Grid};
+    use crate::solver::progress::*;
+    use crate::solver::solve_grid;
+    use crate::test_utils::random_unique_puzzle;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_progress() {
+        let empty = progress(&Grid::new());
+        assert_eq!(empty.filled_cells, 0);
+        assert_eq!(empty.percent_filled, 0.0);
+        assert!(empty.remaining.solved_with_guesses);
+
+        let mut solved_grid = Grid::new();
+        solve_grid(&mut solved_grid);
+        let solved = progress(&solved_grid);
+        assert_eq!(solved.filled_cells, 81);
+        assert_eq!(solved.percent_filled, 100.0);
+        assert_eq!(solved.remaining_grade.unwrap().score, 0.0);
+
+        // Leaving out one digit only needs a single to finish
+        let nearly_solved = solved_grid.clone();
+        nearly_solved
+            .get(4, 4)
+            .unwrap()
+            .set_value(CellValue::Unknown((1..10).collect()));
+        let nearly = progress(&nearly_solved);
+        assert_eq!(nearly.filled_cells, 80);
+        assert!(nearly.remaining_grade.unwrap().score < empty.remaining_grade.unwrap().score);
+        assert_eq!(nearly_solved.empty_count(), 1);
+
+        // A possible but wrong digit makes the rest impossible
+        let mut rng = SmallRng::seed_from_u64(430);
+        let puzzle = random_unique_puzzle(&mut rng);
+        let mut solution = puzzle.clone();
+        solve_grid(&mut solution);
+        let (x, y, wrong_digit) = (0..81)
+            .filter_map(|index| {
+                let (x, y) = (index / 9, index % 9);
+                let right_digit = solution.to_digits()[x][y];
+                puzzle
+                    .candidates_at(x, y)?
+                    .into_iter()
+                    .find(|&digit| digit != right_digit)
+                    .map(|digit| (x, y, digit))
+            })
+            .next()
+            .unwrap();
+        puzzle.get(x, y).unwrap().set(wrong_digit);
+        let wrong = progress(&puzzle);
+        assert_eq!(wrong.status, SolveStatus::Invalid);
+        assert!(wrong.remaining_grade.is_none());
+    }
+}
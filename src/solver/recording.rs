@@ -0,0 +1,703 @@
+use crate::grid::{CellValue, Grid, GridSnapshot, SectionType};
+use crate::solver::{SolveAction, SolveStatistics, SolveStatus, Technique};
+
+/// What a solving strategy was looking at when it removed a possibility.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EliminationSource {
+    /// The row, column, or square the strategy was run on.
+    Section(SectionType, usize),
+
+    /// The cell, as `(x, y)`, that a guess was made in.
+    Cell(usize, usize),
+}
+
+/// A single possibility being removed from a cell.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Elimination {
+    /// Counts up across the whole solve, so eliminations from different cells can be put back in
+    /// order. Eliminations made by the same strategy run share a step.
+    pub step: usize,
+    pub digit: u8,
+    pub action: SolveAction,
+
+    /// Which of `action`'s techniques made the step.
+    pub technique: Technique,
+    pub source: EliminationSource,
+}
+
+/// Every possibility removed from each cell during a solve, in the order they were removed. When
+/// guesses were needed, only the eliminations that led to the returned solution are kept.
+#[derive(Clone, Debug)]
+pub struct EliminationHistory {
+    cells: Vec<Vec<Elimination>>,
+    num_steps: usize,
+}
+
+impl EliminationHistory {
+    fn new() -> EliminationHistory {
+        EliminationHistory {
+            cells: vec![Vec::new(); 81],
+            num_steps: 0,
+        }
+    }
+
+    /// Returns the eliminations made in the cell at the specified coordinates, or None if they
+    /// are out of bounds.
+    pub fn get(&self, r: usize, c: usize) -> Option<&[Elimination]> {
+        if r >= 9 || c >= 9 {
+            return None;
+        }
+        self.cells
+            .get(r * 9 + c)
+            .map(|eliminations| eliminations.as_slice())
+    }
+
+    /// Returns the number of strategy runs or guesses that removed at least one possibility.
+    pub fn num_steps(&self) -> usize {
+        self.num_steps
+    }
+
+    /// Returns every elimination along with the `(x, y)` of its cell, ordered by step.
+    pub fn timeline(&self) -> Vec<(usize, usize, Elimination)> {
+        let mut timeline: Vec<(usize, usize, Elimination)> = self
+            .cells
+            .iter()
+            .enumerate()
+            .flat_map(|(index, eliminations)| {
+                eliminations
+                    .iter()
+                    .map(move |&elimination| (index / 9, index % 9, elimination))
+            })
+            .collect();
+
+        // Stable, so eliminations within a step stay grouped by cell
+        timeline.sort_by_key(|&(_x, _y, elimination)| elimination.step);
+        timeline
+    }
+
+    fn record(
+        &mut self,
+        before: &GridSnapshot,
+        after: &Grid,
+        action: SolveAction,
+        technique: Technique,
+        source: EliminationSource,
+    ) {
+        let step = self.num_steps;
+        let mut removed_any = false;
+
+        for x in 0..9 {
+            for y in 0..9 {
+                let before_digits = match before.get(x, y).unwrap() {
+                    CellValue::Unknown(possibilities) => possibilities,
+                    CellValue::Fixed(_) => continue,
+                };
+                let after_value = after.get(x, y).unwrap().value.borrow().clone();
+
+                for &digit in before_digits.iter() {
+                    let still_possible = match &after_value {
+                        CellValue::Unknown(possibilities) => possibilities.contains(&digit),
+                        CellValue::Fixed(fixed) => *fixed == digit,
+                    };
+                    if !still_possible {
+                        self.cells[x * 9 + y].push(Elimination {
+                            step,
+                            digit,
+                            action,
+                            technique,
+                            source,
+                        });
+                        removed_any = true;
+                    }
+                }
+            }
+        }
+
+        if removed_any {
+            self.num_steps += 1;
+        }
+    }
+}
+
+/// One strategy run or guess that changed the grid, and the new value of every cell it changed.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolveStep {
+    pub action: SolveAction,
+
+    /// Which of `action`'s techniques made the step, like `NakedTriple` for a `NakedSubset`.
+    pub technique: Technique,
+    pub source: EliminationSource,
+
+    /// The changed cells, as `(x, y, new value)`.
+    pub changes: Vec<(usize, usize, CellValue)>,
+}
+
+impl SolveStep {
+    /// A sentence explaining what the step did, such as "Hidden single in Row 3: r3c7 is 4".
+    /// `before` is the grid as it was just before the step, which is needed to tell which
+    /// possibilities were removed. Rows and columns are numbered from 1.
+    pub fn describe(&self, before: &GridSnapshot) -> String {
+        let action = self.technique;
+        let source = match self.source {
+            EliminationSource::Section(section_type, index) => {
+                format!("{:?} {}", section_type, index + 1)
+            }
+            EliminationSource::Cell(x, y) => format!("r{}c{}", x + 1, y + 1),
+        };
+
+        let changes: Vec<String> = self
+            .changes
+            .iter()
+            .map(|(x, y, value)| match value {
+                CellValue::Fixed(digit) => format!("r{}c{} is {}", x + 1, y + 1, digit),
+                CellValue::Unknown(possibilities) => {
+                    let removed: Vec<String> = match before.get(*x, *y) {
+                        Some(CellValue::Unknown(previous)) => previous
+                            .iter()
+                            .filter(|digit| !possibilities.contains(digit))
+                            .map(|digit| digit.to_string())
+                            .collect(),
+                        _ => Vec::new(),
+                    };
+                    format!("removes {} from r{}c{}", removed.join(", "), x + 1, y + 1)
+                }
+            })
+            .collect();
+
+        format!("{} in {}: {}", action, source, changes.join("; "))
+    }
+}
+
+/// The grid a solve started from and every step that changed it, in order. When guesses were
+/// needed, only the steps that led to the returned solution are kept.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolveReplay {
+    pub start: GridSnapshot,
+    pub steps: Vec<SolveStep>,
+}
+
+impl SolveReplay {
+    /// Returns the grid as it was after the first `num_steps` steps were applied. Asking for more
+    /// steps than there are gives the final grid.
+    pub fn snapshot_after(&self, num_steps: usize) -> GridSnapshot {
+        let mut snapshot = self.start.clone();
+        for step in self.steps.iter().take(num_steps) {
+            for (x, y, value) in step.changes.iter() {
+                snapshot.set(*x, *y, value.clone());
+            }
+        }
+        snapshot
+    }
+
+    /// Returns the starting grid followed by the grid after each step, one frame per step.
+    pub fn frames(&self) -> Vec<GridSnapshot> {
+        let mut frames = Vec::with_capacity(self.steps.len() + 1);
+        let mut snapshot = self.start.clone();
+        frames.push(snapshot.clone());
+        for step in self.steps.iter() {
+            for (x, y, value) in step.changes.iter() {
+                snapshot.set(*x, *y, value.clone());
+            }
+            frames.push(snapshot.clone());
+        }
+        frames
+    }
+}
+
+/// When, during a solve, a cell was narrowed down to a single digit.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Determination {
+    /// Index into `SolveReplay::steps` of the step that determined the cell.
+    pub step: usize,
+    pub digit: u8,
+    pub action: SolveAction,
+
+    /// Which of `action`'s techniques made the step.
+    pub technique: Technique,
+    pub source: EliminationSource,
+}
+
+/// The step at which each cell of a puzzle was first narrowed down to a single digit, and the
+/// strategy that did it. Cells that were given in the puzzle have no `Determination`.
+#[derive(Clone, Debug)]
+pub struct SolvabilityTimeline {
+    cells: Vec<Option<Determination>>,
+}
+
+impl SolvabilityTimeline {
+    /// Returns how the cell at the specified coordinates was determined, or None if it was given
+    /// or the coordinates are out of bounds.
+    pub fn get(&self, r: usize, c: usize) -> Option<&Determination> {
+        if r >= 9 || c >= 9 {
+            return None;
+        }
+        self.cells[r * 9 + c].as_ref()
+    }
+}
+
+impl SolveReplay {
+    /// Works out at which step each cell was first left with one possible digit.
+    pub fn solvability_timeline(&self) -> SolvabilityTimeline {
+        let mut cells: Vec<Option<Determination>> = vec![None; 81];
+        // Only clues count as known from the start; an empty cell that starts out with one
+        // candidate is credited to the step that fills it in
+        let mut determined: Vec<bool> = (0..81)
+            .map(
+                |index| match self.start.get(index / 9, index % 9).unwrap() {
+                    CellValue::Fixed(_) => true,
+                    CellValue::Unknown(_) => false,
+                },
+            )
+            .collect();
+
+        for (step_index, step) in self.steps.iter().enumerate() {
+            for (x, y, value) in step.changes.iter() {
+                let index = x * 9 + y;
+                if determined[index] {
+                    continue;
+                }
+                if let Some(digit) = single_digit(value) {
+                    determined[index] = true;
+                    cells[index] = Some(Determination {
+                        step: step_index,
+                        digit,
+                        action: step.action,
+                        technique: step.technique,
+                        source: step.source,
+                    });
+                }
+            }
+        }
+
+        SolvabilityTimeline { cells }
+    }
+}
+
+fn single_digit(value: &CellValue) -> Option<u8> {
+    match value {
+        CellValue::Fixed(digit) => Some(*digit),
+        CellValue::Unknown(possibilities) if possibilities.len() == 1 => Some(possibilities[0]),
+        CellValue::Unknown(_) => None,
+    }
+}
+
+fn changed_cells(before: &GridSnapshot, after: &Grid) -> Vec<(usize, usize, CellValue)> {
+    let mut changes = Vec::new();
+    for x in 0..9 {
+        for y in 0..9 {
+            let after_value = after.get(x, y).unwrap().value.borrow().clone();
+            if *before.get(x, y).unwrap() != after_value {
+                changes.push((x, y, after_value));
+            }
+        }
+    }
+    changes
+}
+
+/// A digit tried in a cell while guessing, and what came of it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GuessNode {
+    /// The cell guessed in, as `(x, y)`.
+    pub cell: (usize, usize),
+    pub digit: u8,
+
+    /// How solving went with the guess in place: `Invalid` for a dead end, otherwise `Complete`
+    /// with what was found about the solutions below it.
+    pub outcome: SolveStatus,
+
+    /// How many guesses were already in place when this one was made; guesses made straight from
+    /// the puzzle have a depth of 0.
+    pub depth: usize,
+
+    /// The guesses made after this one, all in the same cell, when strategies alone couldn't
+    /// finish the grid.
+    pub children: Vec<GuessNode>,
+}
+
+/// Every guess made during a solve, including the ones that led to dead ends. Each list of
+/// siblings is one branch point: the digits tried, in order, in the cell with the fewest
+/// possibilities.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GuessTree {
+    /// The guesses made in the puzzle before any other guess; empty if none were needed.
+    pub roots: Vec<GuessNode>,
+}
+
+impl GuessTree {
+    /// Returns every guess in the tree, parents before their children.
+    pub fn nodes(&self) -> Vec<&GuessNode> {
+        let mut nodes = Vec::new();
+        let mut to_visit: Vec<&GuessNode> = self.roots.iter().rev().collect();
+        while let Some(node) = to_visit.pop() {
+            nodes.push(node);
+            to_visit.extend(node.children.iter().rev());
+        }
+        nodes
+    }
+
+    /// Returns the total number of digits tried across all branch points.
+    pub fn num_guesses(&self) -> usize {
+        self.nodes().len()
+    }
+
+    /// Returns how many digits were tried at each branch point, parents before their children.
+    pub fn branching_factors(&self) -> Vec<usize> {
+        let mut factors = Vec::new();
+        if !self.roots.is_empty() {
+            factors.push(self.roots.len());
+        }
+        for node in self.nodes() {
+            if !node.children.is_empty() {
+                factors.push(node.children.len());
+            }
+        }
+        factors
+    }
+
+    /// Returns the number of guesses made at each depth, starting from 0.
+    pub fn depth_counts(&self) -> Vec<usize> {
+        let mut counts = Vec::new();
+        for node in self.nodes() {
+            if counts.len() <= node.depth {
+                counts.resize(node.depth + 1, 0);
+            }
+            counts[node.depth] += 1;
+        }
+        counts
+    }
+
+    /// Returns how many guesses deep the search went, or 0 if there were no guesses.
+    pub fn max_depth(&self) -> usize {
+        self.depth_counts().len()
+    }
+}
+
+/// How far a solve has got, as reported by `solve_grid_with_progress`. The counts are for the whole
+/// solve so far, including branches that turned out to be wrong.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SolveProgress {
+    /// How many times the strategies have been run on a row, column, or square.
+    pub houses_processed: u32,
+
+    /// How many times a cell has had to be guessed in.
+    pub guesses: u32,
+
+    /// How many guesses are in place right now.
+    pub guess_depth: u32,
+}
+
+// Keeps track of whatever the caller asked to have recorded while solving, of when the caller
+// wants the solve to give up, and of who to tell how it's going. Guesses clone it for each branch
+// and keep the copy belonging to the solution, except for the guess tree, which keeps every branch.
+#[derive(Clone)]
+pub(crate) struct Recorder<'a> {
+    pub history: Option<EliminationHistory>,
+    pub steps: Option<Vec<SolveStep>>,
+    pub guesses: Option<Vec<GuessNode>>,
+    pub guess_depth: usize,
+    pub stop_when: Option<&'a dyn Fn(&SolveStatistics) -> bool>,
+    pub stopped: bool,
+    pub on_progress: Option<&'a dyn Fn(&SolveProgress)>,
+}
+
+impl<'a> Recorder<'a> {
+    pub fn none() -> Recorder<'a> {
+        Recorder {
+            history: None,
+            steps: None,
+            guesses: None,
+            guess_depth: 0,
+            stop_when: None,
+            stopped: false,
+            on_progress: None,
+        }
+    }
+
+    pub fn with_stop_when(stop_when: &'a dyn Fn(&SolveStatistics) -> bool) -> Recorder<'a> {
+        Recorder {
+            stop_when: Some(stop_when),
+            ..Recorder::none()
+        }
+    }
+
+    // Whether the solve should give up now; once it has, it stays stopped
+    pub fn should_stop(&mut self, solve_statistics: &SolveStatistics) -> bool {
+        if let Some(stop_when) = self.stop_when {
+            self.stopped = self.stopped || stop_when(solve_statistics);
+        }
+        self.stopped
+    }
+
+    pub fn with_progress(on_progress: &'a dyn Fn(&SolveProgress)) -> Recorder<'a> {
+        Recorder {
+            on_progress: Some(on_progress),
+            ..Recorder::none()
+        }
+    }
+
+    pub fn report_progress(&self, solve_statistics: &SolveStatistics) {
+        if let Some(on_progress) = self.on_progress {
+            on_progress(&SolveProgress {
+                houses_processed: solve_statistics.iterations,
+                guesses: solve_statistics.guesses,
+                guess_depth: solve_statistics.guess_depth,
+            });
+        }
+    }
+
+    pub fn with_history() -> Recorder<'a> {
+        Recorder {
+            history: Some(EliminationHistory::new()),
+            ..Recorder::none()
+        }
+    }
+
+    pub fn with_steps() -> Recorder<'a> {
+        Recorder {
+            steps: Some(Vec::new()),
+            ..Recorder::none()
+        }
+    }
+
+    pub fn with_guesses() -> Recorder<'a> {
+        Recorder {
+            guesses: Some(Vec::new()),
+            ..Recorder::none()
+        }
+    }
+
+    // Record all that can be, to check that doing so doesn't change the solve
+    pub fn with_everything() -> Recorder<'a> {
+        Recorder {
+            history: Some(EliminationHistory::new()),
+            steps: Some(Vec::new()),
+            guesses: Some(Vec::new()),
+            ..Recorder::none()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.history.is_some() || self.steps.is_some()
+    }
+
+    // Take a copy of the grid before a step if we need to compare against it afterwards
+    pub fn before_step(&self, grid: &Grid) -> Option<GridSnapshot> {
+        if self.is_enabled() {
+            Some(grid.snapshot())
+        } else {
+            None
+        }
+    }
+
+    pub fn after_step(
+        &mut self,
+        before: Option<GridSnapshot>,
+        after: &Grid,
+        action: SolveAction,
+        technique: Technique,
+        source: EliminationSource,
+    ) {
+        let before = match before {
+            Some(before) => before,
+            None => return,
+        };
+
+        if let Some(history) = &mut self.history {
+            history.record(&before, after, action, technique, source);
+        }
+        if let Some(steps) = &mut self.steps {
+            let changes = changed_cells(&before, after);
+            if !changes.is_empty() {
+                steps.push(SolveStep {
+                    action,
+                    technique,
+                    source,
+                    changes,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::CellValue;
+    use crate::solver::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_solve_grid_with_history() {
+        let mut rng = SmallRng::seed_from_u64(372);
+        let puzzle = crate::test_utils::random_unique_puzzle(&mut rng);
+        let solve_controller = crate::test_utils::full_solve_controller();
+
+        let mut grid = puzzle.clone();
+        let (status, _statistics, history) = solve_grid_with_history(&mut grid, &solve_controller);
+        assert_eq!(status, SolveStatus::Complete(Some(Uniqueness::Unique)));
+        assert!(history.num_steps() > 0);
+
+        // Each cell's possibilities, less everything eliminated from it, should leave the solution
+        for x in 0..9 {
+            for y in 0..9 {
+                let mut remaining = match puzzle.get(x, y).unwrap().get_value_copy() {
+                    CellValue::Unknown(possibilities) => possibilities,
+                    CellValue::Fixed(_) => {
+                        assert!(history.get(x, y).unwrap().is_empty());
+                        continue;
+                    }
+                };
+                for elimination in history.get(x, y).unwrap() {
+                    remaining.retain(|&digit| digit != elimination.digit);
+                }
+
+                let solution = grid.get(x, y).unwrap().get_value_copy();
+                assert_eq!(CellValue::Fixed(remaining[0]), solution);
+                assert_eq!(remaining.len(), 1);
+            }
+        }
+
+        let timeline = history.timeline();
+        assert!(timeline
+            .windows(2)
+            .all(|pair| pair[0].2.step <= pair[1].2.step));
+        assert_eq!(timeline.last().unwrap().2.step, history.num_steps() - 1);
+    }
+
+    #[test]
+    fn test_solve_grid_with_replay() {
+        let mut rng = SmallRng::seed_from_u64(373);
+        let puzzle = crate::test_utils::random_unique_puzzle(&mut rng);
+        let solve_controller = crate::test_utils::full_solve_controller();
+
+        let mut grid = puzzle.clone();
+        let (status, _statistics, replay) = solve_grid_with_replay(&mut grid, &solve_controller);
+        assert_eq!(status, SolveStatus::Complete(Some(Uniqueness::Unique)));
+        assert_eq!(replay.start, puzzle.snapshot());
+        assert!(replay.steps.iter().all(|step| !step.changes.is_empty()));
+
+        // Playing every step back from the start ends on the solution
+        assert_eq!(replay.snapshot_after(0), replay.start);
+        assert_eq!(replay.snapshot_after(replay.steps.len()), grid.snapshot());
+
+        let frames = replay.frames();
+        assert_eq!(frames.len(), replay.steps.len() + 1);
+        assert_eq!(frames.last().unwrap(), &grid.snapshot());
+        assert_eq!(frames[1], replay.snapshot_after(1));
+    }
+
+    #[test]
+    fn test_describe_step() {
+        let before = crate::grid::Grid::new().snapshot();
+        let step = SolveStep {
+            action: SolveAction::HiddenSingle,
+            technique: Technique::HiddenSingle,
+            source: EliminationSource::Section(crate::grid::SectionType::Row, 0),
+            changes: vec![
+                (0, 0, CellValue::Fixed(4)),
+                (0, 1, CellValue::Unknown(vec![1, 2, 3, 5, 6, 8, 9])),
+            ],
+        };
+        assert_eq!(
+            step.describe(&before),
+            "Hidden single in Row 1: r1c1 is 4; removes 4, 7 from r1c2"
+        );
+    }
+
+    #[test]
+    fn test_solve_grid_with_guess_tree() {
+        let mut rng = SmallRng::seed_from_u64(403);
+        let puzzle = crate::test_utils::random_unique_puzzle(&mut rng);
+        let solve_controller = SolveController {
+            search_hidden_singles: false,
+            search_naked_subsets: false,
+            search_hidden_subsets: false,
+            find_possibility_groups: false,
+            search_useful_constraint: false,
+            search_pointing_constraints: false,
+            search_claiming_constraints: false,
+            ..crate::test_utils::full_solve_controller()
+        };
+
+        let mut grid = puzzle.clone();
+        let (status, statistics, tree) = solve_grid_with_guess_tree(&mut grid, &solve_controller);
+        assert_eq!(status, SolveStatus::Complete(Some(Uniqueness::Unique)));
+        assert!(!tree.roots.is_empty());
+
+        // One branch point per call to guess, and the depth matches the statistics
+        assert_eq!(tree.branching_factors().len(), statistics.guesses as usize);
+        assert_eq!(tree.max_depth(), statistics.max_guess_depth as usize);
+        assert_eq!(
+            tree.depth_counts().iter().sum::<usize>(),
+            tree.num_guesses()
+        );
+
+        // Exactly one digit of the first cell leads to the solution
+        let solved: Vec<&GuessNode> = tree
+            .roots
+            .iter()
+            .filter(|node| node.outcome == SolveStatus::Complete(Some(Uniqueness::Unique)))
+            .collect();
+        assert_eq!(solved.len(), 1);
+        let (x, y) = solved[0].cell;
+        assert_eq!(
+            grid.get(x, y).unwrap().get_value_copy(),
+            CellValue::Fixed(solved[0].digit)
+        );
+
+        for node in tree.nodes() {
+            assert!(node
+                .children
+                .iter()
+                .all(|child| child.depth == node.depth + 1));
+        }
+
+        // Without guesses there's nothing to record
+        let mut grid = puzzle.clone();
+        let (_status, _statistics, tree) = solve_grid_with_guess_tree(
+            &mut grid,
+            &SolveController {
+                make_guesses: false,
+                ..solve_controller
+            },
+        );
+        assert_eq!(tree, GuessTree::default());
+    }
+
+    #[test]
+    fn test_solvability_timeline() {
+        let mut rng = SmallRng::seed_from_u64(374);
+        let puzzle = crate::test_utils::random_unique_puzzle(&mut rng);
+        let solve_controller = crate::test_utils::full_solve_controller();
+
+        let timeline = solvability_timeline(&puzzle, &solve_controller).unwrap();
+        let mut solution = puzzle.clone();
+        solve_grid_with_solve_controller(
+            &mut solution,
+            &solve_controller,
+            &mut SolveStatistics::new(),
+        );
+
+        for x in 0..9 {
+            for y in 0..9 {
+                match puzzle.get(x, y).unwrap().get_value_copy() {
+                    CellValue::Fixed(_) => assert!(timeline.get(x, y).is_none()),
+                    CellValue::Unknown(_) => {
+                        let determination = timeline.get(x, y).unwrap();
+                        assert_eq!(
+                            CellValue::Fixed(determination.digit),
+                            solution.get(x, y).unwrap().get_value_copy()
+                        );
+                    }
+                }
+            }
+        }
+        assert!(timeline.get(9, 0).is_none());
+
+        // An empty grid has many solutions
+        assert!(solvability_timeline(&crate::grid::Grid::new(), &solve_controller).is_none());
+    }
+}
@@ -0,0 +1,127 @@
+// Naked and hidden subset detection, shared by the solver and by `find_all`. Both work on the
+// possibilities of one row, column, or square as bitmasks: `masks[i]` has bit d set if digit d is
+// possible in the section's i-th cell, and is 0 for cells that are already Fixed.
+
+// A group of cells of a section, as a bitmask of their positions in it, and the digits (with bit d
+// for digit d) that a subset confines to them
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Subset {
+    pub cells: u16,
+    pub digits: u16,
+}
+
+// The possibilities of a list of digits as a bitmask
+pub(crate) fn to_mask(digits: &[u8]) -> u16 {
+    digits.iter().fold(0, |mask, digit| mask | 1 << digit)
+}
+
+// The digits of a bitmask, from the lowest up
+pub(crate) fn from_mask(mask: u16) -> Vec<u8> {
+    (1..10).filter(|digit| mask & 1 << digit != 0).collect()
+}
+
+// Every way to choose `size` of the bits set in `allowed`, where only the lowest `width` bits are
+// considered
+fn combinations(allowed: u16, size: usize, width: usize) -> impl Iterator<Item = u16> {
+    (0..1u16 << width).filter(move |&combination| {
+        combination & !allowed == 0 && combination.count_ones() as usize == size
+    })
+}
+
+// Groups of `size` cells whose possibilities only have `size` digits between them, and which share
+// a digit with some other cell of the section, so that the digits can be removed from it
+pub(crate) fn naked_subsets(masks: &[u16; 9], size: usize) -> Vec<Subset> {
+    let unknown = (0..9).filter(|&i| masks[i] != 0).count();
+    if unknown <= size {
+        return Vec::new();
+    }
+
+    let small_cells = (0..9)
+        .filter(|&i| masks[i] != 0 && masks[i].count_ones() as usize <= size)
+        .fold(0u16, |cells, i| cells | 1 << i);
+
+    combinations(small_cells, size, 9)
+        .filter_map(|cells| {
+            let digits = (0..9)
+                .filter(|&i| cells & 1 << i != 0)
+                .fold(0, |digits, i| digits | masks[i]);
+            let removes_something = (0..9).any(|i| cells & 1 << i == 0 && masks[i] & digits != 0);
+            if digits.count_ones() as usize == size && removes_something {
+                Some(Subset { cells, digits })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// Groups of `size` digits that are only possible in the same `size` cells, which also have some
+// other possibility that can then be removed
+pub(crate) fn hidden_subsets(masks: &[u16; 9], size: usize) -> Vec<Subset> {
+    // Where each digit is possible, as a bitmask of positions
+    let mut positions = [0u16; 10];
+    for (i, &mask) in masks.iter().enumerate() {
+        for (digit, digit_positions) in positions.iter_mut().enumerate().skip(1) {
+            if mask & 1 << digit != 0 {
+                *digit_positions |= 1 << i;
+            }
+        }
+    }
+
+    let few_places = (1..10)
+        .filter(|&digit| {
+            let places = positions[digit].count_ones() as usize;
+            places > 0 && places <= size
+        })
+        .fold(0u16, |digits, digit| digits | 1 << digit);
+
+    combinations(few_places, size, 10)
+        .filter_map(|digits| {
+            let cells = (1..10)
+                .filter(|&digit| digits & 1 << digit != 0)
+                .fold(0, |cells, digit| cells | positions[digit]);
+            let removes_something = (0..9).any(|i| cells & 1 << i != 0 && masks[i] & !digits != 0);
+            if cells.count_ones() as usize == size && removes_something {
+                Some(Subset { cells, digits })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solver::subsets::*;
+
+    #[test]
+    fn test_subsets() {
+        let mut masks = [to_mask(&[1, 2, 3, 4, 5, 6, 7, 8, 9]); 9];
+        masks[0] = to_mask(&[1, 2]);
+        masks[4] = to_mask(&[2, 3]);
+        masks[8] = to_mask(&[1, 3]);
+
+        assert!(naked_subsets(&masks, 2).is_empty());
+        assert_eq!(
+            naked_subsets(&masks, 3),
+            vec![Subset {
+                cells: 1 << 0 | 1 << 4 | 1 << 8,
+                digits: to_mask(&[1, 2, 3]),
+            }]
+        );
+        assert_eq!(from_mask(to_mask(&[1, 2, 3])), vec![1, 2, 3]);
+
+        // 8 and 9 only fit in the first two cells
+        let mut masks = [to_mask(&[1, 2, 3, 4, 5, 6, 7]); 9];
+        masks[0] = to_mask(&[1, 8, 9]);
+        masks[1] = to_mask(&[2, 3, 8, 9]);
+        assert_eq!(
+            hidden_subsets(&masks, 2),
+            vec![Subset {
+                cells: 1 << 0 | 1 << 1,
+                digits: to_mask(&[8, 9]),
+            }]
+        );
+        assert!(hidden_subsets(&masks, 3).is_empty());
+    }
+}
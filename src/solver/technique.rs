@@ -0,0 +1,228 @@
+use crate::solver::SolveAction;
+use std::str::FromStr;
+
+/// A named way of making progress on a puzzle. The solver's own strategies (see `SolveAction`),
+/// the patterns `find_all` looks for, and the steps of a `SolveReplay` are all reported with these.
+///
+/// `name` gives a stable name for scripts and command line flags, which `from_str` accepts back,
+/// while `Display` gives one meant for people, like "Hidden single".
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Technique {
+    /// A cell with only one possibility left.
+    Single,
+
+    /// A digit that's only possible in one cell of a row, column, or square.
+    HiddenSingle,
+
+    /// Two cells of a row, column, or square that have the same two possibilities, which then
+    /// can't go anywhere else in it.
+    NakedPair,
+
+    /// Like a naked pair, but three cells whose possibilities only have three digits between them.
+    NakedTriple,
+
+    /// Like a naked pair, but four cells whose possibilities only have four digits between them.
+    NakedQuad,
+
+    /// Two digits that are only possible in the same two cells of a row, column, or square, which
+    /// then can't be anything else.
+    HiddenPair,
+
+    /// Like a hidden pair, but three digits confined to three cells.
+    HiddenTriple,
+
+    /// Like a hidden pair, but four digits confined to four cells.
+    HiddenQuad,
+
+    /// A digit whose possible cells in a square all lie in one row or column, which then can't
+    /// have that digit outside of the square.
+    PointingPair,
+
+    /// The unset cells of a row, column, or square split into groups whose possibilities don't
+    /// overlap, found by repeatedly bisecting the section. This finds every naked and hidden
+    /// subset and more, but what it finds doesn't always have a standard name.
+    PossibilityGroup,
+
+    /// A digit that must go where two sections overlap, and so can't go in the rest of either.
+    /// Pointing pairs are one case.
+    UsefulConstraints,
+
+    /// Trying each possibility of a cell in turn.
+    Guess,
+}
+
+impl Technique {
+    /// Every technique, roughly from the simplest to guessing.
+    pub const ALL: [Technique; 12] = [
+        Technique::Single,
+        Technique::HiddenSingle,
+        Technique::NakedPair,
+        Technique::NakedTriple,
+        Technique::NakedQuad,
+        Technique::HiddenPair,
+        Technique::HiddenTriple,
+        Technique::HiddenQuad,
+        Technique::PointingPair,
+        Technique::PossibilityGroup,
+        Technique::UsefulConstraints,
+        Technique::Guess,
+    ];
+
+    /// The techniques that `find_all` looks for, from the easiest for a person to spot to the
+    /// hardest, in the order of the default `GradingWeights`.
+    pub const EASIEST_FIRST: [Technique; 9] = [
+        Technique::HiddenSingle,
+        Technique::Single,
+        Technique::PointingPair,
+        Technique::NakedPair,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::HiddenTriple,
+        Technique::NakedQuad,
+        Technique::HiddenQuad,
+    ];
+
+    /// The technique's name in lowercase with underscores, like "hidden_single".
+    pub fn name(self) -> &'static str {
+        match self {
+            Technique::Single => "single",
+            Technique::HiddenSingle => "hidden_single",
+            Technique::NakedPair => "naked_pair",
+            Technique::NakedTriple => "naked_triple",
+            Technique::NakedQuad => "naked_quad",
+            Technique::HiddenPair => "hidden_pair",
+            Technique::HiddenTriple => "hidden_triple",
+            Technique::HiddenQuad => "hidden_quad",
+            Technique::PointingPair => "pointing_pair",
+            Technique::PossibilityGroup => "possibility_group",
+            Technique::UsefulConstraints => "useful_constraint",
+            Technique::Guess => "guess",
+        }
+    }
+
+    /// The number of cells (and digits) of a naked or hidden subset, or None for the other
+    /// techniques.
+    pub fn subset_size(self) -> Option<usize> {
+        match self {
+            Technique::NakedPair | Technique::HiddenPair => Some(2),
+            Technique::NakedTriple | Technique::HiddenTriple => Some(3),
+            Technique::NakedQuad | Technique::HiddenQuad => Some(4),
+            _ => None,
+        }
+    }
+
+    // The naked (or hidden) subset technique for subsets of `size` cells
+    pub(crate) fn subset(naked: bool, size: usize) -> Technique {
+        match (naked, size) {
+            (true, 2) => Technique::NakedPair,
+            (true, 3) => Technique::NakedTriple,
+            (true, 4) => Technique::NakedQuad,
+            (false, 2) => Technique::HiddenPair,
+            (false, 3) => Technique::HiddenTriple,
+            (false, 4) => Technique::HiddenQuad,
+            _ => panic!("Subsets have 2 to 4 cells, not {}", size),
+        }
+    }
+}
+
+impl std::fmt::Display for Technique {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Technique::Single => "Single",
+            Technique::HiddenSingle => "Hidden single",
+            Technique::NakedPair => "Naked pair",
+            Technique::NakedTriple => "Naked triple",
+            Technique::NakedQuad => "Naked quad",
+            Technique::HiddenPair => "Hidden pair",
+            Technique::HiddenTriple => "Hidden triple",
+            Technique::HiddenQuad => "Hidden quad",
+            Technique::PointingPair => "Pointing pair",
+            Technique::PossibilityGroup => "Possibility group",
+            Technique::UsefulConstraints => "Useful constraint",
+            Technique::Guess => "Guess",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Technique {
+    // Needed for argparse
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase().replace(['-', ' '], "_");
+        Technique::ALL
+            .iter()
+            .copied()
+            .find(|technique| technique.name() == normalized)
+            .ok_or_else(|| format!("{} is not a valid technique", s))
+    }
+}
+
+impl SolveAction {
+    /// The techniques the strategy applies; steps it makes are reported as one of them.
+    pub fn techniques(self) -> &'static [Technique] {
+        match self {
+            SolveAction::Single => &[Technique::Single],
+            SolveAction::HiddenSingle => &[Technique::HiddenSingle],
+            SolveAction::NakedSubset => &[
+                Technique::NakedPair,
+                Technique::NakedTriple,
+                Technique::NakedQuad,
+            ],
+            SolveAction::HiddenSubset => &[
+                Technique::HiddenPair,
+                Technique::HiddenTriple,
+                Technique::HiddenQuad,
+            ],
+            SolveAction::PossibilityGroup => &[Technique::PossibilityGroup],
+            SolveAction::UsefulConstraints => &[Technique::UsefulConstraints],
+            SolveAction::Guess => &[Technique::Guess],
+        }
+    }
+
+    /// The strategy's name in lowercase with underscores, which is its technique's name unless it
+    /// applies several, like "naked_subset".
+    pub fn name(self) -> &'static str {
+        match self {
+            SolveAction::NakedSubset => "naked_subset",
+            SolveAction::HiddenSubset => "hidden_subset",
+            _ => self.techniques()[0].name(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solver::technique::*;
+
+    #[test]
+    fn test_technique_names() {
+        for technique in Technique::ALL.iter() {
+            assert_eq!(Technique::from_str(technique.name()), Ok(*technique));
+        }
+        assert_eq!(
+            Technique::from_str("Hidden-Single"),
+            Ok(Technique::HiddenSingle)
+        );
+        assert_eq!(
+            Technique::from_str(&Technique::NakedPair.to_string()),
+            Ok(Technique::NakedPair)
+        );
+        assert!(Technique::from_str("x_wing").is_err());
+
+        assert_eq!(SolveAction::Guess.techniques(), &[Technique::Guess]);
+        assert_eq!(SolveAction::HiddenSingle.name(), "hidden_single");
+        assert_eq!(SolveAction::HiddenSubset.name(), "hidden_subset");
+        assert_eq!(
+            SolveAction::UsefulConstraints.techniques()[0].to_string(),
+            "Useful constraint"
+        );
+        for technique in SolveAction::NakedSubset.techniques() {
+            let size = technique.subset_size().unwrap();
+            assert_eq!(Technique::subset(true, size), *technique);
+        }
+    }
+}
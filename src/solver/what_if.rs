@@ -0,0 +1,108 @@
+use crate::grid::{CellDiff, Grid, SudokuError};
+use crate::solver::{
+    check_uniqueness, solve_grid_with_solve_controller, SolveController, SolveStatistics,
+    Uniqueness,
+};
+
+/// What would follow from placing some digits in a puzzle; see `what_if`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WhatIf {
+    /// The first placement that contradicts the puzzle or an earlier placement. If there is one,
+    /// nothing else is worked out.
+    pub conflict: Option<SudokuError>,
+
+    /// Whether the puzzle has a unique solution with the placements, or None if it has none.
+    pub uniqueness: Option<Uniqueness>,
+
+    /// Every cell that the solving strategies could then fill in or narrow down without guessing,
+    /// with `before` being its value just after the placements.
+    pub deductions: Vec<CellDiff>,
+}
+
+/// Try placing digits, given as `(x, y, digit)`, in a copy of `puzzle`, and report whether the
+/// puzzle is still consistent and unique, and what the strategies enabled in `solve_controller`
+/// can deduce from there. Guessing is never used for the deductions.
+///
+/// # Panics
+///
+/// Panics if a placement's coordinates are out of bounds.
+pub fn what_if(
+    puzzle: &Grid,
+    placements: &[(usize, usize, u8)],
+    solve_controller: &SolveController,
+) -> WhatIf {
+    let grid = puzzle.snapshot().to_grid();
+
+    for &(x, y, digit) in placements {
+        if let Err(error) = grid.get(x, y).unwrap().try_set(digit) {
+            return WhatIf {
+                conflict: Some(error),
+                uniqueness: None,
+                deductions: Vec::new(),
+            };
+        }
+    }
+
+    let solve_controller = SolveController {
+        make_guesses: false,
+        ..*solve_controller
+    };
+    let mut deduced = grid.clone();
+    solve_grid_with_solve_controller(&mut deduced, &solve_controller, &mut SolveStatistics::new());
+
+    WhatIf {
+        conflict: None,
+        uniqueness: check_uniqueness(&grid),
+        deductions: grid.diff(&deduced),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::{CellValue, Coord, Grid, SudokuError};
+    use crate::solver::what_if::*;
+
+    fn singles_only() -> SolveController {
+        SolveController {
+            search_hidden_singles: false,
+            search_naked_subsets: false,
+            search_hidden_subsets: false,
+            find_possibility_groups: false,
+            search_useful_constraint: false,
+            search_pointing_constraints: false,
+            search_claiming_constraints: false,
+            ..crate::test_utils::full_solve_controller()
+        }
+    }
+
+    #[test]
+    fn test_what_if() {
+        // The first row is missing its last two digits, 8 and 9
+        let puzzle = Grid::new();
+        for y in 0..7 {
+            puzzle.get(0, y).unwrap().set(y as u8 + 1);
+        }
+
+        let result = what_if(&puzzle, &[(0, 7, 8)], &singles_only());
+        assert_eq!(result.conflict, None);
+        assert_eq!(result.uniqueness, Some(Uniqueness::NotUnique));
+        // 9 goes in the last cell of the row, and is then removed from the rest of its column and
+        // square
+        assert_eq!(result.deductions.len(), 1 + 8 + 4);
+        assert_eq!((result.deductions[0].x, result.deductions[0].y), (0, 8));
+        assert_eq!(result.deductions[0].after, CellValue::Fixed(9));
+
+        let result = what_if(&puzzle, &[(0, 7, 8), (0, 8, 8)], &singles_only());
+        assert_eq!(
+            result.conflict,
+            Some(SudokuError::ConflictsWithFixed {
+                digit: 8,
+                peer: Coord::new(0, 7).unwrap()
+            })
+        );
+        assert!(result.deductions.is_empty());
+
+        // The puzzle itself is untouched
+        assert_eq!(puzzle.value_at(0, 7), Some(CellValue::Unknown(vec![8, 9])));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::generator::{generate_full_grid, generate_grid};
+use crate::grid::{CellValue, Grid};
+use crate::solver::{
+    evaluate_grid_with_solve_controller, SolveController, SolveStatus, Uniqueness,
+};
+use rand::prelude::*;
+
+// Everything enabled, so that the solver can settle uniqueness for any puzzle we hand it
+pub(crate) fn full_solve_controller() -> SolveController {
+    SolveController::all_techniques()
+}
+
+/// Generate a random, fully solved and valid `Grid`.
+pub fn random_completed_grid(rng: &mut SmallRng) -> Grid {
+    generate_full_grid(rng)
+}
+
+/// Generate a random puzzle that has exactly one solution. No difficulty criteria are applied;
+/// clues are removed for as long as the solution stays unique.
+pub fn random_unique_puzzle(rng: &mut SmallRng) -> Grid {
+    let (grid, _num_hints, _statistics) = generate_grid(rng, &full_solve_controller());
+    grid
+}
+
+/// Generate a random puzzle by keeping `num_hints` randomly chosen cells of a random completed
+/// grid. The puzzle always has at least one solution; whether that solution is unique is returned
+/// alongside it.
+///
+/// # Panics
+///
+/// Panics if `num_hints` is larger than 81.
+pub fn random_puzzle(rng: &mut SmallRng, num_hints: usize) -> (Grid, Uniqueness) {
+    assert!(num_hints <= 81, "A grid can't have more than 81 hints");
+
+    let solution = generate_full_grid(rng);
+
+    let mut coordinates = Vec::new();
+    for x in 0..9 {
+        for y in 0..9 {
+            coordinates.push((x, y));
+        }
+    }
+    coordinates.shuffle(rng);
+
+    let puzzle = Grid::new();
+    for &(x, y) in coordinates.iter().take(num_hints) {
+        if let CellValue::Fixed(digit) = solution.get(x, y).unwrap().get_value_copy() {
+            puzzle.get(x, y).unwrap().set(digit);
+        }
+    }
+
+    let (status, _statistics) =
+        evaluate_grid_with_solve_controller(&puzzle, &full_solve_controller());
+    let uniqueness = match status {
+        SolveStatus::Complete(uniqueness) => {
+            uniqueness.expect("Uniqueness is always determined with guesses enabled")
+        }
+        _ => panic!("A subset of a valid solution should always be solvable"),
+    };
+
+    (puzzle, uniqueness)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solver::Uniqueness;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_random_puzzle() {
+        let mut rng = SmallRng::seed_from_u64(123);
+
+        let (puzzle, uniqueness) = random_puzzle(&mut rng, 81);
+        assert_eq!(uniqueness, Uniqueness::Unique);
+
+        let (puzzle2, uniqueness) = random_puzzle(&mut rng, 10);
+        assert_eq!(uniqueness, Uniqueness::NotUnique);
+
+        assert_eq!(puzzle.clue_count(), 81);
+        assert_eq!(puzzle2.clue_count(), 10);
+    }
+}
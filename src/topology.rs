@@ -0,0 +1,193 @@
+//! The fixed layout of a Sudoku grid: which cells are in each row, column, and square, which of
+//! those houses each cell is in, and which other cells each cell shares one with. Cells are
+//! numbered from 0 to 80 row by row, the same as `Coord::index`, and houses from 0 to 26: the nine
+//! rows, then the nine columns, then the nine squares. The tables are worked out at compile time.
+
+use crate::grid::{Coord, SectionType};
+
+/// The number of cells in a grid.
+pub const CELLS: usize = 81;
+
+/// The number of rows, columns, and squares in a grid together.
+pub const HOUSES: usize = 27;
+
+/// The number of other cells that share a row, column, or square with any one cell.
+pub const PEERS_PER_CELL: usize = 20;
+
+/// The index of the square that the cell at `row` and `column` is in, with the squares numbered
+/// row by row.
+pub const fn square_index(row: usize, column: usize) -> usize {
+    (row / 3) * 3 + column / 3
+}
+
+/// The row and column of the `position`th cell of a row, column, or square, with the cells of a
+/// square read row by row. This is the order `Grid` keeps each `Section`'s cells in.
+pub const fn house_cell(
+    section_type: SectionType,
+    index: usize,
+    position: usize,
+) -> (usize, usize) {
+    match section_type {
+        SectionType::Row => (index, position),
+        SectionType::Column => (position, index),
+        SectionType::Square => (
+            (index / 3) * 3 + position / 3,
+            (index % 3) * 3 + position % 3,
+        ),
+    }
+}
+
+/// The number of a row, column, or square among all 27 houses.
+pub const fn house_number(section_type: SectionType, index: usize) -> usize {
+    match section_type {
+        SectionType::Row => index,
+        SectionType::Column => 9 + index,
+        SectionType::Square => 18 + index,
+    }
+}
+
+/// The inverse of `house_number`.
+///
+/// # Panics
+///
+/// Panics if `house` is 27 or more.
+pub const fn house(house: usize) -> (SectionType, usize) {
+    match house / 9 {
+        0 => (SectionType::Row, house),
+        1 => (SectionType::Column, house - 9),
+        2 => (SectionType::Square, house - 18),
+        _ => panic!("There are only 27 houses"),
+    }
+}
+
+/// The cells of each house, by their number as in `house_number`, in the order of `house_cell`.
+pub const HOUSE_CELLS: [[u8; 9]; HOUSES] = house_cells_table();
+
+/// The row, column, and square each cell is in, by their house numbers.
+pub const CELL_HOUSES: [[u8; 3]; CELLS] = cell_houses_table();
+
+/// The other cells that share a row, column, or square with each cell, from the lowest numbered
+/// up.
+pub const PEERS: [[u8; PEERS_PER_CELL]; CELLS] = peers_table();
+
+const fn house_cells_table() -> [[u8; 9]; HOUSES] {
+    let mut table = [[0; 9]; HOUSES];
+    let mut number = 0;
+    while number < HOUSES {
+        let (section_type, index) = house(number);
+        let mut position = 0;
+        while position < 9 {
+            let (row, column) = house_cell(section_type, index, position);
+            table[number][position] = (row * 9 + column) as u8;
+            position += 1;
+        }
+        number += 1;
+    }
+    table
+}
+
+const fn cell_houses_table() -> [[u8; 3]; CELLS] {
+    let mut table = [[0; 3]; CELLS];
+    let mut cell = 0;
+    while cell < CELLS {
+        let (row, column) = (cell / 9, cell % 9);
+        table[cell] = [
+            house_number(SectionType::Row, row) as u8,
+            house_number(SectionType::Column, column) as u8,
+            house_number(SectionType::Square, square_index(row, column)) as u8,
+        ];
+        cell += 1;
+    }
+    table
+}
+
+const fn peers_table() -> [[u8; PEERS_PER_CELL]; CELLS] {
+    let mut table = [[0; PEERS_PER_CELL]; CELLS];
+    let mut cell = 0;
+    while cell < CELLS {
+        let (row, column) = (cell / 9, cell % 9);
+        let mut found = 0;
+        let mut other = 0;
+        while other < CELLS {
+            let (other_row, other_column) = (other / 9, other % 9);
+            let is_peer = other != cell
+                && (other_row == row
+                    || other_column == column
+                    || square_index(other_row, other_column) == square_index(row, column));
+            if is_peer {
+                table[cell][found] = other as u8;
+                found += 1;
+            }
+            other += 1;
+        }
+        cell += 1;
+    }
+    table
+}
+
+/// The cells of a row, column, or square, in the order of `house_cell`.
+///
+/// # Panics
+///
+/// Panics if `index` is 9 or more.
+pub fn house_coords(section_type: SectionType, index: usize) -> impl Iterator<Item = Coord> {
+    assert!(index < 9, "Section index should be less than 9");
+    HOUSE_CELLS[house_number(section_type, index)]
+        .iter()
+        .map(|&cell| Coord::from_index(cell as usize).unwrap())
+}
+
+/// The row, column, and square that `coord` is in.
+pub fn houses_of(coord: Coord) -> [(SectionType, usize); 3] {
+    [
+        (SectionType::Row, coord.row()),
+        (SectionType::Column, coord.column()),
+        (SectionType::Square, coord.square()),
+    ]
+}
+
+/// The 20 other cells that share a row, column, or square with `coord`, row by row.
+pub fn peers(coord: Coord) -> impl Iterator<Item = Coord> {
+    PEERS[coord.index()]
+        .iter()
+        .map(|&cell| Coord::from_index(cell as usize).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::{Coord, SectionType};
+    use crate::topology::*;
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(square_index(4, 8), 5);
+        assert_eq!(house_cell(SectionType::Square, 5, 7), (5, 7));
+        for number in 0..HOUSES {
+            let (section_type, index) = house(number);
+            assert_eq!(house_number(section_type, index), number);
+        }
+
+        let center = Coord::new(4, 4).unwrap();
+        for cell in HOUSE_CELLS[house_number(SectionType::Square, 4)].iter() {
+            assert_eq!(
+                CELL_HOUSES[*cell as usize][2] as usize,
+                house_number(SectionType::Square, 4)
+            );
+        }
+        assert_eq!(house_coords(SectionType::Column, 4).nth(4), Some(center));
+        assert!(houses_of(center).contains(&(SectionType::Square, 4)));
+
+        for coord in Coord::all() {
+            let peers: Vec<Coord> = peers(coord).collect();
+            let expected: Vec<Coord> = Coord::all()
+                .filter(|&other| other != coord)
+                .filter(|&other| {
+                    houses_of(coord)
+                        .iter()
+                        .any(|house| houses_of(other).contains(house))
+                })
+                .collect();
+            assert_eq!(peers, expected);
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! Transformations that turn a puzzle into a different looking but equivalent one: relabelling
+//! the digits, reordering rows and columns within their bands, reordering the bands themselves,
+//! and reflecting or transposing the grid. A transformed puzzle has as many solutions as the
+//! original and needs the same strategies to solve.
+
+use crate::grid::Grid;
+use rand::prelude::*;
+use std::collections::HashSet;
+
+/// A combination of the transformations that preserve a puzzle's solutions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Transformation {
+    /// The digit that each of 1 to 9 becomes; digit `d` is replaced with `digits[d - 1]`.
+    pub digits: [u8; 9],
+
+    /// The row of the original grid that ends up at each row. Rows can only be moved within their
+    /// band of three, or with their whole band, for the puzzle to stay valid.
+    pub rows: [usize; 9],
+
+    /// Like `rows`, but for columns and their stacks of three.
+    pub columns: [usize; 9],
+
+    /// Whether to swap rows and columns after reordering them.
+    pub transpose: bool,
+}
+
+impl Transformation {
+    /// The transformation that leaves every puzzle as it was.
+    pub fn identity() -> Transformation {
+        Transformation {
+            digits: [1, 2, 3, 4, 5, 6, 7, 8, 9],
+            rows: [0, 1, 2, 3, 4, 5, 6, 7, 8],
+            columns: [0, 1, 2, 3, 4, 5, 6, 7, 8],
+            transpose: false,
+        }
+    }
+
+    /// Reverse the order of the columns, mirroring the grid left to right.
+    pub fn mirror() -> Transformation {
+        Transformation {
+            columns: [8, 7, 6, 5, 4, 3, 2, 1, 0],
+            ..Transformation::identity()
+        }
+    }
+
+    /// Reverse the order of the rows, flipping the grid upside down.
+    pub fn flip() -> Transformation {
+        Transformation {
+            rows: [8, 7, 6, 5, 4, 3, 2, 1, 0],
+            ..Transformation::identity()
+        }
+    }
+
+    /// Swap the rows and columns.
+    pub fn transposition() -> Transformation {
+        Transformation {
+            transpose: true,
+            ..Transformation::identity()
+        }
+    }
+
+    /// A random relabelling of the digits along with a random valid reordering of the rows and
+    /// columns, transposed half of the time.
+    pub fn random(rng: &mut SmallRng) -> Transformation {
+        let mut digits = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        digits.shuffle(rng);
+
+        Transformation {
+            digits,
+            rows: random_line_order(rng),
+            columns: random_line_order(rng),
+            transpose: rng.gen(),
+        }
+    }
+
+    /// Build the transformed puzzle. Only `Fixed` digits are carried over; the new `Grid`'s
+    /// possibilities are worked out from them.
+    pub fn apply(&self, grid: &Grid) -> Grid {
+        let original = grid.to_digits();
+        let mut digits = [[0; 9]; 9];
+
+        for (x, row) in digits.iter_mut().enumerate() {
+            for (y, digit) in row.iter_mut().enumerate() {
+                let (x, y) = if self.transpose { (y, x) } else { (x, y) };
+                let original_digit = original[self.rows[x]][self.columns[y]];
+                if original_digit > 0 {
+                    *digit = self.digits[original_digit as usize - 1];
+                }
+            }
+        }
+
+        Grid::from_digits(digits)
+    }
+
+    /// The transformation that takes `grid` to its canonical form, the smallest of all its
+    /// equivalent puzzles when read row by row, with the digits numbered in the order they're
+    /// first read and empty cells before any digit. Two puzzles are equivalent exactly when their
+    /// canonical forms are the same; see `canonical_form`. Only `Fixed` digits are looked at.
+    pub fn canonical(grid: &Grid) -> Transformation {
+        let original = grid.to_digits();
+        let mut best: Option<([[u8; 9]; 9], Transformation)> = None;
+
+        for &transpose in [false, true].iter() {
+            let mut digits = original;
+            if transpose {
+                for (x, row) in digits.iter_mut().enumerate() {
+                    for (y, digit) in row.iter_mut().enumerate() {
+                        *digit = original[y][x];
+                    }
+                }
+            }
+
+            // Build the canonical form a row at a time, keeping only the ways of ordering the
+            // rows and columns that give the smallest rows so far
+            let mut partials: Vec<PartialOrder> = all_line_orders()
+                .into_iter()
+                .map(|columns| PartialOrder {
+                    rows: Vec::with_capacity(9),
+                    columns,
+                    labels: [0; 10],
+                    next_label: 1,
+                })
+                .collect();
+            let mut form = [[0; 9]; 9];
+            for form_row in form.iter_mut() {
+                let mut smallest: Option<[u8; 9]> = None;
+                let mut kept = Vec::new();
+                // Orders that have placed the same rows, in whatever order, with the same columns
+                // and labels carry on the same way, so only the first of them is kept
+                let mut seen = HashSet::new();
+                for partial in partials.iter() {
+                    for row in partial.next_rows() {
+                        let mut extended = partial.clone();
+                        let labelled = extended.push(row, &digits);
+                        match smallest.map(|smallest| labelled.cmp(&smallest)) {
+                            Some(std::cmp::Ordering::Greater) => continue,
+                            Some(std::cmp::Ordering::Less) | None => {
+                                smallest = Some(labelled);
+                                kept.clear();
+                                seen.clear();
+                            }
+                            Some(std::cmp::Ordering::Equal) => {}
+                        }
+                        if seen.insert(extended.key()) {
+                            kept.push(extended);
+                        }
+                    }
+                }
+                *form_row = smallest.unwrap();
+                partials = kept;
+            }
+
+            if best
+                .as_ref()
+                .is_some_and(|(best_form, _)| *best_form <= form)
+            {
+                continue;
+            }
+            let chosen = &partials[0];
+            let mut rows = [0; 9];
+            rows.copy_from_slice(&chosen.rows);
+            best = Some((form, chosen.transformation(rows, transpose)));
+        }
+
+        best.unwrap().1
+    }
+}
+
+/// `grid` in its canonical form; see `Transformation::canonical`. Puzzles that can be turned into
+/// each other by relabelling digits, reordering lines, and reflecting have the same canonical
+/// form, so it can be used to find repeats among puzzles that were disguised.
+pub fn canonical_form(grid: &Grid) -> Grid {
+    Transformation::canonical(grid).apply(grid)
+}
+
+// Part of a way to reorder a grid's lines while looking for its canonical form: the rows placed
+// so far, every column, and the labels given to the digits read so far
+#[derive(Clone)]
+struct PartialOrder {
+    rows: Vec<usize>,
+    columns: [usize; 9],
+    labels: [u8; 10],
+    next_label: u8,
+}
+
+impl PartialOrder {
+    // What decides how the order can carry on: the rows placed so far as a bitmask, the columns,
+    // and the labels
+    fn key(&self) -> (u16, [usize; 9], [u8; 10]) {
+        let placed = self.rows.iter().fold(0, |placed, row| placed | 1 << row);
+        (placed, self.columns, self.labels)
+    }
+
+    // The rows that can go next: the rest of the current band, or the first of any unused band
+    fn next_rows(&self) -> Vec<usize> {
+        let position = self.rows.len();
+        if position.is_multiple_of(3) {
+            (0..9)
+                .filter(|row| !self.rows.iter().any(|used| used / 3 == row / 3))
+                .collect()
+        } else {
+            let band = self.rows[position - 1] / 3;
+            (band * 3..band * 3 + 3)
+                .filter(|row| !self.rows.contains(row))
+                .collect()
+        }
+    }
+
+    // Place `row` of `digits` next, returning it with its columns reordered and digits relabelled
+    fn push(&mut self, row: usize, digits: &[[u8; 9]; 9]) -> [u8; 9] {
+        self.rows.push(row);
+        let mut labelled = [0; 9];
+        for (position, &column) in self.columns.iter().enumerate() {
+            let digit = digits[row][column] as usize;
+            if digit > 0 {
+                if self.labels[digit] == 0 {
+                    self.labels[digit] = self.next_label;
+                    self.next_label += 1;
+                }
+                labelled[position] = self.labels[digit];
+            }
+        }
+        labelled
+    }
+
+    // The Transformation that reorders the lines this way. `apply` transposes after picking out
+    // the lines, so the rows and columns found for the transposed grid swap places.
+    fn transformation(&self, rows: [usize; 9], transpose: bool) -> Transformation {
+        // Digits that never appeared get the labels that are left, in order
+        let mut labels = self.labels;
+        let mut next_label = self.next_label;
+        for label in labels.iter_mut().skip(1) {
+            if *label == 0 {
+                *label = next_label;
+                next_label += 1;
+            }
+        }
+        let mut digits = [0; 9];
+        digits.copy_from_slice(&labels[1..]);
+
+        let (rows, columns) = if transpose {
+            (self.columns, rows)
+        } else {
+            (rows, self.columns)
+        };
+        Transformation {
+            digits,
+            rows,
+            columns,
+            transpose,
+        }
+    }
+}
+
+// Every valid order of the nine lines: the bands in any order, and the lines of each band in any
+// order
+fn all_line_orders() -> Vec<[usize; 9]> {
+    const PERMUTATIONS: [[usize; 3]; 6] = [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ];
+
+    let mut orders = Vec::with_capacity(6 * 6 * 6 * 6);
+    for bands in PERMUTATIONS.iter() {
+        for first in PERMUTATIONS.iter() {
+            for second in PERMUTATIONS.iter() {
+                for third in PERMUTATIONS.iter() {
+                    let mut order = [0; 9];
+                    for (band_position, (&band, lines)) in
+                        bands.iter().zip([first, second, third].iter()).enumerate()
+                    {
+                        for (line_position, &line) in lines.iter().enumerate() {
+                            order[band_position * 3 + line_position] = band * 3 + line;
+                        }
+                    }
+                    orders.push(order);
+                }
+            }
+        }
+    }
+    orders
+}
+
+// Shuffle the bands of three lines, and the lines within each band
+fn random_line_order(rng: &mut SmallRng) -> [usize; 9] {
+    let mut bands = [0, 1, 2];
+    bands.shuffle(rng);
+
+    let mut order = [0; 9];
+    for (band_position, &band) in bands.iter().enumerate() {
+        let mut lines = [0, 1, 2];
+        lines.shuffle(rng);
+        for (line_position, &line) in lines.iter().enumerate() {
+            order[band_position * 3 + line_position] = band * 3 + line;
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::Grid;
+    use crate::solver::{check_uniqueness, dlx, Uniqueness};
+    use crate::test_utils::random_unique_puzzle;
+    use crate::transform::*;
+
+    #[test]
+    fn test_reflections() {
+        let grid = Grid::new();
+        grid.get(0, 1).unwrap().set(5);
+
+        let mirrored = Transformation::mirror().apply(&grid);
+        assert_eq!(mirrored.clue_count(), 1);
+        assert_eq!(mirrored.to_digits()[0][7], 5);
+
+        let flipped = Transformation::flip().apply(&grid);
+        assert_eq!(flipped.to_digits()[8][1], 5);
+
+        let transposed = Transformation::transposition().apply(&grid);
+        assert_eq!(transposed.to_digits()[1][0], 5);
+
+        assert!(Transformation::identity().apply(&grid) == grid);
+    }
+
+    #[test]
+    fn test_random_transformation() {
+        let mut rng = SmallRng::seed_from_u64(402);
+        let puzzle = random_unique_puzzle(&mut rng);
+        let transformation = Transformation::random(&mut rng);
+
+        let transformed = transformation.apply(&puzzle);
+        assert_eq!(transformed.clue_count(), puzzle.clue_count());
+        assert_eq!(check_uniqueness(&transformed), Some(Uniqueness::Unique));
+
+        // Transforming the solution gives the transformed puzzle's solution
+        let mut solution = puzzle.snapshot().to_grid();
+        dlx::solve_grid(&mut solution);
+        let mut transformed_solution = transformed.snapshot().to_grid();
+        dlx::solve_grid(&mut transformed_solution);
+        assert!(transformation.apply(&solution) == transformed_solution);
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        let mut rng = SmallRng::seed_from_u64(457);
+        let puzzle = random_unique_puzzle(&mut rng);
+        let canonical = canonical_form(&puzzle).to_digits();
+        assert_eq!(
+            canonical_form(&Grid::from_digits(canonical)).to_digits(),
+            canonical
+        );
+
+        for transformation in [
+            Transformation::random(&mut rng),
+            Transformation::random(&mut rng),
+            Transformation::transposition(),
+        ]
+        .iter()
+        {
+            let variant = transformation.apply(&puzzle);
+            assert_eq!(canonical_form(&variant).to_digits(), canonical);
+        }
+
+        let other = random_unique_puzzle(&mut rng);
+        assert_ne!(canonical_form(&other).to_digits(), canonical);
+
+        // Empty cells sort first, and digits are numbered as they're read
+        let first_digit = canonical
+            .iter()
+            .flatten()
+            .find(|&&digit| digit != 0)
+            .unwrap();
+        assert_eq!(*first_digit, 1);
+        assert_eq!(canonical[0][0], 0);
+
+        assert!(canonical_form(&Grid::new()) == Grid::new());
+    }
+}